@@ -0,0 +1,29 @@
+#version 330
+
+in vec2 fragTexCoord;
+out vec4 finalColor;
+
+uniform sampler2D texture0;
+uniform float intensity = 1.5;
+uniform float bloom = 0.6;
+uniform vec2 resolution = vec2(1200.0, 900.0);
+
+// Glow around bright pixels and nothing else, the picture stays flat and sharp
+void main()
+{
+    vec2 texel = 1.0 / resolution;
+    vec3 color = texture(texture0, fragTexCoord).rgb;
+
+    // Bright parts of a 9x9 neighbourhood, sampled every other pixel
+    vec3 glow = vec3(0.0);
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            vec3 s = texture(texture0, fragTexCoord + vec2(x, y) * texel * 2.0).rgb;
+            float brightness = dot(s, vec3(0.299, 0.587, 0.114));
+            glow += s * smoothstep(0.3, 0.8, brightness);
+        }
+    }
+    color += glow / 81.0 * bloom * 4.0;
+
+    finalColor = vec4(color * intensity, 1.0);
+}
@@ -0,0 +1,43 @@
+#version 330
+
+in vec2 fragTexCoord;
+out vec4 finalColor;
+
+uniform sampler2D texture0;
+uniform float intensity = 1.5;
+uniform float scanlines = 0.12;
+uniform float curvature = 0.04;
+uniform float noise = 0.03;
+// Seconds since start and window size in pixels, set every frame
+uniform float time;
+uniform vec2 resolution = vec2(1200.0, 900.0);
+
+float hash(vec2 p)
+{
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+// A plain CRT: the theme's own colors behind a curved, scanlined tube
+void main()
+{
+    vec2 uv = fragTexCoord;
+
+    // Barrel distortion
+    vec2 centered_uv = uv * 2.0 - 1.0;
+    uv = uv + centered_uv * (dot(centered_uv, centered_uv) * curvature);
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        finalColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 color = texture(texture0, uv).rgb;
+
+    // Scanlines, about one every five pixel rows whatever the window size
+    color -= sin(uv.y * resolution.y * 1.33) * scanlines;
+    color += (hash(uv * resolution + fract(time) * 100.0) - 0.5) * noise;
+
+    // Vignette
+    color *= 1.0 - dot(centered_uv, centered_uv) * 0.45;
+
+    finalColor = vec4(color * intensity, 1.0);
+}
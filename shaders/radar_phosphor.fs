@@ -5,6 +5,18 @@ out vec4 finalColor;
 
 uniform sampler2D texture0;
 uniform float intensity = 1.5;
+uniform float scanlines = 0.12;
+uniform float bloom = 0.6;
+uniform float curvature = 0.04;
+uniform float noise = 0.03;
+// Seconds since start and window size in pixels, set every frame
+uniform float time;
+uniform vec2 resolution = vec2(1200.0, 900.0);
+
+float hash(vec2 p)
+{
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
 
 void main()
 {
@@ -12,7 +24,7 @@ void main()
     
     // 1. CRT Barrel Distortion
     vec2 centered_uv = uv * 2.0 - 1.0;
-    uv = uv + centered_uv * (dot(centered_uv, centered_uv) * 0.04);
+    uv = uv + centered_uv * (dot(centered_uv, centered_uv) * curvature);
 
     // Hard borders for the tube edge
     if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
@@ -32,15 +44,17 @@ void main()
     color = mix(color, phosphorGreen * brightness, 0.5);
     
     // Add Bloom/Glow (highlights turn white)
-    color += pow(brightness, 3.0) * 0.6;
+    color += pow(brightness, 3.0) * bloom;
 
-    // 3. Scanlines (Fixed the undeclared variable error)
-    // We use a fixed high number to represent screen density
-    float scanline = sin(uv.y * 1200.0) * 0.12;
+    // 3. Scanlines, about one every five pixel rows whatever the window size
+    float scanline = sin(uv.y * resolution.y * 1.33) * scanlines;
     color -= scanline;
 
+    // Phosphor noise, a new pattern every frame
+    color += (hash(uv * resolution + fract(time) * 100.0) - 0.5) * noise;
+
     // 4. Aperture Grille Mask (Vertical stripes)
-    float mask = 0.95 + 0.05 * sin(uv.x * 2000.0);
+    float mask = 0.95 + 0.05 * sin(uv.x * resolution.x * 1.67);
     color *= mask;
 
     // 5. Vignette (Dark edges)
@@ -0,0 +1,19 @@
+#version 330
+
+// Text from a signed distance field atlas: the alpha is the distance to the
+// glyph outline, 0.5 right on it. Smoothed over about one screen pixel, so
+// glyphs stay sharp however far they're scaled.
+in vec2 fragTexCoord;
+in vec4 fragColor;
+out vec4 finalColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+void main()
+{
+    float distance = texture(texture0, fragTexCoord).a - 0.5;
+    float edge = length(vec2(dFdx(distance), dFdy(distance)));
+    float alpha = smoothstep(-edge, edge, distance);
+    finalColor = vec4(fragColor.rgb, fragColor.a * alpha) * colDiffuse;
+}
@@ -0,0 +1,308 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use toml_edit::{Array, ArrayOfTables, Table, value};
+
+use crate::config;
+use crate::i18n::{self, Strings};
+use crate::radar::{self, Detection};
+use crate::units::Unit;
+
+/// A zone stays active this long after the last detection inside it, so a
+/// sweeping sensor doesn't re-trigger it on every pass.
+const ZONE_HOLD: Duration = Duration::from_millis(1500);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// One `[[alarm]]` entry of the config file: anything closer than `max_cm`
+/// (and at least `min_cm` away) between `min_angle` and `max_angle`, through
+/// 0° when `min_angle` is the larger one, like 350 to 10. With a
+/// `polygon` it's anything inside that instead, angles and distances are
+/// ignored then.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AlarmZone {
+    pub name: String,
+    pub min_angle: f32,
+    pub max_angle: f32,
+    pub min_cm: f32,
+    pub max_cm: f32,
+    pub severity: Severity,
+    /// Corners as `[x, y]` in cm on the map, +x towards 0° and +y towards
+    /// 90°, for rooms where a sector doesn't fit. Needs at least three.
+    pub polygon: Vec<[f32; 2]>,
+}
+
+impl Default for AlarmZone {
+    fn default() -> Self {
+        Self {
+            name: String::from("zone"),
+            min_angle: 0.0,
+            max_angle: 180.0,
+            min_cm: 0.0,
+            max_cm: 15.0,
+            severity: Severity::Warning,
+            polygon: Vec::new(),
+        }
+    }
+}
+
+impl AlarmZone {
+    pub fn is_polygon(&self) -> bool {
+        self.polygon.len() >= 3
+    }
+
+    pub fn contains(&self, det: &Detection) -> bool {
+        if self.is_polygon() {
+            let rad = det.angle.to_radians();
+            return polygon_contains(
+                &self.polygon,
+                det.distance * rad.cos(),
+                det.distance * rad.sin(),
+            );
+        }
+        radar::in_sector(det.angle, self.min_angle, self.max_angle)
+            && (self.min_cm..=self.max_cm).contains(&det.distance)
+    }
+
+    /// Its edges for drawing, the second past 360 when it wraps through 0°.
+    pub fn sector(&self) -> (f32, f32) {
+        let width = radar::sector_width(self.min_angle, self.max_angle);
+        (self.min_angle, self.min_angle + width)
+    }
+}
+
+/// Even-odd rule: a ray from the point crosses the edges an odd number of
+/// times when it's inside, concave rooms included.
+fn polygon_contains(polygon: &[[f32; 2]], x: f32, y: f32) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let ([xi, yi], [xj, yj]) = (polygon[i], polygon[j]);
+        if (yi > y) != (yj > y) && x < xi + (y - yi) / (yj - yi) * (xj - xi) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// A zone that just went off.
+pub struct AlarmEvent {
+    pub zone: usize,
+    pub angle: f32,
+    pub distance: f32,
+}
+
+/// Watches the configured zones and reports when something enters one.
+pub struct AlarmMonitor {
+    zones: Vec<AlarmZone>,
+    /// Last time something was seen inside each zone
+    last_hit: Vec<Option<Instant>>,
+    /// Zones acknowledged while active, until they clear
+    acknowledged: Vec<bool>,
+}
+
+impl AlarmMonitor {
+    pub fn new(zones: Vec<AlarmZone>) -> Self {
+        Self {
+            last_hit: vec![None; zones.len()],
+            acknowledged: vec![false; zones.len()],
+            zones,
+        }
+    }
+
+    pub fn zones(&self) -> &[AlarmZone] {
+        &self.zones
+    }
+
+    pub fn zones_mut(&mut self) -> &mut [AlarmZone] {
+        &mut self.zones
+    }
+
+    pub fn add(&mut self, zone: AlarmZone) {
+        self.zones.push(zone);
+        self.last_hit.push(None);
+        self.acknowledged.push(false);
+    }
+
+    /// Removes the last added polygon zone around the map point. Returns
+    /// whether there was one.
+    pub fn remove_polygon_at(&mut self, x_cm: f32, y_cm: f32) -> bool {
+        let hit = self
+            .zones
+            .iter()
+            .rposition(|z| z.is_polygon() && polygon_contains(&z.polygon, x_cm, y_cm));
+        match hit {
+            Some(i) => {
+                self.zones.remove(i);
+                self.last_hit.remove(i);
+                self.acknowledged.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether something was inside zone `i` recently.
+    pub fn is_active(&self, i: usize, now: Instant) -> bool {
+        self.last_hit[i].is_some_and(|t| now.saturating_duration_since(t) < ZONE_HOLD)
+    }
+
+    /// Acknowledges every active zone, they stop flashing until they clear
+    /// and go off again. Returns how many there were.
+    pub fn acknowledge(&mut self, now: Instant) -> usize {
+        let mut count = 0;
+        for i in 0..self.zones.len() {
+            if self.is_active(i, now) && !self.acknowledged[i] {
+                self.acknowledged[i] = true;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    pub fn is_acknowledged(&self, i: usize) -> bool {
+        self.acknowledged[i]
+    }
+
+    /// Checks a new detection against every zone. Returns an event for each
+    /// zone that wasn't active before.
+    pub fn check(&mut self, det: &Detection) -> Vec<AlarmEvent> {
+        let mut events = Vec::new();
+        for i in 0..self.zones.len() {
+            if !self.zones[i].contains(det) {
+                continue;
+            }
+            if !self.is_active(i, det.time) {
+                self.acknowledged[i] = false;
+                events.push(AlarmEvent {
+                    zone: i,
+                    angle: det.angle,
+                    distance: det.distance,
+                });
+            }
+            self.last_hit[i] = Some(det.time);
+        }
+        events
+    }
+
+    /// One line for stdout.
+    pub fn describe(&self, event: &AlarmEvent, unit: Unit, strings: &Strings) -> String {
+        let zone = &self.zones[event.zone];
+        i18n::fill(
+            &strings.alarm,
+            &[
+                ("severity", strings.severity(zone.severity)),
+                ("zone", &zone.name),
+                ("distance", &unit.format(event.distance)),
+                ("angle", &format!("{:.0}", event.angle)),
+            ],
+        )
+    }
+}
+
+/// Replaces the `[[alarm]]` entries of the config file at `path` with
+/// `zones`.
+pub fn save(path: &str, zones: &[AlarmZone], profile: Option<&str>) -> Result<(), String> {
+    config::edit(path, |doc| {
+        let rounded = |x: f32| (f64::from(x) * 1000.0).round() / 1000.0;
+        let mut tables = ArrayOfTables::new();
+        for zone in zones {
+            let mut table = Table::new();
+            table["name"] = value(zone.name.as_str());
+            if zone.is_polygon() {
+                let mut polygon = Array::new();
+                for &[x, y] in &zone.polygon {
+                    polygon.push(Array::from_iter([rounded(x), rounded(y)]));
+                }
+                table["polygon"] = value(polygon);
+            } else {
+                table["min_angle"] = value(rounded(zone.min_angle));
+                table["max_angle"] = value(rounded(zone.max_angle));
+                table["min_cm"] = value(rounded(zone.min_cm));
+                table["max_cm"] = value(rounded(zone.max_cm));
+            }
+            table["severity"] = value(zone.severity.label());
+            tables.push(table);
+        }
+        config::set_entries(doc, profile, "alarm", tables);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An L shaped room: the square 0..20 with its top right quarter cut out.
+    const ROOM: [[f32; 2]; 6] = [
+        [0.0, 0.0],
+        [20.0, 0.0],
+        [20.0, 10.0],
+        [10.0, 10.0],
+        [10.0, 20.0],
+        [0.0, 20.0],
+    ];
+
+    #[test]
+    fn polygon_contains_points_inside() {
+        assert!(polygon_contains(&ROOM, 5.0, 5.0));
+        assert!(polygon_contains(&ROOM, 15.0, 5.0));
+        assert!(polygon_contains(&ROOM, 5.0, 15.0));
+    }
+
+    #[test]
+    fn polygon_leaves_out_the_concave_corner() {
+        assert!(!polygon_contains(&ROOM, 15.0, 15.0));
+        assert!(!polygon_contains(&ROOM, -1.0, 5.0));
+        assert!(!polygon_contains(&ROOM, 5.0, 25.0));
+    }
+
+    #[test]
+    fn polygon_zones_ignore_the_sector() {
+        let zone = AlarmZone {
+            min_angle: 170.0,
+            max_angle: 180.0,
+            polygon: ROOM.to_vec(),
+            ..AlarmZone::default()
+        };
+        let det = |angle: f32, distance: f32| Detection {
+            angle,
+            distance,
+            bearing: angle,
+            time: Instant::now(),
+            multi_echo: false,
+            strength: None,
+            layer: 0,
+        };
+        // 45° at 10 cm is about (7, 7), inside the room
+        assert!(zone.contains(&det(45.0, 10.0)));
+        assert!(!zone.contains(&det(45.0, 20.0)));
+    }
+
+    #[test]
+    fn fewer_than_three_corners_is_a_sector() {
+        let zone = AlarmZone {
+            polygon: vec![[0.0, 0.0], [10.0, 0.0]],
+            ..AlarmZone::default()
+        };
+        assert!(!zone.is_polygon());
+    }
+}
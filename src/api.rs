@@ -0,0 +1,442 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::{Pipeline, Update};
+use crate::source::Source;
+use crate::stats::LinkStats;
+
+/// Detections kept for `/samples`, oldest are dropped first.
+const DETECTION_CAPACITY: usize = 4096;
+
+/// A detection with wall clock time, so clients can ask for everything since
+/// the last one they saw.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Reading {
+    /// Unix seconds
+    pub time: f64,
+    pub angle: f32,
+    pub distance: f32,
+}
+
+/// Answer to `/status`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Status {
+    pub source: String,
+    pub link: &'static str,
+    pub protocol: &'static str,
+    pub frames: u64,
+    pub malformed: u64,
+    pub checksum_errors: u64,
+    pub samples_per_sec: f32,
+    pub last_reading: Option<Reading>,
+}
+
+/// Counters and gauges for `/metrics` that `/status` doesn't have.
+#[derive(Default)]
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+struct Metrics {
+    samples: u64,
+    detections: u64,
+    alarms: u64,
+    reconnects: u64,
+    rejected_out_of_range: u64,
+    rejected_spikes: u64,
+    rejected_weak: u64,
+    /// Closest detection of the last finished sweep
+    closest_cm: Option<f32>,
+    frame_seconds: f32,
+    tracks: usize,
+}
+
+/// One message to the web viewer: the samples of a frame plus what it needs
+/// to scale and label them.
+#[derive(Serialize)]
+struct LiveFrame<'a> {
+    /// `[angle, distance, echo]`
+    samples: Vec<(f32, f32, u8)>,
+    max_range_cm: Option<f32>,
+    source: &'a str,
+    link: &'static str,
+}
+
+/// Settings that can be changed through `/config`. Every field is optional in
+/// a change, so `{"max_range_cm": 100}` leaves the rest alone. Range settings
+/// are left out in headless mode, there is no plot to scale.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_range_cm: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_range: Option<bool>,
+    pub filter_enabled: Option<bool>,
+}
+
+impl Settings {
+    /// Fields set in `other` win.
+    #[cfg(feature = "http")]
+    fn merge(&mut self, other: Settings) {
+        self.max_range_cm = other.max_range_cm.or(self.max_range_cm);
+        self.auto_range = other.auto_range.or(self.auto_range);
+        self.filter_enabled = other.filter_enabled.or(self.filter_enabled);
+    }
+}
+
+/// Written by the main loop, read by the server thread.
+#[derive(Default)]
+struct Shared {
+    status: Status,
+    detections: VecDeque<Reading>,
+    settings: Settings,
+    /// Changes posted since the main loop last looked
+    pending: Option<Settings>,
+    /// Web viewers connected to `/ws`
+    clients: Vec<SyncSender<String>>,
+    metrics: Metrics,
+}
+
+/// Small HTTP server for dashboards and scripts:
+///
+/// - `GET /status`: link state, decoder counters, sample rate, last reading
+/// - `GET /samples?since=<unix seconds>`: recent detections
+/// - `GET /config`, `POST /config`: live settings, posting a JSON object
+///   with some of the fields changes them
+/// - `GET /metrics`: the same and more in the Prometheus text format
+/// - `GET /` and `/ws`: the web viewer and its sample stream, with the `web`
+///   feature
+///
+/// Requests are served on their own thread from a snapshot the main loop
+/// publishes once per frame.
+pub struct ApiServer {
+    shared: Arc<Mutex<Shared>>,
+    /// Closest detection of the sweep in progress
+    sweep_closest: Option<f32>,
+    sweeps: u64,
+}
+
+impl ApiServer {
+    #[cfg(feature = "http")]
+    pub fn start(addr: &str) -> Result<Self, String> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let thread_shared = Arc::clone(&shared);
+        std::thread::spawn(move || serve(server, thread_shared));
+        tracing::info!("HTTP API on http://{}", addr);
+        Ok(Self {
+            shared,
+            sweep_closest: None,
+            sweeps: 0,
+        })
+    }
+
+    #[cfg(not(feature = "http"))]
+    pub fn start(_addr: &str) -> Result<Self, String> {
+        Err("HTTP support is not compiled in, rebuild with --features http".to_string())
+    }
+
+    /// Hands the latest state to the server thread. The sample rate and
+    /// reconnects are the diagnostics overlay's, from `link`, and
+    /// `frame_seconds` is how long the last pass of the main loop took.
+    pub fn publish(
+        &mut self,
+        source: &dyn Source,
+        pipeline: &Pipeline,
+        update: &Update,
+        settings: Settings,
+        link: &LinkStats,
+        frame_seconds: f32,
+    ) {
+        let now = Instant::now();
+        let unix_now = unix_seconds();
+        let to_reading = |time: Instant, angle, distance| Reading {
+            time: unix_now - now.saturating_duration_since(time).as_secs_f64(),
+            angle,
+            distance,
+        };
+
+        let sweep_done = pipeline.radar.sweeps != self.sweeps;
+        let finished_closest = self.sweep_closest;
+        if sweep_done {
+            self.sweep_closest = None;
+            self.sweeps = pipeline.radar.sweeps;
+        }
+        for det in &update.detections {
+            self.sweep_closest = Some(
+                self.sweep_closest
+                    .map_or(det.distance, |c| c.min(det.distance)),
+            );
+        }
+
+        let radar = &pipeline.radar;
+        let (protocol, stats) = source.protocol_stats();
+        let rejected = pipeline.validator.stats();
+        let mut shared = self.shared.lock().unwrap();
+        let metrics = &mut shared.metrics;
+        metrics.samples += update.samples.len() as u64;
+        metrics.detections += update.detections.len() as u64;
+        metrics.alarms += update.alarms.len() as u64;
+        metrics.reconnects = u64::from(link.reconnects);
+        metrics.rejected_out_of_range = rejected.out_of_range;
+        metrics.rejected_spikes = rejected.spikes;
+        metrics.rejected_weak = rejected.weak;
+        if sweep_done {
+            metrics.closest_cm = finished_closest;
+        }
+        metrics.frame_seconds = frame_seconds;
+        metrics.tracks = pipeline.tracker.confirmed().count();
+
+        let status = &mut shared.status;
+        status.source = source.describe();
+        status.link = source.state().label();
+        status.protocol = protocol;
+        status.frames = stats.frames;
+        status.malformed = stats.malformed;
+        status.checksum_errors = stats.checksum_errors;
+        status.samples_per_sec = link.sample_rate;
+        if !update.samples.is_empty() {
+            status.last_reading = Some(to_reading(now, radar.angle, radar.distance()));
+        }
+        for det in &update.detections {
+            if shared.detections.len() == DETECTION_CAPACITY {
+                shared.detections.pop_front();
+            }
+            let reading = to_reading(det.time, det.angle, det.distance);
+            shared.detections.push_back(reading);
+        }
+        shared.settings = settings;
+
+        if !shared.clients.is_empty() && !update.samples.is_empty() {
+            let frame = LiveFrame {
+                samples: update
+                    .samples
+                    .iter()
+                    .map(|s| (s.angle, s.distance, s.echo))
+                    .collect(),
+                max_range_cm: settings.max_range_cm,
+                source: &shared.status.source,
+                link: shared.status.link,
+            };
+            if let Ok(text) = serde_json::to_string(&frame) {
+                // A full queue is a slow browser, it just misses this frame
+                shared.clients.retain(|client| {
+                    !matches!(
+                        client.try_send(text.clone()),
+                        Err(TrySendError::Disconnected(_))
+                    )
+                });
+            }
+        }
+    }
+
+    /// Settings posted to `/config` since the last call.
+    pub fn take_changes(&self) -> Option<Settings> {
+        self.shared.lock().unwrap().pending.take()
+    }
+}
+
+fn unix_seconds() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+#[cfg(feature = "http")]
+fn serve(server: tiny_http::Server, shared: Arc<Mutex<Shared>>) {
+    use tiny_http::{Header, Method, Response};
+
+    for mut request in server.incoming_requests() {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        #[cfg(feature = "web")]
+        if path == "/ws" {
+            if let Some(client) = crate::web::accept(request) {
+                shared.lock().unwrap().clients.push(client);
+            }
+            continue;
+        }
+        let result = match (request.method(), path) {
+            #[cfg(feature = "web")]
+            (Method::Get, "/") => Ok(("text/html; charset=utf-8", crate::web::PAGE.to_string())),
+            (Method::Get, "/status") => json(&shared.lock().unwrap().status),
+            (Method::Get, "/samples") => {
+                let since = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("since="))
+                    .map(|v| v.parse::<f64>());
+                match since {
+                    Some(Err(_)) => Err((400, "since must be a Unix timestamp".to_string())),
+                    since => {
+                        let since = since.and_then(Result::ok).unwrap_or(0.0);
+                        let shared = shared.lock().unwrap();
+                        let readings: Vec<&Reading> = shared
+                            .detections
+                            .iter()
+                            .filter(|r| r.time > since)
+                            .collect();
+                        json(&readings)
+                    }
+                }
+            }
+            (Method::Get, "/config") => json(&shared.lock().unwrap().settings),
+            (Method::Get, "/metrics") => Ok((
+                "text/plain; version=0.0.4",
+                render_metrics(&shared.lock().unwrap()),
+            )),
+            (Method::Post | Method::Put, "/config") => {
+                let mut body = String::new();
+                match std::io::Read::read_to_string(request.as_reader(), &mut body) {
+                    Err(e) => Err((400, e.to_string())),
+                    Ok(_) => match serde_json::from_str::<Settings>(&body) {
+                        Err(e) => Err((400, format!("Invalid settings: {}", e))),
+                        Ok(change) => {
+                            let mut shared = shared.lock().unwrap();
+                            shared.pending.get_or_insert_default().merge(change);
+                            // Answer with what it will look like once applied
+                            shared.settings.merge(change);
+                            json(&shared.settings)
+                        }
+                    },
+                }
+            }
+            _ => Err((404, "Not found".to_string())),
+        };
+
+        let response = match result {
+            Ok((content_type, body)) => Response::from_string(body).with_header(
+                Header::from_bytes("Content-Type", content_type).expect("static header is valid"),
+            ),
+            Err((code, msg)) => Response::from_string(msg).with_status_code(code),
+        };
+        // The client hanging up early is its problem
+        let _ = request.respond(response);
+    }
+}
+
+#[cfg(feature = "http")]
+fn json<T: Serialize + ?Sized>(value: &T) -> Result<(&'static str, String), (u16, String)> {
+    serde_json::to_string(value)
+        .map(|body| ("application/json", body))
+        .map_err(|e| (500, e.to_string()))
+}
+
+/// Prometheus text exposition format.
+#[cfg(feature = "http")]
+fn render_metrics(shared: &Shared) -> String {
+    use std::fmt::Write;
+
+    use crate::source::LinkState;
+
+    let status = &shared.status;
+    let m = &shared.metrics;
+    let source = status.source.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+        let _ = writeln!(out, "# HELP radar_{} {}", name, help);
+        let _ = writeln!(out, "# TYPE radar_{} {}", name, kind);
+        let _ = writeln!(out, "radar_{}{{source=\"{}\"}} {}", name, source, value);
+    };
+
+    let up = status.link == LinkState::Connected.label();
+    metric(
+        "link_up",
+        "gauge",
+        "1 while the source is connected",
+        f64::from(u8::from(up)),
+    );
+    metric(
+        "reconnects_total",
+        "counter",
+        "Times the link came back",
+        m.reconnects as f64,
+    );
+    metric(
+        "samples_total",
+        "counter",
+        "Samples that passed validation",
+        m.samples as f64,
+    );
+    metric(
+        "samples_per_second",
+        "gauge",
+        "Sample rate over the last second",
+        f64::from(status.samples_per_sec),
+    );
+    metric(
+        "detections_total",
+        "counter",
+        "Echoes that hit something",
+        m.detections as f64,
+    );
+    metric(
+        "frames_total",
+        "counter",
+        "Frames decoded",
+        status.frames as f64,
+    );
+    metric(
+        "malformed_frames_total",
+        "counter",
+        "Frames that could not be parsed",
+        status.malformed as f64,
+    );
+    metric(
+        "checksum_errors_total",
+        "counter",
+        "Frames with a bad checksum",
+        status.checksum_errors as f64,
+    );
+    metric(
+        "rejected_out_of_range_total",
+        "counter",
+        "Samples dropped as out of range",
+        m.rejected_out_of_range as f64,
+    );
+    metric(
+        "rejected_spikes_total",
+        "counter",
+        "Samples dropped as spikes",
+        m.rejected_spikes as f64,
+    );
+    metric(
+        "rejected_weak_total",
+        "counter",
+        "Samples dropped below the minimum strength",
+        m.rejected_weak as f64,
+    );
+    metric(
+        "alarms_total",
+        "counter",
+        "Alarm zone events",
+        m.alarms as f64,
+    );
+    metric("tracks", "gauge", "Confirmed tracks", m.tracks as f64);
+    if let Some(closest) = m.closest_cm {
+        metric(
+            "closest_cm",
+            "gauge",
+            "Closest detection of the last sweep",
+            f64::from(closest),
+        );
+    }
+    if let Some(last) = status.last_reading {
+        metric(
+            "last_sample_age_seconds",
+            "gauge",
+            "Time since the last sample",
+            (unix_seconds() - last.time).max(0.0),
+        );
+    }
+    metric(
+        "frame_seconds",
+        "gauge",
+        "Duration of the last main loop pass",
+        f64::from(m.frame_seconds),
+    );
+    out
+}
@@ -0,0 +1,178 @@
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::alarm::Severity;
+
+const SAMPLE_RATE: u32 = 22050;
+const BEEP_HZ: f32 = 880.0;
+const BEEP_SECONDS: f32 = 0.25;
+const PING_HZ: f32 = 1200.0;
+const PING_SECONDS: f32 = 0.6;
+const TICK_HZ: f32 = 2000.0;
+const TICK_SECONDS: f32 = 0.03;
+const COLLISION_HZ: f32 = 660.0;
+const COLLISION_SECONDS: f32 = 0.12;
+/// Pings closer together than this would just be noise.
+const MIN_PING_INTERVAL: Duration = Duration::from_millis(150);
+
+/// `[audio]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Start muted when false, M toggles it
+    pub enabled: bool,
+    /// 0..1, applies to every sound
+    pub volume: f32,
+    /// Sonar ping on detections, higher and louder the closer they are
+    pub ping: bool,
+    /// Quiet tick every `tick_step_deg` of sweep
+    pub tick: bool,
+    pub tick_step_deg: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.8,
+            ping: false,
+            tick: false,
+            tick_step_deg: 10.0,
+        }
+    }
+}
+
+/// Sounds generated at startup, there are no audio files to ship.
+pub struct Audio<'a> {
+    config: AudioConfig,
+    pub muted: bool,
+    beep: Sound<'a>,
+    ping: Sound<'a>,
+    tick: Sound<'a>,
+    collision: Sound<'a>,
+    last_ping: Option<Instant>,
+    last_collision: Option<Instant>,
+    /// Sweep angle divided by the tick step, a tick plays when it changes
+    last_tick_step: Option<i32>,
+}
+
+impl<'a> Audio<'a> {
+    pub fn load(device: &'a RaylibAudio, config: AudioConfig) -> Option<Self> {
+        let sound = |freq_hz, seconds, decay| {
+            let wave = device
+                .new_wave_from_memory(".wav", &tone_wav(freq_hz, seconds, decay))
+                .ok()?;
+            device.new_sound_from_wave(&wave).ok()
+        };
+        Some(Self {
+            muted: !config.enabled,
+            beep: sound(BEEP_HZ, BEEP_SECONDS, 0.0)?,
+            ping: sound(PING_HZ, PING_SECONDS, 8.0)?,
+            tick: sound(TICK_HZ, TICK_SECONDS, 0.0)?,
+            collision: sound(COLLISION_HZ, COLLISION_SECONDS, 0.0)?,
+            config,
+            last_ping: None,
+            last_collision: None,
+            last_tick_step: None,
+        })
+    }
+
+    /// Alarm beep, higher pitched the more severe the zone is.
+    pub fn alarm(&self, severity: Severity) {
+        if self.muted {
+            return;
+        }
+        let pitch = match severity {
+            Severity::Info => 0.75,
+            Severity::Warning => 1.0,
+            Severity::Critical => 1.5,
+        };
+        self.beep.set_volume(self.config.volume);
+        self.beep.set_pitch(pitch);
+        self.beep.play();
+    }
+
+    /// Sonar ping for a detection at `fraction` of the max range.
+    pub fn ping(&mut self, fraction: f32, now: Instant) {
+        if self.muted || !self.config.ping {
+            return;
+        }
+        if self
+            .last_ping
+            .is_some_and(|t| now.saturating_duration_since(t) < MIN_PING_INTERVAL)
+        {
+            return;
+        }
+        self.last_ping = Some(now);
+
+        let closeness = 1.0 - fraction.clamp(0.0, 1.0);
+        self.ping.set_pitch(0.7 + 0.8 * closeness);
+        self.ping
+            .set_volume(self.config.volume * (0.3 + 0.7 * closeness));
+        self.ping.play();
+    }
+
+    /// Collision warning while something is on its way in, `urgency` from 0
+    /// to 1: the beeps come faster and go up in pitch the closer it gets.
+    pub fn collision(&mut self, urgency: f32, now: Instant) {
+        if self.muted {
+            return;
+        }
+        let urgency = urgency.clamp(0.0, 1.0);
+        let interval = Duration::from_secs_f32(0.6 - 0.48 * urgency);
+        if self
+            .last_collision
+            .is_some_and(|t| now.saturating_duration_since(t) < interval)
+        {
+            return;
+        }
+        self.last_collision = Some(now);
+        self.collision.set_volume(self.config.volume);
+        self.collision.set_pitch(0.8 + 1.2 * urgency);
+        self.collision.play();
+    }
+
+    /// Ticks whenever the sweep passes another `tick_step_deg`.
+    pub fn sweep(&mut self, angle: f32) {
+        let step = (angle / self.config.tick_step_deg.max(1.0)).floor() as i32;
+        let moved = self.last_tick_step.is_some_and(|s| s != step);
+        self.last_tick_step = Some(step);
+        if moved && !self.muted && self.config.tick {
+            self.tick.set_volume(self.config.volume * 0.3);
+            self.tick.play();
+        }
+    }
+}
+
+/// A sine tone as a 16-bit mono WAV file, with short fades so it doesn't
+/// click. `decay` > 0 makes it ring out exponentially like a sonar ping.
+fn tone_wav(freq_hz: f32, seconds: f32, decay: f32) -> Vec<u8> {
+    let frames = (SAMPLE_RATE as f32 * seconds) as u32;
+    let fade = (SAMPLE_RATE / 100) as f32;
+    let data_len = frames * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+
+    for i in 0..frames {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let envelope =
+            (i as f32 / fade).min((frames - i) as f32 / fade).min(1.0) * (-decay * t).exp();
+        let sample = (t * freq_hz * std::f32::consts::TAU).sin() * envelope * 0.8;
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
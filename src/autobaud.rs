@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::protocol::{ProtocolKind, ProtocolStats, Raw};
+use crate::source::{LinkState, Sample, SerialSource, Source};
+
+/// Tried in turn by the auto mode, the rates hobby firmware actually uses.
+const CANDIDATES: [u32; 4] = [9600, 19200, 57600, 115200];
+/// How long each rate gets to produce frames before the next one is tried.
+const TRY_FOR: Duration = Duration::from_secs(2);
+/// Frames that have to decode at a rate before it's taken, one or two can
+/// come out of noise by chance.
+const MIN_FRAMES: u64 = 5;
+
+/// Baud rate given on the command line or picked in the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Baud {
+    Fixed(u32),
+    /// Find the rate that produces parseable frames
+    Auto,
+}
+
+impl Baud {
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Some(Baud::Auto);
+        }
+        s.parse().ok().map(Baud::Fixed)
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Baud::Fixed(rate) => rate.to_string(),
+            Baud::Auto => String::from("auto"),
+        }
+    }
+}
+
+/// Opens a serial port at a fixed rate, or searching for it.
+pub fn open(port_name: &str, baud: Baud, protocol: ProtocolKind) -> Box<dyn Source> {
+    match baud {
+        Baud::Fixed(rate) => Box::new(SerialSource::open(port_name, rate, protocol.create())),
+        Baud::Auto => Box::new(AutoBaudSource::open(port_name, protocol)),
+    }
+}
+
+/// A serial port opened at each common rate in turn until one of them
+/// decodes into frames. A wrong rate reads as garbage that the protocol
+/// counts as malformed, the right one as frames. Keeps cycling until the
+/// device talks, so it also works when it's switched on later.
+pub struct AutoBaudSource {
+    port_name: String,
+    protocol: ProtocolKind,
+    port: SerialSource,
+    /// Index into CANDIDATES being tried
+    candidate: usize,
+    since: Instant,
+    detected: Option<u32>,
+}
+
+impl AutoBaudSource {
+    pub fn open(port_name: &str, protocol: ProtocolKind) -> Self {
+        info!("Detecting the baud rate of {}", port_name);
+        Self {
+            port_name: port_name.to_string(),
+            protocol,
+            port: SerialSource::open(port_name, CANDIDATES[0], protocol.create()),
+            candidate: 0,
+            since: Instant::now(),
+            detected: None,
+        }
+    }
+
+    fn rate(&self) -> u32 {
+        CANDIDATES[self.candidate]
+    }
+
+    fn try_next(&mut self) {
+        self.candidate = (self.candidate + 1) % CANDIDATES.len();
+        if self.candidate == 0 {
+            warn!(
+                "No baud rate produced frames on {}, trying them again.",
+                self.port_name
+            );
+        }
+        // The old handle has to be closed before the port can be reopened
+        self.port.close();
+        self.port = SerialSource::open(&self.port_name, self.rate(), self.protocol.create());
+        self.since = Instant::now();
+    }
+}
+
+impl Source for AutoBaudSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let samples = self.port.poll();
+        if self.detected.is_some() {
+            return samples;
+        }
+        let (_, stats) = self.port.protocol_stats();
+        if stats.frames >= MIN_FRAMES && stats.frames > stats.malformed + stats.checksum_errors {
+            info!("Detected {} baud on {}", self.rate(), self.port_name);
+            self.detected = Some(self.rate());
+            return samples;
+        }
+        if self.since.elapsed() >= TRY_FOR {
+            self.try_next();
+        }
+        // Whatever decoded at a rate that isn't settled yet may be noise
+        Vec::new()
+    }
+
+    fn state(&self) -> LinkState {
+        match (self.detected, self.port.state()) {
+            (None, LinkState::Connected) => LinkState::Connecting,
+            (_, state) => state,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.detected {
+            Some(rate) => format!("{} @ {} baud (detected)", self.port_name, rate),
+            None => format!("{} (trying {} baud)", self.port_name, self.rate()),
+        }
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        self.port.protocol_stats()
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        self.port.send(line)
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        self.port.messages()
+    }
+
+    fn tap(&mut self, on: bool) {
+        self.port.tap(on);
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        self.port.raw()
+    }
+}
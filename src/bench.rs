@@ -0,0 +1,234 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::protocol::ProtocolStats;
+use crate::source::{LinkState, Sample, Source};
+
+/// Full turns a second of the simulated lidar.
+const SWEEP_HZ: f32 = 10.0;
+/// Samples that fell further behind than this are skipped, so one slow
+/// frame doesn't snowball into ever bigger batches.
+const MAX_BACKLOG_S: f64 = 0.5;
+/// Frames at the start that are left out of the numbers, while textures
+/// are created and shaders compiled.
+const WARMUP: Duration = Duration::from_secs(1);
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+/// Set by [`Bench::new`], outside of `--bench` nothing is counted
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+/// The system allocator, counting allocations so `--bench` can tell how many
+/// a frame makes. Other threads' allocations count too. Without `--bench`
+/// all it costs is a relaxed load of a flag that never changes, no shared
+/// counter is written to.
+pub struct CountingAlloc;
+
+fn count() {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Synthetic lidar for `--bench`: a rectangular room with a target circling
+/// in it, sampled at any rate, scaled to fit the range.
+pub struct BenchSource {
+    points_per_s: f64,
+    max_range_cm: f32,
+    started: Instant,
+    emitted: u64,
+    /// xorshift state for the noise
+    noise: u32,
+}
+
+impl BenchSource {
+    pub fn new(points_per_s: f64, max_range_cm: f32) -> Self {
+        Self {
+            points_per_s: points_per_s.max(1.0),
+            max_range_cm,
+            started: Instant::now(),
+            emitted: 0,
+            noise: 0x9E37_79B9,
+        }
+    }
+
+    /// -1 to 1.
+    fn noise(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// What the beam at `angle` hits `time` seconds in.
+    fn distance(&mut self, angle: f32, time: f32) -> f32 {
+        let (half_w, half_h) = (self.max_range_cm * 0.8, self.max_range_cm * 0.5);
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let wall = (half_w / cos.abs()).min(half_h / sin.abs());
+
+        // A target going round at half the range, once every five seconds
+        let (target_sin, target_cos) = (time * TAU / 5.0).sin_cos();
+        let (cx, cy) = (target_cos * half_h * 0.6, target_sin * half_h * 0.6);
+        let radius = self.max_range_cm * 0.05;
+        let along = cx * cos + cy * sin;
+        let off_sq = cx * cx + cy * cy - along * along;
+        let target = (along > 0.0 && off_sq < radius * radius)
+            .then(|| along - (radius * radius - off_sq).sqrt());
+
+        let distance = target.map_or(wall, |t| t.min(wall));
+        distance + self.noise() * self.max_range_cm * 0.005
+    }
+}
+
+impl Source for BenchSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let due = (elapsed * self.points_per_s) as u64;
+        let behind = ((elapsed - MAX_BACKLOG_S) * self.points_per_s).max(0.0) as u64;
+        self.emitted = self.emitted.max(behind);
+        let mut samples = Vec::with_capacity((due - self.emitted.min(due)) as usize);
+        while self.emitted < due {
+            let time = (self.emitted as f64 / self.points_per_s) as f32;
+            let angle = (time * 360.0 * SWEEP_HZ).rem_euclid(360.0);
+            let distance = self.distance(angle, time);
+            samples.push(Sample::new(angle, distance));
+            self.emitted += 1;
+        }
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        LinkState::Connected
+    }
+
+    fn describe(&self) -> String {
+        format!("bench {} points/s", self.points_per_s)
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        (
+            "bench",
+            ProtocolStats {
+                frames: self.emitted,
+                ..ProtocolStats::default()
+            },
+        )
+    }
+}
+
+/// Frame times and allocations of a `--bench` run.
+pub struct Bench {
+    started: Instant,
+    duration: Duration,
+    /// Milliseconds
+    frame_times: Vec<f32>,
+    allocations: Vec<u64>,
+    last_allocations: u64,
+    samples: u64,
+}
+
+impl Bench {
+    pub fn new(seconds: f32, now: Instant) -> Self {
+        COUNTING.store(true, Ordering::Relaxed);
+        Self {
+            started: now,
+            duration: WARMUP + Duration::from_secs_f32(seconds.max(1.0)),
+            frame_times: Vec::new(),
+            allocations: Vec::new(),
+            last_allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            samples: 0,
+        }
+    }
+
+    /// Call once a frame with how long the last one took and the samples
+    /// that came in. Returns `true` once the run is over.
+    pub fn frame(&mut self, frame_time: f32, samples: usize, now: Instant) -> bool {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let made = allocations - self.last_allocations;
+        self.last_allocations = allocations;
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= WARMUP {
+            self.frame_times.push(frame_time * 1000.0);
+            self.allocations.push(made);
+            self.samples += samples as u64;
+        }
+        elapsed >= self.duration
+    }
+
+    /// Percentiles of the frame times and allocations, for stdout.
+    pub fn report(&self) -> String {
+        let mut times = self.frame_times.clone();
+        times.sort_by(f32::total_cmp);
+        let mut allocations = self.allocations.clone();
+        allocations.sort_unstable();
+        let seconds = (self.duration - WARMUP).as_secs_f32();
+        let frames = times.len();
+        format!(
+            "Bench: {} frames in {:.0} s ({:.1} FPS), {:.0} samples/s\n\
+             Frame time ms: p50 {:.2}  p90 {:.2}  p99 {:.2}  p99.9 {:.2}  max {:.2}\n\
+             Allocations per frame: p50 {}  p99 {}  max {}",
+            frames,
+            seconds,
+            frames as f32 / seconds,
+            self.samples as f32 / seconds,
+            percentile(&times, 0.5),
+            percentile(&times, 0.9),
+            percentile(&times, 0.99),
+            percentile(&times, 0.999),
+            times.last().copied().unwrap_or_default(),
+            percentile(&allocations, 0.5),
+            percentile(&allocations, 0.99),
+            allocations.last().copied().unwrap_or_default(),
+        )
+    }
+}
+
+/// Nearest rank percentile of sorted values, `p` from 0 to 1.
+fn percentile<T: Copy + Default>(sorted: &[T], p: f32) -> T {
+    if sorted.is_empty() {
+        return T::default();
+    }
+    let rank = ((sorted.len() - 1) as f32 * p).round() as usize;
+    sorted[rank]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_of_nothing_is_the_default() {
+        assert_eq!(percentile::<f32>(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn percentile_takes_the_nearest_rank() {
+        let sorted = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+        assert_eq!(percentile(&sorted, 0.0), 1);
+        assert_eq!(percentile(&sorted, 0.5), 6);
+        assert_eq!(percentile(&sorted, 0.9), 10);
+        assert_eq!(percentile(&sorted, 1.0), 11);
+    }
+
+    #[test]
+    fn percentile_of_one_value_is_that_value() {
+        assert_eq!(percentile(&[4.5], 0.999), 4.5);
+    }
+}
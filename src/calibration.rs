@@ -0,0 +1,350 @@
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+use toml_edit::value;
+
+use crate::config;
+use crate::source::Sample;
+use crate::ui::UiScale;
+use crate::units::Unit;
+
+/// A capture gives up after this long without two full sweeps, so a sensor
+/// that doesn't sweep is captured too.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+const CAPTURE_SWEEPS: u64 = 2;
+/// Share of the captured readings, nearest first, taken as the target.
+const TARGET_SHARE: f32 = 0.2;
+/// Two points closer than this can't give a scale, only an offset.
+const MIN_SPREAD: f32 = 1.0;
+const WIDTH: f32 = 460.0;
+const HEIGHT: f32 = 330.0;
+const TEXT_SIZE: i32 = 16;
+
+/// `[calibration]` section of the config file, turns what the sensor
+/// reports into what's really there: `true = reported * scale + offset`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Calibration {
+    pub angle_offset_deg: f32,
+    pub angle_scale: f32,
+    pub distance_offset_cm: f32,
+    pub distance_scale: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            angle_offset_deg: 0.0,
+            angle_scale: 1.0,
+            distance_offset_cm: 0.0,
+            distance_scale: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    /// Nothing heard stays nothing heard, whatever the distance offset, and
+    /// angles stay within one turn.
+    pub fn apply(&self, sample: Sample) -> Sample {
+        let distance = if sample.distance > 0.0 {
+            (sample.distance * self.distance_scale + self.distance_offset_cm).max(0.0)
+        } else {
+            sample.distance
+        };
+        Sample {
+            angle: (sample.angle * self.angle_scale + self.angle_offset_deg).rem_euclid(360.0),
+            distance,
+            ..sample
+        }
+    }
+
+    /// Offsets from one target, or offsets and scales from two. `points` are
+    /// (reported angle, reported distance, true angle, true distance). Two
+    /// targets that would flip or collapse the readings, a scale of 0 or
+    /// below, are usually the wrong way round and rejected.
+    fn fit(points: &[(f32, f32, f32, f32)]) -> Result<Self, String> {
+        let line = |a: (f32, f32), b: Option<(f32, f32)>| match b {
+            Some(b) if (b.0 - a.0).abs() >= MIN_SPREAD => {
+                let scale = (b.1 - a.1) / (b.0 - a.0);
+                (a.1 - a.0 * scale, scale)
+            }
+            _ => (a.1 - a.0, 1.0),
+        };
+        let Some(&(ra, rd, ta, td)) = points.first() else {
+            return Ok(Self::default());
+        };
+        let second = points.get(1);
+        let (angle_offset_deg, angle_scale) = line((ra, ta), second.map(|p| (p.0, p.2)));
+        let (distance_offset_cm, distance_scale) = line((rd, td), second.map(|p| (p.1, p.3)));
+        for (what, scale) in [("angle", angle_scale), ("distance", distance_scale)] {
+            if scale <= 0.0 || !scale.is_finite() {
+                return Err(format!(
+                    "The targets give a {} scale of {:.3}, start over",
+                    what, scale
+                ));
+            }
+        }
+        Ok(Self {
+            angle_offset_deg,
+            angle_scale,
+            distance_offset_cm,
+            distance_scale,
+        })
+    }
+}
+
+struct Capture {
+    started: Instant,
+    first_sweep: u64,
+    /// Raw angle and distance of everything heard
+    readings: Vec<(f32, f32)>,
+}
+
+/// Calibration wizard: put a target where you know its angle and
+/// distance, capture what the sensor reports, optionally a second target for
+/// the scales, then apply. The target has to be the nearest thing the
+/// sensor sees while capturing.
+#[derive(Default)]
+pub struct CalibrationWizard {
+    pub open: bool,
+    true_angle: f32,
+    true_distance: f32,
+    capture: Option<Capture>,
+    /// Capture was clicked, it starts with the next samples
+    capture_requested: bool,
+    /// (reported angle, reported distance, true angle, true distance)
+    points: Vec<(f32, f32, f32, f32)>,
+    status: String,
+    /// Window title, naming the key that opens it
+    title: String,
+}
+
+impl CalibrationWizard {
+    /// Opens the wizard, `key` is the label of the key that toggles it.
+    pub fn show(&mut self, key: &str) {
+        self.title = format!("Calibration ({key})");
+        self.start_over();
+    }
+
+    fn start_over(&mut self) {
+        self.open = true;
+        self.points.clear();
+        self.capture = None;
+        self.capture_requested = false;
+        self.true_angle = 90.0;
+        self.true_distance = 50.0;
+        self.status = String::from("Place a target and capture it");
+    }
+
+    /// Feeds the raw samples, before calibration, while a capture runs.
+    /// `sweep` is the pipeline's sweep count.
+    pub fn record(&mut self, samples: &[Sample], sweep: u64, now: Instant) {
+        if std::mem::take(&mut self.capture_requested) {
+            self.capture = Some(Capture {
+                started: now,
+                first_sweep: sweep,
+                readings: Vec::new(),
+            });
+        }
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        capture.readings.extend(
+            samples
+                .iter()
+                .filter(|s| s.distance > 0.0 && s.layer == 0)
+                .map(|s| (s.angle, s.distance)),
+        );
+        let done = sweep >= capture.first_sweep + CAPTURE_SWEEPS
+            || now.saturating_duration_since(capture.started) >= CAPTURE_TIMEOUT;
+        if !done {
+            return;
+        }
+        let mut readings = std::mem::take(&mut capture.readings);
+        self.capture = None;
+        if readings.is_empty() {
+            self.status = String::from("Nothing heard, try again");
+            return;
+        }
+        readings.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let take = ((readings.len() as f32 * TARGET_SHARE).ceil() as usize).max(1);
+        let target = &readings[..take];
+        let angle = target.iter().map(|r| r.0).sum::<f32>() / take as f32;
+        let distance = target.iter().map(|r| r.1).sum::<f32>() / take as f32;
+        self.points
+            .push((angle, distance, self.true_angle, self.true_distance));
+        self.status = format!(
+            "Target {}: read {:.1} deg, {:.1} cm",
+            self.points.len(),
+            angle,
+            distance
+        );
+    }
+
+    /// Draws the wizard. Returns the calibration once Apply is clicked.
+    pub fn draw<D: RaylibDraw>(
+        &mut self,
+        d: &mut D,
+        now: Instant,
+        unit: Unit,
+        screen_w: f32,
+        screen_h: f32,
+        ui: UiScale,
+    ) -> Option<Calibration> {
+        if !self.open {
+            return None;
+        }
+        let x = (screen_w - ui.px(WIDTH)) / 2.0;
+        let y = (screen_h - ui.px(HEIGHT)) / 2.0;
+        // Laid out at the designed size, from the top left corner
+        let rect = |rx: f32, ry: f32, w: f32, h: f32| {
+            Rectangle::new(x + ui.px(rx), y + ui.px(ry), ui.px(w), ui.px(h))
+        };
+        d.gui_set_style(
+            GuiControl::DEFAULT,
+            GuiDefaultProperty::TEXT_SIZE,
+            ui.font(TEXT_SIZE),
+        );
+        if d.gui_window_box(rect(0.0, 0.0, WIDTH, HEIGHT), &self.title) {
+            self.open = false;
+            self.capture = None;
+            self.capture_requested = false;
+            return None;
+        }
+
+        let label_w = 130.0;
+        let slider = |row: f32| rect(10.0 + label_w, row, WIDTH - label_w - 90.0, 24.0);
+        d.gui_slider_bar(
+            slider(40.0),
+            "True angle",
+            &format!("{:.0} deg", self.true_angle),
+            &mut self.true_angle,
+            0.0,
+            360.0,
+        );
+        self.true_angle = self.true_angle.round();
+        d.gui_slider_bar(
+            slider(70.0),
+            "True distance",
+            &unit.format(self.true_distance),
+            &mut self.true_distance,
+            1.0,
+            500.0,
+        );
+        self.true_distance = self.true_distance.round();
+
+        let status = match &self.capture {
+            Some(capture) => format!(
+                "Capturing... {:.0} s",
+                now.saturating_duration_since(capture.started).as_secs_f32()
+            ),
+            None if self.capture_requested => String::from("Capturing..."),
+            None => self.status.clone(),
+        };
+        d.gui_label(rect(10.0, 110.0, WIDTH - 20.0, 24.0), &status);
+        for (i, p) in self.points.iter().enumerate() {
+            d.gui_label(
+                rect(10.0, 140.0 + i as f32 * 26.0, WIDTH - 20.0, 24.0),
+                &format!(
+                    "{}: read {:.1} deg {:.1} cm, really {:.0} deg {:.0} cm",
+                    i + 1,
+                    p.0,
+                    p.1,
+                    p.2,
+                    p.3
+                ),
+            );
+        }
+        let fitted = Calibration::fit(&self.points);
+        if !self.points.is_empty() {
+            let text = match &fitted {
+                Ok(fitted) => format!(
+                    "angle x{:.3} {:+.1} deg, distance x{:.3} {:+.1} cm",
+                    fitted.angle_scale,
+                    fitted.angle_offset_deg,
+                    fitted.distance_scale,
+                    fitted.distance_offset_cm
+                ),
+                Err(e) => e.clone(),
+            };
+            d.gui_label(rect(10.0, 200.0, WIDTH - 20.0, 24.0), &text);
+        }
+
+        let buttons_y = HEIGHT - 50.0;
+        let idle = self.capture.is_none() && !self.capture_requested;
+        if d.gui_button(rect(10.0, buttons_y, 140.0, 36.0), "Capture")
+            && idle
+            && self.points.len() < 2
+        {
+            self.capture_requested = true;
+        }
+        if d.gui_button(rect(160.0, buttons_y, 140.0, 36.0), "Start over") {
+            self.start_over();
+        }
+        if d.gui_button(
+            rect(WIDTH - 150.0, buttons_y, 140.0, 36.0),
+            "Apply and save",
+        ) && !self.points.is_empty()
+            && idle
+            && let Ok(fitted) = fitted
+        {
+            self.open = false;
+            return Some(fitted);
+        }
+        None
+    }
+}
+
+/// Writes `[calibration]` into the config file at `path`.
+pub fn save(path: &str, calibration: &Calibration, profile: Option<&str>) -> Result<(), String> {
+    config::edit(path, |doc| {
+        let section = config::section(doc, profile);
+        let rounded = |x: f32| (f64::from(x) * 1000.0).round() / 1000.0;
+        section["calibration"]["angle_offset_deg"] = value(rounded(calibration.angle_offset_deg));
+        section["calibration"]["angle_scale"] = value(rounded(calibration.angle_scale));
+        section["calibration"]["distance_offset_cm"] =
+            value(rounded(calibration.distance_offset_cm));
+        section["calibration"]["distance_scale"] = value(rounded(calibration.distance_scale));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_target_gives_offsets() {
+        let fitted = Calibration::fit(&[(88.0, 48.0, 90.0, 50.0)]).unwrap();
+        assert_eq!(fitted.angle_offset_deg, 2.0);
+        assert_eq!(fitted.distance_offset_cm, 2.0);
+        assert_eq!((fitted.angle_scale, fitted.distance_scale), (1.0, 1.0));
+    }
+
+    #[test]
+    fn two_targets_give_scales() {
+        let fitted =
+            Calibration::fit(&[(40.0, 20.0, 45.0, 25.0), (80.0, 40.0, 90.0, 50.0)]).unwrap();
+        assert!((fitted.angle_scale - 1.125).abs() < 1e-4);
+        assert!((fitted.distance_scale - 1.25).abs() < 1e-4);
+        let sample = fitted.apply(Sample::new(80.0, 40.0));
+        assert!((sample.angle - 90.0).abs() < 1e-3);
+        assert!((sample.distance - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rejects_a_flipped_scale() {
+        let points = [(40.0, 20.0, 90.0, 25.0), (80.0, 40.0, 45.0, 50.0)];
+        assert!(Calibration::fit(&points).is_err());
+    }
+
+    #[test]
+    fn keeps_angles_within_a_turn() {
+        let calibration = Calibration {
+            angle_offset_deg: 10.0,
+            ..Calibration::default()
+        };
+        assert_eq!(calibration.apply(Sample::new(355.0, 20.0)).angle, 5.0);
+        assert_eq!(calibration.apply(Sample::new(0.0, 0.0)).distance, 0.0);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use raylib::prelude::*;
+
+use crate::ui::UiScale;
+
+/// UTC wall clock time split into its parts, there is no date crate to lean on.
+pub struct Timestamp {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millis: u32,
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let secs = since_epoch.as_secs() as i64;
+        let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+        let time = secs.rem_euclid(86_400) as u32;
+        Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+            millis: since_epoch.subsec_millis(),
+        }
+    }
+
+    /// "2026-01-21 14:23:09 UTC", for overlays.
+    pub fn display(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+
+    /// "20260121_142309_123", for file names.
+    pub fn file_stem(&self) -> String {
+        format!(
+            "{:04}{:02}{:02}_{:02}{:02}{:02}_{:03}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second, self.millis
+        )
+    }
+}
+
+/// Days since 1970-01-01 to (year, month, day), Howard Hinnant's algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Draws the annotation lines in a box in the top right corner, so they're
+/// part of the frame that gets saved.
+pub fn draw_annotation<D>(d: &mut D, lines: &[String], screen_w: f32, ui: UiScale, color: Color)
+where
+    D: RaylibDraw + Deref<Target = RaylibHandle>,
+{
+    let font_size = ui.font(18);
+    let line_height = ui.pxi(22);
+    let width = lines
+        .iter()
+        .map(|l| d.measure_text(l, font_size))
+        .max()
+        .unwrap_or(0)
+        + 16;
+    let x = screen_w as i32 - width - 10;
+    d.draw_rectangle(
+        x,
+        10,
+        width,
+        lines.len() as i32 * line_height + 12,
+        Color::BLACK.alpha(0.7),
+    );
+    for (i, line) in lines.iter().enumerate() {
+        d.draw_text(line, x + 8, 16 + i as i32 * line_height, font_size, color);
+    }
+}
+
+/// Saves what is on screen right now as a PNG in `dir`. Has to be called
+/// while drawing, before the frame is presented.
+pub fn save_screenshot(
+    rl: &RaylibHandle,
+    thread: &RaylibThread,
+    dir: &Path,
+    stamp: &Timestamp,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("radar_{}.png", stamp.file_stem()));
+    let image = rl.load_image_from_screen(thread);
+    image.export_image(&path.to_string_lossy());
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(format!("Failed to write {}", path.display()))
+    }
+}
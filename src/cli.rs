@@ -0,0 +1,333 @@
+use crate::autobaud::Baud;
+use crate::plugin;
+use crate::protocol::ProtocolKind;
+use crate::report::{ReportFormat, Request};
+use crate::store::{Command, DEFAULT_DB_PATH, ExportFormat};
+
+pub const USAGE: &str = "\
+Usage: radar [PORT BAUD] [OPTIONS]
+       radar sessions [--db <PATH>]
+       radar export --session <ID> [--format csv|jsonl] [--alarms] [--db <PATH>]
+       radar trim --keep <N> [--db <PATH>]
+       radar report (--session <ID> [--db <PATH>] | --log <CSV>) [--format html|md] [--out <PATH>]
+                     [--against <ID|CSV>]
+
+Without PORT and BAUD the port is picked in the window (or asked for on stdin
+with --headless). BAUD can be auto to try the common rates until one of them
+produces data. PORT can also be usb:VID[:PID] (hex) or arduino to find the
+device among the connected USB ones, again after it's replugged.
+
+sessions lists what's stored in the database (default: radar.db), export writes
+a session's samples, or alarms with --alarms, to stdout and trim drops all but
+the newest N sessions. report summarizes a stored session or a --log CSV:
+coverage, average range per sector, closest approaches, alarm events and a few
+of its sweeps drawn, as HTML (default: report.html) or Markdown. --against
+adds how each sector changed from another session or log.
+
+Options:
+  --config <PATH>         Config file to load (default: radar.toml if it exists)
+  --profile <NAME>        Start with the [profile.NAME] table of the config file on top
+  --protocol <NAME>       Wire format: csv, json, binary, rplidar, tfmini or nmea (default: csv)
+  --range <CM>            Distance at the edge of the plot (default: 40)
+  --headless              Run without a window, alarms are printed to stdout
+  --log <PATH>            Write every accepted sample to a CSV file
+  --db <PATH>             Store samples and alarms as a session in SQLite (needs the sqlite feature)
+  --http <ADDR>           Serve the status/REST API, e.g. 0.0.0.0:8080 (needs the http feature)
+  --rerun <DEST>          Log to rerun.io, an .rrd file or a viewer URL (needs the rerun feature)
+  --pose <serial|ADDR>    The sensor is on a moving platform: read x,y,heading poses from
+                          POSE lines on the sensor's link or UDP datagrams on ADDR and
+                          keep the map in the world's frame
+  --screenshot-dir <DIR>  Where F12 screenshots and F10 recordings go (default: .)
+  --mqtt <URL>            Subscribe to an MQTT broker instead of a serial port (tcp://host:1883)
+  --source <NAME:ARG>     Read from a source of the list below instead of a serial port
+  --bench <POINTS_PER_S>  Stress test: draw a synthetic lidar at this rate with the frame rate
+                          uncapped, then print frame time percentiles and allocations per frame
+  --bench-seconds <S>     How long --bench measures (default: 30)
+  --sink <NAME:ARG>       Also send every sample to a sink of the list below, can be repeated
+  --stdin                 Same as --source stdin
+  --ipc <PATH>            Same as --source ipc:PATH, a named pipe or Unix domain socket
+  --ros-listen <ADDR>     Read LaserScan JSON datagrams from a UDP port instead (needs the ros feature)
+  --ros-publish <ADDR>    Send every sweep as a LaserScan datagram to HOST:PORT (needs the ros feature)
+  --topic <TOPIC>         MQTT topic to subscribe to (default: radar/samples)
+  --qos <0|1|2>           MQTT quality of service (default: 0)
+  --payload <FORMAT>      Same as --protocol, kept for MQTT setups
+  -h, --help              Print this help";
+
+/// Command line arguments. Positional `PORT BAUD` are kept from the original
+/// interface, everything else is a `--flag value` pair.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub config: Option<String>,
+    /// `[profile.NAME]` of the config file to start with
+    pub profile: Option<String>,
+    pub port: Option<String>,
+    pub baud: Option<Baud>,
+    pub protocol: Option<ProtocolKind>,
+    /// `NAME:ARG` of a `plugin::SOURCES` entry, also set by the flags of the
+    /// single sources
+    pub source: Option<String>,
+    /// `NAME:ARG` of `plugin::SINKS` entries, `--log`, `--rerun` and
+    /// `--ros-publish` included
+    pub sinks: Vec<String>,
+    pub topic: String,
+    pub qos: u8,
+    pub range_cm: Option<f32>,
+    pub screenshot_dir: Option<String>,
+    pub headless: bool,
+    pub db: Option<String>,
+    pub http: Option<String>,
+    /// `serial` or the UDP address poses of a moving platform come from
+    pub pose: Option<String>,
+    /// Synthetic data, frame times printed at the end
+    pub bench: bool,
+    pub bench_seconds: Option<f32>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args {
+            topic: String::from("radar/samples"),
+            ..Args::default()
+        };
+        let mut positional = Vec::new();
+        let mut sources = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {}", name))
+            };
+            match arg.as_str() {
+                "--config" => parsed.config = Some(value("--config")?),
+                "--profile" => parsed.profile = Some(value("--profile")?),
+                "--protocol" | "--payload" => {
+                    let v = value(&arg)?;
+                    parsed.protocol = Some(
+                        ProtocolKind::parse(&v)
+                            .ok_or_else(|| format!("Unknown protocol: {}", v))?,
+                    );
+                }
+                "--range" => {
+                    parsed.range_cm = Some(
+                        value("--range")?
+                            .parse()
+                            .map_err(|_| "Range must be a distance in cm".to_string())?,
+                    )
+                }
+                "--screenshot-dir" => parsed.screenshot_dir = Some(value("--screenshot-dir")?),
+                "--headless" => parsed.headless = true,
+                "--db" => parsed.db = Some(value("--db")?),
+                "--http" => parsed.http = Some(value("--http")?),
+                "--pose" => parsed.pose = Some(value("--pose")?),
+                "--source" => sources.push(value("--source")?),
+                "--sink" => parsed.sinks.push(value("--sink")?),
+                "--log" => parsed.sinks.push(format!("csv:{}", value("--log")?)),
+                "--rerun" => parsed.sinks.push(format!("rerun:{}", value("--rerun")?)),
+                "--ros-publish" => parsed
+                    .sinks
+                    .push(format!("laserscan:{}", value("--ros-publish")?)),
+                "--ros-listen" => sources.push(format!("laserscan:{}", value("--ros-listen")?)),
+                "--stdin" => sources.push(String::from("stdin")),
+                "--ipc" => sources.push(format!("ipc:{}", value("--ipc")?)),
+                "--mqtt" => sources.push(format!("mqtt:{}", value("--mqtt")?)),
+                "--bench" => {
+                    sources.push(format!("bench:{}", value("--bench")?));
+                    parsed.bench = true;
+                }
+                "--bench-seconds" => {
+                    parsed.bench_seconds = Some(
+                        value("--bench-seconds")?
+                            .parse()
+                            .map_err(|_| "Bench duration must be in seconds".to_string())?,
+                    )
+                }
+                "--topic" => parsed.topic = value("--topic")?,
+                "--qos" => {
+                    parsed.qos = value("--qos")?
+                        .parse()
+                        .map_err(|_| "QoS must be 0, 1 or 2".to_string())?
+                }
+                "-h" | "--help" => return Err(format!("{}\n\n{}", USAGE, plugin::usage())),
+                s if s.starts_with("--") => return Err(format!("Unknown option: {}", s)),
+                _ => positional.push(arg),
+            }
+        }
+
+        if sources.len() > 1 {
+            return Err(format!("Only one source at a time: {}", sources.join(", ")));
+        }
+        parsed.source = sources.pop();
+        if parsed.bench && parsed.headless {
+            return Err("--bench measures the window, it can't run --headless".to_string());
+        }
+        if positional.len() >= 2 {
+            parsed.port = Some(positional[0].clone());
+            parsed.baud = Some(Baud::parse(&positional[1]).unwrap_or(Baud::Fixed(9600)));
+        }
+        Ok(parsed)
+    }
+}
+
+/// The database subcommands, if the first argument names one.
+pub fn parse_command(args: &[String]) -> Result<Option<Command>, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    if !matches!(name.as_str(), "sessions" | "export" | "trim") {
+        return Ok(None);
+    }
+    let mut db = DEFAULT_DB_PATH.to_string();
+    let mut session = None;
+    let mut format = ExportFormat::Csv;
+    let mut alarms = false;
+    let mut keep = None;
+
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        let mut value = |name: &str| {
+            rest.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--db" => db = value("--db")?,
+            "--session" => {
+                session = Some(
+                    value("--session")?
+                        .parse()
+                        .map_err(|_| "Session must be a number, see radar sessions".to_string())?,
+                )
+            }
+            "--format" => {
+                let v = value("--format")?;
+                format = ExportFormat::parse(&v).ok_or_else(|| format!("Unknown format: {}", v))?;
+            }
+            "--alarms" => alarms = true,
+            "--keep" => {
+                keep = Some(
+                    value("--keep")?
+                        .parse()
+                        .map_err(|_| "Keep must be a number of sessions".to_string())?,
+                )
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            s => return Err(format!("Unknown option for {}: {}", name, s)),
+        }
+    }
+
+    let command = match name.as_str() {
+        "sessions" => Command::Sessions { db },
+        "export" => Command::Export {
+            db,
+            session: session.ok_or("export needs --session <ID>")?,
+            format,
+            alarms,
+        },
+        _ => Command::Trim {
+            db,
+            keep: keep.ok_or("trim needs --keep <N>")?,
+        },
+    };
+    Ok(Some(command))
+}
+
+/// `radar report`, if that's what the first argument asks for.
+pub fn parse_report(args: &[String]) -> Result<Option<Request>, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    if name != "report" {
+        return Ok(None);
+    }
+    let mut request = Request {
+        db: DEFAULT_DB_PATH.to_string(),
+        session: None,
+        log: None,
+        format: ReportFormat::Html,
+        out: None,
+        against: None,
+    };
+
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        let mut value = |name: &str| {
+            rest.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--db" => request.db = value("--db")?,
+            "--session" => {
+                request.session = Some(
+                    value("--session")?
+                        .parse()
+                        .map_err(|_| "Session must be a number, see radar sessions".to_string())?,
+                )
+            }
+            "--log" => request.log = Some(value("--log")?),
+            "--format" => {
+                let v = value("--format")?;
+                request.format =
+                    ReportFormat::parse(&v).ok_or_else(|| format!("Unknown format: {}", v))?;
+            }
+            "--out" => request.out = Some(value("--out")?),
+            "--against" => request.against = Some(value("--against")?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            s => return Err(format!("Unknown option for report: {}", s)),
+        }
+    }
+    Ok(Some(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Command>, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_command(&args)
+    }
+
+    #[test]
+    fn other_arguments_are_not_commands() {
+        assert!(parse("").unwrap().is_none());
+        assert!(parse("/dev/ttyUSB0 9600").unwrap().is_none());
+        assert!(parse("report --log a.csv").unwrap().is_none());
+    }
+
+    #[test]
+    fn sessions_default_to_the_default_database() {
+        let command = parse("sessions").unwrap().unwrap();
+        assert!(matches!(command, Command::Sessions { db } if db == DEFAULT_DB_PATH));
+    }
+
+    #[test]
+    fn export_reads_its_options() {
+        let command = parse("export --session 3 --format jsonl --alarms --db x.db")
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            command,
+            Command::Export {
+                db,
+                session: 3,
+                format: ExportFormat::Jsonl,
+                alarms: true,
+            } if db == "x.db"
+        ));
+    }
+
+    #[test]
+    fn missing_and_broken_values_are_errors() {
+        assert!(parse("export").is_err());
+        assert!(parse("export --session x").is_err());
+        assert!(parse("export --session").is_err());
+        assert!(parse("trim").is_err());
+        assert!(parse("trim --keep 2 --frobnicate").is_err());
+        assert!(matches!(
+            parse("trim --keep 2"),
+            Ok(Some(Command::Trim { keep: 2, .. }))
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Write;
+
+use raylib::prelude::*;
+use tracing::{info, warn};
+
+use crate::radar::Detection;
+use crate::tracker::Track;
+
+/// The closest object, see `ClosestTracker::closest`.
+pub fn closest_csv(closest: Option<(f32, f32)>) -> Option<String> {
+    let (angle, distance) = closest?;
+    Some(format!(
+        "angle_deg,distance_cm\n{:.1},{:.1}\n",
+        angle, distance
+    ))
+}
+
+/// Every confirmed track.
+pub fn tracks_csv<'a>(tracks: impl Iterator<Item = &'a Track>) -> Option<String> {
+    let mut text = String::from("id,angle_deg,distance_cm,radial_velocity_cm_s,speed_cm_s\n");
+    let mut any = false;
+    for track in tracks {
+        any = true;
+        let _ = writeln!(
+            text,
+            "{},{:.1},{:.1},{:.1},{:.1}",
+            track.id,
+            track.angle,
+            track.distance,
+            track.radial_velocity,
+            track.speed()
+        );
+    }
+    any.then_some(text)
+}
+
+/// The detections of the last pass, by angle. Strength is empty for sensors
+/// that don't report one.
+pub fn sweep_csv<'a>(detections: impl Iterator<Item = &'a Detection>) -> Option<String> {
+    let mut text = String::from("angle_deg,distance_cm,strength,layer\n");
+    let mut any = false;
+    for det in detections {
+        any = true;
+        let strength = det
+            .strength
+            .map(|s| format!("{:.2}", s))
+            .unwrap_or_default();
+        let _ = writeln!(
+            text,
+            "{:.1},{:.1},{},{}",
+            det.angle, det.distance, strength, det.layer
+        );
+    }
+    any.then_some(text)
+}
+
+/// Puts `text` on the system clipboard, ready to paste into a spreadsheet.
+/// `what` names it in the log, nothing is copied without `text`.
+pub fn copy(rl: &mut RaylibHandle, what: &str, text: Option<String>) {
+    let Some(text) = text else {
+        info!("No {} to copy", what);
+        return;
+    };
+    let rows = text.lines().count() - 1;
+    match rl.set_clipboard_text(&text) {
+        Ok(()) => info!("Copied {} to the clipboard, {} rows", what, rows),
+        Err(e) => warn!("Failed to copy {}: {}", what, e),
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::i18n::{self, Strings};
+use crate::source::Sample;
+use crate::units::Unit;
+use crate::view::{self, Layout};
+
+pub const READOUT_FONT_SIZE: i32 = 40;
+/// Width of the highlighted ring, pixels.
+const RING_WIDTH: f32 = 3.0;
+
+/// `[closest]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ClosestConfig {
+    /// Start with the readout shown, N toggles it
+    pub enabled: bool,
+    /// Width of the sectors the minimum is kept for
+    pub sector_deg: f32,
+    /// A sector nothing swept over for this long no longer counts
+    pub hold_ms: u64,
+}
+
+impl Default for ClosestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sector_deg: 10.0,
+            hold_ms: 3000,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Nearest {
+    angle: f32,
+    distance: f32,
+    time: Instant,
+}
+
+/// Nearest echo per sector from the latest pass of the sweep over it, for
+/// parking-sensor style use where only the closest obstacle matters. A
+/// sector starts over each time the sweep comes back to it, so something
+/// that moved away stops counting right away.
+pub struct ClosestTracker {
+    pub enabled: bool,
+    sector_deg: f32,
+    hold: Duration,
+    /// `None` once the sweep passed without hearing anything
+    sectors: Vec<Option<Nearest>>,
+    /// Sweep each sector was last passed in, see `RadarState::sweeps`
+    passes: Vec<u64>,
+}
+
+impl ClosestTracker {
+    pub fn new(config: &ClosestConfig) -> Self {
+        let sector_deg = config.sector_deg.clamp(1.0, 360.0);
+        let count = (360.0 / sector_deg).ceil() as usize;
+        Self {
+            enabled: config.enabled,
+            sector_deg,
+            hold: Duration::from_millis(config.hold_ms),
+            sectors: vec![None; count],
+            passes: vec![u64::MAX; count],
+        }
+    }
+
+    /// `sweep` is the number of the sweep the samples belong to.
+    pub fn record(&mut self, samples: &[Sample], sweep: u64, now: Instant) {
+        for sample in samples {
+            let i = (sample.angle.rem_euclid(360.0) / self.sector_deg) as usize;
+            let Some(slot) = self.sectors.get_mut(i) else {
+                continue;
+            };
+            if self.passes[i] != sweep {
+                // First reading of a new pass over this sector
+                self.passes[i] = sweep;
+                *slot = None;
+            }
+            if sample.distance <= 0.0 {
+                continue;
+            }
+            if slot.is_none_or(|n| sample.distance < n.distance) {
+                *slot = Some(Nearest {
+                    angle: sample.angle,
+                    distance: sample.distance,
+                    time: now,
+                });
+            }
+        }
+    }
+
+    /// Angle and distance of the nearest echo in any sector.
+    pub fn closest(&self, now: Instant) -> Option<(f32, f32)> {
+        self.sectors
+            .iter()
+            .flatten()
+            .filter(|n| now.saturating_duration_since(n.time) < self.hold)
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+            .map(|n| (n.angle, n.distance))
+    }
+
+    /// Ring at the closest range across the plot and a marker on the echo.
+    pub fn draw_marker<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+        now: Instant,
+        color: Color,
+    ) {
+        let Some((angle, distance)) = self.closest(now) else {
+            return;
+        };
+        if !self.enabled || distance >= max_range_cm {
+            return;
+        }
+        let r = distance * pixels_per_cm;
+        view::draw_sector(
+            d,
+            layout,
+            layout.sector,
+            ((r - RING_WIDTH / 2.0).max(0.0), r + RING_WIDTH / 2.0),
+            color.alpha(0.7),
+            true,
+        );
+        let pos = layout.polar_to_screen(angle, r);
+        d.draw_circle_lines(pos.x as i32, pos.y as i32, 12.0, color);
+        d.draw_circle_lines(pos.x as i32, pos.y as i32, 14.0, color);
+    }
+
+    /// "CLOSEST: 12.4 cm @ 87 deg", if shown.
+    pub fn readout(&self, now: Instant, unit: Unit, strings: &Strings) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        Some(match self.closest(now) {
+            Some((angle, distance)) => i18n::fill(
+                &strings.closest,
+                &[
+                    ("distance", &unit.format(distance)),
+                    ("angle", &format!("{:.0}", angle)),
+                ],
+            ),
+            None => strings.closest_clear.clone(),
+        })
+    }
+}
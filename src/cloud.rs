@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::source::Sample;
+use crate::theme::{GradientConfig, Theme};
+
+/// Radians of orbit per pixel of mouse drag.
+const ORBIT_SPEED: f32 = 0.01;
+/// One mouse wheel notch moves the camera this much closer or further.
+const ZOOM_STEP: f32 = 1.15;
+const MIN_PITCH: f32 = -1.5;
+const MAX_PITCH: f32 = 1.5;
+/// Spacing of the floor grid, in meters like everything in the 3D view.
+const GRID_SPACING_M: f32 = 0.5;
+
+/// `[cloud]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CloudConfig {
+    /// Oldest points are dropped past this
+    pub max_points: usize,
+}
+
+impl Default for CloudConfig {
+    fn default() -> Self {
+        Self {
+            max_points: 100_000,
+        }
+    }
+}
+
+/// Every echo of a pan/tilt rig as a point in space. The sensor sits at the
+/// origin, pan 0° points along +x, 90° along -z and positive tilt goes up +y.
+/// Sensors without tilt fill the level plane.
+pub struct PointCloud {
+    /// Position in meters and distance in cm, for coloring
+    points: VecDeque<(Vector3, f32)>,
+    max_points: usize,
+}
+
+impl PointCloud {
+    pub fn new(config: &CloudConfig) -> Self {
+        Self {
+            points: VecDeque::new(),
+            max_points: config.max_points.max(1),
+        }
+    }
+
+    pub fn record(&mut self, sample: &Sample) {
+        if sample.distance <= 0.0 {
+            return;
+        }
+        let pan = sample.angle.to_radians();
+        let tilt = sample.tilt.unwrap_or(0.0).to_radians();
+        let meters = sample.distance / 100.0;
+        let level = meters * tilt.cos();
+        let pos = Vector3::new(level * pan.cos(), meters * tilt.sin(), -level * pan.sin());
+        if self.points.len() == self.max_points {
+            self.points.pop_front();
+        }
+        self.points.push_back((pos, sample.distance));
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Draws the floor grid, the sensor and every point colored by distance.
+    pub fn draw<D: RaylibDraw3D>(
+        &self,
+        d: &mut D,
+        max_range_cm: f32,
+        theme: &Theme,
+        gradient: &GradientConfig,
+    ) {
+        let range_m = max_range_cm / 100.0;
+        let slices = ((range_m * 2.0 / GRID_SPACING_M).ceil() as i32).max(2);
+        d.draw_grid(slices, GRID_SPACING_M);
+        d.draw_sphere(Vector3::zero(), 0.03, theme.sweep);
+        // Pan 0° direction
+        d.draw_line_3D(
+            Vector3::zero(),
+            Vector3::new(range_m, 0.0, 0.0),
+            theme.outline,
+        );
+        for (pos, distance) in &self.points {
+            if *distance >= max_range_cm {
+                continue;
+            }
+            let color = theme.detection_color(gradient, distance / max_range_cm);
+            d.draw_point3D(*pos, color);
+        }
+    }
+}
+
+/// Camera circling the sensor: drag to orbit, wheel to zoom.
+pub struct Orbit {
+    yaw: f32,
+    pitch: f32,
+    /// Distance from the sensor, meters
+    distance: f32,
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        Self {
+            yaw: 0.8,
+            pitch: 0.6,
+            distance: 3.0,
+        }
+    }
+}
+
+impl Orbit {
+    pub fn update(&mut self, rl: &RaylibHandle) {
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT)
+            || rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_MIDDLE)
+        {
+            let delta = rl.get_mouse_delta();
+            self.yaw -= delta.x * ORBIT_SPEED;
+            self.pitch = (self.pitch + delta.y * ORBIT_SPEED).clamp(MIN_PITCH, MAX_PITCH);
+        }
+        let wheel = rl.get_mouse_wheel_move();
+        if wheel != 0.0 {
+            self.distance = (self.distance / ZOOM_STEP.powf(wheel)).clamp(0.2, 100.0);
+        }
+    }
+
+    pub fn camera(&self) -> Camera3D {
+        let position = Vector3::new(
+            self.distance * self.pitch.cos() * self.yaw.cos(),
+            self.distance * self.pitch.sin(),
+            self.distance * self.pitch.cos() * self.yaw.sin(),
+        );
+        Camera3D::perspective(position, Vector3::zero(), Vector3::up(), 45.0)
+    }
+}
@@ -0,0 +1,261 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::radar::Detection;
+use crate::units::Unit;
+use crate::view::Layout;
+
+const OUTLINE_THICKNESS: f32 = 4.0;
+/// Clusters too small to outline are drawn as blips this big.
+const BLIP_RADIUS: f32 = 6.0;
+const LABEL_FONT_SIZE: i32 = 16;
+
+/// `[clusters]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ClusterConfig {
+    /// Start with clusters shown, B toggles them
+    pub enabled: bool,
+    /// Neighbouring detections closer than this belong to the same object
+    pub gap_cm: f32,
+    /// Clusters with fewer detections are drawn as plain blips, unlabelled
+    pub min_points: usize,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gap_cm: 8.0,
+            min_points: 3,
+        }
+    }
+}
+
+fn to_cartesian(det: &Detection) -> (f32, f32) {
+    let rad = det.angle.to_radians();
+    (det.distance * rad.cos(), det.distance * rad.sin())
+}
+
+fn gap(a: &Detection, b: &Detection) -> f32 {
+    let (ax, ay) = to_cartesian(a);
+    let (bx, by) = to_cartesian(b);
+    (bx - ax).hypot(by - ay)
+}
+
+/// Neighbouring detections of one object, in angle order, each with the
+/// alpha it's drawn at.
+pub struct Cluster {
+    pub points: Vec<(Detection, f32)>,
+}
+
+impl Cluster {
+    /// Mean position as angle and distance.
+    pub fn centroid(&self) -> (f32, f32) {
+        let n = self.points.len() as f32;
+        let (x, y) = self.points.iter().fold((0.0, 0.0), |(x, y), (det, _)| {
+            let (dx, dy) = to_cartesian(det);
+            (x + dx, y + dy)
+        });
+        let (x, y) = (x / n, y / n);
+        (y.atan2(x).to_degrees(), x.hypot(y))
+    }
+
+    /// Straight distance between the two ends, in cm.
+    pub fn width(&self) -> f32 {
+        match (self.points.first(), self.points.last()) {
+            (Some((a, _)), Some((b, _))) => gap(a, b),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Groups the detections on screen into objects and draws each as one
+/// outline with its width, rather than a shadow line per detection.
+pub struct Clustering {
+    pub enabled: bool,
+    gap_cm: f32,
+    min_points: usize,
+}
+
+impl Clustering {
+    pub fn new(config: &ClusterConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            gap_cm: config.gap_cm.max(0.1),
+            min_points: config.min_points.max(2),
+        }
+    }
+
+    /// Walks the detections in angle order, starting a new cluster wherever
+    /// the gap to the previous one is too wide. Sensors are never mixed.
+    pub fn group<'a>(
+        &self,
+        detections: impl Iterator<Item = (&'a Detection, f32)>,
+    ) -> Vec<Cluster> {
+        let mut sorted: Vec<(Detection, f32)> = detections.map(|(det, a)| (*det, a)).collect();
+        sorted.sort_by(|a, b| {
+            a.0.layer.cmp(&b.0.layer).then(
+                a.0.angle
+                    .rem_euclid(360.0)
+                    .total_cmp(&b.0.angle.rem_euclid(360.0)),
+            )
+        });
+
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for point in sorted {
+            match clusters.last_mut() {
+                Some(cluster)
+                    if cluster.points.last().is_some_and(|(prev, _)| {
+                        prev.layer == point.0.layer && gap(prev, &point.0) <= self.gap_cm
+                    }) =>
+                {
+                    cluster.points.push(point);
+                }
+                _ => clusters.push(Cluster {
+                    points: vec![point],
+                }),
+            }
+        }
+
+        // An object across 0° ends up split in two, the last cluster of a
+        // sensor carries on into its first
+        let mut i = 0;
+        while i < clusters.len() {
+            let layer = clusters[i].points[0].0.layer;
+            let end = clusters[i..]
+                .iter()
+                .position(|c| c.points[0].0.layer != layer)
+                .map_or(clusters.len(), |n| i + n);
+            let last = end - 1;
+            if last > i {
+                let (head, tail) = (&clusters[i].points[0].0, &clusters[last].points);
+                if tail
+                    .last()
+                    .is_some_and(|(det, _)| gap(det, head) <= self.gap_cm)
+                {
+                    let first = clusters.remove(i);
+                    clusters[last - 1].points.extend(first.points);
+                    i = end - 1;
+                    continue;
+                }
+            }
+            i = end;
+        }
+        clusters
+    }
+
+    /// Outline through each cluster with its width next to the centroid,
+    /// `color` gives the color of a detection at an alpha.
+    pub fn draw<'a, D, F>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        detections: impl Iterator<Item = (&'a Detection, f32)>,
+        unit: Unit,
+        color: F,
+    ) where
+        D: RaylibDraw,
+        F: Fn(&Detection, f32) -> Color,
+    {
+        let to_screen =
+            |det: &Detection| layout.polar_to_screen(det.angle, det.distance * pixels_per_cm);
+        for cluster in self.group(detections) {
+            if cluster.points.len() < self.min_points {
+                for (det, alpha) in &cluster.points {
+                    d.draw_circle_v(to_screen(det), BLIP_RADIUS * det.size(), color(det, *alpha));
+                }
+                continue;
+            }
+            for pair in cluster.points.windows(2) {
+                let (a, alpha) = &pair[0];
+                let (b, _) = &pair[1];
+                d.draw_line_ex(
+                    to_screen(a),
+                    to_screen(b),
+                    OUTLINE_THICKNESS,
+                    color(a, *alpha),
+                );
+            }
+            let label_color = color(&cluster.points[0].0, 1.0);
+            let (angle, distance) = cluster.centroid();
+            let pos = layout.polar_to_screen(angle, distance * pixels_per_cm);
+            d.draw_circle_v(pos, 3.0, label_color);
+            d.draw_text(
+                &unit.format(cluster.width()),
+                (pos.x + 6.0) as i32,
+                (pos.y + 6.0) as i32,
+                LABEL_FONT_SIZE,
+                label_color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn det(angle: f32, distance: f32, layer: u8) -> Detection {
+        Detection {
+            angle,
+            distance,
+            bearing: angle,
+            time: Instant::now(),
+            multi_echo: false,
+            strength: None,
+            layer,
+        }
+    }
+
+    fn group(detections: &[Detection]) -> Vec<Vec<f32>> {
+        let clustering = Clustering::new(&ClusterConfig::default());
+        clustering
+            .group(detections.iter().map(|d| (d, 1.0)))
+            .iter()
+            .map(|c| c.points.iter().map(|(d, _)| d.angle).collect())
+            .collect()
+    }
+
+    #[test]
+    fn splits_where_the_gap_is_too_wide() {
+        // 1° apart at 100 cm is under 2 cm, 28° is far beyond the gap
+        let clusters = group(&[
+            det(12.0, 100.0, 0),
+            det(10.0, 100.0, 0),
+            det(40.0, 100.0, 0),
+            det(11.0, 100.0, 0),
+        ]);
+        assert_eq!(clusters, [vec![10.0, 11.0, 12.0], vec![40.0]]);
+    }
+
+    #[test]
+    fn never_mixes_sensors() {
+        let clusters = group(&[det(10.0, 100.0, 0), det(10.5, 100.0, 1)]);
+        assert_eq!(clusters.len(), 2);
+    }
+
+    #[test]
+    fn joins_an_object_across_zero() {
+        let clusters = group(&[
+            det(359.0, 100.0, 0),
+            det(1.0, 100.0, 0),
+            det(90.0, 100.0, 0),
+        ]);
+        assert_eq!(clusters, [vec![90.0], vec![359.0, 1.0]]);
+    }
+
+    #[test]
+    fn centroid_and_width_of_a_pair() {
+        let cluster = Cluster {
+            points: vec![(det(0.0, 100.0, 0), 1.0), (det(90.0, 100.0, 0), 1.0)],
+        };
+        let (angle, distance) = cluster.centroid();
+        assert!((angle - 45.0).abs() < 1e-3);
+        assert!((distance - 100.0 / 2f32.sqrt()).abs() < 1e-3);
+        assert!((cluster.width() - 100.0 * 2f32.sqrt()).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, Item, value};
+
+use crate::alarm::AlarmZone;
+use crate::audio::AudioConfig;
+use crate::calibration::Calibration;
+use crate::closest::ClosestConfig;
+use crate::cloud::CloudConfig;
+use crate::cluster::ClusterConfig;
+use crate::filter::FilterConfig;
+use crate::font::FontConfig;
+use crate::fusion::FusionConfig;
+use crate::gamepad::GamepadConfig;
+use crate::handshake::HandshakeConfig;
+use crate::heatmap::HeatmapConfig;
+use crate::hooks::HookConfig;
+use crate::idle::IdleConfig;
+use crate::keymap::KeyNames;
+use crate::logging::LoggingConfig;
+use crate::markers::Marker;
+use crate::mask::MaskZone;
+use crate::mti::MtiConfig;
+use crate::notify::NotifyConfig;
+use crate::protocol::ProtocolKind;
+use crate::range::RangeConfig;
+use crate::rate::SweepRateConfig;
+use crate::record::RecordConfig;
+use crate::sectors::SectorConfig;
+use crate::sensors::SensorConfig;
+use crate::settings::VisualsConfig;
+use crate::split::ViewConfig;
+use crate::state::StateConfig;
+use crate::theme::{GradientConfig, ThemeKind};
+use crate::ttc::TtcConfig;
+use crate::units::Unit;
+use crate::validate::ValidationConfig;
+use crate::view::{CoverageConfig, DisplayMode, Projection};
+use crate::window::WindowConfig;
+
+/// Looked up in the working directory when no `--config` is given.
+pub const DEFAULT_CONFIG_PATH: &str = "radar.toml";
+/// Seconds between two looks at the config file's modification time.
+const RELOAD_INTERVAL: f64 = 1.0;
+
+/// Settings read from `radar.toml`. Every field is optional in the file,
+/// command line flags override whatever is set here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Wire format of the incoming data
+    pub protocol: ProtocolKind,
+    /// Half circle or full 360° plot
+    pub display: DisplayMode,
+    /// Polar rings or an x/y map grid under the detections
+    pub projection: Projection,
+    /// Color preset
+    pub theme: ThemeKind,
+    /// Unit distances are shown in
+    pub units: Unit,
+    pub coverage: CoverageConfig,
+    pub range: RangeConfig,
+    pub gradient: GradientConfig,
+    pub visuals: VisualsConfig,
+    pub validation: ValidationConfig,
+    pub calibration: Calibration,
+    pub filter: FilterConfig,
+    pub audio: AudioConfig,
+    pub heatmap: HeatmapConfig,
+    pub fusion: FusionConfig,
+    pub cloud: CloudConfig,
+    pub closest: ClosestConfig,
+    pub clusters: ClusterConfig,
+    pub mti: MtiConfig,
+    pub record: RecordConfig,
+    pub handshake: HandshakeConfig,
+    pub logging: LoggingConfig,
+    /// `[commands]`: key name to the line it sends to the device
+    pub commands: HashMap<String, String>,
+    /// `[keys]`: action name to the key or keys that trigger it
+    pub keys: HashMap<String, KeyNames>,
+    /// Locale file the on-screen labels and alarm messages are read from,
+    /// English when not set
+    pub locale: Option<String>,
+    pub gamepad: GamepadConfig,
+    pub window: WindowConfig,
+    pub font: FontConfig,
+    pub idle: IdleConfig,
+    pub sweep_rate: SweepRateConfig,
+    pub sectors: SectorConfig,
+    pub ttc: TtcConfig,
+    pub notifications: NotifyConfig,
+    pub state: StateConfig,
+    /// `[[sensor]]` entries, read together instead of a single port
+    #[serde(rename = "sensor")]
+    pub sensors: Vec<SensorConfig>,
+    /// `[[alarm]]` zones
+    #[serde(rename = "alarm")]
+    pub alarms: Vec<AlarmZone>,
+    /// `[[mask]]` zones of known clutter
+    #[serde(rename = "mask")]
+    pub masks: Vec<MaskZone>,
+    /// `[[marker]]` labels of known features
+    #[serde(rename = "marker")]
+    pub markers: Vec<Marker>,
+    /// `[[view]]` parts of a split screen
+    #[serde(rename = "view")]
+    pub views: Vec<ViewConfig>,
+    /// `[[hook]]` commands run on events
+    #[serde(rename = "hook")]
+    pub hooks: Vec<HookConfig>,
+    /// `[profile.NAME]` tables of keys that replace the ones above while
+    /// the profile is in use, e.g. a classroom and a rover setup
+    #[serde(rename = "profile")]
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+impl Config {
+    /// Loads the config file at `path` with the keys of `profile` on top.
+    /// A missing default file is not an error, a missing file that was asked
+    /// for explicitly is, and so is a profile it doesn't have.
+    pub fn load(path: Option<&str>, profile: Option<&str>) -> Result<Self, String> {
+        let (path, explicit) = match path {
+            Some(p) => (p, true),
+            None => (DEFAULT_CONFIG_PATH, false),
+        };
+
+        let mut table = if !explicit && !Path::new(path).exists() {
+            toml::Table::new()
+        } else {
+            let text =
+                fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            toml::from_str(&text).map_err(|e| format!("Invalid config {}: {}", path, e))?
+        };
+        if let Some(name) = profile {
+            let overrides = table
+                .get("profile")
+                .and_then(|p| p.get(name))
+                .and_then(|p| p.as_table())
+                .cloned()
+                .ok_or_else(|| format!("No profile {} in {}", name, path))?;
+            merge(&mut table, overrides);
+        }
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| format!("Invalid config {}: {}", path, e))
+    }
+
+    /// Names of the `[profile.NAME]` tables, in order.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+}
+
+/// Puts the keys of `overrides` over `base`: tables key by key, anything
+/// else, arrays of tables included, as a whole.
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        if let toml::Value::Table(inner) = &value
+            && let Some(toml::Value::Table(existing)) = base.get_mut(&key)
+        {
+            merge(existing, inner.clone());
+            continue;
+        }
+        base.insert(key, value);
+    }
+}
+
+/// Changes the config file at `path` with `change`, leaving everything else
+/// in it (comments included) as it was. The file is created if missing.
+pub fn edit(path: &str, change: impl FnOnce(&mut DocumentMut)) -> Result<(), String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path, e)),
+    };
+    let mut doc: DocumentMut = text
+        .parse()
+        .map_err(|e| format!("Invalid config {}: {}", path, e))?;
+    change(&mut doc);
+    fs::write(path, doc.to_string()).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// The part of `doc` that settings are written to: the table of `profile`
+/// while one is in use, so the base file stays as it was, the top level
+/// otherwise.
+pub fn section<'a>(doc: &'a mut DocumentMut, profile: Option<&str>) -> &'a mut Item {
+    match profile {
+        Some(name) => &mut doc["profile"][name],
+        None => doc.as_item_mut(),
+    }
+}
+
+/// Puts `tables` in as the `[[key]]` entries of `profile`'s section. With
+/// none left a profile gets an empty list, so the shared entries don't come
+/// back through it.
+pub fn set_entries(doc: &mut DocumentMut, profile: Option<&str>, key: &str, tables: ArrayOfTables) {
+    match profile {
+        None if tables.is_empty() => {
+            doc.remove(key);
+        }
+        Some(_) if tables.is_empty() => section(doc, profile)[key] = value(Array::new()),
+        _ => section(doc, profile)[key] = Item::ArrayOfTables(tables),
+    }
+}
+
+/// Notices when the config file is saved, so the settings that can change
+/// on the fly are picked up without a restart.
+pub struct ConfigWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    last_check: f64,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            modified: Self::modified(path),
+            last_check: 0.0,
+        }
+    }
+
+    fn modified(path: &str) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Whether the file was written since the last time this said so,
+    /// looked at once a second.
+    pub fn changed(&mut self, time: f64) -> bool {
+        if time - self.last_check < RELOAD_INTERVAL {
+            return false;
+        }
+        self.last_check = time;
+        let modified = Self::modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> toml::Table {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn merge_goes_into_tables_key_by_key() {
+        let mut base = table("protocol = \"csv\"\n[range]\nmax_cm = 40.0\nauto = true\n");
+        merge(&mut base, table("[range]\nmax_cm = 400.0\n"));
+        assert_eq!(
+            base,
+            table("protocol = \"csv\"\n[range]\nmax_cm = 400.0\nauto = true\n")
+        );
+    }
+
+    #[test]
+    fn merge_replaces_arrays_of_tables_whole() {
+        let mut base = table("[[alarm]]\nname = \"a\"\n[[alarm]]\nname = \"b\"\n");
+        merge(&mut base, table("[[alarm]]\nname = \"c\"\n"));
+        assert_eq!(base, table("[[alarm]]\nname = \"c\"\n"));
+    }
+
+    #[test]
+    fn merge_adds_missing_keys() {
+        let mut base = table("protocol = \"csv\"\n");
+        merge(&mut base, table("units = \"m\"\n[range]\nmax_cm = 10.0\n"));
+        assert_eq!(base.get("units").and_then(|u| u.as_str()), Some("m"));
+        assert!(base.get("range").is_some_and(|r| r.is_table()));
+    }
+}
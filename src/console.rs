@@ -0,0 +1,136 @@
+use std::collections::{HashMap, VecDeque};
+
+use raylib::prelude::*;
+use tracing::warn;
+
+use crate::keymap::parse_key;
+use crate::theme::Theme;
+use crate::ui::UiScale;
+
+/// Lines kept in the scrollback.
+const HISTORY_LINES: usize = 200;
+const VISIBLE_LINES: usize = 10;
+const FONT_SIZE: i32 = 18;
+const LINE_HEIGHT: i32 = 22;
+
+/// One line in the scrollback.
+enum Entry {
+    Sent(String),
+    Received(String),
+    Error(String),
+}
+
+/// Mini console for talking to the firmware: typed lines and bound keys are
+/// written to the source, text lines coming back that aren't samples show up
+/// underneath.
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: VecDeque<Entry>,
+    bindings: Vec<(KeyboardKey, String)>,
+}
+
+impl Console {
+    /// `commands` maps key names to the line they send, from `[commands]`.
+    pub fn new(commands: &HashMap<String, String>) -> Self {
+        let mut bindings = Vec::new();
+        for (name, line) in commands {
+            match parse_key(name) {
+                Some(key) => bindings.push((key, line.clone())),
+                None => warn!("Unknown key {} in [commands], ignored.", name),
+            }
+        }
+        Self {
+            open: false,
+            input: String::new(),
+            history: VecDeque::new(),
+            bindings,
+        }
+    }
+
+    /// Escape closes the console instead of the window while it's open.
+    pub fn exit_key(&self) -> Option<KeyboardKey> {
+        (!self.open).then_some(KeyboardKey::KEY_ESCAPE)
+    }
+
+    pub fn bindings(&self) -> &[(KeyboardKey, String)] {
+        &self.bindings
+    }
+
+    fn push(&mut self, entry: Entry) {
+        if self.history.len() == HISTORY_LINES {
+            self.history.pop_front();
+        }
+        self.history.push_back(entry);
+    }
+
+    /// Records the outcome of sending `line`.
+    pub fn sent(&mut self, line: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.push(Entry::Sent(line.to_string())),
+            Err(e) => self.push(Entry::Error(e)),
+        }
+    }
+
+    pub fn received(&mut self, lines: Vec<String>) {
+        for line in lines {
+            self.push(Entry::Received(line));
+        }
+    }
+
+    /// Takes typed text while the console is open. Returns the line once
+    /// Enter is pressed.
+    pub fn type_line(&mut self, rl: &mut RaylibHandle) -> Option<String> {
+        while let Some(c) = rl.get_char_pressed() {
+            // The key that opened the console isn't part of the command
+            if c != '`' && c != '~' && !c.is_control() {
+                self.input.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE)
+            || rl.is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE)
+        {
+            self.input.pop();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) && !self.input.trim().is_empty() {
+            return Some(std::mem::take(&mut self.input).trim().to_string());
+        }
+        None
+    }
+
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        screen_w: f32,
+        screen_h: f32,
+        ui: UiScale,
+        theme: &Theme,
+    ) {
+        if !self.open {
+            return;
+        }
+        let (font_size, line_height) = (ui.font(FONT_SIZE), ui.pxi(LINE_HEIGHT));
+        let height = (VISIBLE_LINES as i32 + 1) * line_height + 16;
+        let y = (screen_h * 0.9) as i32 - height;
+        let width = (screen_w * 0.5) as i32;
+        d.draw_rectangle(10, y, width, height, Color::BLACK.alpha(0.8));
+        d.draw_rectangle_lines(10, y, width, height, theme.outline);
+
+        let first = self.history.len().saturating_sub(VISIBLE_LINES);
+        for (i, entry) in self.history.iter().skip(first).enumerate() {
+            let (text, color) = match entry {
+                Entry::Sent(line) => (format!("> {}", line), theme.sweep),
+                Entry::Received(line) => (line.clone(), theme.outline),
+                Entry::Error(e) => (e.clone(), theme.warning),
+            };
+            d.draw_text(&text, 18, y + 8 + i as i32 * line_height, font_size, color);
+        }
+        d.draw_text(
+            &format!("> {}_", self.input),
+            18,
+            y + 8 + VISIBLE_LINES as i32 * line_height,
+            font_size,
+            theme.sweep,
+        );
+    }
+}
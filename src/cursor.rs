@@ -0,0 +1,104 @@
+use raylib::prelude::*;
+
+use crate::units::Unit;
+use crate::view::Layout;
+
+const CROSSHAIR_SIZE: f32 = 12.0;
+
+/// A point picked on the plot, kept in real-world coordinates so it stays
+/// put when the range or window size changes.
+#[derive(Clone, Copy, Debug)]
+struct PolarPoint {
+    angle: f32,
+    distance: f32,
+}
+
+impl PolarPoint {
+    fn cartesian(self) -> (f32, f32) {
+        let rad = self.angle.to_radians();
+        (self.distance * rad.cos(), self.distance * rad.sin())
+    }
+}
+
+/// Hover readout and two-point distance measurement.
+#[derive(Default)]
+pub struct MeasureCursor {
+    pub enabled: bool,
+    /// Up to two clicked points, a third click starts over
+    points: Vec<PolarPoint>,
+}
+
+impl MeasureCursor {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.points.clear();
+    }
+
+    pub fn click(&mut self, layout: &Layout, pixels_per_cm: f32, mouse: Vector2) {
+        if self.points.len() == 2 {
+            self.points.clear();
+        }
+        let (angle, dist_px) = layout.screen_to_polar(mouse);
+        self.points.push(PolarPoint {
+            angle,
+            distance: dist_px / pixels_per_cm,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Draws the crosshair with its readout and the measurement, if any.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        mouse: Vector2,
+        unit: Unit,
+        color: Color,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        let to_screen = |p: PolarPoint| layout.polar_to_screen(p.angle, p.distance * pixels_per_cm);
+        for p in &self.points {
+            d.draw_circle_lines_v(to_screen(*p), 5.0, color);
+        }
+        if let [a, b] = self.points[..] {
+            let (ax, ay) = a.cartesian();
+            let (bx, by) = b.cartesian();
+            let (pa, pb) = (to_screen(a), to_screen(b));
+            d.draw_line_ex(pa, pb, 2.0, color);
+            let mid = (pa + pb) / 2.0;
+            d.draw_text(
+                &unit.format((bx - ax).hypot(by - ay)),
+                (mid.x + 8.0) as i32,
+                (mid.y + 8.0) as i32,
+                20,
+                color,
+            );
+        }
+
+        d.draw_line_v(
+            Vector2::new(mouse.x - CROSSHAIR_SIZE, mouse.y),
+            Vector2::new(mouse.x + CROSSHAIR_SIZE, mouse.y),
+            color,
+        );
+        d.draw_line_v(
+            Vector2::new(mouse.x, mouse.y - CROSSHAIR_SIZE),
+            Vector2::new(mouse.x, mouse.y + CROSSHAIR_SIZE),
+            color,
+        );
+        let (angle, dist_px) = layout.screen_to_polar(mouse);
+        d.draw_text(
+            &format!("{:.0} deg, {}", angle, unit.format(dist_px / pixels_per_cm)),
+            (mouse.x + CROSSHAIR_SIZE + 4.0) as i32,
+            (mouse.y - CROSSHAIR_SIZE - 16.0) as i32,
+            16,
+            color,
+        );
+    }
+}
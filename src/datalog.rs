@@ -0,0 +1,137 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::warn;
+
+use crate::source::Sample;
+
+/// CSV log of every accepted sample, one row per echo:
+/// `time,angle,distance,echo,strength,layer,sensor_ms` with `time` in Unix
+/// seconds when the batch came in. `strength` and `sensor_ms` are empty for
+/// sensors that don't report them, `sensor_ms` tells the samples of a batch
+/// apart when they do.
+pub struct DataLog {
+    path: String,
+    out: BufWriter<File>,
+}
+
+impl DataLog {
+    pub fn create(path: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        let mut out = BufWriter::new(file);
+        writeln!(out, "time,angle,distance,echo,strength,layer,sensor_ms")
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        Ok(Self {
+            path: path.to_string(),
+            out,
+        })
+    }
+
+    /// Appends a batch and flushes it, so a killed process loses at most one
+    /// batch.
+    pub fn write(&mut self, samples: &[Sample]) {
+        if samples.is_empty() {
+            return;
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let result = samples
+            .iter()
+            .try_for_each(|s| {
+                let strength = s.strength.map(|v| format!("{:.2}", v)).unwrap_or_default();
+                let sensor_ms = s.sensor_ms.map(|v| v.to_string()).unwrap_or_default();
+                writeln!(
+                    self.out,
+                    "{:.3},{:.1},{:.1},{},{},{},{}",
+                    time, s.angle, s.distance, s.echo, strength, s.layer, sensor_ms
+                )
+            })
+            .and_then(|_| self.out.flush());
+        if let Err(e) = result {
+            warn!("Failed to write {}: {}", self.path, e);
+        }
+    }
+}
+
+/// Reads a log written by `DataLog` back, as Unix time and sample per row.
+/// Older logs with only the first four columns still read. The header and
+/// broken rows are skipped.
+pub fn read(path: &str) -> Result<Vec<(f64, Sample)>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(text.lines().filter_map(parse_row).collect())
+}
+
+fn parse_row(line: &str) -> Option<(f64, Sample)> {
+    let fields: Vec<&str> = line.split(',').collect();
+    let (head, extra) = fields.split_at_checked(4)?;
+    let &[time, angle, distance, echo] = head else {
+        return None;
+    };
+    let mut sample = Sample {
+        echo: echo.parse().ok()?,
+        ..Sample::new(angle.parse().ok()?, distance.parse().ok()?)
+    };
+    let time = time.parse().ok()?;
+    if let [strength, layer, sensor_ms] = extra {
+        sample.strength = optional(strength)?;
+        sample.layer = layer.parse().ok()?;
+        sample.sensor_ms = optional(sensor_ms)?;
+    } else if !extra.is_empty() {
+        return None;
+    }
+    Some((time, sample))
+}
+
+/// An empty field is `None`, a broken one makes the row broken.
+fn optional<T: std::str::FromStr>(field: &str) -> Option<Option<T>> {
+    if field.is_empty() {
+        return Some(None);
+    }
+    field.parse().ok().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_full_row() {
+        let (time, sample) = parse_row("1700000000.250,45.0,120.5,1,0.75,2,123456").unwrap();
+        assert_eq!(time, 1_700_000_000.25);
+        assert_eq!(
+            (sample.angle, sample.distance, sample.echo),
+            (45.0, 120.5, 1)
+        );
+        assert_eq!(sample.strength, Some(0.75));
+        assert_eq!(sample.layer, 2);
+        assert_eq!(sample.sensor_ms, Some(123_456));
+    }
+
+    #[test]
+    fn reads_empty_optional_columns() {
+        let (_, sample) = parse_row("1.0,45.0,120.5,0,,0,").unwrap();
+        assert_eq!(sample.strength, None);
+        assert_eq!(sample.sensor_ms, None);
+    }
+
+    #[test]
+    fn reads_four_column_logs() {
+        let (time, sample) = parse_row("2.5,90.0,30.0,0").unwrap();
+        assert_eq!(time, 2.5);
+        assert_eq!(
+            (sample.angle, sample.distance, sample.layer),
+            (90.0, 30.0, 0)
+        );
+    }
+
+    #[test]
+    fn skips_the_header_and_broken_rows() {
+        assert!(parse_row("time,angle,distance,echo,strength,layer,sensor_ms").is_none());
+        assert!(parse_row("1.0,45.0,120.5,0,x,0,").is_none());
+        assert!(parse_row("1.0,45.0,120.5,0,0.5").is_none());
+        assert!(parse_row("1.0,45.0").is_none());
+    }
+}
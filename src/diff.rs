@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+
+use crate::source::Sample;
+use crate::view::{self, Layout};
+
+/// Angular resolution of the comparison, degrees.
+const BIN_DEG: f32 = 1.0;
+const BINS: usize = (360.0 / BIN_DEG) as usize;
+/// Returns moving less than this count as unchanged.
+const TOLERANCE_CM: f32 = 5.0;
+/// A reference capture stops after this long even without a full sweep.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Thickness of the difference marks, pixels.
+const MARK_WIDTH: f32 = 5.0;
+const NEW_COLOR: Color = Color::new(255, 60, 60, 255);
+const MISSING_COLOR: Color = Color::new(70, 140, 255, 255);
+
+/// What one degree saw: `None` if it wasn't swept, `Some(None)` if nothing
+/// echoed.
+type Bins = Vec<Option<Option<f32>>>;
+
+fn bin(angle: f32) -> usize {
+    ((angle.rem_euclid(360.0) / BIN_DEG) as usize).min(BINS - 1)
+}
+
+/// Compares the latest sweep against a reference sweep, e.g. of the empty
+/// room: returns nearer than the reference are new (red), reference returns
+/// that are gone or further are missing (blue).
+pub struct SweepDiff {
+    pub enabled: bool,
+    reference: Option<Bins>,
+    current: Bins,
+    /// Reference being recorded and when and at which sweep it started
+    capture: Option<(Bins, Instant, u64)>,
+}
+
+impl Default for SweepDiff {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reference: None,
+            current: vec![None; BINS],
+            capture: None,
+        }
+    }
+}
+
+impl SweepDiff {
+    /// Records the next full sweep as the reference and shows the diff.
+    pub fn snapshot(&mut self, sweep: u64, now: Instant) {
+        self.capture = Some((vec![None; BINS], now, sweep));
+        self.enabled = true;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    pub fn has_reference(&self) -> bool {
+        self.reference.is_some()
+    }
+
+    /// First echo of every reading of the first sensor. `sweep` is the
+    /// pipeline's sweep count.
+    pub fn record(&mut self, samples: &[Sample], sweep: u64, now: Instant) {
+        for sample in samples.iter().filter(|s| s.layer == 0 && s.echo == 0) {
+            let distance = (sample.distance > 0.0).then_some(sample.distance);
+            let i = bin(sample.angle);
+            self.current[i] = Some(distance);
+            if let Some((bins, _, _)) = &mut self.capture {
+                // Several readings in one degree, the nearest counts
+                bins[i] = match (bins[i], distance) {
+                    (Some(Some(a)), Some(b)) => Some(Some(a.min(b))),
+                    (Some(Some(a)), None) => Some(Some(a)),
+                    _ => Some(distance),
+                };
+            }
+        }
+        if let Some((_, started, first)) = &self.capture
+            // The rest of the sweep in progress plus a full one
+            && (sweep >= *first + 2 || now.saturating_duration_since(*started) >= CAPTURE_TIMEOUT)
+            && let Some((bins, _, _)) = self.capture.take()
+        {
+            self.reference = Some(bins);
+        }
+    }
+
+    /// Marks every degree that differs from the reference.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+    ) {
+        let Some(reference) = self.reference.as_ref().filter(|_| self.enabled) else {
+            return;
+        };
+        let mut mark = |i: usize, distance: f32, color: Color| {
+            if distance >= max_range_cm {
+                return;
+            }
+            let start = i as f32 * BIN_DEG;
+            let r = distance * pixels_per_cm;
+            view::draw_sector(
+                d,
+                layout,
+                (start, start + BIN_DEG),
+                ((r - MARK_WIDTH / 2.0).max(0.0), r + MARK_WIDTH / 2.0),
+                color,
+                true,
+            );
+        };
+        for (i, (then, now)) in reference.iter().zip(&self.current).enumerate() {
+            // Degrees either sweep missed say nothing
+            let (Some(then), Some(now)) = (then, now) else {
+                continue;
+            };
+            match (then, now) {
+                (None, Some(now)) => mark(i, *now, NEW_COLOR),
+                (Some(then), None) => mark(i, *then, MISSING_COLOR),
+                (Some(then), Some(now)) if now + TOLERANCE_CM < *then => {
+                    mark(i, *now, NEW_COLOR);
+                }
+                (Some(then), Some(now)) if *now > then + TOLERANCE_CM => {
+                    mark(i, *then, MISSING_COLOR);
+                }
+                _ => {}
+            }
+        }
+    }
+}
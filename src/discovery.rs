@@ -0,0 +1,321 @@
+use std::time::{Duration, Instant};
+
+use serialport::{SerialPortType, UsbPortInfo};
+use tracing::{debug, info, warn};
+
+use crate::autobaud::{self, Baud};
+use crate::protocol::{ProtocolKind, ProtocolStats, Raw};
+use crate::source::{LinkState, Sample, Source};
+
+/// Vendor IDs `arduino` matches: Arduino LLC, Arduino SRL and the CH340
+/// adapter on most clone boards.
+const ARDUINO_VIDS: [u16; 3] = [0x2341, 0x2a03, 0x1a86];
+/// How often the ports are listed again while the device is missing.
+const RESCAN_EVERY: Duration = Duration::from_secs(1);
+
+/// What a port argument names: a path, or a USB device to look for among
+/// the connected ones, `usb:VID[:PID]` in hex or `arduino`, in any case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PortSpec {
+    Path(String),
+    Usb { vid: u16, pid: Option<u16> },
+    Arduino,
+}
+
+impl PortSpec {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if s.eq_ignore_ascii_case("arduino") {
+            return Ok(PortSpec::Arduino);
+        }
+        let Some(ids) = s
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("usb:"))
+            .map(|_| &s[4..])
+        else {
+            return Ok(PortSpec::Path(s.to_string()));
+        };
+        let hex = |id: &str| {
+            let digits = id.strip_prefix("0x").or(id.strip_prefix("0X"));
+            u16::from_str_radix(digits.unwrap_or(id), 16)
+                .map_err(|_| format!("Invalid USB id {} in {}", id, s))
+        };
+        match ids.split_once(':') {
+            Some((vid, pid)) => Ok(PortSpec::Usb {
+                vid: hex(vid)?,
+                pid: Some(hex(pid)?),
+            }),
+            None => Ok(PortSpec::Usb {
+                vid: hex(ids)?,
+                pid: None,
+            }),
+        }
+    }
+
+    fn matches(&self, usb: &UsbPortInfo) -> bool {
+        match self {
+            PortSpec::Path(_) => false,
+            PortSpec::Usb { vid, pid } => usb.vid == *vid && pid.is_none_or(|pid| usb.pid == pid),
+            PortSpec::Arduino => ARDUINO_VIDS.contains(&usb.vid),
+        }
+    }
+}
+
+/// Opens whatever `port` names, at a fixed baud rate or detecting it.
+pub fn open(port: &str, baud: Baud, protocol: ProtocolKind) -> Box<dyn Source> {
+    match PortSpec::parse(port) {
+        Ok(PortSpec::Path(path)) => autobaud::open(&path, baud, protocol),
+        Ok(spec) => Box::new(DiscoveredSource::open(port, spec, baud, protocol)),
+        Err(e) => {
+            warn!("{}, trying it as a path.", e);
+            autobaud::open(port, baud, protocol)
+        }
+    }
+}
+
+/// Which device was found first, to find it again among several matching.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Identity {
+    vid: u16,
+    pid: u16,
+    serial_number: Option<String>,
+}
+
+impl Identity {
+    fn of(usb: &UsbPortInfo) -> Self {
+        Self {
+            vid: usb.vid,
+            pid: usb.pid,
+            serial_number: usb.serial_number.clone(),
+        }
+    }
+}
+
+/// Of the matching ports, sorted by name, the one with `first`'s serial
+/// number, else one with its VID:PID, else the first.
+fn pick(
+    mut found: Vec<(String, Identity)>,
+    first: Option<&Identity>,
+) -> Option<(String, Identity)> {
+    let same = first.and_then(|first| {
+        let serial = found.iter().position(|(_, id)| {
+            first.serial_number.is_some() && id.serial_number == first.serial_number
+        });
+        serial.or_else(|| {
+            found
+                .iter()
+                .position(|(_, id)| id.vid == first.vid && id.pid == first.pid)
+        })
+    });
+    match same {
+        Some(i) => Some(found.swap_remove(i)),
+        None => found.into_iter().next(),
+    }
+}
+
+/// A USB serial device found by its IDs instead of a path. It's looked for
+/// again whenever the link drops, and the same device (by USB serial number,
+/// or VID:PID without one) is preferred over other matching ones, so a
+/// replug that lands on another tty number picks it up again.
+pub struct DiscoveredSource {
+    name: String,
+    spec: PortSpec,
+    baud: Baud,
+    protocol: ProtocolKind,
+    /// The device found first
+    first: Option<Identity>,
+    /// Port it was last found on, "Found" is only logged when that changes
+    found_on: Option<String>,
+    source: Option<Box<dyn Source>>,
+    last_scan: Instant,
+}
+
+impl DiscoveredSource {
+    pub fn open(name: &str, spec: PortSpec, baud: Baud, protocol: ProtocolKind) -> Self {
+        let mut discovered = Self {
+            name: name.to_string(),
+            spec,
+            baud,
+            protocol,
+            first: None,
+            found_on: None,
+            source: None,
+            last_scan: Instant::now(),
+        };
+        discovered.scan();
+        if discovered.source.is_none() {
+            warn!(
+                "No USB device matching {} is connected, waiting for it.",
+                name
+            );
+        }
+        discovered
+    }
+
+    /// The port of the matching device, the one seen before if it's back,
+    /// and its product name.
+    fn find(&self) -> Option<(String, Identity, Option<String>)> {
+        let mut found: Vec<(String, UsbPortInfo)> = serialport::available_ports()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|p| match p.port_type {
+                SerialPortType::UsbPort(usb) if self.spec.matches(&usb) => Some((p.port_name, usb)),
+                _ => None,
+            })
+            .collect();
+        // Same names in the same order every time, the first one is picked
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        let ids = found
+            .iter()
+            .map(|(port, usb)| (port.clone(), Identity::of(usb)))
+            .collect();
+        let (port, id) = pick(ids, self.first.as_ref())?;
+        let product = found
+            .into_iter()
+            .find(|(p, _)| *p == port)
+            .and_then(|(_, usb)| usb.product);
+        Some((port, id, product))
+    }
+
+    fn scan(&mut self) {
+        self.last_scan = Instant::now();
+        // Drop the old handle first, the device may come back on the same path
+        self.source = None;
+        let Some((port, id, product)) = self.find() else {
+            self.found_on = None;
+            return;
+        };
+        // A device that's there but won't connect is found every second
+        if self.found_on.as_ref() != Some(&port) {
+            info!(
+                "Found {} ({:04x}:{:04x} {}) on {}",
+                self.name,
+                id.vid,
+                id.pid,
+                product.as_deref().unwrap_or("unknown product"),
+                port
+            );
+        } else {
+            debug!("Found {} on {} again", self.name, port);
+        }
+        self.found_on = Some(port.clone());
+        if self.first.is_none() {
+            self.first = Some(id);
+        }
+        self.source = Some(autobaud::open(&port, self.baud, self.protocol));
+    }
+}
+
+impl Source for DiscoveredSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let lost = self
+            .source
+            .as_ref()
+            .is_none_or(|s| s.state() == LinkState::Disconnected);
+        if lost && self.last_scan.elapsed() >= RESCAN_EVERY {
+            self.scan();
+        }
+        match &mut self.source {
+            Some(source) => source.poll(),
+            None => Vec::new(),
+        }
+    }
+
+    fn state(&self) -> LinkState {
+        match &self.source {
+            Some(source) => source.state(),
+            None => LinkState::Disconnected,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{} on {}", self.name, source.describe()),
+            None => format!("{} (not connected)", self.name),
+        }
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        match &self.source {
+            Some(source) => source.protocol_stats(),
+            None => ("none", ProtocolStats::default()),
+        }
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        match &mut self.source {
+            Some(source) => source.send(line),
+            None => Err(format!("{} is not connected", self.name)),
+        }
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        match &mut self.source {
+            Some(source) => source.messages(),
+            None => Vec::new(),
+        }
+    }
+
+    fn tap(&mut self, on: bool) {
+        if let Some(source) = &mut self.source {
+            source.tap(on);
+        }
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        match &mut self.source {
+            Some(source) => source.raw(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(vid: u16, pid: u16, serial: Option<&str>) -> Identity {
+        Identity {
+            vid,
+            pid,
+            serial_number: serial.map(str::to_string),
+        }
+    }
+
+    fn ports(ids: &[Identity]) -> Vec<(String, Identity)> {
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| (format!("/dev/ttyACM{}", i), id.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_usb_specs_in_any_case() {
+        let usb = PortSpec::Usb {
+            vid: 0x2341,
+            pid: Some(0x43),
+        };
+        assert_eq!(PortSpec::parse("usb:2341:0043"), Ok(usb.clone()));
+        assert_eq!(PortSpec::parse("USB:0x2341:0X43"), Ok(usb));
+        assert_eq!(PortSpec::parse("Arduino"), Ok(PortSpec::Arduino));
+        assert_eq!(
+            PortSpec::parse("/dev/usb:x"),
+            Ok(PortSpec::Path("/dev/usb:x".to_string()))
+        );
+        assert!(PortSpec::parse("usb:zz").is_err());
+    }
+
+    #[test]
+    fn prefers_the_same_serial_number() {
+        let first = id(0x2341, 0x43, Some("A"));
+        let found = ports(&[id(0x2341, 0x43, Some("B")), first.clone()]);
+        assert_eq!(pick(found, Some(&first)).unwrap().1, first);
+    }
+
+    #[test]
+    fn prefers_the_same_vid_and_pid_without_a_serial_number() {
+        let first = id(0x1a86, 0x7523, None);
+        let found = ports(&[id(0x2341, 0x43, None), first.clone()]);
+        assert_eq!(pick(found.clone(), Some(&first)).unwrap().1, first);
+        assert_eq!(pick(found, None).unwrap().0, "/dev/ttyACM0");
+    }
+}
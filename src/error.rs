@@ -0,0 +1,157 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use raylib::prelude::*;
+use tracing::{error, info};
+
+use crate::ui::UiScale;
+
+const WIDTH: f32 = 560.0;
+const HEIGHT: f32 = 200.0;
+const TEXT_SIZE: i32 = 16;
+
+/// Failures that used to panic to the terminal. The ones in the window get
+/// an error screen to retry from, see [`retrying`].
+#[derive(Debug)]
+pub enum AppError {
+    /// A texture or render texture couldn't be created, `what` for
+    Texture { what: &'static str, reason: String },
+    /// Reading the port or baud rate headless
+    Stdin(io::Error),
+    /// Stdin ended before a port was entered, e.g. run from a service
+    StdinClosed,
+}
+
+impl AppError {
+    /// For `map_err`: `.map_err(AppError::texture("the waterfall"))`.
+    pub fn texture<E: Display>(what: &'static str) -> impl FnOnce(E) -> Self {
+        move |e| Self::Texture {
+            what,
+            reason: e.to_string(),
+        }
+    }
+
+    /// What the user can do about it, under the message.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::Texture { .. } => {
+                "Usually out of GPU memory: close other programs or make the window smaller."
+            }
+            Self::Stdin(_) | Self::StdinClosed => "Pass the port with --port and --baud instead.",
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Texture { what, reason } => {
+                write!(f, "Failed to create the texture for {}: {}", what, reason)
+            }
+            Self::Stdin(e) => write!(f, "Failed to read stdin: {}", e),
+            Self::StdinClosed => write!(f, "Stdin closed before a port was entered"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Runs `attempt`, and while it fails shows the error in the window with a
+/// retry and a quit button. Enter retries, Escape or closing the window
+/// quits. `None` when the user gave up. Raylib can't tell the exit key, so
+/// the caller's is passed in as `exit_key` to be put back afterwards.
+/// `ui_scale` is `[window] ui_scale`, the screen is sized like the rest.
+pub fn retrying<T>(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    exit_key: Option<KeyboardKey>,
+    ui_scale: f32,
+    mut attempt: impl FnMut(&mut RaylibHandle, &RaylibThread) -> Result<T, AppError>,
+) -> Option<T> {
+    let failure = match attempt(rl, thread) {
+        Ok(value) => return Some(value),
+        Err(e) => e,
+    };
+    error!("{}", failure);
+    rl.set_exit_key(Some(KeyboardKey::KEY_ESCAPE));
+    let result = error_screen(rl, thread, failure, ui_scale, attempt);
+    rl.set_exit_key(exit_key);
+    result
+}
+
+fn error_screen<T>(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    mut failure: AppError,
+    ui_scale: f32,
+    mut attempt: impl FnMut(&mut RaylibHandle, &RaylibThread) -> Result<T, AppError>,
+) -> Option<T> {
+    loop {
+        if rl.window_should_close() {
+            return None;
+        }
+        let (retry, quit) = draw_screen(rl, thread, &failure, ui_scale);
+        if quit {
+            return None;
+        }
+        if retry {
+            match attempt(rl, thread) {
+                Ok(value) => {
+                    info!("Recovered after a retry");
+                    return Some(value);
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    failure = e;
+                }
+            }
+        }
+    }
+}
+
+/// One frame of the error screen. Returns whether retry and quit were picked.
+fn draw_screen(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    failure: &AppError,
+    ui_scale: f32,
+) -> (bool, bool) {
+    let enter = rl.is_key_pressed(KeyboardKey::KEY_ENTER);
+    let (screen_w, screen_h) = (rl.get_screen_width() as f32, rl.get_screen_height() as f32);
+    let ui = UiScale::new(screen_w, screen_h, rl.get_window_scale_dpi().x, ui_scale);
+    let x = (screen_w - ui.px(WIDTH)) / 2.0;
+    let y = (screen_h - ui.px(HEIGHT)) / 2.0;
+    // Laid out at the designed size, from the top left corner
+    let rect = |rx: f32, ry: f32, w: f32, h: f32| {
+        Rectangle::new(x + ui.px(rx), y + ui.px(ry), ui.px(w), ui.px(h))
+    };
+    let text_size = ui.font(TEXT_SIZE);
+
+    let mut d = rl.begin_drawing(thread);
+    d.clear_background(Color::BLACK);
+    d.gui_set_style(
+        GuiControl::DEFAULT,
+        GuiDefaultProperty::TEXT_SIZE,
+        text_size,
+    );
+    let closed = d.gui_window_box(rect(0.0, 0.0, WIDTH, HEIGHT), "Error");
+    let text_x = (x + ui.px(10.0)) as i32;
+    d.draw_text(
+        &failure.to_string(),
+        text_x,
+        (y + ui.px(44.0)) as i32,
+        text_size,
+        Color::RED,
+    );
+    d.draw_text(
+        failure.hint(),
+        text_x,
+        (y + ui.px(74.0)) as i32,
+        text_size,
+        Color::LIGHTGRAY,
+    );
+    let buttons_y = HEIGHT - 50.0;
+    let retry = d.gui_button(rect(10.0, buttons_y, 160.0, 36.0), "Retry (Enter)");
+    let quit = d.gui_button(rect(WIDTH - 170.0, buttons_y, 160.0, 36.0), "Quit (Esc)");
+    (retry || enter, quit || closed)
+}
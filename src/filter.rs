@@ -0,0 +1,195 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::Deserialize;
+
+use crate::source::Sample;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterKind {
+    #[default]
+    None,
+    /// Median of the last `window` readings
+    Median,
+    /// Exponential moving average with weight `alpha`
+    Ema,
+    /// Constant-position 1D Kalman filter
+    Kalman,
+}
+
+impl FilterKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterKind::None => "none",
+            FilterKind::Median => "median",
+            FilterKind::Ema => "ema",
+            FilterKind::Kalman => "kalman",
+        }
+    }
+}
+
+/// `[filter]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    pub kind: FilterKind,
+    /// Width of the angle buckets that are filtered independently, in degrees
+    pub bucket_deg: f32,
+    /// Median window size
+    pub window: usize,
+    /// EMA weight of the newest reading, 0..1
+    pub alpha: f32,
+    /// Kalman process noise (how fast the true distance may drift), cm²
+    pub process_noise: f32,
+    /// Kalman measurement noise (sensor jitter), cm²
+    pub measurement_noise: f32,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            kind: FilterKind::None,
+            bucket_deg: 2.0,
+            window: 5,
+            alpha: 0.3,
+            process_noise: 0.5,
+            measurement_noise: 4.0,
+        }
+    }
+}
+
+enum BucketState {
+    Median(VecDeque<f32>),
+    Ema(f32),
+    Kalman { estimate: f32, variance: f32 },
+}
+
+/// Smooths distances between the data source and the renderer. Each angle
+/// bucket (and each sensor and echo within it) keeps its own filter state,
+/// so the sweep moving across different objects doesn't smear them into
+/// each other.
+pub struct Filter {
+    config: FilterConfig,
+    /// When false samples pass through untouched, but the filter state keeps
+    /// updating so switching back is instant.
+    pub enabled: bool,
+    buckets: HashMap<(i32, u8, u8), BucketState>,
+}
+
+impl Filter {
+    pub fn new(config: FilterConfig) -> Self {
+        Self {
+            enabled: config.kind != FilterKind::None,
+            config,
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn kind(&self) -> FilterKind {
+        self.config.kind
+    }
+
+    pub fn apply(&mut self, sample: Sample) -> Sample {
+        // No echo, nothing to smooth
+        if self.config.kind == FilterKind::None || sample.distance <= 0.0 {
+            return sample;
+        }
+
+        let bucket = (sample.angle / self.config.bucket_deg.max(0.1)).floor() as i32;
+        let config = &self.config;
+        let state = self
+            .buckets
+            .entry((bucket, sample.layer, sample.echo))
+            .or_insert_with(|| match config.kind {
+                FilterKind::Ema => BucketState::Ema(sample.distance),
+                FilterKind::Kalman => BucketState::Kalman {
+                    estimate: sample.distance,
+                    variance: config.measurement_noise,
+                },
+                _ => BucketState::Median(VecDeque::new()),
+            });
+
+        let filtered = match state {
+            BucketState::Median(window) => {
+                if window.len() >= config.window.max(1) {
+                    window.pop_front();
+                }
+                window.push_back(sample.distance);
+                let mut sorted: Vec<f32> = window.iter().copied().collect();
+                sorted.sort_by(f32::total_cmp);
+                sorted[sorted.len() / 2]
+            }
+            BucketState::Ema(avg) => {
+                *avg += (sample.distance - *avg) * config.alpha.clamp(0.0, 1.0);
+                *avg
+            }
+            BucketState::Kalman { estimate, variance } => {
+                // Predict: the target may have moved a little since last time
+                *variance += config.process_noise;
+                // Update with the new measurement
+                let gain = *variance / (*variance + config.measurement_noise);
+                *estimate += gain * (sample.distance - *estimate);
+                *variance *= 1.0 - gain;
+                *estimate
+            }
+        };
+
+        if self.enabled {
+            Sample {
+                distance: filtered,
+                ..sample
+            }
+        } else {
+            sample
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(kind: FilterKind) -> Filter {
+        Filter::new(FilterConfig {
+            kind,
+            ..FilterConfig::default()
+        })
+    }
+
+    fn distance(filter: &mut Filter, distance: f32) -> f32 {
+        filter.apply(Sample::new(90.0, distance)).distance
+    }
+
+    #[test]
+    fn median_rejects_one_outlier() {
+        let mut median = filter(FilterKind::Median);
+        for reading in [100.0, 100.0, 300.0, 100.0, 100.0] {
+            assert_eq!(distance(&mut median, reading), 100.0);
+        }
+    }
+
+    #[test]
+    fn ema_converges_on_a_new_distance() {
+        let mut ema = filter(FilterKind::Ema);
+        distance(&mut ema, 100.0);
+        let mut last = 100.0;
+        for _ in 0..30 {
+            let next = distance(&mut ema, 200.0);
+            assert!(next > last);
+            last = next;
+        }
+        assert!((last - 200.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn kalman_variance_shrinks_on_a_steady_distance() {
+        let mut kalman = filter(FilterKind::Kalman);
+        for _ in 0..20 {
+            assert_eq!(distance(&mut kalman, 100.0), 100.0);
+        }
+        let Some(BucketState::Kalman { variance, .. }) = kalman.buckets.values().next() else {
+            panic!("no kalman state");
+        };
+        assert!(*variance < kalman.config.measurement_noise / 2.0);
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+use std::ptr;
+
+use raylib::ffi;
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::theme::ThemeKind;
+
+/// Printable ASCII, all the labels and readouts use.
+const GLYPH_COUNT: i32 = 95;
+const SDF_SHADER: &str = include_str!("../shaders/sdf.fs");
+/// DejaVu Sans Mono, the labels' font without `path`, see
+/// `fonts/LICENSE-DejaVu.txt`.
+const EMBEDDED: &[u8] = include_bytes!("../fonts/DejaVuSansMono.ttf");
+const EMBEDDED_NAME: &str = "the built-in DejaVu Sans Mono";
+
+/// `[font]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    /// TTF or OTF file for the angle and range labels and the readouts.
+    /// The embedded DejaVu Sans Mono is used when not set or unreadable
+    pub path: Option<String>,
+    /// Render through a signed distance field, sharp at any size. Off draws
+    /// a plain bitmap rasterized at `base_size`
+    pub sdf: bool,
+    /// Pixel size the glyphs are rasterized at
+    pub base_size: i32,
+    /// Fonts for single themes, by theme name, instead of `path`
+    pub themes: HashMap<ThemeKind, String>,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            sdf: true,
+            base_size: 48,
+            themes: HashMap::new(),
+        }
+    }
+}
+
+/// SDF fonts aren't in the safe bindings, the atlas is put together the way
+/// raylib's own SDF example does it. `name` is for the errors.
+fn load_sdf(name: &str, data: &[u8], base_size: i32) -> Result<Font, String> {
+    let len = i32::try_from(data.len()).map_err(|_| format!("{} is too big", name))?;
+    // SAFETY: the glyphs and rectangles raylib allocates here are handed to
+    // the Font, which frees them with UnloadFont when dropped
+    let font = unsafe {
+        let glyphs = ffi::LoadFontData(
+            data.as_ptr(),
+            len,
+            base_size,
+            ptr::null_mut(),
+            GLYPH_COUNT,
+            ffi::FontType::FONT_SDF as i32,
+        );
+        if glyphs.is_null() {
+            return Err(format!("{} is not a font raylib can read", name));
+        }
+        let mut font: ffi::Font = std::mem::zeroed();
+        font.baseSize = base_size;
+        font.glyphCount = GLYPH_COUNT;
+        font.glyphs = glyphs;
+        let atlas = ffi::GenImageFontAtlas(glyphs, &mut font.recs, GLYPH_COUNT, base_size, 0, 1);
+        font.texture = ffi::LoadTextureFromImage(atlas);
+        ffi::UnloadImage(atlas);
+        // The distance field needs to be sampled smoothly between texels
+        ffi::SetTextureFilter(
+            font.texture,
+            ffi::TextureFilter::TEXTURE_FILTER_BILINEAR as i32,
+        );
+        Font::from_raw(font)
+    };
+    Ok(font)
+}
+
+fn current<'a>(
+    themes: &'a [(ThemeKind, Font)],
+    default: Option<&'a Font>,
+    theme: ThemeKind,
+) -> Option<&'a Font> {
+    themes
+        .iter()
+        .find(|(kind, _)| *kind == theme)
+        .map(|(_, font)| font)
+        .or(default)
+}
+
+/// The labels' font of every theme, drawn through the SDF shader if they
+/// are distance fields.
+pub struct Fonts {
+    /// From `path` or the embedded one, raylib's own if neither loaded
+    default: Option<Font>,
+    themes: Vec<(ThemeKind, Font)>,
+    sdf: Option<Shader>,
+    theme: ThemeKind,
+}
+
+impl Fonts {
+    /// Loads every configured font. Ones that fail to load are left out with
+    /// a warning, their text falls back to the next font.
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread, config: &FontConfig) -> Self {
+        let base_size = config.base_size.clamp(8, 256);
+        let from_memory = |rl: &mut RaylibHandle, name: &str, data: &[u8]| {
+            if config.sdf {
+                load_sdf(name, data, base_size)
+            } else {
+                // The type only picks the loader, TTF's reads OTF too
+                rl.load_font_from_memory(thread, ".ttf", data, base_size, None)
+                    .map_err(|e| format!("Failed to load {}: {}", name, e))
+            }
+        };
+        let load = |rl: &mut RaylibHandle, path: &str| {
+            let font = fs::read(path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))
+                .and_then(|data| from_memory(rl, path, &data));
+            match font {
+                Ok(font) => {
+                    info!("Loaded font {}", path);
+                    Some(font)
+                }
+                Err(e) => {
+                    warn!("{}, falling back to the default font.", e);
+                    None
+                }
+            }
+        };
+        let default = config
+            .path
+            .as_deref()
+            .and_then(|path| load(rl, path))
+            .or_else(|| match from_memory(rl, EMBEDDED_NAME, EMBEDDED) {
+                Ok(font) => Some(font),
+                Err(e) => {
+                    warn!("{}, using raylib's font.", e);
+                    None
+                }
+            });
+        let mut themes: Vec<(ThemeKind, Font)> = config
+            .themes
+            .iter()
+            .filter_map(|(kind, path)| load(rl, path).map(|font| (*kind, font)))
+            .collect();
+        themes.sort_by_key(|(kind, _)| kind.label());
+        let loaded = default.is_some() || !themes.is_empty();
+        let sdf = (config.sdf && loaded)
+            .then(|| rl.load_shader_from_memory(thread, None, Some(SDF_SHADER)));
+        Self {
+            default,
+            themes,
+            sdf,
+            theme: ThemeKind::default(),
+        }
+    }
+
+    /// Picks the font of a theme, called whenever it changes.
+    pub fn select(&mut self, theme: ThemeKind) {
+        self.theme = theme;
+    }
+
+    fn current(&self) -> Option<&Font> {
+        current(&self.themes, self.default.as_ref(), self.theme)
+    }
+
+    /// Like `draw_text`, roughly as wide as the built-in font at that size.
+    fn spacing(size: i32) -> f32 {
+        size as f32 / 20.0
+    }
+
+    pub fn measure<D: Deref<Target = RaylibHandle>>(&self, d: &D, text: &str, size: i32) -> i32 {
+        match self.current() {
+            Some(font) => font
+                .measure_text(text, size as f32, Self::spacing(size))
+                .x
+                .round() as i32,
+            None => d.measure_text(text, size),
+        }
+    }
+
+    pub fn draw<D: RaylibDraw>(
+        &mut self,
+        d: &mut D,
+        text: &str,
+        x: i32,
+        y: i32,
+        size: i32,
+        color: Color,
+    ) {
+        // Not through self.current(), the shader is borrowed mutably below
+        let Some(font) = current(&self.themes, self.default.as_ref(), self.theme) else {
+            d.draw_text(text, x, y, size, color);
+            return;
+        };
+        let pos = Vector2::new(x as f32, y as f32);
+        let (size, spacing) = (size as f32, Self::spacing(size));
+        match &mut self.sdf {
+            Some(shader) => {
+                let mut s = d.begin_shader_mode(shader);
+                s.draw_text_ex(font, text, pos, size, spacing, color);
+            }
+            None => d.draw_text_ex(font, text, pos, size, spacing, color),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::radar::Detection;
+use crate::sensors::SensorConfig;
+use crate::theme::{GradientConfig, Theme};
+use crate::view::{self, Layout};
+
+/// A reading that passes through a cell counts this much less than one that
+/// ends in it. Ultrasonic beams are wide, "nothing here" is weaker evidence.
+const FREE_FACTOR: f32 = 0.5;
+
+/// `[fusion]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FusionConfig {
+    /// Start with the fused view instead of the blips
+    pub enabled: bool,
+    pub cell_deg: f32,
+    pub cell_cm: f32,
+    pub max_cm: f32,
+    /// How much a single reading is trusted, 0..1, unless its `[[sensor]]`
+    /// says otherwise
+    pub confidence: f32,
+    /// Time for a cell nobody confirms to lose half its confidence
+    pub half_life_ms: u64,
+    /// Cells below this are not drawn
+    pub min_confidence: f32,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cell_deg: 3.0,
+            cell_cm: 5.0,
+            max_cm: 400.0,
+            confidence: 0.6,
+            half_life_ms: 1500,
+            min_confidence: 0.2,
+        }
+    }
+}
+
+/// Where a sensor sits and how much it's trusted.
+struct Mount {
+    x_cm: f32,
+    y_cm: f32,
+    confidence: f32,
+}
+
+/// Polar occupancy estimate every sensor contributes to. A detection raises
+/// the confidence of its cell, the cells its beam crossed on the way there
+/// lose some, so two sensors disagreeing about a spot settle on what more of
+/// the readings support instead of both drawing a blip.
+pub struct Fusion {
+    cell_deg: f32,
+    cell_cm: f32,
+    angle_bins: usize,
+    range_bins: usize,
+    half_life: f32,
+    min_confidence: f32,
+    default_mount: Mount,
+    mounts: Vec<Mount>,
+    cells: Vec<f32>,
+}
+
+impl Fusion {
+    pub fn new(config: &FusionConfig, sensors: &[SensorConfig]) -> Self {
+        let cell_deg = config.cell_deg.max(0.5);
+        let cell_cm = config.cell_cm.max(0.5);
+        let angle_bins = (360.0 / cell_deg).ceil() as usize;
+        let range_bins = (config.max_cm / cell_cm).ceil().max(1.0) as usize;
+        Self {
+            cell_deg,
+            cell_cm,
+            angle_bins,
+            range_bins,
+            half_life: (config.half_life_ms as f32 / 1000.0).max(0.01),
+            min_confidence: config.min_confidence,
+            default_mount: Mount {
+                x_cm: 0.0,
+                y_cm: 0.0,
+                confidence: config.confidence.clamp(0.0, 1.0),
+            },
+            mounts: sensors
+                .iter()
+                .map(|s| Mount {
+                    x_cm: s.x_cm,
+                    y_cm: s.y_cm,
+                    confidence: s.confidence.unwrap_or(config.confidence).clamp(0.0, 1.0),
+                })
+                .collect(),
+            cells: vec![0.0; angle_bins * range_bins],
+        }
+    }
+
+    fn cell(&self, angle: f32, distance: f32) -> Option<usize> {
+        let a = (angle.rem_euclid(360.0) / self.cell_deg) as usize;
+        let r = (distance / self.cell_cm) as usize;
+        (a < self.angle_bins && r < self.range_bins).then_some(a * self.range_bins + r)
+    }
+
+    pub fn add(&mut self, det: &Detection) {
+        let mount = self
+            .mounts
+            .get(det.layer as usize)
+            .unwrap_or(&self.default_mount);
+        let weight = mount.confidence * det.strength.unwrap_or(1.0);
+        let (mount_x, mount_y) = (mount.x_cm, mount.y_cm);
+        let Some(hit) = self.cell(det.angle, det.distance) else {
+            return;
+        };
+
+        // Later echoes of a reading lie behind the first, their beam didn't
+        // really cross free space
+        if !det.multi_echo {
+            let rad = det.angle.to_radians();
+            let (x, y) = (det.distance * rad.cos(), det.distance * rad.sin());
+            let (dx, dy) = (x - mount_x, y - mount_y);
+            let length = dx.hypot(dy);
+            let steps = (length / (self.cell_cm * 0.5)) as usize;
+            let mut previous = None;
+            for i in 0..steps {
+                let t = i as f32 / steps as f32;
+                let (px, py) = (mount_x + dx * t, mount_y + dy * t);
+                let cell = self.cell(py.atan2(px).to_degrees(), px.hypot(py));
+                if cell == previous || cell == Some(hit) {
+                    continue;
+                }
+                previous = cell;
+                if let Some(cell) = cell {
+                    self.cells[cell] *= 1.0 - weight * FREE_FACTOR;
+                }
+            }
+        }
+
+        // Independent readings of the same spot add up, never past 1
+        let cell = &mut self.cells[hit];
+        *cell += weight * (1.0 - *cell);
+    }
+
+    /// Lets every cell fade, `dt` seconds after the last call.
+    pub fn decay(&mut self, dt: f32) {
+        let factor = 0.5f32.powf(dt / self.half_life);
+        for cell in &mut self.cells {
+            *cell *= factor;
+        }
+    }
+
+    /// Draws the cells that are likely occupied, more opaque the surer.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+        theme: &Theme,
+        gradient: &GradientConfig,
+    ) {
+        for (i, confidence) in self.cells.iter().enumerate() {
+            if *confidence < self.min_confidence {
+                continue;
+            }
+            let (a, r) = (i / self.range_bins, i % self.range_bins);
+            let inner_cm = r as f32 * self.cell_cm;
+            if inner_cm >= max_range_cm {
+                continue;
+            }
+            let outer_cm = (inner_cm + self.cell_cm).min(max_range_cm);
+            let color = theme.detection_color(gradient, inner_cm / max_range_cm);
+            let start = a as f32 * self.cell_deg;
+            view::draw_sector(
+                d,
+                layout,
+                (start, start + self.cell_deg),
+                (inner_cm * pixels_per_cm, outer_cm * pixels_per_cm),
+                color.alpha(0.9 * confidence),
+                true,
+            );
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use raylib::prelude::GamepadAxis as A;
+use raylib::prelude::*;
+use serde::Deserialize;
+
+/// `[gamepad]` section of the config file. The buttons are bound in `[keys]`
+/// like the keys are.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    /// Which pad, if several are plugged in
+    pub index: i32,
+    /// Stick movement below this is ignored, as sticks rarely rest at zero
+    pub deadzone: f32,
+    /// Pixels per second the left stick pans at when pushed all the way
+    pub pan_speed: f32,
+    /// Zoom steps per second of the right stick, like mouse wheel notches
+    pub zoom_speed: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            index: 0,
+            deadzone: 0.2,
+            pan_speed: 600.0,
+            zoom_speed: 4.0,
+        }
+    }
+}
+
+/// The sticks of a pad: the left one pans the view, the right one zooms it.
+pub struct Gamepad {
+    index: Option<i32>,
+    deadzone: f32,
+    pan_speed: f32,
+    zoom_speed: f32,
+}
+
+impl Gamepad {
+    pub fn new(config: &GamepadConfig) -> Self {
+        Self {
+            index: config.enabled.then_some(config.index.max(0)),
+            deadzone: config.deadzone.clamp(0.0, 0.95),
+            pan_speed: config.pan_speed.max(0.0),
+            zoom_speed: config.zoom_speed.max(0.0),
+        }
+    }
+
+    /// Pad whose buttons count for the hotkeys, none if gamepads are off.
+    pub fn index(&self) -> Option<i32> {
+        self.index
+    }
+
+    /// Position of a stick axis past the deadzone, rescaled to -1..1.
+    fn axis(&self, rl: &RaylibHandle, axis: GamepadAxis) -> f32 {
+        let Some(i) = self.index.filter(|i| rl.is_gamepad_available(*i)) else {
+            return 0.0;
+        };
+        let value = rl.get_gamepad_axis_movement(i, axis);
+        if value.abs() <= self.deadzone {
+            return 0.0;
+        }
+        (value.abs() - self.deadzone) / (1.0 - self.deadzone) * value.signum()
+    }
+
+    /// Whether a button is held or a stick pushed past the deadzone, which
+    /// keeps the frame rate up like the mouse does.
+    pub fn touched(&self, rl: &RaylibHandle) -> bool {
+        const STICKS: [GamepadAxis; 4] = [
+            A::GAMEPAD_AXIS_LEFT_X,
+            A::GAMEPAD_AXIS_LEFT_Y,
+            A::GAMEPAD_AXIS_RIGHT_X,
+            A::GAMEPAD_AXIS_RIGHT_Y,
+        ];
+        if !self.index.is_some_and(|i| rl.is_gamepad_available(i)) {
+            return false;
+        }
+        // Raylib only tells the last button of any pad
+        rl.get_gamepad_button_pressed().is_some() || STICKS.iter().any(|a| self.axis(rl, *a) != 0.0)
+    }
+
+    /// How far to pan this frame. The stick moves the view over the plot, so
+    /// the plot moves the other way.
+    pub fn pan(&self, rl: &RaylibHandle, dt: f32) -> Vector2 {
+        let x = self.axis(rl, A::GAMEPAD_AXIS_LEFT_X);
+        let y = self.axis(rl, A::GAMEPAD_AXIS_LEFT_Y);
+        Vector2::new(-x, -y) * self.pan_speed * dt
+    }
+
+    /// Zoom steps this frame, pushing the right stick up zooms in.
+    pub fn zoom(&self, rl: &RaylibHandle, dt: f32) -> f32 {
+        -self.axis(rl, A::GAMEPAD_AXIS_RIGHT_Y) * self.zoom_speed * dt
+    }
+}
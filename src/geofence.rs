@@ -0,0 +1,110 @@
+use raylib::prelude::*;
+
+use crate::alarm::AlarmZone;
+use crate::ui::UiScale;
+use crate::view::Layout;
+
+/// A click this close to the first corner closes the polygon, pixels.
+const CLOSE_RADIUS_PX: f32 = 10.0;
+
+/// Drawing polygon alarm zones with the mouse, toggled with \: click to
+/// place corners, click the first one again to close the zone. Right-click
+/// takes back the last corner, or removes the polygon zone under the mouse
+/// when there are none.
+#[derive(Default)]
+pub struct GeofenceEditor {
+    pub enabled: bool,
+    /// Corners so far, on the map in cm
+    points: Vec<[f32; 2]>,
+}
+
+impl GeofenceEditor {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.points.clear();
+    }
+
+    pub fn is_drawing(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    /// Places a corner, or closes the polygon on its first one. Returns
+    /// the finished zone. `count` is how many zones there are, for the name.
+    pub fn click(
+        &mut self,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        mouse: Vector2,
+        ui: UiScale,
+        count: usize,
+    ) -> Option<AlarmZone> {
+        if let Some(&[x, y]) = self.points.first()
+            && self.points.len() >= 3
+            && layout.map_to_screen(x, y, pixels_per_cm).distance_to(mouse)
+                <= ui.px(CLOSE_RADIUS_PX)
+        {
+            return Some(AlarmZone {
+                name: format!("fence {}", count + 1),
+                polygon: std::mem::take(&mut self.points),
+                ..AlarmZone::default()
+            });
+        }
+        let (x, y) = layout.screen_to_map(mouse, pixels_per_cm);
+        let round = |v: f32| (v * 10.0).round() / 10.0;
+        self.points.push([round(x), round(y)]);
+        None
+    }
+
+    /// Takes back the last corner. Returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        self.points.pop().is_some()
+    }
+
+    /// The corners placed so far and the edge that follows the mouse.
+    pub fn draw_preview<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        mouse: Vector2,
+        ui: UiScale,
+        color: Color,
+    ) {
+        if self.points.is_empty() {
+            return;
+        }
+        let mut corners: Vec<Vector2> = self
+            .points
+            .iter()
+            .map(|&[x, y]| layout.map_to_screen(x, y, pixels_per_cm))
+            .collect();
+        d.draw_circle_lines(
+            corners[0].x as i32,
+            corners[0].y as i32,
+            ui.px(CLOSE_RADIUS_PX),
+            color,
+        );
+        corners.push(mouse);
+        for pair in corners.windows(2) {
+            d.draw_line_ex(pair[0], pair[1], ui.px(2.0), color);
+        }
+    }
+}
+
+/// Outline of a polygon zone.
+pub fn draw_polygon<D: RaylibDraw>(
+    d: &mut D,
+    layout: &Layout,
+    pixels_per_cm: f32,
+    polygon: &[[f32; 2]],
+    thick: f32,
+    color: Color,
+) {
+    let corners: Vec<Vector2> = polygon
+        .iter()
+        .map(|&[x, y]| layout.map_to_screen(x, y, pixels_per_cm))
+        .collect();
+    for (i, &corner) in corners.iter().enumerate() {
+        d.draw_line_ex(corner, corners[(i + 1) % corners.len()], thick, color);
+    }
+}
@@ -0,0 +1,381 @@
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::pipeline::Update;
+use crate::plugin::Sink;
+use crate::protocol::ProtocolStats;
+use crate::radar::RadarState;
+use crate::source::{LinkState, Sample, Source};
+
+#[cfg(feature = "grpc")]
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+/// A sensor that never finishes a sweep, e.g. a fixed rangefinder, gets its
+/// samples sent in sweeps of this many instead of buffering them forever.
+#[cfg(feature = "grpc")]
+const MAX_SWEEP_SAMPLES: usize = 4096;
+
+/// The messages and service of `proto/radar.proto`, written out the way
+/// prost and tonic would generate them so the build doesn't need protoc.
+#[cfg(feature = "grpc")]
+mod proto {
+    use std::convert::Infallible;
+    use std::task::{Context, Poll};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use tokio::sync::broadcast;
+    use tokio_stream::StreamExt;
+    use tokio_stream::wrappers::BroadcastStream;
+    use tonic::codegen::{Body, BoxFuture, BoxStream, Service, StdError, http};
+    use tonic::server::{Grpc, NamedService, ServerStreamingService};
+    use tonic_prost::ProstCodec;
+
+    use crate::source::Sample;
+
+    pub const SERVICE: &str = "radar.v1.Radar";
+    pub const STREAM_SAMPLES: &str = "/radar.v1.Radar/StreamSamples";
+    pub const STREAM_SWEEPS: &str = "/radar.v1.Radar/StreamSweeps";
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RadarSample {
+        #[prost(float, tag = "1")]
+        pub angle: f32,
+        #[prost(float, tag = "2")]
+        pub distance: f32,
+        #[prost(uint32, tag = "3")]
+        pub echo: u32,
+        #[prost(float, optional, tag = "4")]
+        pub strength: Option<f32>,
+        #[prost(uint32, tag = "5")]
+        pub layer: u32,
+        #[prost(float, optional, tag = "6")]
+        pub tilt: Option<f32>,
+        #[prost(double, tag = "7")]
+        pub time: f64,
+        #[prost(uint32, optional, tag = "8")]
+        pub sensor_ms: Option<u32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Sweep {
+        #[prost(uint64, tag = "1")]
+        pub number: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub samples: Vec<RadarSample>,
+        #[prost(double, tag = "3")]
+        pub time: f64,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct StreamRequest {}
+
+    pub fn unix_now() -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+    }
+
+    impl RadarSample {
+        pub fn new(sample: &Sample, time: f64) -> Self {
+            Self {
+                angle: sample.angle,
+                distance: sample.distance,
+                echo: u32::from(sample.echo),
+                strength: sample.strength,
+                layer: u32::from(sample.layer),
+                tilt: sample.tilt,
+                time,
+                sensor_ms: sample.sensor_ms,
+            }
+        }
+
+        pub fn sample(&self) -> Sample {
+            Sample {
+                echo: self.echo.min(u32::from(u8::MAX)) as u8,
+                strength: self.strength,
+                layer: self.layer.min(u32::from(u8::MAX)) as u8,
+                tilt: self.tilt,
+                sensor_ms: self.sensor_ms,
+                ..Sample::new(self.angle, self.distance)
+            }
+        }
+    }
+
+    /// Hands every subscriber what's sent on the channel from then on. A
+    /// client that falls behind skips what it missed.
+    struct Subscribe<T>(broadcast::Sender<T>);
+
+    impl<T> ServerStreamingService<StreamRequest> for Subscribe<T>
+    where
+        T: prost::Message + Clone + Default + 'static,
+    {
+        type Response = T;
+        type ResponseStream = BoxStream<T>;
+        type Future = BoxFuture<tonic::Response<BoxStream<T>>, tonic::Status>;
+
+        fn call(&mut self, _request: tonic::Request<StreamRequest>) -> Self::Future {
+            let stream = BroadcastStream::new(self.0.subscribe()).filter_map(|m| m.ok().map(Ok));
+            Box::pin(async move { Ok(tonic::Response::new(Box::pin(stream) as BoxStream<T>)) })
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct RadarService {
+        pub samples: broadcast::Sender<RadarSample>,
+        pub sweeps: broadcast::Sender<Sweep>,
+    }
+
+    impl<B> Service<http::Request<B>> for RadarService
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                STREAM_SAMPLES => {
+                    let method = Subscribe(self.samples.clone());
+                    Box::pin(async move {
+                        let mut grpc =
+                            Grpc::new(ProstCodec::<RadarSample, StreamRequest>::default());
+                        Ok(grpc.server_streaming(method, req).await)
+                    })
+                }
+                STREAM_SWEEPS => {
+                    let method = Subscribe(self.sweeps.clone());
+                    Box::pin(async move {
+                        let mut grpc = Grpc::new(ProstCodec::<Sweep, StreamRequest>::default());
+                        Ok(grpc.server_streaming(method, req).await)
+                    })
+                }
+                _ => Box::pin(async move {
+                    let mut response = http::Response::new(tonic::body::Body::default());
+                    let headers = response.headers_mut();
+                    headers.insert(
+                        tonic::Status::GRPC_STATUS,
+                        (tonic::Code::Unimplemented as i32).into(),
+                    );
+                    headers.insert(
+                        http::header::CONTENT_TYPE,
+                        tonic::metadata::GRPC_CONTENT_TYPE,
+                    );
+                    Ok(response)
+                }),
+            }
+        }
+    }
+
+    impl NamedService for RadarService {
+        const NAME: &'static str = SERVICE;
+    }
+}
+
+/// Serves the detections over gRPC, `StreamSamples` as they come and
+/// `StreamSweeps` once a sweep is done, for test rigs built out of services.
+pub struct GrpcServer {
+    #[cfg(feature = "grpc")]
+    service: proto::RadarService,
+    #[cfg(feature = "grpc")]
+    pending: Vec<proto::RadarSample>,
+    #[cfg(feature = "grpc")]
+    sweeps: u64,
+}
+
+impl GrpcServer {
+    /// Listens on `addr`, e.g. `0.0.0.0:50051`, on a thread of its own.
+    #[cfg(feature = "grpc")]
+    pub fn start(addr: &str) -> Result<Self, String> {
+        use tokio::sync::broadcast;
+
+        let listener = std::net::TcpListener::bind(addr)
+            .and_then(|l| l.set_nonblocking(true).map(|_| l))
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start the gRPC server: {}", e))?;
+        let service = proto::RadarService {
+            samples: broadcast::channel(4096).0,
+            sweeps: broadcast::channel(16).0,
+        };
+        let served = service.clone();
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                let incoming = match tokio::net::TcpListener::from_std(listener) {
+                    Ok(l) => tokio_stream::wrappers::TcpListenerStream::new(l),
+                    Err(e) => return tracing::warn!("gRPC server failed: {}", e),
+                };
+                let result = tonic::transport::Server::builder()
+                    .add_service(served)
+                    .serve_with_incoming(incoming)
+                    .await;
+                if let Err(e) = result {
+                    tracing::warn!("gRPC server failed: {}", e);
+                }
+            })
+        });
+        tracing::info!("Serving gRPC on {}", addr);
+        Ok(Self {
+            service,
+            pending: Vec::new(),
+            sweeps: 0,
+        })
+    }
+
+    #[cfg(not(feature = "grpc"))]
+    pub fn start(_addr: &str) -> Result<Self, String> {
+        Err("gRPC support is not compiled in, rebuild with --features grpc".to_string())
+    }
+
+    /// Sends what came in since the last sweep, nothing when that's nothing.
+    #[cfg(feature = "grpc")]
+    fn flush(&mut self, time: f64) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let sweep = proto::Sweep {
+            number: self.sweeps,
+            samples: std::mem::take(&mut self.pending),
+            time,
+        };
+        // Fails only without subscribers
+        let _ = self.service.sweeps.send(sweep);
+    }
+}
+
+impl Sink for GrpcServer {
+    #[cfg(feature = "grpc")]
+    fn write(&mut self, update: &Update, radar: &RadarState, _now: Instant) {
+        let time = proto::unix_now();
+        if radar.sweeps != self.sweeps {
+            self.flush(time);
+            self.sweeps = radar.sweeps;
+        }
+        for sample in &update.samples {
+            let sample = proto::RadarSample::new(sample, time);
+            let _ = self.service.samples.send(sample.clone());
+            self.pending.push(sample);
+            if self.pending.len() >= MAX_SWEEP_SAMPLES {
+                self.flush(time);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "grpc"))]
+    fn write(&mut self, _update: &Update, _radar: &RadarState, _now: Instant) {}
+}
+
+/// Reads `StreamSamples` of another radar's gRPC server, reconnecting when
+/// it goes away.
+pub struct GrpcSource {
+    url: String,
+    rx: Receiver<Sample>,
+    state: Arc<Mutex<LinkState>>,
+    stats: ProtocolStats,
+}
+
+impl GrpcSource {
+    /// `url` is the server's, e.g. `http://rig:50051`.
+    #[cfg(feature = "grpc")]
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let endpoint = tonic::transport::Endpoint::from_shared(url.to_string())
+            .map_err(|e| format!("Invalid gRPC address {}: {}", url, e))?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start the gRPC client: {}", e))?;
+        let (tx, rx) = std::sync::mpsc::channel();
+        let state = Arc::new(Mutex::new(LinkState::Connecting));
+        let thread_state = Arc::clone(&state);
+        let thread_url = url.to_string();
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                loop {
+                    match stream_samples(&endpoint, &tx, &thread_state).await {
+                        // The source was dropped
+                        Ok(()) => return,
+                        Err(e) => {
+                            if *thread_state.lock().unwrap() != LinkState::Disconnected {
+                                tracing::warn!("gRPC stream from {}: {}", thread_url, e);
+                            }
+                            *thread_state.lock().unwrap() = LinkState::Disconnected;
+                        }
+                    }
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            })
+        });
+        Ok(Self {
+            url: url.to_string(),
+            rx,
+            state,
+            stats: ProtocolStats::default(),
+        })
+    }
+
+    #[cfg(not(feature = "grpc"))]
+    pub fn connect(_url: &str) -> Result<Self, String> {
+        Err("gRPC support is not compiled in, rebuild with --features grpc".to_string())
+    }
+}
+
+/// Forwards samples until the stream breaks, which is an error, or the
+/// receiving end is gone.
+#[cfg(feature = "grpc")]
+async fn stream_samples(
+    endpoint: &tonic::transport::Endpoint,
+    tx: &std::sync::mpsc::Sender<Sample>,
+    state: &Mutex<LinkState>,
+) -> Result<(), String> {
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic_prost::ProstCodec;
+
+    let channel = endpoint.connect().await.map_err(|e| e.to_string())?;
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await.map_err(|e| e.to_string())?;
+    let codec = ProstCodec::<proto::StreamRequest, proto::RadarSample>::default();
+    let mut stream = client
+        .server_streaming(
+            tonic::Request::new(proto::StreamRequest {}),
+            PathAndQuery::from_static(proto::STREAM_SAMPLES),
+            codec,
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .into_inner();
+    *state.lock().unwrap() = LinkState::Connected;
+    while let Some(message) = stream.message().await.map_err(|e| e.to_string())? {
+        if tx.send(message.sample()).is_err() {
+            return Ok(());
+        }
+    }
+    Err(String::from("the server closed the stream"))
+}
+
+impl Source for GrpcSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let samples: Vec<Sample> = self.rx.try_iter().collect();
+        self.stats.frames += samples.len() as u64;
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        *self.state.lock().unwrap()
+    }
+
+    fn describe(&self) -> String {
+        format!("grpc {}", self.url)
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        ("grpc", self.stats)
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tracing::info;
+
+use crate::source::{LinkState, Source};
+
+/// The request is repeated this often, an Arduino that resets when the port
+/// opens misses the first one while it boots.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `[handshake]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HandshakeConfig {
+    pub enabled: bool,
+    /// Line sent to ask the firmware who it is
+    pub request: String,
+    /// Config values are used if no banner arrives within this time
+    pub timeout_ms: u64,
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            request: String::from("ID?"),
+            timeout_ms: 3000,
+        }
+    }
+}
+
+/// What the firmware says about itself, e.g.
+/// `RADAR v1.2 maxrange=400 sweep=15-165`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Banner {
+    pub version: Option<String>,
+    pub max_range_cm: Option<f32>,
+    /// Swept sector in degrees, min to max
+    pub sweep: Option<(f32, f32)>,
+}
+
+impl Banner {
+    /// Parses a banner line, `None` for anything that doesn't start with
+    /// `RADAR`. Unknown `key=value` pairs are ignored so newer firmware can
+    /// add its own.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        if !tokens.next()?.eq_ignore_ascii_case("radar") {
+            return None;
+        }
+        let mut banner = Banner::default();
+        for token in tokens {
+            match token.split_once('=') {
+                Some(("maxrange", v)) => {
+                    banner.max_range_cm = v.parse().ok().filter(|cm: &f32| *cm > 0.0)
+                }
+                Some(("sweep", v)) => {
+                    banner.sweep = v
+                        .split_once('-')
+                        .and_then(|(a, b)| Some((a.parse().ok()?, b.parse().ok()?)))
+                        .filter(|(min, max): &(f32, f32)| min < max)
+                }
+                Some(_) => {}
+                None if token.starts_with(['v', 'V']) => {
+                    banner.version = Some(token[1..].to_string())
+                }
+                None => {}
+            }
+        }
+        Some(banner)
+    }
+
+    /// One line for stdout.
+    pub fn describe(&self) -> String {
+        let mut text = String::from("Firmware: RADAR");
+        if let Some(version) = &self.version {
+            text += &format!(" v{}", version);
+        }
+        if let Some(range) = self.max_range_cm {
+            text += &format!(", range {} cm", range);
+        }
+        if let Some((min, max)) = self.sweep {
+            text += &format!(", sweep {}-{}°", min, max);
+        }
+        text
+    }
+}
+
+/// Asks the firmware to identify itself once the link is up and waits for
+/// its banner.
+pub struct Handshake {
+    config: HandshakeConfig,
+    connected_at: Option<Instant>,
+    last_request: Option<Instant>,
+    done: bool,
+}
+
+impl Handshake {
+    pub fn new(config: HandshakeConfig) -> Self {
+        Self {
+            done: !config.enabled,
+            config,
+            connected_at: None,
+            last_request: None,
+        }
+    }
+
+    /// Sends the request when it's due and looks for a banner in the text the
+    /// source received. Returns it once, then stays quiet.
+    pub fn poll(
+        &mut self,
+        source: &mut dyn Source,
+        messages: &[String],
+        now: Instant,
+    ) -> Option<Banner> {
+        if self.done {
+            return None;
+        }
+        if let Some(banner) = messages.iter().find_map(|line| Banner::parse(line)) {
+            self.done = true;
+            return Some(banner);
+        }
+        if source.state() != LinkState::Connected {
+            return None;
+        }
+
+        let connected_at = *self.connected_at.get_or_insert(now);
+        if now.saturating_duration_since(connected_at)
+            >= Duration::from_millis(self.config.timeout_ms)
+        {
+            info!("No firmware banner, using the configured range and coverage.");
+            self.done = true;
+            return None;
+        }
+        let due = self
+            .last_request
+            .is_none_or(|t| now.saturating_duration_since(t) >= RETRY_INTERVAL);
+        if due {
+            self.last_request = Some(now);
+            // Read-only sources can still announce themselves
+            let _ = source.send(&self.config.request);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_full_banner() {
+        let banner = Banner::parse("RADAR v1.2 maxrange=400 sweep=15-165").unwrap();
+        assert_eq!(
+            banner,
+            Banner {
+                version: Some("1.2".to_string()),
+                max_range_cm: Some(400.0),
+                sweep: Some((15.0, 165.0)),
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_pairs_and_case() {
+        let banner = Banner::parse("radar V2 leds=3").unwrap();
+        assert_eq!(banner.version.as_deref(), Some("2"));
+        assert_eq!(banner.max_range_cm, None);
+    }
+
+    #[test]
+    fn drops_bad_values() {
+        let banner = Banner::parse("RADAR maxrange=-5 sweep=165-15").unwrap();
+        assert_eq!(banner, Banner::default());
+        assert_eq!(Banner::parse("RADAR sweep=a-b").unwrap().sweep, None);
+    }
+
+    #[test]
+    fn other_lines_are_not_banners() {
+        assert_eq!(Banner::parse("90,25.3"), None);
+        assert_eq!(Banner::parse(""), None);
+        assert_eq!(Banner::parse("RADARS v1"), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::api::{ApiServer, Settings};
+use crate::config::Config;
+use crate::hooks::Hooks;
+use crate::i18n::Strings;
+use crate::odometry::Odometry;
+use crate::pipeline::Pipeline;
+use crate::plugin::Sink;
+use crate::rate::SweepRate;
+use crate::source::Source;
+use crate::stats::LinkStats;
+use crate::store::SessionStore;
+use crate::timing::SensorClock;
+
+/// Sources poll without blocking, this keeps the loop from spinning.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs the data pipeline without a window: samples go to the sinks,
+/// alarms and link changes to stdout. Runs until the process is killed.
+pub fn run(
+    mut source: Box<dyn Source>,
+    config: Config,
+    mut sinks: Vec<Box<dyn Sink>>,
+    mut store: Option<SessionStore>,
+    mut api: Option<ApiServer>,
+    mut odometry: Option<Odometry>,
+) {
+    let mut pipeline = Pipeline::new(
+        config.validation,
+        config.calibration,
+        config.filter,
+        config.masks,
+        config.alarms,
+    );
+    let unit = config.units;
+    let mut hooks = Hooks::new(config.hooks);
+    let strings = Strings::load_or_default(config.locale.as_deref());
+    info!("Running headless on {}", source.describe());
+
+    let mut link_state = None;
+    // Timestamped samples get put back in order here as well
+    let mut clock = SensorClock::new(Instant::now());
+    // Only for the warning when the servo stalls
+    let mut sweep_rate = SweepRate::new(&config.sweep_rate, Instant::now());
+    // The sample rate and reconnects for the API
+    let mut link_stats = LinkStats::new(Instant::now());
+    let mut last_pass = Instant::now();
+    loop {
+        let now = Instant::now();
+        let frame_seconds = now.saturating_duration_since(last_pass).as_secs_f32();
+        last_pass = now;
+        if let Some(odometry) = &mut odometry {
+            // Nobody reads the other lines without a window
+            odometry.update(source.messages());
+            pipeline.radar.pose = odometry.pose;
+        }
+        let polled = clock.arrange(source.poll(), now);
+        link_stats.polled(polled.len(), source.state(), now);
+        let update = pipeline.process(polled, now);
+        sweep_rate.record(&update.samples, pipeline.radar.sweeps, now);
+        for sink in &mut sinks {
+            sink.write(&update, &pipeline.radar, now);
+        }
+        if let Some(store) = &mut store {
+            store.write(&update, pipeline.alarms.zones());
+        }
+        hooks.update(&update, &pipeline.radar, pipeline.alarms.zones());
+        for event in &update.alarms {
+            warn!("{}", pipeline.alarms.describe(event, unit, &strings));
+        }
+        if let Some(api) = &mut api {
+            if let Some(enabled) = api.take_changes().and_then(|c| c.filter_enabled) {
+                pipeline.filter.enabled = enabled;
+            }
+            let settings = Settings {
+                filter_enabled: Some(pipeline.filter.enabled),
+                ..Settings::default()
+            };
+            api.publish(
+                source.as_ref(),
+                &pipeline,
+                &update,
+                settings,
+                &link_stats,
+                frame_seconds,
+            );
+        }
+        pipeline.prune(now);
+
+        let state = source.state();
+        if link_state != Some(state) {
+            info!("{} [{}]", source.describe(), strings.link_state(state));
+            hooks.link(state, &source.describe());
+            link_state = Some(state);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
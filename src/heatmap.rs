@@ -0,0 +1,106 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::radar::Detection;
+use crate::view::{self, Layout};
+
+/// `[heatmap]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HeatmapConfig {
+    pub cell_deg: f32,
+    pub cell_cm: f32,
+    /// Detections further away than this are not accumulated
+    pub max_cm: f32,
+}
+
+impl Default for HeatmapConfig {
+    fn default() -> Self {
+        Self {
+            cell_deg: 2.0,
+            cell_cm: 5.0,
+            max_cm: 400.0,
+        }
+    }
+}
+
+/// Polar occupancy grid: every detection adds a hit to its cell. Static
+/// obstacles are hit on every sweep and end up bright, noise stays dim.
+pub struct Heatmap {
+    cell_deg: f32,
+    cell_cm: f32,
+    angle_bins: usize,
+    range_bins: usize,
+    hits: Vec<u32>,
+    max_hits: u32,
+}
+
+impl Heatmap {
+    pub fn new(config: &HeatmapConfig) -> Self {
+        let cell_deg = config.cell_deg.max(0.5);
+        let cell_cm = config.cell_cm.max(0.5);
+        let angle_bins = (360.0 / cell_deg).ceil() as usize;
+        let range_bins = (config.max_cm / cell_cm).ceil().max(1.0) as usize;
+        Self {
+            cell_deg,
+            cell_cm,
+            angle_bins,
+            range_bins,
+            hits: vec![0; angle_bins * range_bins],
+            max_hits: 0,
+        }
+    }
+
+    pub fn add(&mut self, det: &Detection) {
+        let a = (det.angle.rem_euclid(360.0) / self.cell_deg) as usize;
+        let r = (det.distance / self.cell_cm) as usize;
+        if a >= self.angle_bins || r >= self.range_bins {
+            return;
+        }
+        let cell = &mut self.hits[a * self.range_bins + r];
+        *cell += 1;
+        self.max_hits = self.max_hits.max(*cell);
+    }
+
+    pub fn clear(&mut self) {
+        self.hits.fill(0);
+        self.max_hits = 0;
+    }
+
+    /// Draws every occupied cell, brightness on a log scale of its hits
+    /// relative to the busiest cell.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+        color: Color,
+    ) {
+        if self.max_hits == 0 {
+            return;
+        }
+        let scale = (1.0 + self.max_hits as f32).ln();
+        for (i, hits) in self.hits.iter().enumerate() {
+            if *hits == 0 {
+                continue;
+            }
+            let (a, r) = (i / self.range_bins, i % self.range_bins);
+            let inner_cm = r as f32 * self.cell_cm;
+            if inner_cm >= max_range_cm {
+                continue;
+            }
+            let outer_cm = (inner_cm + self.cell_cm).min(max_range_cm);
+            let intensity = (1.0 + *hits as f32).ln() / scale;
+            let start = a as f32 * self.cell_deg;
+            view::draw_sector(
+                d,
+                layout,
+                (start, start + self.cell_deg),
+                (inner_cm * pixels_per_cm, outer_cm * pixels_per_cm),
+                color.alpha(0.6 * intensity),
+                true,
+            );
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Deserialize;
+
+use crate::radar::Detection;
+use crate::source::Sample;
+
+/// Angular resolution of the sweep hold, degrees.
+const BIN_DEG: f32 = 1.0;
+const BINS: usize = (360.0 / BIN_DEG) as usize;
+
+/// How detections leave the screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Persistence {
+    /// Fade out over a few seconds, however far the sweep is
+    #[default]
+    Fade,
+    /// Stay until the sweep line comes back over them, like a real radar
+    Sweep,
+}
+
+impl Persistence {
+    pub fn toggled(self) -> Self {
+        match self {
+            Persistence::Fade => Persistence::Sweep,
+            Persistence::Sweep => Persistence::Fade,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Persistence::Fade => "fade",
+            Persistence::Sweep => "sweep",
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Bin {
+    /// Sweep that last passed over it, see `RadarState::sweeps`
+    pass: Option<u64>,
+    detections: Vec<Detection>,
+}
+
+/// Detections of the previous pass, kept per degree until the sweep line
+/// passes that degree again and replaces them. Independent of the history
+/// buffer, which forgets them after a few seconds no matter how slow the
+/// servo is.
+pub struct SweepHold {
+    bins: Vec<Bin>,
+}
+
+impl Default for SweepHold {
+    fn default() -> Self {
+        Self {
+            bins: vec![Bin::default(); BINS],
+        }
+    }
+}
+
+fn bin(angle: f32) -> usize {
+    ((angle.rem_euclid(360.0) / BIN_DEG) as usize).min(BINS - 1)
+}
+
+impl SweepHold {
+    /// `samples` from the first sensor move the sweep, `sweep` is the sweep
+    /// they belong to.
+    pub fn record(&mut self, samples: &[Sample], detections: &[Detection], sweep: u64) {
+        for sample in samples.iter().filter(|s| s.layer == 0) {
+            let bin = &mut self.bins[bin(sample.angle)];
+            if bin.pass != Some(sweep) {
+                bin.pass = Some(sweep);
+                bin.detections.clear();
+            }
+        }
+        // Binned like the samples, by where the sensor pointed
+        for det in detections {
+            self.bins[bin(det.bearing)].detections.push(*det);
+        }
+    }
+
+    pub fn detections(&self) -> impl Iterator<Item = &Detection> {
+        self.bins.iter().flat_map(|b| b.detections.iter())
+    }
+
+    pub fn clear(&mut self) {
+        for bin in &mut self.bins {
+            bin.detections.clear();
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::alarm::{AlarmEvent, AlarmZone};
+use crate::pipeline::Update;
+use crate::protocol;
+use crate::radar::RadarState;
+use crate::source::{LinkState, Sample};
+use crate::ttc::Collision;
+
+/// Hooks still running past this are skipped, a hung command mustn't pile
+/// up a process per alarm.
+const MAX_RUNNING: usize = 16;
+/// A sensor that never finishes a sweep gets its sweep hooks run every this
+/// many samples instead.
+const MAX_SWEEP_SAMPLES: usize = 4096;
+
+/// What a hook runs on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookEvent {
+    /// Something entered an alarm zone
+    #[default]
+    Alarm,
+    Connect,
+    Disconnect,
+    /// A track is on a collision course, see `[ttc]`
+    Collision,
+    /// A sweep finished, its samples go to the command's stdin as csv
+    Sweep,
+}
+
+/// One `[[hook]]` entry of the config file: a shell command run on an event,
+/// e.g. to switch a light or a relay. Details come in `RADAR_*` environment
+/// variables.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HookConfig {
+    pub event: HookEvent,
+    pub command: String,
+    /// Only alarms of the zone with this name
+    pub zone: Option<String>,
+}
+
+/// Runs the configured hooks without waiting for them.
+pub struct Hooks {
+    hooks: Vec<HookConfig>,
+    /// Still running, reaped as they exit
+    children: Vec<Child>,
+    /// Samples of the sweep in progress, only kept when a hook wants them
+    sweep: Vec<Sample>,
+    sweeps: u64,
+    /// Whether hooks are being skipped for `MAX_RUNNING`, warned about once
+    skipping: bool,
+}
+
+impl Hooks {
+    pub fn new(mut hooks: Vec<HookConfig>) -> Self {
+        for hook in hooks.iter().filter(|h| h.command.trim().is_empty()) {
+            warn!("Ignoring a {:?} hook without a command", hook.event);
+        }
+        hooks.retain(|h| !h.command.trim().is_empty());
+        if !hooks.is_empty() {
+            info!("{} event hooks", hooks.len());
+        }
+        Self {
+            hooks,
+            children: Vec::new(),
+            sweep: Vec::new(),
+            sweeps: 0,
+            skipping: false,
+        }
+    }
+
+    /// Call once per pass with its update: runs the alarm hooks and, once a
+    /// sweep is done, the sweep hooks.
+    pub fn update(&mut self, update: &Update, radar: &RadarState, zones: &[AlarmZone]) {
+        self.children
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+        for event in &update.alarms {
+            self.alarm(event, &zones[event.zone]);
+        }
+        if !self.hooks.iter().any(|h| h.event == HookEvent::Sweep) {
+            return;
+        }
+        if radar.sweeps != self.sweeps {
+            self.sweeps = radar.sweeps;
+            self.sweep_done();
+        }
+        self.sweep.extend_from_slice(&update.samples);
+        if self.sweep.len() >= MAX_SWEEP_SAMPLES {
+            self.sweep_done();
+        }
+    }
+
+    fn sweep_done(&mut self) {
+        let samples = std::mem::take(&mut self.sweep);
+        if samples.is_empty() {
+            return;
+        }
+        let vars = [
+            ("RADAR_SWEEP", self.sweeps.to_string()),
+            ("RADAR_SAMPLES", samples.len().to_string()),
+        ];
+        self.run(
+            HookEvent::Sweep,
+            None,
+            &vars,
+            Some(protocol::format_csv(&samples)),
+        );
+    }
+
+    fn alarm(&mut self, event: &AlarmEvent, zone: &AlarmZone) {
+        let vars = [
+            ("RADAR_ZONE", zone.name.clone()),
+            ("RADAR_SEVERITY", zone.severity.label().to_string()),
+            ("RADAR_ANGLE", format!("{:.1}", event.angle)),
+            ("RADAR_DISTANCE_CM", format!("{:.1}", event.distance)),
+        ];
+        self.run(HookEvent::Alarm, Some(&zone.name), &vars, None);
+    }
+
+    /// Call when the link state changes.
+    pub fn link(&mut self, state: LinkState, source: &str) {
+        let event = match state {
+            LinkState::Connected => HookEvent::Connect,
+            LinkState::Disconnected => HookEvent::Disconnect,
+            LinkState::Connecting => return,
+        };
+        self.run(event, None, &[("RADAR_SOURCE", source.to_string())], None);
+    }
+
+    /// Call when a collision alert starts.
+    pub fn collision(&mut self, collision: &Collision) {
+        let vars = [
+            ("RADAR_TRACK", collision.track.to_string()),
+            ("RADAR_TTC_S", format!("{:.2}", collision.seconds)),
+            ("RADAR_ANGLE", format!("{:.1}", collision.angle)),
+            ("RADAR_DISTANCE_CM", format!("{:.1}", collision.distance)),
+        ];
+        self.run(HookEvent::Collision, None, &vars, None);
+    }
+
+    fn run(
+        &mut self,
+        event: HookEvent,
+        zone: Option<&str>,
+        vars: &[(&str, String)],
+        input: Option<String>,
+    ) {
+        let matching = self.hooks.iter().filter(|h| {
+            h.event == event && h.zone.as_deref().is_none_or(|name| zone == Some(name))
+        });
+        for hook in matching {
+            if self.children.len() >= MAX_RUNNING {
+                if !self.skipping {
+                    warn!(
+                        "{} hooks still running, skipping new ones until they exit",
+                        self.children.len()
+                    );
+                    self.skipping = true;
+                }
+                return;
+            }
+            self.skipping = false;
+            let mut command = if cfg!(windows) {
+                let mut c = Command::new("cmd");
+                c.arg("/C");
+                c
+            } else {
+                let mut c = Command::new("sh");
+                c.arg("-c");
+                c
+            };
+            command
+                .arg(&hook.command)
+                .env("RADAR_EVENT", format!("{:?}", event).to_lowercase())
+                .envs(vars.iter().map(|(k, v)| (k, v)))
+                .stdin(if input.is_some() {
+                    Stdio::piped()
+                } else {
+                    Stdio::null()
+                });
+            let mut child = match command.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    warn!("Failed to run hook `{}`: {}", hook.command, e);
+                    continue;
+                }
+            };
+            // A slow reader must not hold up the display
+            if let (Some(text), Some(mut stdin)) = (input.clone(), child.stdin.take()) {
+                thread::spawn(move || {
+                    let _ = stdin.write_all(text.as_bytes());
+                });
+            }
+            self.children.push(child);
+        }
+    }
+}
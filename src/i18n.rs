@@ -0,0 +1,158 @@
+use std::fs;
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::alarm::Severity;
+use crate::source::LinkState;
+
+/// UI strings, read from the locale file named by `locale` in the config.
+/// Missing ones stay English. `{name}` in a template is replaced by the
+/// value it names.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Strings {
+    pub angle: String,
+    pub distance: String,
+    pub range: String,
+    /// After the range while it scales itself
+    pub auto: String,
+    pub connecting: String,
+    pub connected: String,
+    pub disconnected: String,
+    /// `{severity}`, `{zone}`, `{distance}` and `{angle}`
+    pub alarm: String,
+    pub info: String,
+    pub warning: String,
+    pub critical: String,
+    /// `{key}`, the freeze key
+    pub paused: String,
+    /// `{distance}` and `{angle}`
+    pub closest: String,
+    pub closest_clear: String,
+    /// `{filter}`
+    pub filter: String,
+    pub filter_raw: String,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self {
+            angle: String::from("Angle"),
+            distance: String::from("Distance"),
+            range: String::from("Range"),
+            auto: String::from("auto"),
+            connecting: String::from("CONNECTING"),
+            connected: String::from("CONNECTED"),
+            disconnected: String::from("DISCONNECTED"),
+            alarm: String::from("Alarm [{severity}] {zone}: {distance} at {angle} deg"),
+            info: Severity::Info.label().to_string(),
+            warning: Severity::Warning.label().to_string(),
+            critical: Severity::Critical.label().to_string(),
+            paused: String::from("PAUSED ({key}), alarms and logging go on"),
+            closest: String::from("CLOSEST: {distance} @ {angle} deg"),
+            closest_clear: String::from("CLOSEST: clear"),
+            filter: String::from("Filter: {filter}"),
+            filter_raw: String::from("Filter: {filter} (showing raw)"),
+        }
+    }
+}
+
+/// Replaces every `{name}` in `template` with its value, in one pass so a
+/// value with braces in it is left alone. Unknown names stay as they are.
+pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('}').and_then(|close| {
+            let name = &after[..close];
+            let (_, value) = values.iter().find(|(n, _)| *n == name)?;
+            Some((*value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                text.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+impl Strings {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid locale {}: {}", path, e))
+    }
+
+    /// The strings of the locale file at `path`, English without one or if
+    /// it can't be read.
+    pub fn load_or_default(path: Option<&str>) -> Self {
+        let Some(path) = path else {
+            return Self::default();
+        };
+        match Self::load(path) {
+            Ok(strings) => {
+                info!("Using locale {}", path);
+                strings
+            }
+            Err(e) => {
+                warn!("{}, staying in English.", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn link_state(&self, state: LinkState) -> &str {
+        match state {
+            LinkState::Connecting => &self.connecting,
+            LinkState::Connected => &self.connected,
+            LinkState::Disconnected => &self.disconnected,
+        }
+    }
+
+    pub fn severity(&self, severity: Severity) -> &str {
+        match severity {
+            Severity::Info => &self.info,
+            Severity::Warning => &self.warning,
+            Severity::Critical => &self.critical,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_replaces_every_name() {
+        let text = fill("{a} and {b}, {a}", &[("a", "1"), ("b", "2")]);
+        assert_eq!(text, "1 and 2, 1");
+    }
+
+    #[test]
+    fn fill_leaves_braces_in_values_alone() {
+        let text = fill("{zone} at {angle}", &[("zone", "{angle}"), ("angle", "90")]);
+        assert_eq!(text, "{angle} at 90");
+    }
+
+    #[test]
+    fn fill_keeps_unknown_and_unclosed_names() {
+        assert_eq!(fill("{x} {y", &[("y", "2")]), "{x} {y");
+    }
+
+    #[test]
+    fn default_strings_are_ascii() {
+        let strings = Strings::default();
+        assert!(strings.alarm.is_ascii());
+        assert!(strings.paused.is_ascii());
+    }
+}
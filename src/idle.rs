@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use raylib::core::input::key_from_i32;
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::gamepad::Gamepad;
+
+/// Frame rate while anything is going on.
+pub const ACTIVE_FPS: u32 = 60;
+
+/// Raylib's key codes, space to the menu key
+const FIRST_KEY: i32 = 32;
+const LAST_KEY: i32 = 348;
+
+/// `[idle]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    pub enabled: bool,
+    /// Seconds without data or input before the frame rate drops, long
+    /// enough for the trail to fade out
+    pub after_s: f32,
+    /// Frame rate while idle, low enough to leave a Raspberry Pi's CPU alone
+    pub fps: u32,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            after_s: 10.0,
+            fps: 5,
+        }
+    }
+}
+
+/// Whether the mouse, keyboard or pad were touched this frame. Held keys are
+/// looked at rather than the key queue, which the hotkeys still need.
+pub fn input_activity(rl: &RaylibHandle, gamepad: &Gamepad) -> bool {
+    const BUTTONS: [MouseButton; 3] = [
+        MouseButton::MOUSE_BUTTON_LEFT,
+        MouseButton::MOUSE_BUTTON_RIGHT,
+        MouseButton::MOUSE_BUTTON_MIDDLE,
+    ];
+    (FIRST_KEY..=LAST_KEY)
+        .filter_map(key_from_i32)
+        .any(|k| rl.is_key_down(k))
+        || gamepad.touched(rl)
+        || rl.get_mouse_delta() != Vector2::zero()
+        || rl.get_mouse_wheel_move() != 0.0
+        || BUTTONS.iter().any(|b| rl.is_mouse_button_down(*b))
+}
+
+/// Drops the frame rate when nothing has happened for a while and brings it
+/// back on the first frame something does.
+pub struct IdleThrottle {
+    after: Option<Duration>,
+    fps: u32,
+    last_activity: Instant,
+    idle: bool,
+}
+
+impl IdleThrottle {
+    pub fn new(config: &IdleConfig, now: Instant) -> Self {
+        Self {
+            after: config
+                .enabled
+                .then(|| Duration::from_secs_f32(config.after_s.max(1.0))),
+            fps: config.fps.clamp(1, ACTIVE_FPS),
+            last_activity: now,
+            idle: false,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Called once a frame, `active` if data came in, there was input or
+    /// something on screen is moving.
+    pub fn update(&mut self, rl: &mut RaylibHandle, active: bool, now: Instant) {
+        let Some(after) = self.after else {
+            return;
+        };
+        if active {
+            self.last_activity = now;
+        }
+        let idle = now.saturating_duration_since(self.last_activity) >= after;
+        if idle == self.idle {
+            return;
+        }
+        self.idle = idle;
+        if idle {
+            info!("Idle, dropping to {} FPS", self.fps);
+            rl.set_target_fps(self.fps);
+        } else {
+            rl.set_target_fps(ACTIVE_FPS);
+        }
+    }
+}
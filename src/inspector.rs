@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+use raylib::prelude::*;
+
+use crate::keymap::{Action, KeyMap};
+use crate::protocol::{ProtocolStats, Raw};
+use crate::theme::Theme;
+use crate::ui::UiScale;
+
+/// Rows kept in the scrollback.
+const HISTORY_ROWS: usize = 2000;
+const VISIBLE_ROWS: usize = 16;
+const BYTES_PER_ROW: usize = 16;
+/// Longer lines are cut off, they wouldn't fit anyway.
+const MAX_LINE_CHARS: usize = 160;
+const FONT_SIZE: i32 = 16;
+const LINE_HEIGHT: i32 = 19;
+
+/// One row in the scrollback.
+enum Row {
+    Line {
+        text: String,
+        valid: bool,
+    },
+    /// Up to 16 bytes starting at `offset` into the stream
+    Hex {
+        offset: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+/// Serial monitor for debugging firmware output without a separate
+/// terminal: the raw lines as they come, the ones that didn't decode
+/// highlighted, or a hex dump for binary protocols. Page Up/Down and the
+/// mouse wheel scroll back, End follows the stream again (all rebindable).
+#[derive(Default)]
+pub struct Inspector {
+    pub open: bool,
+    rows: VecDeque<Row>,
+    /// Bytes seen so far, for the hex offsets
+    offset: u64,
+    /// Rows scrolled up from the newest, 0 follows the stream
+    scroll: usize,
+    /// Where it was last drawn
+    rect: Rectangle,
+}
+
+impl Inspector {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.scroll = 0;
+    }
+
+    /// Whether `pos` is on the panel, the plot ignores the mouse there.
+    pub fn covers(&self, pos: Vector2) -> bool {
+        self.open && self.rect.check_collision_point_rec(pos)
+    }
+
+    fn push(&mut self, row: Row) {
+        if self.rows.len() == HISTORY_ROWS {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+        // Scrolled back, the rows on screen stay where they are
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.rows.len() - 1);
+        }
+    }
+
+    pub fn record(&mut self, raw: Vec<Raw>) {
+        for chunk in raw {
+            match chunk {
+                Raw::Line { text, valid } => {
+                    let text = text.chars().take(MAX_LINE_CHARS).collect();
+                    self.push(Row::Line { text, valid });
+                }
+                Raw::Bytes(bytes) => self.record_bytes(&bytes),
+            }
+        }
+    }
+
+    fn record_bytes(&mut self, mut bytes: &[u8]) {
+        // Fill up the last row before starting a new one
+        if let Some(Row::Hex { bytes: row, .. }) = self.rows.back_mut()
+            && row.len() < BYTES_PER_ROW
+        {
+            let n = (BYTES_PER_ROW - row.len()).min(bytes.len());
+            row.extend_from_slice(&bytes[..n]);
+            self.offset += n as u64;
+            bytes = &bytes[n..];
+        }
+        for chunk in bytes.chunks(BYTES_PER_ROW) {
+            self.push(Row::Hex {
+                offset: self.offset,
+                bytes: chunk.to_vec(),
+            });
+            self.offset += chunk.len() as u64;
+        }
+    }
+
+    /// The inspector's keys, Page Up/Down, Home and End unless rebound, and
+    /// the mouse wheel over the panel.
+    pub fn handle_input(&mut self, rl: &RaylibHandle, keys: &KeyMap) {
+        let oldest = self.rows.len().saturating_sub(VISIBLE_ROWS);
+        if keys.repeated(rl, Action::InspectorPageUp) {
+            self.scroll += VISIBLE_ROWS;
+        }
+        if keys.repeated(rl, Action::InspectorPageDown) {
+            self.scroll = self.scroll.saturating_sub(VISIBLE_ROWS);
+        }
+        if keys.pressed(rl, Action::InspectorOldest) {
+            self.scroll = oldest;
+        }
+        if keys.pressed(rl, Action::InspectorNewest) {
+            self.scroll = 0;
+        }
+        if self.covers(rl.get_mouse_position()) {
+            let wheel = rl.get_mouse_wheel_move();
+            let lines = (wheel.abs() * 3.0).round() as usize;
+            if wheel > 0.0 {
+                self.scroll += lines;
+            } else {
+                self.scroll = self.scroll.saturating_sub(lines);
+            }
+        }
+        self.scroll = self.scroll.min(oldest);
+    }
+
+    /// In the bottom right corner, next to the console. `protocol` is the
+    /// name and counters of the source's decoder, for the title.
+    pub fn draw<D>(
+        &mut self,
+        d: &mut D,
+        screen_w: f32,
+        screen_h: f32,
+        ui: UiScale,
+        theme: &Theme,
+        protocol: (&str, ProtocolStats),
+    ) where
+        D: RaylibDraw + Deref<Target = RaylibHandle>,
+    {
+        if !self.open {
+            return;
+        }
+        let (font_size, line_height) = (ui.font(FONT_SIZE), ui.pxi(LINE_HEIGHT));
+        let height = (VISIBLE_ROWS as i32 + 1) * line_height + 16;
+        let width = (screen_w * 0.5) as i32 - 20;
+        let (x, y) = (
+            (screen_w * 0.5) as i32 + 10,
+            (screen_h * 0.9) as i32 - height,
+        );
+        self.rect = Rectangle::new(x as f32, y as f32, width as f32, height as f32);
+        d.draw_rectangle(x, y, width, height, Color::BLACK.alpha(0.8));
+        d.draw_rectangle_lines(x, y, width, height, theme.outline);
+
+        let (name, stats) = protocol;
+        let mut title = format!(
+            "RAW {}  {} frames, {} malformed, {} checksum errors",
+            name, stats.frames, stats.malformed, stats.checksum_errors
+        );
+        if self.scroll > 0 {
+            title.push_str(&format!("  [-{}, End follows]", self.scroll));
+        }
+        d.draw_text(&title, x + 8, y + 8, font_size, theme.sweep);
+
+        let byte_w = d.measure_text("00 ", font_size);
+        let offset_w = d.measure_text("00000000  ", font_size);
+        let last = self.rows.len() - self.scroll.min(self.rows.len());
+        let first = last.saturating_sub(VISIBLE_ROWS);
+        for (i, row) in self.rows.range(first..last).enumerate() {
+            let row_y = y + 8 + (i as i32 + 1) * line_height;
+            match row {
+                Row::Line { text, valid } => {
+                    let color = if *valid { theme.outline } else { theme.warning };
+                    d.draw_text(text, x + 8, row_y, font_size, color);
+                }
+                Row::Hex { offset, bytes } => {
+                    // The default font isn't monospaced, every byte gets a column
+                    let text = format!("{:08X}", offset);
+                    d.draw_text(&text, x + 8, row_y, font_size, theme.outline);
+                    for (j, b) in bytes.iter().enumerate() {
+                        let gap = if j >= BYTES_PER_ROW / 2 {
+                            byte_w / 2
+                        } else {
+                            0
+                        };
+                        let col = x + 8 + offset_w + j as i32 * byte_w + gap;
+                        d.draw_text(&format!("{:02X}", b), col, row_y, font_size, theme.sweep);
+                    }
+                    let ascii: String = bytes
+                        .iter()
+                        .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+                        .collect();
+                    let col = x + 8 + offset_w + (BYTES_PER_ROW as i32 + 1) * byte_w;
+                    d.draw_text(&ascii, col, row_y, font_size, theme.outline);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::protocol::{Protocol, ProtocolStats, Raw};
+use crate::source::{LinkState, Sample, Source};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// What the reading thread hands over.
+enum Chunk {
+    /// A new writer, whatever was left of the last one's line is gone
+    Connected,
+    Bytes(Vec<u8>),
+}
+
+/// Reads the wire format from a named pipe or a Unix domain socket, so an
+/// acquisition daemon with access to the hardware can feed a display
+/// running as another user. The path is opened again whenever the writer
+/// goes away, e.g. when the daemon restarts.
+pub struct IpcSource {
+    path: String,
+    rx: Receiver<Chunk>,
+    state: Arc<Mutex<LinkState>>,
+    protocol: Box<dyn Protocol>,
+}
+
+impl IpcSource {
+    pub fn open(path: &str, protocol: Box<dyn Protocol>) -> Result<Self, String> {
+        check(path)?;
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(LinkState::Connecting));
+        let thread_state = Arc::clone(&state);
+        let thread_path = path.to_string();
+        thread::spawn(move || run(&thread_path, tx, thread_state));
+        Ok(Self {
+            path: path.to_string(),
+            rx,
+            state,
+            protocol,
+        })
+    }
+}
+
+/// Fails unless `path` is a FIFO or a socket.
+#[cfg(unix)]
+fn check(path: &str) -> Result<(), String> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let kind = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?
+        .file_type();
+    if kind.is_fifo() || kind.is_socket() {
+        Ok(())
+    } else {
+        Err(format!("{} is neither a FIFO nor a socket", path))
+    }
+}
+
+#[cfg(not(unix))]
+fn check(path: &str) -> Result<(), String> {
+    Err(format!(
+        "Can't read {}: FIFOs and Unix sockets need a Unix system",
+        path
+    ))
+}
+
+/// Connects to the socket, or blocks until the FIFO has a writer.
+#[cfg(unix)]
+fn connect(path: &str) -> io::Result<Box<dyn Read>> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    if std::fs::metadata(path)?.file_type().is_socket() {
+        Ok(Box::new(UnixStream::connect(path)?))
+    } else {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+}
+
+#[cfg(not(unix))]
+fn connect(_path: &str) -> io::Result<Box<dyn Read>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn run(path: &str, tx: Sender<Chunk>, state: Arc<Mutex<LinkState>>) {
+    let set_state = |s| *state.lock().unwrap() = s;
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut stream = match connect(path) {
+            Ok(stream) => stream,
+            Err(e) => {
+                // Once, not every second while the daemon is down
+                if *state.lock().unwrap() != LinkState::Disconnected {
+                    warn!("Failed to open {}: {}", path, e);
+                }
+                set_state(LinkState::Disconnected);
+                thread::sleep(RECONNECT_DELAY);
+                continue;
+            }
+        };
+        info!("Reading from {}", path);
+        if tx.send(Chunk::Connected).is_err() {
+            return;
+        }
+        set_state(LinkState::Connected);
+        loop {
+            match stream.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if tx.send(Chunk::Bytes(buf[..n].to_vec())).is_err() {
+                        // Receiver dropped, the window was closed
+                        return;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    warn!("Failed to read {}: {}", path, e);
+                    break;
+                }
+            }
+        }
+        warn!("{} was closed by the writer, waiting for it again.", path);
+        set_state(LinkState::Disconnected);
+        thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+impl Source for IpcSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        for chunk in self.rx.try_iter() {
+            match chunk {
+                Chunk::Connected => self.protocol.reset(),
+                Chunk::Bytes(bytes) => self.protocol.decode(&bytes, &mut samples),
+            }
+        }
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        *self.state.lock().unwrap()
+    }
+
+    fn describe(&self) -> String {
+        self.path.clone()
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        (self.protocol.name(), self.protocol.stats())
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        self.protocol.take_messages()
+    }
+
+    /// Text lines only, like stdin
+    fn tap(&mut self, on: bool) {
+        self.protocol.tap(on);
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        self.protocol.take_raw()
+    }
+}
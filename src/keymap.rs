@@ -0,0 +1,703 @@
+use std::collections::HashMap;
+
+use raylib::prelude::GamepadButton as P;
+use raylib::prelude::KeyboardKey as K;
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::theme::Theme;
+use crate::ui::UiScale;
+
+const HELP_FONT_SIZE: i32 = 16;
+const HELP_LINE_HEIGHT: i32 = 20;
+const HELP_COLUMN_WIDTH: i32 = 460;
+/// Room for the keys and buttons left of each description.
+const HELP_LABEL_WIDTH: i32 = 150;
+
+/// Something a hotkey does. The console (~), Escape and the layer keys 1-9
+/// are fixed, everything else can be rebound in `[keys]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Help,
+    Shader,
+    ShaderPreset,
+    ShaderParam,
+    ShaderDown,
+    ShaderUp,
+    Fullscreen,
+    Diagnostics,
+    Persistence,
+    Closest,
+    Clusters,
+    Mti,
+    Relearn,
+    Reference,
+    Diff,
+    Freeze,
+    EventLog,
+    Tracks,
+    RawDistances,
+    ZoomIn,
+    ZoomOut,
+    ResetView,
+    AutoRange,
+    Unit,
+    Theme,
+    Mute,
+    Scope,
+    Waterfall,
+    Sectors,
+    Inspector,
+    InspectorPageUp,
+    InspectorPageDown,
+    InspectorOldest,
+    InspectorNewest,
+    Heatmap,
+    Fusion,
+    View3d,
+    Clear,
+    Cursor,
+    Masks,
+    Markers,
+    Geofence,
+    DisplayMode,
+    Projection,
+    Settings,
+    Screenshot,
+    CopyClosest,
+    CopyTracks,
+    CopySweep,
+    Record,
+    PortPicker,
+    Calibration,
+    Profile,
+    Acknowledge,
+}
+
+impl Action {
+    /// In the order the help lists them.
+    const ALL: [Action; 54] = [
+        Action::Help,
+        Action::Shader,
+        Action::ShaderPreset,
+        Action::ShaderParam,
+        Action::ShaderDown,
+        Action::ShaderUp,
+        Action::Fullscreen,
+        Action::Diagnostics,
+        Action::Persistence,
+        Action::Closest,
+        Action::Clusters,
+        Action::Mti,
+        Action::Relearn,
+        Action::Reference,
+        Action::Diff,
+        Action::Freeze,
+        Action::EventLog,
+        Action::Tracks,
+        Action::RawDistances,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ResetView,
+        Action::AutoRange,
+        Action::Unit,
+        Action::Theme,
+        Action::Mute,
+        Action::Scope,
+        Action::Waterfall,
+        Action::Sectors,
+        Action::Inspector,
+        Action::InspectorPageUp,
+        Action::InspectorPageDown,
+        Action::InspectorOldest,
+        Action::InspectorNewest,
+        Action::Heatmap,
+        Action::Fusion,
+        Action::View3d,
+        Action::Clear,
+        Action::Cursor,
+        Action::Masks,
+        Action::Markers,
+        Action::Geofence,
+        Action::DisplayMode,
+        Action::Projection,
+        Action::Settings,
+        Action::Screenshot,
+        Action::CopyClosest,
+        Action::CopyTracks,
+        Action::CopySweep,
+        Action::Record,
+        Action::PortPicker,
+        Action::Calibration,
+        Action::Profile,
+        Action::Acknowledge,
+    ];
+
+    /// Name in `[keys]`.
+    fn name(self) -> &'static str {
+        match self {
+            Action::Help => "help",
+            Action::Shader => "shader",
+            Action::ShaderPreset => "shader-preset",
+            Action::ShaderParam => "shader-param",
+            Action::ShaderDown => "shader-down",
+            Action::ShaderUp => "shader-up",
+            Action::Fullscreen => "fullscreen",
+            Action::Diagnostics => "diagnostics",
+            Action::Persistence => "persistence",
+            Action::Closest => "closest",
+            Action::Clusters => "clusters",
+            Action::Mti => "mti",
+            Action::Relearn => "relearn",
+            Action::Reference => "reference",
+            Action::Diff => "diff",
+            Action::Freeze => "freeze",
+            Action::EventLog => "event-log",
+            Action::Tracks => "tracks",
+            Action::RawDistances => "raw",
+            Action::ZoomIn => "zoom-in",
+            Action::ZoomOut => "zoom-out",
+            Action::ResetView => "reset-view",
+            Action::AutoRange => "auto-range",
+            Action::Unit => "unit",
+            Action::Theme => "theme",
+            Action::Mute => "mute",
+            Action::Scope => "scope",
+            Action::Waterfall => "waterfall",
+            Action::Sectors => "sectors",
+            Action::Inspector => "inspector",
+            Action::InspectorPageUp => "inspector-page-up",
+            Action::InspectorPageDown => "inspector-page-down",
+            Action::InspectorOldest => "inspector-oldest",
+            Action::InspectorNewest => "inspector-newest",
+            Action::Heatmap => "heatmap",
+            Action::Fusion => "fusion",
+            Action::View3d => "3d",
+            Action::Clear => "clear",
+            Action::Cursor => "cursor",
+            Action::Masks => "masks",
+            Action::Markers => "markers",
+            Action::Geofence => "geofence",
+            Action::DisplayMode => "display",
+            Action::Projection => "projection",
+            Action::Settings => "settings",
+            Action::Screenshot => "screenshot",
+            Action::CopyClosest => "copy-closest",
+            Action::CopyTracks => "copy-tracks",
+            Action::CopySweep => "copy-sweep",
+            Action::Record => "record",
+            Action::PortPicker => "port",
+            Action::Calibration => "calibration",
+            Action::Profile => "profile",
+            Action::Acknowledge => "acknowledge",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Action::Help => "Show this help",
+            Action::Shader => "Toggle the CRT shader",
+            Action::ShaderPreset => "Cycle the shader preset",
+            Action::ShaderParam => "Pick the shader parameter",
+            Action::ShaderDown => "Lower the shader parameter",
+            Action::ShaderUp => "Raise the shader parameter",
+            Action::Fullscreen => "Toggle fullscreen",
+            Action::Diagnostics => "Toggle the diagnostics overlay",
+            Action::Persistence => "Fade or sweep persistence",
+            Action::Closest => "Show the closest object",
+            Action::Clusters => "Draw detections as clusters",
+            Action::Mti => "Moving target indication",
+            Action::Relearn => "Learn the MTI background again",
+            Action::Reference => "Take a reference sweep",
+            Action::Diff => "Toggle the diff view",
+            Action::Freeze => "Freeze the display",
+            Action::EventLog => "Toggle the event log",
+            Action::Tracks => "Toggle track labels",
+            Action::RawDistances => "Filtered or raw distances",
+            Action::ZoomIn => "Zoom the range in",
+            Action::ZoomOut => "Zoom the range out",
+            Action::ResetView => "Reset zoom and pan",
+            Action::AutoRange => "Toggle auto range",
+            Action::Unit => "Cycle the distance unit",
+            Action::Theme => "Cycle the color theme",
+            Action::Mute => "Mute or unmute",
+            Action::Scope => "Toggle the A-scope panel",
+            Action::Waterfall => "Toggle the waterfall panel",
+            Action::Sectors => "Toggle the sector statistics",
+            Action::Inspector => "Toggle the raw data inspector",
+            Action::InspectorPageUp => "Inspector: a page back",
+            Action::InspectorPageDown => "Inspector: a page forward",
+            Action::InspectorOldest => "Inspector: the oldest rows",
+            Action::InspectorNewest => "Inspector: follow the stream",
+            Action::Heatmap => "Toggle the occupancy heatmap",
+            Action::Fusion => "Toggle the fused occupancy view",
+            Action::View3d => "2D view or 3D point cloud",
+            Action::Clear => "Clear heatmap, cloud and hold",
+            Action::Cursor => "Toggle the measure cursor",
+            Action::Masks => "Toggle mask editing",
+            Action::Markers => "Toggle marker editing",
+            Action::Geofence => "Toggle polygon zone editing",
+            Action::DisplayMode => "Half circle or full circle",
+            Action::Projection => "Polar rings or x/y map grid",
+            Action::Settings => "Toggle the settings panel",
+            Action::Screenshot => "Save a screenshot",
+            Action::CopyClosest => "Copy the closest object",
+            Action::CopyTracks => "Copy the tracked targets",
+            Action::CopySweep => "Copy the last sweep as CSV",
+            Action::Record => "Start or stop recording",
+            Action::PortPicker => "Pick another serial port",
+            Action::Calibration => "Calibration wizard",
+            Action::Profile => "Switch to the next config profile",
+            Action::Acknowledge => "Acknowledge the alarms",
+        }
+    }
+
+    fn default_keys(self) -> &'static [KeyboardKey] {
+        match self {
+            Action::Help => &[K::KEY_H, K::KEY_F1],
+            Action::Shader => &[K::KEY_S],
+            Action::ShaderPreset => &[K::KEY_F7],
+            Action::ShaderParam => &[K::KEY_F6],
+            Action::ShaderDown => &[K::KEY_LEFT_BRACKET],
+            Action::ShaderUp => &[K::KEY_RIGHT_BRACKET],
+            Action::Fullscreen => &[K::KEY_F],
+            Action::Diagnostics => &[K::KEY_D],
+            Action::Persistence => &[K::KEY_K],
+            Action::Closest => &[K::KEY_N],
+            Action::Clusters => &[K::KEY_B],
+            Action::Mti => &[K::KEY_I],
+            Action::Relearn => &[K::KEY_J],
+            Action::Reference => &[K::KEY_Y],
+            Action::Diff => &[K::KEY_Q],
+            Action::Freeze => &[K::KEY_SPACE],
+            Action::EventLog => &[K::KEY_L],
+            Action::Tracks => &[K::KEY_T],
+            Action::RawDistances => &[K::KEY_R],
+            Action::ZoomIn => &[K::KEY_EQUAL, K::KEY_KP_ADD],
+            Action::ZoomOut => &[K::KEY_MINUS, K::KEY_KP_SUBTRACT],
+            Action::ResetView => &[K::KEY_Z],
+            Action::AutoRange => &[K::KEY_A],
+            Action::Unit => &[K::KEY_U],
+            Action::Theme => &[K::KEY_C],
+            Action::Mute => &[K::KEY_M],
+            Action::Scope => &[K::KEY_G],
+            Action::Waterfall => &[K::KEY_W],
+            Action::Sectors => &[K::KEY_SLASH],
+            Action::Inspector => &[K::KEY_PERIOD],
+            Action::InspectorPageUp => &[K::KEY_PAGE_UP],
+            Action::InspectorPageDown => &[K::KEY_PAGE_DOWN],
+            Action::InspectorOldest => &[K::KEY_HOME],
+            Action::InspectorNewest => &[K::KEY_END],
+            Action::Heatmap => &[K::KEY_O],
+            Action::Fusion => &[K::KEY_V],
+            Action::View3d => &[K::KEY_F3],
+            Action::Clear => &[K::KEY_BACKSPACE],
+            Action::Cursor => &[K::KEY_X],
+            Action::Masks => &[K::KEY_E],
+            Action::Markers => &[K::KEY_F11],
+            Action::Geofence => &[K::KEY_BACKSLASH],
+            Action::DisplayMode => &[K::KEY_P],
+            Action::Projection => &[K::KEY_F9],
+            Action::Settings => &[K::KEY_TAB],
+            Action::Screenshot => &[K::KEY_F12],
+            Action::CopyClosest => &[K::KEY_COMMA],
+            Action::CopyTracks => &[K::KEY_SEMICOLON],
+            Action::CopySweep => &[K::KEY_APOSTROPHE],
+            Action::Record => &[K::KEY_F10],
+            Action::PortPicker => &[K::KEY_F2],
+            Action::Calibration => &[K::KEY_F8],
+            Action::Profile => &[K::KEY_INSERT],
+            Action::Acknowledge => &[K::KEY_ENTER],
+        }
+    }
+
+    /// Buttons of the first pad, as on an Xbox controller: A pauses, B
+    /// acknowledges, X cycles the theme, Y the shader, the bumpers zoom.
+    fn default_buttons(self) -> &'static [GamepadButton] {
+        match self {
+            Action::Help => &[P::GAMEPAD_BUTTON_MIDDLE_LEFT],
+            Action::Fullscreen => &[P::GAMEPAD_BUTTON_MIDDLE_RIGHT],
+            Action::Freeze => &[P::GAMEPAD_BUTTON_RIGHT_FACE_DOWN],
+            Action::Acknowledge => &[P::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT],
+            Action::Theme => &[P::GAMEPAD_BUTTON_RIGHT_FACE_LEFT],
+            Action::Shader => &[P::GAMEPAD_BUTTON_RIGHT_FACE_UP],
+            Action::ZoomIn => &[P::GAMEPAD_BUTTON_RIGHT_TRIGGER_1],
+            Action::ZoomOut => &[P::GAMEPAD_BUTTON_LEFT_TRIGGER_1],
+            Action::ResetView => &[P::GAMEPAD_BUTTON_RIGHT_THUMB],
+            _ => &[],
+        }
+    }
+}
+
+/// Keys with a name of their own: the name in the config file and the label
+/// shown in the help.
+const NAMED_KEYS: &[(&str, &str, KeyboardKey)] = &[
+    ("SPACE", "Space", K::KEY_SPACE),
+    ("TAB", "Tab", K::KEY_TAB),
+    ("BACKSPACE", "Backspace", K::KEY_BACKSPACE),
+    ("ENTER", "Enter", K::KEY_ENTER),
+    ("MINUS", "-", K::KEY_MINUS),
+    ("EQUAL", "=", K::KEY_EQUAL),
+    ("LEFT_BRACKET", "[", K::KEY_LEFT_BRACKET),
+    ("RIGHT_BRACKET", "]", K::KEY_RIGHT_BRACKET),
+    ("COMMA", ",", K::KEY_COMMA),
+    ("PERIOD", ".", K::KEY_PERIOD),
+    ("SLASH", "/", K::KEY_SLASH),
+    ("SEMICOLON", ";", K::KEY_SEMICOLON),
+    ("APOSTROPHE", "'", K::KEY_APOSTROPHE),
+    ("BACKSLASH", "\\", K::KEY_BACKSLASH),
+    ("KP_ADD", "KP+", K::KEY_KP_ADD),
+    ("KP_SUBTRACT", "KP-", K::KEY_KP_SUBTRACT),
+    ("UP", "Up", K::KEY_UP),
+    ("DOWN", "Down", K::KEY_DOWN),
+    ("LEFT", "Left", K::KEY_LEFT),
+    ("RIGHT", "Right", K::KEY_RIGHT),
+    ("HOME", "Home", K::KEY_HOME),
+    ("END", "End", K::KEY_END),
+    ("PAGE_UP", "PgUp", K::KEY_PAGE_UP),
+    ("PAGE_DOWN", "PgDn", K::KEY_PAGE_DOWN),
+    ("INSERT", "Ins", K::KEY_INSERT),
+    ("DELETE", "Del", K::KEY_DELETE),
+];
+
+/// Pad buttons by their name in the config file, after `PAD_`, and label.
+const NAMED_BUTTONS: &[(&str, &str, GamepadButton)] = &[
+    ("A", "Pad A", P::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+    ("B", "Pad B", P::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT),
+    ("X", "Pad X", P::GAMEPAD_BUTTON_RIGHT_FACE_LEFT),
+    ("Y", "Pad Y", P::GAMEPAD_BUTTON_RIGHT_FACE_UP),
+    ("LB", "Pad LB", P::GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+    ("RB", "Pad RB", P::GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+    ("LT", "Pad LT", P::GAMEPAD_BUTTON_LEFT_TRIGGER_2),
+    ("RT", "Pad RT", P::GAMEPAD_BUTTON_RIGHT_TRIGGER_2),
+    ("UP", "Pad Up", P::GAMEPAD_BUTTON_LEFT_FACE_UP),
+    ("DOWN", "Pad Down", P::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+    ("LEFT", "Pad Left", P::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+    ("RIGHT", "Pad Right", P::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+    ("BACK", "Pad Back", P::GAMEPAD_BUTTON_MIDDLE_LEFT),
+    ("START", "Pad Start", P::GAMEPAD_BUTTON_MIDDLE_RIGHT),
+    ("LSTICK", "Pad LS", P::GAMEPAD_BUTTON_LEFT_THUMB),
+    ("RSTICK", "Pad RS", P::GAMEPAD_BUTTON_RIGHT_THUMB),
+];
+
+/// A key or a pad button bound to an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Input {
+    Key(KeyboardKey),
+    Pad(GamepadButton),
+}
+
+impl Input {
+    /// A key as for [`parse_key`], or `PAD_` and a button, like `PAD_A`.
+    fn parse(name: &str) -> Option<Self> {
+        let upper = name.to_ascii_uppercase();
+        match upper.strip_prefix("PAD_") {
+            Some(button) => NAMED_BUTTONS
+                .iter()
+                .find(|(n, _, _)| *n == button)
+                .map(|(_, _, b)| Input::Pad(*b)),
+            None => parse_key(name).map(Input::Key),
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Input::Key(key) => key_label(key),
+            Input::Pad(button) => NAMED_BUTTONS
+                .iter()
+                .find(|(_, _, b)| *b == button)
+                .map_or_else(
+                    || format!("{:?}", button),
+                    |(_, label, _)| label.to_string(),
+                ),
+        }
+    }
+}
+
+/// `A`-`Z`, `0`-`9`, `F1`-`F12`, `KP0`-`KP9` or one of the named keys, like
+/// `SPACE` or `LEFT_BRACKET`.
+pub fn parse_key(name: &str) -> Option<KeyboardKey> {
+    let name = name.to_ascii_uppercase();
+    if let Some((_, _, key)) = NAMED_KEYS.iter().find(|(n, _, _)| *n == name) {
+        return Some(*key);
+    }
+    let code = if let Some(n) = name.strip_prefix("KP").filter(|n| !n.is_empty()) {
+        320 + n.parse::<i32>().ok().filter(|n| *n <= 9)?
+    } else if let Some(n) = name.strip_prefix('F').filter(|n| !n.is_empty()) {
+        289 + n.parse::<i32>().ok().filter(|n| (1..=12).contains(n))?
+    } else {
+        match name.as_bytes() {
+            [c @ (b'0'..=b'9' | b'A'..=b'Z')] => i32::from(*c),
+            _ => return None,
+        }
+    };
+    raylib::core::input::key_from_i32(code)
+}
+
+/// What a key does that isn't an action, for the collision warnings.
+fn fixed_use(key: KeyboardKey) -> Option<&'static str> {
+    match key {
+        K::KEY_GRAVE => Some("the console key"),
+        K::KEY_ESCAPE => Some("the quit key"),
+        _ if (49..=57).contains(&(key as i32)) => Some("a layer key"),
+        _ => None,
+    }
+}
+
+/// Every input bound to two actions, and every key of an action or
+/// `[commands]` that's also a fixed key or sends a command.
+fn collisions(
+    bindings: &[(Action, Vec<Input>)],
+    commands: &[(KeyboardKey, String)],
+) -> Vec<String> {
+    let mut found = Vec::new();
+    for (i, (action, inputs)) in bindings.iter().enumerate() {
+        for input in inputs {
+            if let Some((other, _)) = bindings[..i].iter().find(|(_, o)| o.contains(input)) {
+                found.push(format!(
+                    "{} is bound to both {} and {}",
+                    input.label(),
+                    other.name(),
+                    action.name()
+                ));
+            }
+            let Input::Key(key) = *input else { continue };
+            if let Some(what) = fixed_use(key) {
+                found.push(format!(
+                    "{} of {} is {}",
+                    key_label(key),
+                    action.name(),
+                    what
+                ));
+            }
+            if commands.iter().any(|(k, _)| *k == key) {
+                found.push(format!(
+                    "{} of {} also sends a [commands] line",
+                    key_label(key),
+                    action.name()
+                ));
+            }
+        }
+    }
+    for (key, _) in commands {
+        if let Some(what) = fixed_use(*key) {
+            found.push(format!("{} in [commands] is {}", key_label(*key), what));
+        }
+    }
+    found
+}
+
+/// How a key is shown in the help.
+fn key_label(key: KeyboardKey) -> String {
+    if let Some((_, label, _)) = NAMED_KEYS.iter().find(|(_, _, k)| *k == key) {
+        return label.to_string();
+    }
+    let code = key as i32;
+    match code {
+        48..=57 | 65..=90 => char::from(code as u8).to_string(),
+        290..=301 => format!("F{}", code - 289),
+        320..=329 => format!("KP{}", code - 320),
+        _ => format!("{:?}", key),
+    }
+}
+
+/// One key or several for an action in `[keys]`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Hotkeys and pad buttons of every action, the defaults with `[keys]` on
+/// top.
+pub struct KeyMap {
+    bindings: Vec<(Action, Vec<Input>)>,
+    /// Pad whose buttons count, none if gamepads are off
+    gamepad: Option<i32>,
+}
+
+impl KeyMap {
+    /// `keys` maps action names to their keys and buttons, from `[keys]`. An
+    /// empty list leaves the action without one. Keys that would do two
+    /// things, with each other or the `commands` keys, are warned about.
+    pub fn new(
+        keys: &HashMap<String, KeyNames>,
+        gamepad: Option<i32>,
+        commands: &[(KeyboardKey, String)],
+    ) -> Self {
+        let mut bindings: Vec<(Action, Vec<Input>)> = Action::ALL
+            .iter()
+            .map(|action| {
+                let keys = action.default_keys().iter().map(|k| Input::Key(*k));
+                let buttons = action.default_buttons().iter().map(|b| Input::Pad(*b));
+                (*action, keys.chain(buttons).collect())
+            })
+            .collect();
+        for (name, names) in keys {
+            let Some(i) = Action::ALL.iter().position(|a| a.name() == name) else {
+                warn!("Unknown action {} in [keys], ignored.", name);
+                continue;
+            };
+            let names = match names {
+                KeyNames::One(name) => std::slice::from_ref(name),
+                KeyNames::Many(names) => names.as_slice(),
+            };
+            bindings[i].1 = names
+                .iter()
+                .filter_map(|key| {
+                    let parsed = Input::parse(key);
+                    if parsed.is_none() {
+                        warn!("Unknown key {} for {} in [keys], ignored.", key, name);
+                    }
+                    parsed
+                })
+                .collect();
+        }
+        for collision in collisions(&bindings, commands) {
+            warn!("{}, both happen on a press.", collision);
+        }
+        Self { bindings, gamepad }
+    }
+
+    pub fn pressed(&self, rl: &RaylibHandle, action: Action) -> bool {
+        self.triggered(rl, action, false)
+    }
+
+    /// Like `pressed`, and again while a key is held down, for scrolling.
+    pub fn repeated(&self, rl: &RaylibHandle, action: Action) -> bool {
+        self.triggered(rl, action, true)
+    }
+
+    /// The keys of `action` as the help shows them, `-` without any.
+    pub fn label(&self, action: Action) -> String {
+        let labels: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(a, _)| *a == action)
+            .flat_map(|(_, inputs)| inputs)
+            .filter(|input| self.gamepad.is_some() || matches!(input, Input::Key(_)))
+            .map(|input| input.label())
+            .collect();
+        if labels.is_empty() {
+            String::from("-")
+        } else {
+            labels.join(" / ")
+        }
+    }
+
+    fn triggered(&self, rl: &RaylibHandle, action: Action, repeat: bool) -> bool {
+        let pad = self.gamepad.filter(|i| rl.is_gamepad_available(*i));
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .is_some_and(|(_, inputs)| {
+                inputs.iter().any(|input| match input {
+                    Input::Key(key) => {
+                        rl.is_key_pressed(*key) || (repeat && rl.is_key_pressed_repeat(*key))
+                    }
+                    Input::Pad(button) => {
+                        pad.is_some_and(|i| rl.is_gamepad_button_pressed(i, *button))
+                    }
+                })
+            })
+    }
+}
+
+/// Every action and its current keys, toggled with the help action.
+#[derive(Default)]
+pub struct HelpOverlay {
+    pub open: bool,
+}
+
+impl HelpOverlay {
+    /// Two columns centered on the screen.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        keys: &KeyMap,
+        screen_w: f32,
+        screen_h: f32,
+        ui: UiScale,
+        theme: &Theme,
+    ) {
+        if !self.open {
+            return;
+        }
+        let (font_size, line_height) = (ui.font(HELP_FONT_SIZE), ui.pxi(HELP_LINE_HEIGHT));
+        let column_width = ui.pxi(HELP_COLUMN_WIDTH);
+        let rows = keys.bindings.len().div_ceil(2) as i32;
+        let width = column_width * 2 + 20;
+        let height = (rows + 2) * line_height + 20;
+        let x = (screen_w as i32 - width) / 2;
+        let y = ((screen_h as i32 - height) / 2).max(0);
+        d.draw_rectangle(x, y, width, height, Color::BLACK.alpha(0.85));
+        d.draw_rectangle_lines(x, y, width, height, theme.outline);
+        d.draw_text(
+            "Keys (rebind them in [keys] of the config file)",
+            x + 10,
+            y + 10,
+            font_size,
+            theme.sweep,
+        );
+
+        for (i, (action, _)) in keys.bindings.iter().enumerate() {
+            let column = i as i32 / rows;
+            let row = i as i32 % rows;
+            let kx = x + 10 + column * (column_width + 10);
+            let ky = y + 10 + (row + 2) * line_height;
+            d.draw_text(&keys.label(*action), kx, ky, font_size, theme.warning);
+            d.draw_text(
+                action.description(),
+                kx + ui.pxi(HELP_LABEL_WIDTH),
+                ky,
+                font_size,
+                theme.outline,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(pairs: &[(&str, &str)]) -> HashMap<String, KeyNames> {
+        pairs
+            .iter()
+            .map(|(action, key)| (action.to_string(), KeyNames::One(key.to_string())))
+            .collect()
+    }
+
+    fn bindings(keys: &HashMap<String, KeyNames>) -> Vec<(Action, Vec<Input>)> {
+        KeyMap::new(keys, None, &[]).bindings
+    }
+
+    #[test]
+    fn the_defaults_dont_collide() {
+        assert!(collisions(&bindings(&HashMap::new()), &[]).is_empty());
+    }
+
+    #[test]
+    fn a_key_of_two_actions_collides() {
+        let found = collisions(&bindings(&bind(&[("freeze", "S")])), &[]);
+        assert_eq!(found, ["S is bound to both shader and freeze"]);
+    }
+
+    #[test]
+    fn fixed_and_command_keys_collide() {
+        let keys = bind(&[("mute", "1"), ("unit", "PAGE_UP")]);
+        let commands = [
+            (K::KEY_T, "PING".to_string()),
+            (K::KEY_F5, "SPEED 5".to_string()),
+            (K::KEY_TWO, "RESET".to_string()),
+        ];
+        let found = collisions(&bindings(&keys), &commands);
+        assert_eq!(found.len(), 4, "{:?}", found);
+        assert!(found.contains(&"1 of mute is a layer key".to_string()));
+        assert!(found.contains(&"PgUp is bound to both unit and inspector-page-up".to_string()));
+        assert!(found.contains(&"2 in [commands] is a layer key".to_string()));
+        assert!(found.contains(&"T of tracks also sends a [commands] line".to_string()));
+    }
+}
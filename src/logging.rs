@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::sync::{Arc, Mutex};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::capture::Timestamp;
+use crate::theme::Theme;
+use crate::ui::UiScale;
+
+/// Events kept for the panel, older ones are dropped.
+const MAX_ENTRIES: usize = 200;
+const VISIBLE_LINES: usize = 12;
+const LINE_HEIGHT: i32 = 20;
+const FONT_SIZE: i32 = 16;
+
+/// Least severe events that are shown and written.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// `[logging]` section of the config file.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub level: LogLevel,
+    /// Every event is appended here too
+    pub file: Option<String>,
+}
+
+struct Entry {
+    time: String,
+    level: Level,
+    message: String,
+}
+
+/// Recent events, shared between the subscriber and the panel drawing them.
+#[derive(Clone, Default)]
+pub struct EventLog {
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+}
+
+/// Collects the formatted message fields of one event.
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        } else {
+            self.0 = format!("{} {}={:?}", self.0, field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = value.to_string();
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for EventLog {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        let stamp = Timestamp::now();
+        let entry = Entry {
+            time: format!("{:02}:{:02}:{:02}", stamp.hour, stamp.minute, stamp.second),
+            level: *event.metadata().level(),
+            message: message.0,
+        };
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// Sends events to the terminal, the log file if one is configured and the
+/// returned log for the in-app panel. Call once, before anything is logged.
+pub fn init(config: &LoggingConfig) -> EventLog {
+    let log = EventLog::default();
+    let file = config.file.as_ref().and_then(|path| {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                // Nothing to log with yet
+                println!("Warning: Failed to open log file {}: {}", path, e);
+                None
+            }
+        }
+    });
+    let file_layer = file.map(|file| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_target(false)
+            .with_writer(Mutex::<File>::new(file))
+    });
+    tracing_subscriber::registry()
+        .with(config.level.filter())
+        .with(tracing_subscriber::fmt::layer().with_target(false))
+        .with(file_layer)
+        .with(log.clone())
+        .init();
+    log
+}
+
+/// Scrolling list of the latest events, toggled with L.
+#[derive(Default)]
+pub struct EventPanel {
+    pub open: bool,
+}
+
+impl EventPanel {
+    /// Drawn in the bottom right, across from the console.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        log: &EventLog,
+        screen_w: f32,
+        screen_h: f32,
+        ui: UiScale,
+        theme: &Theme,
+    ) {
+        if !self.open {
+            return;
+        }
+        let line_height = ui.pxi(LINE_HEIGHT);
+        let height = VISIBLE_LINES as i32 * line_height + 16;
+        let width = (screen_w * 0.48) as i32;
+        let x = screen_w as i32 - width - 10;
+        let y = (screen_h * 0.9) as i32 - height;
+        d.draw_rectangle(x, y, width, height, Color::BLACK.alpha(0.8));
+        d.draw_rectangle_lines(x, y, width, height, theme.outline);
+
+        let Ok(entries) = log.entries.lock() else {
+            return;
+        };
+        let first = entries.len().saturating_sub(VISIBLE_LINES);
+        for (i, entry) in entries.iter().skip(first).enumerate() {
+            let color = if entry.level <= Level::WARN {
+                theme.warning
+            } else {
+                theme.outline
+            };
+            d.draw_text(
+                &format!("{} {}", entry.time, entry.message),
+                x + 8,
+                y + 8 + i as i32 * line_height,
+                ui.font(FONT_SIZE),
+                color,
+            );
+        }
+    }
+}
@@ -1,64 +1,342 @@
+mod alarm;
+mod api;
+mod audio;
+mod autobaud;
+mod bench;
+mod calibration;
+mod capture;
+mod cli;
+mod clipboard;
+mod closest;
+mod cloud;
+mod cluster;
+mod config;
+mod console;
+mod cursor;
+mod datalog;
+mod diff;
+mod discovery;
+mod error;
+mod filter;
+mod font;
+mod fusion;
+mod gamepad;
+mod geofence;
+mod grpc;
+mod handshake;
+mod headless;
+mod heatmap;
+mod hold;
+mod hooks;
+mod i18n;
+mod idle;
+mod inspector;
+mod ipc;
+mod keymap;
+mod logging;
+mod markers;
+mod mask;
+mod mqtt;
+mod mti;
+mod nmea;
+mod notify;
+mod odometry;
+mod picker;
+mod pipeline;
+mod plugin;
+mod points;
+mod protocol;
+mod radar;
+mod range;
+mod rate;
+mod rebroadcast;
+mod record;
+mod replay;
+mod report;
+mod rerun_sink;
+mod ros;
+mod rplidar;
+mod scope;
+mod sectors;
+mod sensors;
+mod settings;
+mod shader;
+mod source;
+mod split;
+mod state;
+mod stats;
+mod stdin;
+mod store;
+mod sweep;
+mod tfmini;
+mod theme;
+mod timing;
+mod tracker;
+mod ttc;
+mod ui;
+mod units;
+mod validate;
+mod view;
+mod waterfall;
+#[cfg(feature = "web")]
+mod web;
+mod window;
+
 use raylib::prelude::*;
 use std::env;
-use std::io::{self, BufRead, BufReader, Write};
-use std::time::Duration;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use std::time::Instant;
+use tracing::{error, info, warn};
 
-// ===================== CONFIG =====================
-const SCREEN_WIDTH: i32 = 1200;
-const SCREEN_HEIGHT: i32 = 700;
-const MAX_RANGE_CM: f32 = 40.0;
+use alarm::Severity;
+use api::{ApiServer, Settings};
+use audio::Audio;
+use autobaud::Baud;
+use bench::{Bench, CountingAlloc};
+use calibration::CalibrationWizard;
+use capture::Timestamp;
+use cli::Args;
+use closest::ClosestTracker;
+use cloud::{Orbit, PointCloud};
+use cluster::Clustering;
+use config::{Config, ConfigWatcher, DEFAULT_CONFIG_PATH};
+use console::Console;
+use cursor::MeasureCursor;
+use diff::SweepDiff;
+use error::AppError;
+use filter::FilterKind;
+use font::Fonts;
+use fusion::Fusion;
+use gamepad::Gamepad;
+use geofence::GeofenceEditor;
+use handshake::Handshake;
+use heatmap::Heatmap;
+use hold::{Persistence, SweepHold};
+use hooks::Hooks;
+use i18n::Strings;
+use idle::IdleThrottle;
+use inspector::Inspector;
+use keymap::{Action, HelpOverlay, KeyMap};
+use logging::EventPanel;
+use markers::MarkerEditor;
+use mask::{MASK_DIM, MaskEditor};
+use mti::Mti;
+use notify::Notifier;
+use odometry::Odometry;
+use picker::PortPicker;
+use pipeline::{DETECTION_LIFETIME, Freeze, Pipeline};
+use plugin::Sink;
+use points::PointBatch;
+use radar::Detection;
+use range::RangeScale;
+use rate::SweepRate;
+use record::Recorder;
+use scope::AScope;
+use sectors::SectorStats;
+use sensors::{Layers, MultiSource, SensorConfig};
+use settings::{SettingsPanel, Values};
+use shader::{Param, Params, PostShader};
+use source::{LinkState, NoSource, Source};
+use split::{SplitLayout, ViewKind};
+use state::{FileSettings, SavedState, StateSaver, WindowState};
+use stats::LinkStats;
+use store::SessionStore;
+use sweep::SweepAnimator;
+use timing::SensorClock;
+use ttc::TtcMonitor;
+use ui::UiScale;
+use view::{DisplayMode, Panel, Projection, Viewport};
+use waterfall::Waterfall;
 
-const BACKGROUND_COLOR: Color = Color::new(10, 15, 10, 255);
-const FADE_ANIMATION_COLOR: Color = Color::new(0, 10, 0, 18);
-const RADAR_OUTLINE: Color = Color::new(30, 120, 50, 255);
-const DETECTED_OBJECT: Color = Color::new(255, 60, 60, 255);
-const SWEEP_LINE_COLOR: Color = Color::new(150, 255, 170, 255);
+// ===================== CONFIG =====================
+const SCREEN_WIDTH: i32 = ui::BASE_WIDTH as i32;
+const SCREEN_HEIGHT: i32 = ui::BASE_HEIGHT as i32;
 
 const SWEEP_LINE_THICKNESS: f32 = 4.0;
 const SWEEP_SPREAD_DEG: f32 = 3.0;
 const SWEEP_STEP_DEG: f32 = 0.3;
+const BLIP_RADIUS: f32 = 6.0;
+// Sweeps with more readings than this (lidars) are drawn as a point cloud
+const DENSE_SWEEP_SAMPLES: usize = 400;
+const POINT_RADIUS: f32 = 2.0;
+// Length of the predicted path drawn ahead of a track, in seconds of motion
+const TRACK_VECTOR_SECONDS: f32 = 2.0;
+// Seconds the shader parameter readout stays up after [ or ]
+const SHADER_HINT_SECONDS: f64 = 2.0;
+// Seconds of frames a --bench run measures without --bench-seconds
+const BENCH_SECONDS: f32 = 30.0;
 
-// This happens at COMPILE time, putting the text inside your EXE
-const SHADER_SOURCE: &str = include_str!("../shaders/radar_phosphor.fs");
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    let (port_name, baud_rate) = if args.len() >= 3 {
-        // Option A: CLI Arguments
-        let p = args[1].clone();
-        let b = args[2].parse().unwrap_or(9600);
-        println!("Using CLI arguments: Port: {}, Baud: {}", p, b);
-        (p, b) // Return these to be assigned
+    let raw_args: Vec<String> = env::args().skip(1).collect();
+    match cli::parse_report(&raw_args) {
+        Ok(Some(request)) => {
+            if let Err(msg) = report::run(request) {
+                println!("{}", msg);
+                process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(msg) => {
+            println!("{}", msg);
+            process::exit(2);
+        }
+    }
+    match cli::parse_command(&raw_args) {
+        Ok(Some(command)) => {
+            if let Err(msg) = store::run(command) {
+                println!("{}", msg);
+                process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(msg) => {
+            println!("{}", msg);
+            process::exit(2);
+        }
+    }
+
+    let args = match Args::parse(raw_args) {
+        Ok(args) => args,
+        Err(msg) => {
+            println!("{}", msg);
+            process::exit(2);
+        }
+    };
+
+    let mut config = match load_config(&args, args.profile.as_deref()) {
+        Ok(config) => config,
+        Err(msg) => {
+            println!("{}", msg);
+            process::exit(2);
+        }
+    };
+
+    let event_log = logging::init(&config.logging);
+    let strings = Strings::load_or_default(config.locale.as_deref());
+    let mut profiles = config.profile_names();
+    // What the file said when it was last read, to tell what an edit changed
+    let mut file_values = Values::from_config(&config);
+    // A bench run starts from the config alone, so runs compare
+    let mut saved = if args.bench {
+        SavedState::default()
+    } else {
+        SavedState::load(&config.state)
+    }
+    .settled(&config, args.profile.is_some());
+    // The saved settings the file had, to tell next time if it was edited
+    let mut file_settings = FileSettings::of(&config);
+
+    // ---- Open Data Source ----
+    let protocol = args.protocol.unwrap_or(config.protocol);
+    // A source on the command line replaces the [[sensor]] list
+    let use_sensors = args.port.is_none() && args.source.is_none() && !config.sensors.is_empty();
+    // Without a port the picker asks for one in the window, or stdin headless
+    let pick_port = !use_sensors
+        && !args.headless
+        && args.source.is_none()
+        && (args.port.is_none() || args.baud.is_none());
+    // The port of the last run is opened again instead of asking for one
+    let resumed = saved
+        .port
+        .clone()
+        .zip(saved.baud.as_deref().and_then(Baud::parse))
+        .filter(|_| pick_port && args.port.is_none());
+    let pick_port = pick_port && resumed.is_none();
+    let mut current_port = resumed.clone();
+    let plugins = plugin::Context {
+        config: &config,
+        protocol,
+        topic: &args.topic,
+        qos: args.qos,
+    };
+    let mut source: Box<dyn Source> = if let Some(spec) = &args.source {
+        match plugin::open_source(spec, &plugins) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("{}", e);
+                process::exit(2);
+            }
+        }
+    } else if use_sensors {
+        Box::new(MultiSource::open(&config.sensors, protocol))
+    } else if let Some((port_name, baud_rate)) = &resumed {
+        info!("Reopening {} from the last run", port_name);
+        discovery::open(port_name, *baud_rate, protocol)
+    } else if pick_port {
+        Box::new(NoSource)
     } else {
-        // Option B: Interactive Fallback
-        println!("\n--- Available Serial Ports ---");
-        if let Ok(ports) = serialport::available_ports() {
-            for p in ports {
-                println!(" -> {}", p.port_name);
-            }
-        }
-
-        print!("\nEnter Serial Port: ");
-        io::stdout().flush().unwrap();
-        let mut input_port = String::new();
-        io::stdin()
-            .read_line(&mut input_port)
-            .expect("Failed to read line");
-
-        print!("Enter Baud Rate (default 9600): ");
-        io::stdout().flush().unwrap();
-        let mut baud_str = String::new();
-        io::stdin()
-            .read_line(&mut baud_str)
-            .expect("Failed to read line");
-
-        (
-            input_port.trim().to_string(),
-            baud_str.trim().parse::<u32>().unwrap_or(9600),
-        )
+        let (port_name, baud_rate) = match (args.port.clone(), args.baud) {
+            (Some(p), Some(b)) => {
+                // Option A: CLI Arguments
+                info!("Using CLI arguments: Port: {}, Baud: {}", p, b.label());
+                (p, b)
+            }
+            _ => match prompt_serial_port() {
+                Ok(choice) => choice,
+                Err(e) => {
+                    error!("{} {}", e, e.hint());
+                    process::exit(2);
+                }
+            },
+        };
+        current_port = Some((port_name.clone(), baud_rate));
+        discovery::open(&port_name, baud_rate, protocol)
     };
 
+    let mut sinks: Vec<Box<dyn Sink>> = args
+        .sinks
+        .iter()
+        .map(|spec| match plugin::open_sink(spec, &plugins) {
+            Ok(sink) => sink,
+            Err(e) => {
+                error!("{}", e);
+                process::exit(2);
+            }
+        })
+        .collect();
+
+    let mut store =
+        args.db
+            .as_deref()
+            .map(|path| match SessionStore::open(path, &source.describe()) {
+                Ok(store) => store,
+                Err(e) => {
+                    error!("{}", e);
+                    process::exit(2);
+                }
+            });
+
+    let mut api = args
+        .http
+        .as_deref()
+        .map(|addr| match ApiServer::start(addr) {
+            Ok(api) => api,
+            Err(e) => {
+                error!("{}", e);
+                process::exit(2);
+            }
+        });
+
+    let mut odometry = args.pose.as_deref().map(|spec| match Odometry::open(spec) {
+        Ok(odometry) => odometry,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(2);
+        }
+    });
+
+    if args.headless {
+        headless::run(source, config, sinks, store, api, odometry);
+        return;
+    }
+
     // ---- Initialize Raylib ----
     let (mut rl, thread) = raylib::init()
         .size(SCREEN_WIDTH, SCREEN_HEIGHT)
@@ -66,191 +344,1183 @@ fn main() {
         .msaa_4x()
         .build();
 
-    rl.set_target_fps(60);
-
-    // ---- Open Serial ----
-    let mut reader = serialport::new(&port_name, baud_rate)
-        .timeout(Duration::from_millis(10))
-        .open()
-        .ok()
-        .map(|port| BufReader::new(port));
+    rl.set_target_fps(idle::ACTIVE_FPS);
+    let mut bench = args.bench.then(|| {
+        // Uncapped, the frame times are what the rendering takes
+        rl.set_target_fps(0);
+        Bench::new(args.bench_seconds.unwrap_or(BENCH_SECONDS), Instant::now())
+    });
+    window::setup(&mut rl, &config.window);
+    if let Some(w) = saved.window
+        && !config.window.start_fullscreen
+    {
+        rl.set_window_size(w.width, w.height);
+        rl.set_window_position(w.x, w.y);
+    }
+    let (start_sw, start_sh) = (rl.get_screen_width() as u32, rl.get_screen_height() as u32);
 
-    if reader.is_none() {
-        println!("Warning: Failed to open serial port.");
+    // ---- Audio ----
+    // Optional, machines without a sound card still get the visual alarms
+    let audio_device = RaylibAudio::init_audio_device().ok();
+    let mut audio = audio_device
+        .as_ref()
+        .and_then(|device| Audio::load(device, config.audio.clone()));
+    if audio.is_none() {
+        warn!("No audio device, alarms will be silent.");
     }
 
     // ---- Shader and Render Texture ----
-    let mut shaders = rl.load_shader_from_memory(&thread, None, Some(SHADER_SOURCE));
-    let intensity_loc = shaders.get_shader_location("intensity");
-    shaders.set_shader_value(intensity_loc, 1.5f32);
+    let mut post = PostShader::load(
+        &mut rl,
+        &thread,
+        &config.visuals.shader_dir,
+        config.visuals.preset,
+        Params::new(&config.visuals),
+    );
+    let mut split = SplitLayout::load(
+        &mut rl,
+        &thread,
+        &config.views,
+        &config.visuals.shader_dir,
+        Params::new(&config.visuals),
+    );
+    let mut shader_param = Param::default();
+    let mut shader_hint_until = 0.0;
+
+    // Persistence layer: grid and sweep trail, faded a little every frame.
+    // Scene: persistence layer plus detections, redrawn every frame and fed
+    // to the shader
+    let Some((mut target, mut scene)) = error::retrying(
+        &mut rl,
+        &thread,
+        Some(KeyboardKey::KEY_ESCAPE),
+        config.window.ui_scale,
+        |rl, thread| render_textures(rl, thread, start_sw, start_sh),
+    ) else {
+        return;
+    };
 
-    let mut target = rl
-        .load_render_texture(&thread, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
-        .expect("Failed to create render texture");
+    let mut theme_kind = saved.theme.unwrap_or(config.theme);
+    let mut theme = theme_kind.theme();
+    let mut fonts = Fonts::load(&mut rl, &thread, &config.font);
+    fonts.select(theme_kind);
+    let mut fade_half_life_s = config
+        .visuals
+        .fade_half_life_s
+        .unwrap_or_else(|| theme.fade_half_life());
+
+    // Sweep history for the waterfall panel
+    let Some(mut waterfall) = error::retrying(
+        &mut rl,
+        &thread,
+        Some(KeyboardKey::KEY_ESCAPE),
+        config.window.ui_scale,
+        |rl, thread| Waterfall::new(rl, thread).map_err(AppError::texture("the waterfall")),
+    ) else {
+        return;
+    };
+    // Detections, queued and drawn in one batch
+    let Some(mut points) = error::retrying(
+        &mut rl,
+        &thread,
+        Some(KeyboardKey::KEY_ESCAPE),
+        config.window.ui_scale,
+        |rl, thread| PointBatch::new(rl, thread).map_err(AppError::texture("the points")),
+    ) else {
+        return;
+    };
 
     // Initial clear
     {
         let mut d = rl.begin_texture_mode(&thread, &mut target);
-        d.clear_background(BACKGROUND_COLOR);
+        d.clear_background(theme.background);
     }
 
     // ---- State ----
-    let mut i_angle = 0.0f32;
-    let mut i_distance = 0.0f32;
+    let mut pipeline = Pipeline::new(
+        config.validation,
+        config.calibration,
+        config.filter,
+        config.masks,
+        config.alarms,
+    );
+    let mut range = RangeScale::new(&config.range);
+    if let Some(max_cm) = saved.max_range_cm
+        && args.range_cm.is_none()
+    {
+        range.set_max_cm(max_cm);
+        range.auto = saved.auto_range;
+    }
+    let mut cursor = MeasureCursor::default();
+    let mut console = Console::new(&config.commands);
+    let mut inspector = Inspector::default();
+    let gamepad = Gamepad::new(&config.gamepad);
+    let mut idle = IdleThrottle::new(&config.idle, Instant::now());
+    let keys = KeyMap::new(&config.keys, gamepad.index(), console.bindings());
+    let mut help = HelpOverlay::default();
+    let mut handshake = Handshake::new(config.handshake.clone());
+    let mut viewport = match saved.zoom {
+        Some(zoom) => Viewport {
+            zoom,
+            pan: Vector2::new(saved.pan[0], saved.pan[1]),
+        },
+        None => Viewport::default(),
+    };
+    let mut scope = AScope::default();
+    let mut show_scope = false;
+    let mut show_waterfall = false;
+    let mut sector_stats = SectorStats::new(&config.sectors);
+    let sensors: &[SensorConfig] = if use_sensors { &config.sensors } else { &[] };
+    let mut layers = Layers::new(sensors);
+    let mut fusion = Fusion::new(&config.fusion, sensors);
+    let mut show_fusion = config.fusion.enabled;
+    let mut cloud = PointCloud::new(&config.cloud);
+    let mut orbit = Orbit::default();
+    let mut show_3d = false;
+    let mut heatmap = Heatmap::new(&config.heatmap);
+    let mut show_heatmap = false;
+    let screenshot_dir = PathBuf::from(args.screenshot_dir.as_deref().unwrap_or("."));
+    let mut recorder = Recorder::new(config.record);
+    let mut settings = SettingsPanel::default();
+    let mut picker = PortPicker::default();
+    let mut wizard = CalibrationWizard::default();
+    let mut mask_editor = MaskEditor::default();
+    let mut geofence_editor = GeofenceEditor::default();
+    let mut marker_editor = MarkerEditor::default();
+    let mut markers = config.markers.clone();
+    if pick_port {
+        picker.show(&keys.label(Action::PortPicker));
+    }
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+    let mut watcher = ConfigWatcher::new(&config_path);
+    let mut profile = args.profile.clone();
+    let mut show_tracks = true;
     let mut use_shader = true;
-    let mut data_received = false;
-    let mut last_angle: f32 = 0.0;
+    let mut show_diagnostics = false;
+    let mut link_stats = LinkStats::new(Instant::now());
+    let mut sensor_clock = SensorClock::new(Instant::now());
+    let mut sweep_rate = SweepRate::new(&config.sweep_rate, Instant::now());
+    let mut last_link_state = None;
+    let mut freeze = Freeze::default();
+    let mut closest = ClosestTracker::new(&config.closest);
+    let mut ttc = TtcMonitor::new(&config.ttc);
+    let mut hooks = Hooks::new(config.hooks.clone());
+    let mut notifier = Notifier::new(&config.notifications);
+    let mut clustering = Clustering::new(&config.clusters);
+    let mut mti = Mti::new(&config.mti);
+    if let Some(background) = saved.background.take() {
+        mti.restore(background);
+    }
+    let mut diff = SweepDiff::default();
+    let mut persistence = config.visuals.persistence;
+    let mut hold = SweepHold::default();
+    let mut sweep_animator = SweepAnimator::default();
+    let mut events = EventPanel::default();
+    let mut display_mode = saved.display.unwrap_or(config.display);
+    let mut projection = config.projection;
+    let mut unit = saved.unit.unwrap_or(config.units);
+    let mut state_saver = StateSaver::new(&config.state, Instant::now());
+    // Quit picked on an error screen, the frame is finished first
+    let mut quitting = false;
+
+    while !rl.window_should_close() && !quitting {
+        let frame_time = rl.get_frame_time();
+
+        // ---- Console ----
+        let mut messages = source.messages();
+        if let Some(odometry) = &mut odometry {
+            messages = odometry.update(messages);
+            pipeline.radar.pose = odometry.pose;
+        }
+        if let Some(banner) = handshake.poll(source.as_mut(), &messages, Instant::now()) {
+            info!("{}", banner.describe());
+            // --range on the command line still wins
+            if let Some(max_cm) = banner.max_range_cm
+                && args.range_cm.is_none()
+            {
+                let auto = range.auto;
+                range.set_max_cm(max_cm);
+                range.auto = auto;
+            }
+            if let Some((min, max)) = banner.sweep {
+                config.coverage.min_angle = min;
+                config.coverage.max_angle = max;
+                // The grid was drawn for the old sector
+                let mut d = rl.begin_texture_mode(&thread, &mut target);
+                d.clear_background(theme.background);
+            }
+        }
+        console.received(messages);
+        // The console and its keys stay out of the way of a marker name being
+        // typed and of the picker and wizard, which have keys of their own
+        let busy = marker_editor.is_naming() || picker.open || wizard.open;
+        if (!busy && rl.is_key_pressed(KeyboardKey::KEY_GRAVE))
+            || (console.open && rl.is_key_pressed(KeyboardKey::KEY_ESCAPE))
+        {
+            console.open = !console.open;
+            rl.set_exit_key(console.exit_key());
+        }
+        if console.open {
+            if let Some(line) = console.type_line(&mut rl) {
+                let result = source.send(&line);
+                console.sent(&line, result);
+            }
+        } else if !busy {
+            let bound: Vec<String> = console
+                .bindings()
+                .iter()
+                .filter(|(key, _)| rl.is_key_pressed(*key))
+                .map(|(_, line)| line.clone())
+                .collect();
+            for line in bound {
+                let result = source.send(&line);
+                console.sent(&line, result);
+            }
+        }
+
+        // Typing a marker name is as good as typing in the console
+        let typing = console.open || marker_editor.is_naming();
+
+        // ---- Port Picker ----
+        if !typing && keys.pressed(&rl, Action::PortPicker) {
+            if picker.open {
+                picker.open = false;
+            } else {
+                picker.show(&keys.label(Action::PortPicker));
+            }
+        }
+        if !typing && keys.pressed(&rl, Action::Calibration) {
+            if wizard.open {
+                wizard.open = false;
+            } else {
+                wizard.show(&keys.label(Action::Calibration));
+            }
+        }
+        let mut chosen_port = if typing {
+            None
+        } else {
+            picker.handle_keys(&rl)
+        };
 
-    while !rl.window_should_close() {
         // ---- Input ----
-        if rl.is_key_pressed(KeyboardKey::KEY_S) {
-            use_shader = !use_shader;
+        let take_screenshot = !typing && keys.pressed(&rl, Action::Screenshot);
+        let switch_profile = !typing && keys.pressed(&rl, Action::Profile);
+        // Typing in the console or a dialog being open keeps the hotkeys quiet
+        if !typing && !picker.open && !wizard.open {
+            if keys.pressed(&rl, Action::Acknowledge) {
+                let acknowledged = pipeline.alarms.acknowledge(Instant::now());
+                if acknowledged > 0 {
+                    info!("Acknowledged {} alarm zones", acknowledged);
+                }
+            }
+            if keys.pressed(&rl, Action::Help) {
+                help.open = !help.open;
+            }
+            if keys.pressed(&rl, Action::Shader) {
+                use_shader = !use_shader;
+            }
+            if keys.pressed(&rl, Action::Diagnostics) {
+                show_diagnostics = !show_diagnostics;
+            }
+            if keys.pressed(&rl, Action::EventLog) {
+                events.open = !events.open;
+            }
+            if keys.pressed(&rl, Action::Persistence) {
+                persistence = persistence.toggled();
+                info!("Detections {} out", persistence.label());
+            }
+            if keys.pressed(&rl, Action::Closest) {
+                closest.enabled = !closest.enabled;
+            }
+            if keys.pressed(&rl, Action::Clusters) {
+                clustering.enabled = !clustering.enabled;
+            }
+            if keys.pressed(&rl, Action::Mti) {
+                mti.enabled = !mti.enabled;
+            }
+            if keys.pressed(&rl, Action::Relearn) {
+                mti.relearn();
+                info!("Learning the background again");
+            }
+            if keys.pressed(&rl, Action::Reference) {
+                diff.snapshot(pipeline.radar.sweeps, Instant::now());
+                info!("Taking a reference sweep");
+            }
+            if keys.pressed(&rl, Action::Diff) {
+                diff.enabled = !diff.enabled;
+            }
+            if keys.pressed(&rl, Action::CopyClosest) {
+                let text = clipboard::closest_csv(closest.closest(Instant::now()));
+                clipboard::copy(&mut rl, "closest object", text);
+            }
+            if keys.pressed(&rl, Action::CopyTracks) {
+                let text = clipboard::tracks_csv(pipeline.tracker.confirmed());
+                clipboard::copy(&mut rl, "tracked targets", text);
+            }
+            if keys.pressed(&rl, Action::CopySweep) {
+                let text = clipboard::sweep_csv(hold.detections());
+                clipboard::copy(&mut rl, "last sweep", text);
+            }
+            if keys.pressed(&rl, Action::Freeze) {
+                freeze.toggle(&pipeline, Instant::now());
+                if freeze.is_frozen() {
+                    info!("Display frozen");
+                } else {
+                    info!("Display resumed");
+                }
+            }
+            if keys.pressed(&rl, Action::Tracks) {
+                show_tracks = !show_tracks;
+            }
+            if keys.pressed(&rl, Action::RawDistances) {
+                pipeline.filter.enabled = !pipeline.filter.enabled;
+            }
+            if keys.pressed(&rl, Action::ZoomIn) {
+                range.zoom(1.0);
+            }
+            if keys.pressed(&rl, Action::ZoomOut) {
+                range.zoom(-1.0);
+            }
+            if keys.pressed(&rl, Action::AutoRange) {
+                range.auto = !range.auto;
+            }
+            if keys.pressed(&rl, Action::Unit) {
+                unit = unit.cycled();
+            }
+            if keys.pressed(&rl, Action::Theme) {
+                theme_kind = theme_kind.cycled();
+                theme = theme_kind.theme();
+                fonts.select(theme_kind);
+                // Trails in the old colors would take a while to fade out
+                let mut d = rl.begin_texture_mode(&thread, &mut target);
+                d.clear_background(theme.background);
+            }
+            if keys.pressed(&rl, Action::Mute)
+                && let Some(audio) = &mut audio
+            {
+                audio.muted = !audio.muted;
+            }
+            if keys.pressed(&rl, Action::Scope) {
+                show_scope = !show_scope;
+            }
+            if keys.pressed(&rl, Action::Waterfall) {
+                show_waterfall = !show_waterfall;
+            }
+            if keys.pressed(&rl, Action::Sectors) {
+                sector_stats.enabled = !sector_stats.enabled;
+            }
+            if keys.pressed(&rl, Action::Inspector) {
+                inspector.toggle();
+            }
+            if keys.pressed(&rl, Action::Heatmap) {
+                show_heatmap = !show_heatmap;
+            }
+            if keys.pressed(&rl, Action::Clear) {
+                heatmap.clear();
+                cloud.clear();
+                hold.clear();
+            }
+            if keys.pressed(&rl, Action::Settings) {
+                settings.open = !settings.open;
+            }
+            if keys.pressed(&rl, Action::View3d) {
+                show_3d = !show_3d;
+            }
+            if keys.pressed(&rl, Action::Fusion) {
+                show_fusion = !show_fusion;
+            }
+            let shader_steps = if keys.pressed(&rl, Action::ShaderUp) {
+                1.0
+            } else if keys.pressed(&rl, Action::ShaderDown) {
+                -1.0
+            } else {
+                0.0
+            };
+            if keys.pressed(&rl, Action::ShaderPreset) {
+                let preset = post.preset().cycled();
+                post.set_preset(&mut rl, &thread, preset);
+                shader_hint_until = rl.get_time() + SHADER_HINT_SECONDS;
+            }
+            if keys.pressed(&rl, Action::ShaderParam) {
+                shader_param = shader_param.cycled();
+                shader_hint_until = rl.get_time() + SHADER_HINT_SECONDS;
+            }
+            if shader_steps != 0.0 {
+                let mut params = post.params();
+                shader_param.adjust(&mut params, shader_steps);
+                post.set_params(params);
+                shader_hint_until = rl.get_time() + SHADER_HINT_SECONDS;
+            }
+            if keys.pressed(&rl, Action::Record) {
+                let result = if recorder.is_recording() {
+                    recorder
+                        .stop()
+                        .map(|path| format!("Saved recording {}", path.display()))
+                } else {
+                    recorder
+                        .start(
+                            &screenshot_dir,
+                            rl.get_screen_width(),
+                            rl.get_screen_height(),
+                        )
+                        .map(|path| format!("Recording to {}", path.display()))
+                };
+                match result {
+                    Ok(msg) => info!("{}", msg),
+                    Err(msg) => warn!("{}", msg),
+                }
+            }
+            if keys.pressed(&rl, Action::Cursor) {
+                cursor.toggle();
+            }
+            if keys.pressed(&rl, Action::Masks) {
+                mask_editor.toggle();
+            }
+            if keys.pressed(&rl, Action::Markers) {
+                marker_editor.toggle(&mut markers);
+            }
+            if keys.pressed(&rl, Action::Geofence) {
+                geofence_editor.toggle();
+            }
+            layers.toggle_keys(&rl);
+            if keys.pressed(&rl, Action::DisplayMode) {
+                display_mode = display_mode.toggled();
+                // The old grid and trail would linger in the new layout
+                let mut d = rl.begin_texture_mode(&thread, &mut target);
+                d.clear_background(theme.background);
+            }
+            if keys.pressed(&rl, Action::Projection) {
+                projection = projection.toggled();
+                let mut d = rl.begin_texture_mode(&thread, &mut target);
+                d.clear_background(theme.background);
+            }
         }
 
-        if rl.is_window_resized() || rl.is_key_pressed(KeyboardKey::KEY_F) {
+        let fullscreen_pressed = !typing && keys.pressed(&rl, Action::Fullscreen);
+        let mut resized = None;
+        if rl.is_window_resized() || fullscreen_pressed {
             // If 'F' was pressed, we toggle first, then wait a frame or
             // use the new dimensions immediately
-            if rl.is_key_pressed(KeyboardKey::KEY_F) {
-                rl.toggle_fullscreen();
+            if fullscreen_pressed {
+                window::toggle_fullscreen(&mut rl, config.window.fullscreen);
             }
 
             let new_sw = rl.get_screen_width();
             let new_sh = rl.get_screen_height();
 
-            // Re-create the texture at the FULL monitor resolution
-            target = rl
-                .load_render_texture(&thread, new_sw as u32, new_sh as u32)
-                .expect("Failed to resize render texture");
+            // Re-create the textures at the FULL monitor resolution. Given
+            // up on, the old ones are kept for the last frame
+            resized = error::retrying(
+                &mut rl,
+                &thread,
+                console.exit_key(),
+                config.window.ui_scale,
+                |rl, thread| render_textures(rl, thread, new_sw as u32, new_sh as u32),
+            );
+            quitting = resized.is_none();
+        }
+        if let Some((new_target, new_scene)) = resized {
+            let (new_sw, new_sh) = (new_target.texture().width, new_target.texture().height);
+            let old = std::mem::replace(&mut target, new_target);
+            scene = new_scene;
 
-            // Clear the new texture once so it doesn't start with garbage data
+            // Clear the new texture once so it doesn't start with garbage data,
+            // then carry the trail over: scaled and moved so the old plot lands
+            // on the new one
+            let (old_w, old_h) = (old.texture().width as f32, old.texture().height as f32);
+            let from = split
+                .radar_layout(display_mode, &config.coverage, old_w, old_h)
+                .viewed(&viewport);
+            let to = split
+                .radar_layout(display_mode, &config.coverage, new_sw as f32, new_sh as f32)
+                .viewed(&viewport);
+            let k = to.radius / from.radius.max(1.0);
             let mut d = rl.begin_texture_mode(&thread, &mut target);
-            d.clear_background(BACKGROUND_COLOR);
+            d.clear_background(theme.background);
+            d.draw_texture_pro(
+                old.texture(),
+                Rectangle::new(0.0, 0.0, old_w, -old_h),
+                Rectangle::new(
+                    to.center.x - from.center.x * k,
+                    to.center.y - from.center.y * k,
+                    old_w * k,
+                    old_h * k,
+                ),
+                Vector2::zero(),
+                0.0,
+                Color::WHITE,
+            );
         }
 
         // ---- Calculate Responsive Geometry ----
         // Get current dimensions (works for both windowed and fullscreen)
         let current_sw = rl.get_screen_width() as f32;
         let current_sh = rl.get_screen_height() as f32;
+        let ui = UiScale::new(
+            current_sw,
+            current_sh,
+            rl.get_window_scale_dpi().x,
+            config.window.ui_scale,
+        );
 
         // Recalculate center and radius based on current screen size
-        let radar_center = Vector2::new(current_sw / 2.0, current_sh * 0.926);
-        let radar_radius = current_sw * 0.5;
-
-        // ---- Read Serial ----
-        if let Some(ref mut port) = reader {
-            let mut line = String::new();
-            if port.read_line(&mut line).is_ok() {
-                let parts: Vec<&str> = line.trim().split(',').collect();
-                if parts.len() == 2 {
-                    if let (Ok(a), Ok(d)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>()) {
-                        i_angle = a;
-                        i_distance = d;
-                        data_received = true;
-                    }
+        let base_layout =
+            split.radar_layout(display_mode, &config.coverage, current_sw, current_sh);
+
+        if inspector.open && !typing && !picker.open && !wizard.open {
+            inspector.handle_input(&rl, &keys);
+        }
+
+        // ---- Zoom and Pan ----
+        let mouse = rl.get_mouse_position();
+        let previous_viewport = viewport;
+        let wheel = rl.get_mouse_wheel_move();
+        let over_settings =
+            settings.covers(mouse) || inspector.covers(mouse) || picker.open || wizard.open;
+        let hovered_view = split.view_at(mouse, current_sw, current_sh);
+        if !over_settings {
+            split.handle_mouse(&rl, hovered_view, display_mode, &config.coverage);
+        }
+        let over_panel = over_settings || hovered_view.is_some();
+        if over_panel {
+            // The settings panel or a split view has the mouse
+        } else if show_3d {
+            // The mouse turns the 3D camera instead
+            orbit.update(&rl);
+        } else {
+            if wheel != 0.0 {
+                viewport.zoom_at(&base_layout, mouse, wheel);
+            }
+            // Left button drags too, unless it's busy placing measure points
+            // or drawing masks
+            let left_free = !cursor.enabled
+                && !mask_editor.enabled
+                && !marker_editor.enabled
+                && !geofence_editor.enabled;
+            if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_MIDDLE)
+                || (left_free && rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT))
+            {
+                viewport.pan += rl.get_mouse_delta();
+            }
+            if !typing {
+                let steps = gamepad.zoom(&rl, frame_time);
+                if steps != 0.0 {
+                    let center = base_layout.center + viewport.pan;
+                    viewport.zoom_at(&base_layout, center, steps);
                 }
+                viewport.pan += gamepad.pan(&rl, frame_time);
             }
         }
-
-        // ---- Draw to Texture (Persistence Layer) ----
-        {
+        if !typing && keys.pressed(&rl, Action::ResetView) && !split.reset_focused() {
+            viewport = Viewport::default();
+        }
+        if viewport != previous_viewport {
+            // The trail was drawn for the old view
             let mut d = rl.begin_texture_mode(&thread, &mut target);
+            d.clear_background(theme.background);
+        }
+        let layout = base_layout.viewed(&viewport);
+        let radar_center = layout.center;
+        let radar_radius = layout.radius;
 
-            // Persistence Fade Animation
-            d.draw_rectangle(
-                0,
-                0,
-                current_sw as i32,
-                (current_sh as f32 * 0.926) as i32,
-                FADE_ANIMATION_COLOR,
+        // ---- Read Source ----
+        let live_now = Instant::now();
+        let time = rl.get_time();
+        source.tap(inspector.open);
+        let polled = sensor_clock.arrange(source.poll(), live_now);
+        inspector.record(source.raw());
+        let state = source.state();
+        link_stats.polled(polled.len(), state, Instant::now());
+        if last_link_state != Some(state) {
+            if state == LinkState::Disconnected {
+                warn!("{} [{}]", source.describe(), strings.link_state(state));
+                notifier.disconnected(&source.describe(), rl.is_window_minimized(), live_now);
+            } else {
+                info!("{} [{}]", source.describe(), strings.link_state(state));
+            }
+            hooks.link(state, &source.describe());
+            last_link_state = Some(state);
+        }
+        wizard.record(&polled, pipeline.radar.sweeps, live_now);
+        // Always on live data, a frozen display only stops the picture
+        let now = live_now;
+        let sweeps = pipeline.radar.sweeps;
+        let update = pipeline.process(polled, now);
+        if pipeline.radar.sweeps != sweeps {
+            sensor_clock.sweep_done();
+        }
+        for sink in &mut sinks {
+            sink.write(&update, &pipeline.radar, now);
+        }
+        if let Some(store) = &mut store {
+            store.write(&update, pipeline.alarms.zones());
+        }
+        hooks.update(&update, &pipeline.radar, pipeline.alarms.zones());
+        if let Some(api) = &mut api {
+            if let Some(change) = api.take_changes() {
+                if let Some(max_cm) = change.max_range_cm {
+                    range.set_max_cm(max_cm);
+                }
+                if let Some(auto) = change.auto_range {
+                    range.auto = auto;
+                }
+                if let Some(enabled) = change.filter_enabled {
+                    pipeline.filter.enabled = enabled;
+                }
+            }
+            let settings = Settings {
+                max_range_cm: Some(range.max_cm()),
+                auto_range: Some(range.auto),
+                filter_enabled: Some(pipeline.filter.enabled),
+            };
+            api.publish(
+                source.as_ref(),
+                &pipeline,
+                &update,
+                settings,
+                &link_stats,
+                frame_time,
             );
+        }
+        sweep_rate.record(&update.samples, pipeline.radar.sweeps, now);
+        // What's only drawn stays as it was while frozen
+        if !freeze.is_frozen() {
+            closest.record(&update.samples, pipeline.radar.sweeps, now);
+            mti.record(&update.samples, pipeline.radar.sweeps, now);
+            diff.record(&update.samples, pipeline.radar.sweeps, now);
+            hold.record(&update.samples, &update.detections, pipeline.radar.sweeps);
+            for sample in &update.samples {
+                scope.record(sample);
+                waterfall.record(sample);
+                cloud.record(sample);
+            }
+            fusion.decay(frame_time);
+            sector_stats.record(&update.detections);
+            for det in &update.detections {
+                heatmap.add(det);
+                if layers.is_visible(det.layer) {
+                    fusion.add(det);
+                }
+            }
+        }
+        for det in &update.detections {
+            if let Some(audio) = &mut audio
+                && det.distance < range.max_cm()
+            {
+                audio.ping(det.distance / range.max_cm(), now);
+            }
+        }
+        for event in &update.alarms {
+            let text = pipeline.alarms.describe(event, unit, &strings);
+            warn!("{}", text);
+            notifier.alarm(&text, rl.is_window_minimized(), live_now);
+            if let Some(audio) = &audio {
+                audio.alarm(pipeline.alarms.zones()[event.zone].severity);
+            }
+        }
+        pipeline.prune(now);
+        let sensor = pipeline.radar.pose.map_or((0.0, 0.0), |p| (p.x_cm, p.y_cm));
+        if let Some(collision) = ttc.update(pipeline.tracker.confirmed(), sensor) {
+            hooks.collision(&collision);
+        }
+        if let Some(collision) = ttc.collision
+            && let Some(audio) = &mut audio
+        {
+            audio.collision(collision.urgency, now);
+        }
 
-            // Radar Arcs
-            let arc_scales = [0.9375, 0.73, 0.521, 0.313];
-            for scale in arc_scales {
-                d.draw_circle_sector_lines(
-                    radar_center,
-                    (current_sw as f32 * scale) / 2.0,
-                    180.0,
-                    360.0,
-                    128,
-                    RADAR_OUTLINE,
+        // Nothing coming in and nothing moving, no need for 60 frames a second
+        let alarm_active =
+            (0..pipeline.alarms.zones().len()).any(|i| pipeline.alarms.is_active(i, now));
+        let active = !update.samples.is_empty()
+            || idle::input_activity(&rl, &gamepad)
+            || viewport != previous_viewport
+            || recorder.is_recording()
+            || alarm_active
+            || ttc.collision.is_some();
+        idle.update(&mut rl, active, live_now);
+
+        if let Some(audio) = &mut audio
+            && pipeline.radar.data_received
+        {
+            audio.sweep(pipeline.radar.angle);
+        }
+        // What's drawn: the snapshot and the moment it froze while frozen
+        let now = freeze.now(live_now);
+        let radar = freeze.radar(&pipeline.radar);
+        let tracker = freeze.tracker(&pipeline.tracker);
+        range.fit(
+            radar
+                .recent(now, DETECTION_LIFETIME)
+                .map(|d| d.distance)
+                .max_by(f32::total_cmp),
+        );
+        let i_angle = radar.angle;
+        sweep_animator.update(i_angle, now);
+        let sweep_angle = if config.visuals.interpolate_sweep {
+            // Not past the ends of the sector, unless the reading already is
+            let (min, max) = layout.sector;
+            sweep_animator
+                .angle(radar.decreasing, now)
+                .clamp(min.min(i_angle), max.max(i_angle))
+        } else {
+            i_angle
+        };
+        let i_distance = radar.distance();
+        let max_range_cm = range.max_cm();
+        let pixels_per_cm = radar_radius / max_range_cm;
+
+        // ---- Mask Editor ----
+        if mask_editor.enabled && !show_3d && !over_panel {
+            let mut changed = false;
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                mask_editor.press(mouse);
+            }
+            if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT)
+                && let Some(zone) =
+                    mask_editor.release(&layout, pixels_per_cm, mouse, pipeline.masks.zones().len())
+            {
+                info!(
+                    "Masked {:.0}-{:.0} deg, {}-{}",
+                    zone.min_angle,
+                    zone.max_angle,
+                    unit.format(zone.min_cm),
+                    unit.format(zone.max_cm)
                 );
+                pipeline.masks.add(zone);
+                changed = true;
+            }
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+                let (angle, dist_px) = layout.screen_to_polar(mouse);
+                changed |= pipeline.masks.remove_at(angle, dist_px / pixels_per_cm);
             }
+            if changed {
+                match mask::save(&config_path, pipeline.masks.zones(), profile.as_deref()) {
+                    Ok(()) => info!("Saved masks to {}", config_path),
+                    Err(e) => warn!("{}", e),
+                }
+            }
+        }
 
-            // Angle Markers
-            for angle in (30..=150).step_by(30) {
-                let rad = (angle as f32).to_radians();
-                let line_end = Vector2::new(
-                    radar_center.x - radar_radius * rad.cos(),
-                    radar_center.y - radar_radius * rad.sin(),
-                );
-                d.draw_line_ex(radar_center, line_end, 2.0, RADAR_OUTLINE);
+        // ---- Markers ----
+        if marker_editor.enabled && !mask_editor.enabled && !show_3d && !over_panel {
+            let mut changed =
+                marker_editor.is_naming() && marker_editor.type_name(&mut rl, &mut markers);
+            if !marker_editor.is_naming()
+                && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+            {
+                let map = projection == Projection::Cartesian;
+                marker_editor.drop_at(&mut markers, &layout, pixels_per_cm, mouse, map);
+            }
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+                changed |= marker_editor.remove_at(&mut markers, &layout, pixels_per_cm, mouse, ui);
+            }
+            if changed {
+                match markers::save(&config_path, &markers, profile.as_deref()) {
+                    Ok(()) => info!("Saved markers to {}", config_path),
+                    Err(e) => warn!("{}", e),
+                }
+            }
+        }
 
-                let text_radius = radar_radius * 1.05;
-                let text_pos = Vector2::new(
-                    radar_center.x - text_radius * rad.cos(),
-                    radar_center.y - text_radius * rad.sin(),
-                );
+        // ---- Polygon Zones ----
+        if geofence_editor.enabled
+            && !mask_editor.enabled
+            && !marker_editor.enabled
+            && !show_3d
+            && !over_panel
+        {
+            let mut changed = false;
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+                && let Some(zone) = geofence_editor.click(
+                    &layout,
+                    pixels_per_cm,
+                    mouse,
+                    ui,
+                    pipeline.alarms.zones().len(),
+                )
+            {
+                info!("Added {} with {} corners", zone.name, zone.polygon.len());
+                pipeline.alarms.add(zone);
+                changed = true;
+            }
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT)
+                && !geofence_editor.undo()
+            {
+                let (x, y) = layout.screen_to_map(mouse, pixels_per_cm);
+                changed |= pipeline.alarms.remove_polygon_at(x, y);
+            }
+            if changed {
+                match alarm::save(&config_path, pipeline.alarms.zones(), profile.as_deref()) {
+                    Ok(()) => info!("Saved alarm zones to {}", config_path),
+                    Err(e) => warn!("{}", e),
+                }
+            }
+        }
 
-                let display_angle = 180 - angle;
-                let label = format!("{}", display_angle);
-                let font_size = 20;
-                let text_size = d.measure_text(&label, font_size);
-                d.draw_text(
-                    &label,
-                    (text_pos.x - text_size as f32 / 2.0) as i32,
-                    (text_pos.y - font_size as f32 / 2.0) as i32,
-                    font_size,
-                    RADAR_OUTLINE,
-                );
+        // ---- Measure Cursor ----
+        if cursor.enabled
+            && !mask_editor.enabled
+            && !marker_editor.enabled
+            && !geofence_editor.enabled
+            && !show_3d
+            && !over_panel
+        {
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                cursor.click(&layout, pixels_per_cm, mouse);
+            }
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+                cursor.clear();
             }
+        }
+
+        // ---- Draw to Texture (Persistence Layer) ----
+        {
+            let mut d = rl.begin_texture_mode(&thread, &mut target);
 
-            if data_received {
+            // Persistence Fade Animation
+            let fade_height = match display_mode {
+                // Panned or zoomed the plot can reach into the readout area
+                DisplayMode::Half if viewport.is_default() => layout.plot_bottom,
+                DisplayMode::Half => current_sh,
+                DisplayMode::Full => current_sh,
+            };
+            if !freeze.is_frozen() {
+                let fade = theme.fade_color(frame_time, fade_half_life_s);
+                d.draw_rectangle(0, 0, current_sw as i32, fade_height as i32, fade);
+            }
+
+            match projection {
+                Projection::Polar => {
+                    view::draw_grid(&mut d, &layout, ui, &mut fonts, theme.outline)
+                }
+                Projection::Cartesian => {
+                    view::draw_map_grid(&mut d, &layout, max_range_cm, ui, theme.outline)
+                }
+            }
+
+            if radar.data_received {
                 // Determine the direction of the Beam Animation trail
-                let direction = if i_angle < last_angle {
-                    1.0 // Moving clockwise
+                let direction = if radar.decreasing {
+                    1.0 // Moving towards smaller angles
                 } else {
-                    -1.0 // Moving counter-clockwise
+                    -1.0 // Moving towards larger angles
                 };
-                last_angle = i_angle;
 
-                // Sweep Line
+                // Sweep Line, from wherever the platform is
+                let (origin, heading) = match radar.pose {
+                    Some(pose) => (pose.screen(&layout, pixels_per_cm), pose.heading_deg),
+                    None => (radar_center, 0.0),
+                };
                 let mut offset = -SWEEP_SPREAD_DEG;
                 while offset <= 0.0 {
-                    let a = (i_angle + offset * direction).to_radians();
-                    let sweep_end = Vector2::new(
-                        radar_center.x + radar_radius * a.cos(),
-                        radar_center.y - radar_radius * a.sin(),
-                    );
-                    d.draw_line_ex(
-                        radar_center,
-                        sweep_end,
-                        SWEEP_LINE_THICKNESS,
-                        SWEEP_LINE_COLOR,
-                    );
+                    let angle = heading + sweep_angle + offset * direction;
+                    let sweep_end =
+                        origin + (layout.polar_to_screen(angle, radar_radius) - radar_center);
+                    d.draw_line_ex(origin, sweep_end, ui.px(SWEEP_LINE_THICKNESS), theme.sweep);
                     offset += SWEEP_STEP_DEG;
                 }
+            }
+        }
 
-                // Detected Object
-                if i_distance > 0.0 && i_distance < MAX_RANGE_CM {
-                    let rad = i_angle.to_radians();
-                    let pixels_per_cm = radar_radius / MAX_RANGE_CM;
-                    let pix_dist = i_distance * pixels_per_cm;
+        // ---- Compose Scene (Persistence Layer + Detections) ----
+        {
+            let mut d = rl.begin_texture_mode(&thread, &mut scene);
+            d.clear_background(theme.background);
+            d.draw_texture_rec(
+                target.texture(),
+                Rectangle::new(
+                    0.0,
+                    0.0,
+                    target.texture().width as f32,
+                    -target.texture().height as f32,
+                ),
+                Vector2::zero(),
+                Color::WHITE,
+            );
+
+            let labels = match projection {
+                Projection::Polar => view::draw_ring_labels,
+                Projection::Cartesian => view::draw_map_labels,
+            };
+            labels(
+                &mut d,
+                &layout,
+                max_range_cm,
+                unit,
+                ui,
+                &mut fonts,
+                theme.outline,
+            );
+
+            // Occupancy Heatmap
+            if show_heatmap {
+                heatmap.draw(&mut d, &layout, pixels_per_cm, max_range_cm, theme.warning);
+            }
 
-                    let object_pos = Vector2::new(
-                        radar_center.x + pix_dist * rad.cos(),
-                        radar_center.y - pix_dist * rad.sin(),
+            // Alarm Zones, flashing while something is inside
+            let flash = ((time * 8.0).sin() * 0.5 + 0.5) as f32;
+            for (i, zone) in pipeline.alarms.zones().iter().enumerate() {
+                let color = match zone.severity {
+                    Severity::Info => theme.sweep,
+                    Severity::Warning => theme.warning,
+                    Severity::Critical => theme.detection,
+                };
+                if zone.is_polygon() {
+                    // No fill for a concave room, a thick flashing outline instead
+                    let (thick, color) = if !pipeline.alarms.is_active(i, live_now) {
+                        (ui.px(1.5), color.alpha(0.6))
+                    } else if pipeline.alarms.is_acknowledged(i) {
+                        (ui.px(4.0), color)
+                    } else {
+                        (ui.px(2.0 + 3.0 * flash), color)
+                    };
+                    geofence::draw_polygon(
+                        &mut d,
+                        &layout,
+                        pixels_per_cm,
+                        &zone.polygon,
+                        thick,
+                        color,
                     );
-                    let edge_pos = Vector2::new(
-                        radar_center.x + radar_radius * rad.cos(),
-                        radar_center.y - radar_radius * rad.sin(),
+                    continue;
+                }
+                let angles = zone.sector();
+                let radii = (
+                    zone.min_cm.min(max_range_cm) * pixels_per_cm,
+                    zone.max_cm.min(max_range_cm) * pixels_per_cm,
+                );
+                if pipeline.alarms.is_active(i, live_now) {
+                    // Acknowledged zones stay lit but stop flashing
+                    let flash = if pipeline.alarms.is_acknowledged(i) {
+                        0.0
+                    } else {
+                        flash
+                    };
+                    let fill = color.alpha(0.15 + 0.35 * flash);
+                    view::draw_sector(&mut d, &layout, angles, radii, fill, true);
+                }
+                view::draw_sector(&mut d, &layout, angles, radii, color.alpha(0.6), false);
+            }
+
+            // Masked Clutter, hatched
+            let mask_color = theme.outline.alpha(0.5);
+            for zone in pipeline.masks.zones() {
+                zone.draw(&mut d, &layout, pixels_per_cm, max_range_cm, mask_color);
+            }
+            mask_editor.draw_preview(
+                &mut d,
+                &layout,
+                pixels_per_cm,
+                max_range_cm,
+                mouse,
+                theme.sweep,
+            );
+            geofence_editor.draw_preview(&mut d, &layout, pixels_per_cm, mouse, ui, theme.sweep);
+
+            // Detected Objects, faded by age, or the fused occupancy estimate
+            if show_fusion {
+                fusion.draw(
+                    &mut d,
+                    &layout,
+                    pixels_per_cm,
+                    max_range_cm,
+                    &theme,
+                    &config.gradient,
+                );
+            } else {
+                let dense = radar.sweep_samples > DENSE_SWEEP_SAMPLES;
+                // With sweep persistence a blip stays at full strength until
+                // the sweep line comes back over it
+                let detections: Box<dyn Iterator<Item = (&Detection, f32)>> = match persistence {
+                    Persistence::Fade => Box::new(
+                        radar
+                            .recent(now, DETECTION_LIFETIME)
+                            .map(|det| (det, det.alpha(now, DETECTION_LIFETIME))),
+                    ),
+                    Persistence::Sweep => Box::new(hold.detections().map(|det| (det, 1.0))),
+                };
+                // In MTI mode the static background is drawn faintly instead
+                mti.draw_background(
+                    &mut d,
+                    &layout,
+                    pixels_per_cm,
+                    max_range_cm,
+                    theme.outline.alpha(0.3),
+                );
+                let detections = detections.filter(|(det, _)| {
+                    det.distance < max_range_cm
+                        && layers.is_visible(det.layer)
+                        && (!mti.is_active() || mti.is_moving(det))
+                });
+                let color_of = |det: &Detection, alpha: f32| {
+                    let dim = match pipeline.masks.matching(det) {
+                        Some(zone) if zone.dim => MASK_DIM,
+                        _ => 1.0,
+                    };
+                    layers
+                        .color(det.layer)
+                        .unwrap_or_else(|| {
+                            theme.detection_color(&config.gradient, det.distance / max_range_cm)
+                        })
+                        .alpha(alpha * det.intensity() * dim)
+                };
+                if clustering.enabled {
+                    clustering.draw(&mut d, &layout, pixels_per_cm, detections, unit, color_of);
+                } else {
+                    for (det, alpha) in detections {
+                        let color = color_of(det, alpha);
+                        let object_pos =
+                            layout.polar_to_screen(det.angle, det.distance * pixels_per_cm);
+
+                        let size = det.size();
+                        if dense {
+                            // Shadow lines at this density would paint everything
+                            points.dot(object_pos, ui.px(POINT_RADIUS * size), color);
+                        } else if det.multi_echo {
+                            // One of several echoes: a blip each
+                            points.dot(object_pos, ui.px(BLIP_RADIUS * size), color);
+                        } else {
+                            // Single echo: everything behind it is in shadow
+                            let edge_pos = layout.polar_to_screen(det.angle, radar_radius);
+                            points.line(object_pos, edge_pos, ui.px(6.0) * size, color);
+                        }
+                    }
+                    points.flush(&mut d);
+                }
+            }
+
+            // Changes against the reference sweep
+            diff.draw(&mut d, &layout, pixels_per_cm, max_range_cm);
+
+            closest.draw_marker(
+                &mut d,
+                &layout,
+                pixels_per_cm,
+                max_range_cm,
+                now,
+                theme.warning,
+            );
+
+            // Tracked Objects: ID, speed, the path so far and where it's heading
+            if show_tracks {
+                for track in tracker.confirmed() {
+                    if track.distance >= max_range_cm {
+                        continue;
+                    }
+                    let pos = layout.polar_to_screen(track.angle, track.distance * pixels_per_cm);
+                    // Fades out towards the oldest point
+                    let trail: Vec<(Vector2, f32)> = track
+                        .trail()
+                        .map(|(angle, distance, time)| {
+                            let age = now.saturating_duration_since(time).as_secs_f32();
+                            (layout.polar_to_screen(angle, distance * pixels_per_cm), age)
+                        })
+                        .collect();
+                    let oldest = trail.first().map_or(0.0, |&(_, age)| age).max(0.1);
+                    for pair in trail.windows(2) {
+                        let fade = 1.0 - pair[0].1 / oldest;
+                        d.draw_line_ex(
+                            pair[0].0,
+                            pair[1].0,
+                            ui.px(2.0),
+                            theme.track.alpha(0.15 + 0.65 * fade),
+                        );
+                    }
+                    let (angle, distance) = track.predicted(TRACK_VECTOR_SECONDS);
+                    let heading = layout.polar_to_screen(angle, distance * pixels_per_cm);
+                    d.draw_circle_lines(
+                        pos.x as i32,
+                        pos.y as i32,
+                        ui.px(BLIP_RADIUS * 1.8),
+                        theme.track,
+                    );
+                    view::draw_dashed_line(
+                        &mut d,
+                        pos,
+                        heading,
+                        ui.px(6.0),
+                        ui.px(2.0),
+                        theme.track,
+                    );
+                    fonts.draw(
+                        &mut d,
+                        &format!("#{} {}", track.id, unit.format_speed(track.speed())),
+                        (pos.x + ui.px(BLIP_RADIUS * 2.5)) as i32,
+                        (pos.y - ui.px(BLIP_RADIUS * 2.5)) as i32,
+                        ui.font(16),
+                        theme.track,
                     );
-                    d.draw_line_ex(object_pos, edge_pos, 6.0, DETECTED_OBJECT);
                 }
             }
+
+            ttc.draw(&mut d, &layout, pixels_per_cm, time, ui, theme.detection);
+
+            if let Some(odometry) = &odometry {
+                odometry.draw(&mut d, &layout, pixels_per_cm, ui, theme.track);
+            }
+            marker_editor.draw(&mut d, &markers, &layout, pixels_per_cm, ui, theme.sweep);
+        }
+
+        // ---- Split Views ----
+        split.prepare(&mut rl, &thread, time, current_sw, current_sh);
+        for view in &mut split.views {
+            let rect = view.config.rect(current_sw, current_sh);
+            let plot = view.layout(display_mode, &config.coverage, rect);
+            let Some(texture) = &mut view.texture else {
+                continue;
+            };
+            let mut d = rl.begin_texture_mode(&thread, texture);
+            d.clear_background(theme.background);
+            let panel = Panel {
+                rect: Rectangle::new(0.0, 0.0, rect.width, rect.height),
+                angles: plot.sector,
+                current_angle: i_angle,
+                max_range_cm,
+                unit,
+                theme,
+                ui,
+            };
+            match view.config.kind {
+                ViewKind::Ppi => {
+                    // Live blips on a plain grid, the radar view has the rest
+                    view::draw_grid(&mut d, &plot, ui, &mut fonts, theme.outline);
+                    let ppcm = plot.radius / max_range_cm;
+                    let layer = view.config.layer;
+                    let detections = radar.recent(now, DETECTION_LIFETIME).filter(|det| {
+                        det.distance < max_range_cm
+                            && layer.is_none_or(|l| u16::from(det.layer) + 1 == u16::from(l))
+                    });
+                    for det in detections {
+                        let color = layers
+                            .color(det.layer)
+                            .unwrap_or_else(|| {
+                                theme.detection_color(&config.gradient, det.distance / max_range_cm)
+                            })
+                            .alpha(det.alpha(now, DETECTION_LIFETIME) * det.intensity());
+                        let pos = plot.polar_to_screen(det.angle, det.distance * ppcm);
+                        points.dot(pos, ui.px(POINT_RADIUS * 1.5) * det.size(), color);
+                    }
+                    points.flush(&mut d);
+                    if radar.data_received {
+                        let end = plot.polar_to_screen(sweep_angle, plot.radius);
+                        d.draw_line_ex(plot.center, end, ui.px(2.0), theme.sweep);
+                    }
+                }
+                ViewKind::Scope => scope.draw(&mut d, &panel),
+                ViewKind::Waterfall => waterfall.draw(&mut d, &panel),
+                ViewKind::Sectors => sector_stats.draw(&mut d, &panel, now),
+                ViewKind::Radar => {}
+            }
         }
 
         // ---- Final Render ----
+        post.reload_if_changed(&mut rl, &thread, time);
+        post.set_frame(time, current_sw, current_sh);
+
         // This rectangle covers the ENTIRE window/screen
         let dest_rect = Rectangle::new(0.0, 0.0, current_sw, current_sh);
 
@@ -258,16 +1528,19 @@ fn main() {
         let source_rect = Rectangle::new(
             0.0,
             0.0,
-            target.texture().width as f32,
-            -target.texture().height as f32,
+            scene.texture().width as f32,
+            -scene.texture().height as f32,
         );
         let mut d = rl.begin_drawing(&thread);
-        d.clear_background(BACKGROUND_COLOR);
+        d.clear_background(theme.background);
 
-        if use_shader {
-            let mut s_mode = d.begin_shader_mode(&mut shaders);
+        if show_3d {
+            let mut d3 = d.begin_mode3D(orbit.camera());
+            cloud.draw(&mut d3, max_range_cm, &theme, &config.gradient);
+        } else if use_shader && let Some(shader) = post.shader_mut() {
+            let mut s_mode = d.begin_shader_mode(shader);
             s_mode.draw_texture_pro(
-                target.texture(),
+                scene.texture(),
                 source_rect,
                 dest_rect,
                 Vector2::zero(),
@@ -276,7 +1549,7 @@ fn main() {
             );
         } else {
             d.draw_texture_pro(
-                target.texture(),
+                scene.texture(),
                 source_rect,
                 dest_rect,
                 Vector2::zero(),
@@ -285,6 +1558,10 @@ fn main() {
             );
         }
 
+        if !show_3d {
+            split.draw(&mut d, ui, &theme);
+        }
+
         // UI Overlay
         // let ui_y_start = current_sh * 0.945;
         // d.draw_rectangle(
@@ -294,19 +1571,656 @@ fn main() {
         //     (current_sh as f32 * 0.070) as i32,
         //     Color::BLACK,
         // );
-        d.draw_text(
-            &format!("Angle: {:.0}", i_angle),
-            (current_sw as f32 * 0.05) as i32,
-            (current_sh as f32 * 0.95) as i32,
-            30,
-            RADAR_OUTLINE,
+        fonts.draw(
+            &mut d,
+            &format!("{}: {:.0}", strings.angle, i_angle),
+            (current_sw * 0.05) as i32,
+            (current_sh * 0.95) as i32,
+            ui.font(30),
+            theme.outline,
+        );
+        let distance_text = if radar.echoes.len() > 1 {
+            format!(
+                "{}: {} (+{})",
+                strings.distance,
+                unit.format(i_distance),
+                radar.echoes.len() - 1
+            )
+        } else {
+            format!("{}: {}", strings.distance, unit.format(i_distance))
+        };
+        fonts.draw(
+            &mut d,
+            &distance_text,
+            (current_sw * 0.75) as i32,
+            (current_sh * 0.95) as i32,
+            ui.font(30),
+            theme.outline,
         );
+
+        let range_text = if range.auto {
+            format!(
+                "{}: {} ({})",
+                strings.range,
+                unit.format(range.max_cm()),
+                strings.auto
+            )
+        } else {
+            format!("{}: {}", strings.range, unit.format(range.max_cm()))
+        };
+        fonts.draw(
+            &mut d,
+            &range_text,
+            (current_sw * 0.40) as i32,
+            (current_sh * 0.95) as i32,
+            ui.font(30),
+            theme.outline,
+        );
+
+        // Side Panels, stacked in the top right corner
+        let mut panel = Panel {
+            rect: Rectangle::new(
+                current_sw * 0.62,
+                ui.px(60.0),
+                current_sw * 0.36,
+                current_sh * 0.28,
+            ),
+            angles: layout.sector,
+            current_angle: i_angle,
+            max_range_cm,
+            unit,
+            theme,
+            ui,
+        };
+        if show_scope {
+            scope.draw(&mut d, &panel);
+            panel.rect.y += panel.rect.height + ui.px(10.0);
+        }
+        if show_waterfall {
+            waterfall.draw(&mut d, &panel);
+            panel.rect.y += panel.rect.height + ui.px(10.0);
+        }
+        if sector_stats.enabled {
+            sector_stats.draw(&mut d, &panel, now);
+        }
+
+        layers.draw_legend(&mut d, current_sh * 0.93, ui, &theme);
+        if show_3d {
+            d.draw_text(
+                &format!("3D: {} points, drag to orbit, wheel to zoom", cloud.len()),
+                (current_sw * 0.40) as i32,
+                (current_sh * 0.95) as i32 - ui.pxi(30),
+                ui.font(20),
+                theme.outline,
+            );
+        } else {
+            cursor.draw(&mut d, &layout, pixels_per_cm, mouse, unit, theme.sweep);
+            if mask_editor.enabled {
+                d.draw_text(
+                    &format!(
+                        "MASKS ({}): drag to mask clutter, right-click a mask to remove it",
+                        keys.label(Action::Masks)
+                    ),
+                    (current_sw * 0.40) as i32,
+                    (current_sh * 0.95) as i32 - ui.pxi(30),
+                    ui.font(20),
+                    theme.outline,
+                );
+            } else if marker_editor.enabled {
+                let key = keys.label(Action::Markers);
+                let text = if marker_editor.is_naming() {
+                    format!("MARKERS ({key}): type a name, Enter to keep it")
+                } else {
+                    format!("MARKERS ({key}): click to drop a marker, right-click one to remove it")
+                };
+                d.draw_text(
+                    &text,
+                    (current_sw * 0.40) as i32,
+                    (current_sh * 0.95) as i32 - ui.pxi(30),
+                    ui.font(20),
+                    theme.outline,
+                );
+            } else if geofence_editor.enabled {
+                let key = keys.label(Action::Geofence);
+                let text = if geofence_editor.is_drawing() {
+                    format!(
+                        "ZONES ({key}): click to add a corner, the first one to close, right-click to undo"
+                    )
+                } else {
+                    format!(
+                        "ZONES ({key}): click to start a polygon zone, right-click one to remove it"
+                    )
+                };
+                d.draw_text(
+                    &text,
+                    (current_sw * 0.40) as i32,
+                    (current_sh * 0.95) as i32 - ui.pxi(30),
+                    ui.font(20),
+                    theme.outline,
+                );
+            }
+        }
+        if time < shader_hint_until {
+            let value = *shader_param.value(&mut post.params());
+            let hint = if use_shader {
+                format!(
+                    "Shader {} {}: {:.2}  ({} preset, {} next, {} {} change)",
+                    post.preset().label(),
+                    shader_param.label(),
+                    value,
+                    keys.label(Action::ShaderPreset),
+                    keys.label(Action::ShaderParam),
+                    keys.label(Action::ShaderDown),
+                    keys.label(Action::ShaderUp)
+                )
+            } else {
+                format!(
+                    "Shader {}: {:.2}  (shader off, {})",
+                    shader_param.label(),
+                    value,
+                    keys.label(Action::Shader)
+                )
+            };
+            d.draw_text(
+                &hint,
+                (current_sw * 0.40) as i32,
+                (current_sh * 0.95) as i32 - ui.pxi(30),
+                ui.font(20),
+                theme.outline,
+            );
+        }
+        if !show_3d && let Some(text) = closest.readout(now, unit, &strings) {
+            let size = ui.font(closest::READOUT_FONT_SIZE);
+            let width = fonts.measure(&d, &text, size);
+            fonts.draw(
+                &mut d,
+                &text,
+                (current_sw as i32 - width) / 2,
+                ui.pxi(64),
+                size,
+                theme.warning,
+            );
+        }
+        if let Some(text) = ttc.readout() {
+            let size = ui.font(closest::READOUT_FONT_SIZE);
+            let width = fonts.measure(&d, &text, size);
+            fonts.draw(
+                &mut d,
+                &text,
+                (current_sw as i32 - width) / 2,
+                ui.pxi(110),
+                size,
+                theme.detection.alpha(0.5 + 0.5 * ttc.pulse(time)),
+            );
+        }
+        console.draw(&mut d, current_sw, current_sh, ui, &theme);
+        let decoder = source.protocol_stats();
+        inspector.draw(&mut d, current_sw, current_sh, ui, &theme, decoder);
+        events.draw(&mut d, &event_log, current_sw, current_sh, ui, &theme);
+
+        // Settings Panel
+        let values = Values {
+            preset: post.preset(),
+            shader: post.params(),
+            fade_half_life_s,
+            max_range_cm,
+            theme: theme_kind,
+            unit,
+            alarm_max_cm: pipeline.alarms.zones().iter().map(|z| z.max_cm).collect(),
+        };
+        let zone_names: Vec<&str> = pipeline
+            .alarms
+            .zones()
+            .iter()
+            .map(|z| z.name.as_str())
+            .collect();
+        let changed = settings.draw(
+            &mut d,
+            &values,
+            &zone_names,
+            &keys.label(Action::Settings),
+            current_sw,
+            ui,
+        );
+
+        // ---- Config Reload ----
+        let mut reloaded = None;
+        if switch_profile && !profiles.is_empty() {
+            // Each profile in turn, then the file without one
+            let next = match &profile {
+                None => profiles.first(),
+                Some(current) => profiles.iter().skip_while(|p| *p != current).nth(1),
+            }
+            .cloned();
+            match load_config(&args, next.as_deref()) {
+                Ok(new) => {
+                    info!("Using profile {}", next.as_deref().unwrap_or("none"));
+                    profiles = new.profile_names();
+                    file_settings = FileSettings::of(&new);
+                    file_values = Values::from_config(&new);
+                    reloaded = Some(file_values.clone());
+                    profile = next;
+                }
+                Err(e) => warn!("{}", e),
+            }
+        } else if watcher.changed(time) {
+            match load_config(&args, profile.as_deref()) {
+                Ok(new) => {
+                    info!("Reloaded {}", config_path);
+                    // Profiles added to the file are next in line too
+                    profiles = new.profile_names();
+                    file_settings = FileSettings::of(&new);
+                    let new_values = Values::from_config(&new);
+                    reloaded = Some(values.reloaded(&file_values, &new_values));
+                    file_values = new_values;
+                }
+                // Keeps running on what it had
+                Err(e) => warn!("{}", e),
+            }
+        }
+        let changed = changed.or(reloaded);
+        if let Some(choice) = picker.draw(&mut d, current_sw, current_sh, ui) {
+            chosen_port = Some(choice);
+        }
+        let calibrated = wizard.draw(&mut d, live_now, unit, current_sw, current_sh, ui);
+
+        // Link State
+        let link_state = source.state();
+        let link_color = match link_state {
+            LinkState::Connected => theme.outline,
+            LinkState::Connecting | LinkState::Disconnected => theme.warning,
+        };
         d.draw_text(
-            &format!("Distance: {:.0} cm", i_distance),
-            (current_sw as f32 * 0.75) as i32,
-            (current_sh as f32 * 0.95) as i32,
-            30,
-            RADAR_OUTLINE,
+            &format!("{} [{}]", source.describe(), strings.link_state(link_state)),
+            10,
+            10,
+            ui.font(20),
+            link_color,
         );
+        if pipeline.filter.kind() != FilterKind::None {
+            let template = if pipeline.filter.enabled {
+                &strings.filter
+            } else {
+                &strings.filter_raw
+            };
+            let filter_text = i18n::fill(template, &[("filter", pipeline.filter.kind().label())]);
+            d.draw_text(
+                &filter_text,
+                10,
+                10 + ui.pxi(24),
+                ui.font(20),
+                theme.outline,
+            );
+        }
+
+        // Diagnostics Overlay
+        if show_diagnostics {
+            let (protocol_name, stats) = source.protocol_stats();
+            let rejected = pipeline.validator.stats();
+            // (text, is an error count that went up)
+            let mut lines = vec![
+                (format!("Protocol: {}", protocol_name), false),
+                (format!("Frames: {}", stats.frames), false),
+                (
+                    format!("Malformed: {}", stats.malformed),
+                    stats.malformed > 0,
+                ),
+                (
+                    format!("Checksum errors: {}", stats.checksum_errors),
+                    stats.checksum_errors > 0,
+                ),
+                (
+                    format!("Rejected out of range: {}", rejected.out_of_range),
+                    rejected.out_of_range > 0,
+                ),
+                (
+                    format!("Rejected spikes: {}", rejected.spikes),
+                    rejected.spikes > 0,
+                ),
+                (
+                    format!("Rejected weak: {}", rejected.weak),
+                    rejected.weak > 0,
+                ),
+                (format!("Sweep reversals: {}", radar.reversals), false),
+                (
+                    match sweep_rate.per_minute(now) {
+                        Some(rate) => format!(
+                            "Sweep rate: {:.1}/min ({:.1} s)  {:.0} deg/s",
+                            rate,
+                            60.0 / rate,
+                            sweep_rate.degrees_per_s
+                        ),
+                        None => format!("Sweep rate: -  {:.0} deg/s", sweep_rate.degrees_per_s),
+                    },
+                    sweep_rate.warning(now).is_some(),
+                ),
+                (format!("Theme: {}", theme_kind.label()), false),
+                (format!("UI scale: {:.2}x", ui.factor()), false),
+                (format!("Samples/s: {:.0}", link_stats.sample_rate), false),
+                (
+                    format!("Reconnects: {}", link_stats.reconnects),
+                    link_stats.reconnects > 0,
+                ),
+                (
+                    format!("Latency (est.): {:.0} ms", link_stats.latency_ms),
+                    false,
+                ),
+                (
+                    format!(
+                        "FPS: {}{}  Frame: {:.1} ms",
+                        d.get_fps(),
+                        if idle.is_idle() { " (idle)" } else { "" },
+                        frame_time * 1000.0
+                    ),
+                    false,
+                ),
+            ];
+            // Only firmware that sends its millis() with the samples
+            if sensor_clock.is_active() {
+                lines.extend([
+                    (
+                        format!("Sensor sweep: {:.2} Hz", sensor_clock.sweep_rate_hz),
+                        false,
+                    ),
+                    (
+                        format!("Dropped frames: {}", sensor_clock.dropped),
+                        sensor_clock.dropped > 0,
+                    ),
+                    (
+                        format!(
+                            "Late samples: {}  Clock resets: {}",
+                            sensor_clock.late, sensor_clock.resyncs
+                        ),
+                        sensor_clock.late > 0,
+                    ),
+                    (
+                        format!("Sensor to screen: +{:.0} ms", sensor_clock.latency_ms),
+                        false,
+                    ),
+                ]);
+            }
+            for (i, (line, bad)) in lines.iter().enumerate() {
+                let color = if *bad { theme.warning } else { theme.outline };
+                d.draw_text(
+                    line,
+                    10,
+                    10 + ui.pxi(54 + i as i32 * 22),
+                    ui.font(20),
+                    color,
+                );
+            }
+        }
+
+        // Screenshot, with the details baked into the frame
+        if take_screenshot {
+            let stamp = Timestamp::now();
+            let lines = [
+                stamp.display(),
+                source.describe(),
+                format!("Range: {}", unit.format(max_range_cm)),
+                format!(
+                    "Angle: {:.0}  Distance: {}",
+                    i_angle,
+                    unit.format(i_distance)
+                ),
+            ];
+            capture::draw_annotation(&mut d, &lines, current_sw, ui, theme.sweep);
+            match capture::save_screenshot(&d, &thread, &screenshot_dir, &stamp) {
+                Ok(path) => info!("Saved screenshot {}", path.display()),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        // Recording, the indicator is drawn after the capture so it stays off the video
+        if recorder.wants_frame(live_now) {
+            recorder.capture(&d, &thread, live_now);
+        }
+        if recorder.is_recording() {
+            let secs = recorder.elapsed().as_secs();
+            let x = (current_sw / 2.0) as i32 - ui.pxi(50);
+            // Blinks once a second
+            if recorder.elapsed().subsec_millis() < 500 {
+                d.draw_circle(x, ui.pxi(22), ui.px(8.0), Color::RED);
+            }
+            d.draw_text(
+                &format!("REC {:02}:{:02}", secs / 60, secs % 60),
+                x + ui.pxi(16),
+                ui.pxi(12),
+                ui.font(20),
+                Color::RED,
+            );
+        }
+        if mti.enabled {
+            let text = match mti.learning(radar.sweeps) {
+                Some((done, sweeps)) => {
+                    format!("MTI: learning background, sweep {}/{}", done, sweeps)
+                }
+                None => format!(
+                    "MTI: moving targets only ({} relearns)",
+                    keys.label(Action::Relearn)
+                ),
+            };
+            d.draw_text(
+                &text,
+                (current_sw * 0.05) as i32,
+                (current_sh * 0.95) as i32 - ui.pxi(30),
+                ui.font(20),
+                theme.warning,
+            );
+        }
+        if diff.enabled {
+            let text = if diff.is_capturing() {
+                String::from("DIFF: taking the reference sweep")
+            } else if diff.has_reference() {
+                format!(
+                    "DIFF: red is new, blue is gone ({} retakes)",
+                    keys.label(Action::Reference)
+                )
+            } else {
+                format!(
+                    "DIFF: no reference yet, {} takes one",
+                    keys.label(Action::Reference)
+                )
+            };
+            d.draw_text(
+                &text,
+                (current_sw * 0.05) as i32,
+                (current_sh * 0.95) as i32 - ui.pxi(60),
+                ui.font(20),
+                theme.warning,
+            );
+        }
+        if let Some(text) = sweep_rate.warning(now) {
+            d.draw_text(
+                &text,
+                (current_sw * 0.05) as i32,
+                (current_sh * 0.95) as i32 - ui.pxi(90),
+                ui.font(20),
+                theme.warning,
+            );
+        }
+        if freeze.is_frozen() {
+            let key = keys.label(Action::Freeze);
+            let text = i18n::fill(&strings.paused, &[("key", &key)]);
+            let width = d.measure_text(&text, ui.font(20));
+            d.draw_text(
+                &text,
+                (current_sw as i32 - width) / 2,
+                ui.pxi(40),
+                ui.font(20),
+                theme.warning,
+            );
+        }
+        help.draw(&mut d, &keys, current_sw, current_sh, ui, &theme);
+        drop(d);
+        link_stats.presented(frame_time, Instant::now());
+        sensor_clock.presented(Instant::now());
+
+        if let Some((port_name, baud_rate)) = chosen_port {
+            info!("Using Port: {}, Baud: {}", port_name, baud_rate.label());
+            // Close the old port first, it may be the same one
+            drop(source);
+            source = discovery::open(&port_name, baud_rate, protocol);
+            current_port = Some((port_name, baud_rate));
+            // The new device gets asked who it is as well
+            handshake = Handshake::new(config.handshake.clone());
+            link_stats.switched_source();
+            sensor_clock = SensorClock::new(Instant::now());
+            last_link_state = None;
+        }
+
+        if let Some(calibration) = calibrated {
+            pipeline.calibration = calibration;
+            match calibration::save(&config_path, &calibration, profile.as_deref()) {
+                Ok(()) => info!("Saved calibration to {}", config_path),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        if let Some(new) = changed {
+            if new.preset != post.preset() {
+                post.set_preset(&mut rl, &thread, new.preset);
+            }
+            if new.shader != post.params() {
+                post.set_params(new.shader);
+            }
+            fade_half_life_s = new.fade_half_life_s;
+            if new.max_range_cm != max_range_cm {
+                range.set_max_cm(new.max_range_cm);
+            }
+            if new.theme != theme_kind {
+                theme_kind = new.theme;
+                theme = theme_kind.theme();
+                fonts.select(theme_kind);
+                let mut d = rl.begin_texture_mode(&thread, &mut target);
+                d.clear_background(theme.background);
+            }
+            unit = new.unit;
+            for (zone, max_cm) in pipeline.alarms.zones_mut().iter_mut().zip(new.alarm_max_cm) {
+                zone.max_cm = max_cm;
+            }
+        }
+        if !settings.open && settings.dirty {
+            settings.dirty = false;
+            match settings::save(&config_path, &values, profile.as_deref()) {
+                Ok(()) => info!("Saved settings to {}", config_path),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        if let Some(bench) = &mut bench
+            && bench.frame(frame_time, update.samples.len(), Instant::now())
+        {
+            println!("{}", bench.report());
+            break;
+        }
+
+        // ---- Save State ----
+        // On a timer, so a power cut doesn't lose the setup, and on the way
+        // out. A bench run leaves the setup alone
+        if bench.is_none()
+            && (state_saver.due(Instant::now()) || rl.window_should_close() || quitting)
+        {
+            let window = (!rl.is_window_fullscreen()).then(|| {
+                let pos = rl.get_window_position();
+                WindowState {
+                    x: pos.x as i32,
+                    y: pos.y as i32,
+                    width: rl.get_screen_width(),
+                    height: rl.get_screen_height(),
+                }
+            });
+            let state = SavedState {
+                window,
+                port: current_port.as_ref().map(|(port, _)| port.clone()),
+                baud: current_port.as_ref().map(|(_, baud)| baud.label()),
+                theme: Some(theme_kind),
+                unit: Some(unit),
+                display: Some(display_mode),
+                max_range_cm: Some(range.max_cm()),
+                auto_range: range.auto,
+                zoom: Some(viewport.zoom),
+                pan: [viewport.pan.x, viewport.pan.y],
+                background: mti.learned(),
+                file: Some(file_settings),
+            };
+            state_saver.save(&state, Instant::now());
+        }
+    }
+
+    // Finish the file when the window is closed mid-recording
+    if recorder.is_recording() {
+        match recorder.stop() {
+            Ok(path) => info!("Saved recording {}", path.display()),
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+/// Reads the config file with `profile` on top, the command line flags that
+/// override it applied.
+fn load_config(args: &Args, profile: Option<&str>) -> Result<Config, String> {
+    let mut config = Config::load(args.config.as_deref(), profile)?;
+    if let Some(range_cm) = args.range_cm {
+        config.range.max_cm = range_cm;
+    }
+    Ok(config)
+}
+
+/// The trail and scene render textures, at `width` by `height`.
+fn render_textures(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    width: u32,
+    height: u32,
+) -> Result<(RenderTexture2D, RenderTexture2D), AppError> {
+    let target = rl
+        .load_render_texture(thread, width, height)
+        .map_err(AppError::texture("the sweep trail"))?;
+    let scene = rl
+        .load_render_texture(thread, width, height)
+        .map_err(AppError::texture("the scene"))?;
+    Ok((target, scene))
+}
+
+/// Lists the available serial ports and asks for port and baud rate on stdin,
+/// for `--headless` without a port. Asks again for an empty port or a baud
+/// rate that doesn't parse.
+fn prompt_serial_port() -> Result<(String, Baud), AppError> {
+    println!("\n--- Available Serial Ports ---");
+    if let Ok(ports) = serialport::available_ports() {
+        for p in ports {
+            println!(" -> {}", p.port_name);
+        }
+    }
+
+    let port = loop {
+        let port = prompt("\nEnter Serial Port: ")?;
+        if !port.is_empty() {
+            break port;
+        }
+    };
+    let baud = loop {
+        let baud = prompt("Enter Baud Rate (default 9600, auto to detect): ")?;
+        if baud.is_empty() {
+            break Baud::Fixed(9600);
+        }
+        match Baud::parse(&baud) {
+            Some(baud) => break baud,
+            None => println!("Not a baud rate: {}", baud),
+        }
+    };
+    Ok((port, baud))
+}
+
+/// One trimmed line from stdin after printing `question`.
+fn prompt(question: &str) -> Result<String, AppError> {
+    print!("{}", question);
+    io::stdout().flush().map_err(AppError::Stdin)?;
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) => Err(AppError::StdinClosed),
+        Ok(_) => Ok(line.trim().to_string()),
+        Err(e) => Err(AppError::Stdin(e)),
     }
 }
@@ -0,0 +1,209 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use toml_edit::{ArrayOfTables, Table, value};
+
+use crate::config;
+use crate::ui::UiScale;
+use crate::view::Layout;
+
+/// A right-click this close to a marker removes it, pixels.
+const PICK_RADIUS_PX: f32 = 12.0;
+const ICON_RADIUS_PX: f32 = 7.0;
+
+/// One `[[marker]]` entry of the config file: a known feature like a doorway
+/// or the charging dock, drawn as a labeled diamond so blips can be matched
+/// against it. The position is `angle` and `distance_cm`, or `x_cm` and
+/// `y_cm` on the map when both are set.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Marker {
+    pub name: String,
+    pub angle: f32,
+    pub distance_cm: f32,
+    pub x_cm: Option<f32>,
+    pub y_cm: Option<f32>,
+}
+
+impl Default for Marker {
+    fn default() -> Self {
+        Self {
+            name: String::from("marker"),
+            angle: 90.0,
+            distance_cm: 20.0,
+            x_cm: None,
+            y_cm: None,
+        }
+    }
+}
+
+impl Marker {
+    /// Angle and distance from the plot center.
+    pub fn position(&self) -> (f32, f32) {
+        match (self.x_cm, self.y_cm) {
+            (Some(x), Some(y)) => (y.atan2(x).to_degrees().rem_euclid(360.0), x.hypot(y)),
+            _ => (self.angle, self.distance_cm),
+        }
+    }
+
+    fn screen(&self, layout: &Layout, pixels_per_cm: f32) -> Vector2 {
+        let (angle, distance) = self.position();
+        layout.polar_to_screen(angle, distance * pixels_per_cm)
+    }
+}
+
+/// Dropping markers with the mouse, toggled with F11: left-click drops one
+/// and takes its name from the keyboard until Enter, right-click removes
+/// the one under the mouse.
+#[derive(Default)]
+pub struct MarkerEditor {
+    pub enabled: bool,
+    /// Index of the marker whose name is being typed
+    naming: Option<usize>,
+}
+
+impl MarkerEditor {
+    /// A marker still being named is dropped, it was never saved.
+    pub fn toggle(&mut self, markers: &mut Vec<Marker>) {
+        self.enabled = !self.enabled;
+        if let Some(i) = self.naming.take()
+            && i < markers.len()
+        {
+            markers.remove(i);
+        }
+    }
+
+    /// A name is being typed, the hotkeys stay quiet meanwhile.
+    pub fn is_naming(&self) -> bool {
+        self.naming.is_some()
+    }
+
+    /// Drops a marker at the mouse and starts naming it. On the map grid it
+    /// keeps x and y instead of angle and distance.
+    pub fn drop_at(
+        &mut self,
+        markers: &mut Vec<Marker>,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        mouse: Vector2,
+        map: bool,
+    ) {
+        let (angle, dist_px) = layout.screen_to_polar(mouse);
+        let distance = dist_px / pixels_per_cm;
+        let round = |x: f32| (x * 10.0).round() / 10.0;
+        let rad = angle.to_radians();
+        markers.push(Marker {
+            name: String::new(),
+            angle: round(angle),
+            distance_cm: round(distance),
+            x_cm: map.then(|| round(distance * rad.cos())),
+            y_cm: map.then(|| round(distance * rad.sin())),
+        });
+        self.naming = Some(markers.len() - 1);
+    }
+
+    /// Removes the marker under the mouse. Returns whether there was one.
+    pub fn remove_at(
+        &mut self,
+        markers: &mut Vec<Marker>,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        mouse: Vector2,
+        ui: UiScale,
+    ) -> bool {
+        let hit = markers.iter().rposition(|m| {
+            m.screen(layout, pixels_per_cm).distance_to(mouse) <= ui.px(PICK_RADIUS_PX)
+        });
+        match hit {
+            Some(i) => {
+                markers.remove(i);
+                self.naming = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes typed text into the name of the marker just dropped. Returns
+    /// `true` once Enter finishes it, an empty name gets a number.
+    pub fn type_name(&mut self, rl: &mut RaylibHandle, markers: &mut [Marker]) -> bool {
+        let Some(marker) = self.naming.and_then(|i| markers.get_mut(i)) else {
+            self.naming = None;
+            return false;
+        };
+        while let Some(c) = rl.get_char_pressed() {
+            if !c.is_control() {
+                marker.name.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE)
+            || rl.is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE)
+        {
+            marker.name.pop();
+        }
+        if !rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return false;
+        }
+        marker.name = marker.name.trim().to_string();
+        if marker.name.is_empty() {
+            marker.name = format!("marker {}", self.naming.unwrap_or_default() + 1);
+        }
+        self.naming = None;
+        true
+    }
+
+    /// Every marker as a diamond with its name next to it, the one being
+    /// named with a cursor.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        markers: &[Marker],
+        layout: &Layout,
+        pixels_per_cm: f32,
+        ui: UiScale,
+        color: Color,
+    ) {
+        for (i, marker) in markers.iter().enumerate() {
+            let pos = marker.screen(layout, pixels_per_cm);
+            let r = ui.px(ICON_RADIUS_PX);
+            d.draw_poly(pos, 4, r, 0.0, color.alpha(0.3));
+            d.draw_poly_lines_ex(pos, 4, r, 0.0, ui.px(2.0), color);
+            let label = if self.naming == Some(i) {
+                format!("{}_", marker.name)
+            } else {
+                marker.name.clone()
+            };
+            d.draw_text(
+                &label,
+                (pos.x + r * 1.5) as i32,
+                (pos.y - r * 1.5) as i32,
+                ui.font(16),
+                color,
+            );
+        }
+    }
+}
+
+/// Replaces the `[[marker]]` entries of the config file at `path` with
+/// `markers`.
+pub fn save(path: &str, markers: &[Marker], profile: Option<&str>) -> Result<(), String> {
+    config::edit(path, |doc| {
+        let rounded = |x: f32| (f64::from(x) * 1000.0).round() / 1000.0;
+        let mut tables = ArrayOfTables::new();
+        for marker in markers {
+            let mut table = Table::new();
+            table["name"] = value(marker.name.as_str());
+            match (marker.x_cm, marker.y_cm) {
+                (Some(x), Some(y)) => {
+                    table["x_cm"] = value(rounded(x));
+                    table["y_cm"] = value(rounded(y));
+                }
+                _ => {
+                    table["angle"] = value(rounded(marker.angle));
+                    table["distance_cm"] = value(rounded(marker.distance_cm));
+                }
+            }
+            tables.push(table);
+        }
+        config::set_entries(doc, profile, "marker", tables);
+    })
+}
@@ -0,0 +1,274 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use toml_edit::{ArrayOfTables, Table, value};
+
+use crate::config;
+use crate::radar::{self, Detection};
+use crate::view::{self, Layout};
+
+/// Gap between hatch lines, pixels.
+const HATCH_SPACING: f32 = 12.0;
+/// Detections in a dimming mask are drawn at this fraction of their alpha.
+pub const MASK_DIM: f32 = 0.25;
+/// Drags shorter than this, in both directions, are taken as clicks.
+const MIN_DRAG_PX: f32 = 6.0;
+
+/// One `[[mask]]` entry of the config file: known clutter, like a tripod leg,
+/// between `min_angle` and `max_angle` and `min_cm` to `max_cm` away, a
+/// `min_angle` above `max_angle` wrapping through 0°. Detections in it never
+/// set off an alarm.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MaskZone {
+    pub name: String,
+    pub min_angle: f32,
+    pub max_angle: f32,
+    pub min_cm: f32,
+    pub max_cm: f32,
+    /// Draw the detections inside faintly instead of at full strength
+    pub dim: bool,
+}
+
+impl Default for MaskZone {
+    fn default() -> Self {
+        Self {
+            name: String::from("mask"),
+            min_angle: 0.0,
+            max_angle: 10.0,
+            min_cm: 0.0,
+            max_cm: 30.0,
+            dim: true,
+        }
+    }
+}
+
+impl MaskZone {
+    pub fn contains(&self, angle: f32, distance: f32) -> bool {
+        radar::in_sector(angle, self.min_angle, self.max_angle)
+            && (self.min_cm..=self.max_cm).contains(&distance)
+    }
+
+    /// Its edges for drawing, the second past 360 when it wraps through 0°.
+    pub fn sector(&self) -> (f32, f32) {
+        let width = radar::sector_width(self.min_angle, self.max_angle);
+        (self.min_angle, self.min_angle + width)
+    }
+
+    /// Outline and diagonal hatching, clipped to the visible range.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+        color: Color,
+    ) {
+        let inner = self.min_cm.min(max_range_cm) * pixels_per_cm;
+        let outer = self.max_cm.min(max_range_cm) * pixels_per_cm;
+        let (min, max) = self.sector();
+        view::draw_sector(d, layout, (min, max), (inner, outer), color, false);
+        if outer - inner < 1.0 || max <= min {
+            return;
+        }
+
+        // Lines run from the inner edge to the outer one while turning by as
+        // many degrees as they climb at the middle radius, so they look
+        // diagonal, then get cut off at the sides
+        let mid = (inner + outer) / 2.0;
+        let turn = ((outer - inner) / mid).to_degrees();
+        let step = (HATCH_SPACING / mid).to_degrees().max(0.5);
+        let mut start = min - turn;
+        while start < max {
+            let t0 = ((min - start) / turn).max(0.0);
+            let t1 = ((max - start) / turn).min(1.0);
+            if t0 < t1 {
+                let at =
+                    |t: f32| layout.polar_to_screen(start + turn * t, inner + (outer - inner) * t);
+                // A few segments follow the curve well enough
+                let mut prev = at(t0);
+                for i in 1..=4 {
+                    let next = at(t0 + (t1 - t0) * i as f32 / 4.0);
+                    d.draw_line_v(prev, next, color);
+                    prev = next;
+                }
+            }
+            start += step;
+        }
+    }
+}
+
+/// All masks, checked by the pipeline before alarms.
+#[derive(Default)]
+pub struct Masks {
+    zones: Vec<MaskZone>,
+}
+
+impl Masks {
+    pub fn new(zones: Vec<MaskZone>) -> Self {
+        Self { zones }
+    }
+
+    pub fn zones(&self) -> &[MaskZone] {
+        &self.zones
+    }
+
+    /// The first mask the detection falls into, if any.
+    pub fn matching(&self, det: &Detection) -> Option<&MaskZone> {
+        self.zones
+            .iter()
+            .find(|z| z.contains(det.angle, det.distance))
+    }
+
+    pub fn is_masked(&self, det: &Detection) -> bool {
+        self.matching(det).is_some()
+    }
+
+    pub fn add(&mut self, zone: MaskZone) {
+        self.zones.push(zone);
+    }
+
+    /// Removes the last added mask covering the point. Returns whether one was.
+    pub fn remove_at(&mut self, angle: f32, distance: f32) -> bool {
+        match self.zones.iter().rposition(|z| z.contains(angle, distance)) {
+            Some(i) => {
+                self.zones.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Drawing masks with the mouse, toggled with E: drag with the left button
+/// across the clutter to mask it, right-click a mask to remove it.
+#[derive(Default)]
+pub struct MaskEditor {
+    pub enabled: bool,
+    /// Where the current drag started, on screen
+    start: Option<Vector2>,
+}
+
+impl MaskEditor {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.start = None;
+    }
+
+    pub fn press(&mut self, mouse: Vector2) {
+        self.start = Some(mouse);
+    }
+
+    /// Ends the drag. Returns the mask it spans unless it was a mere click.
+    /// `count` is how many masks there are, for the name.
+    pub fn release(
+        &mut self,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        mouse: Vector2,
+        count: usize,
+    ) -> Option<MaskZone> {
+        let start = self.start.take()?;
+        let delta = mouse - start;
+        if delta.x.abs() < MIN_DRAG_PX && delta.y.abs() < MIN_DRAG_PX {
+            return None;
+        }
+        let mut zone = self.span(layout, pixels_per_cm, start, mouse);
+        zone.name = format!("mask {}", count + 1);
+        Some(zone)
+    }
+
+    /// Sector and annulus between the two corners of a drag, the short way
+    /// round, so a drag across 0° masks the few degrees it crossed.
+    fn span(&self, layout: &Layout, pixels_per_cm: f32, a: Vector2, b: Vector2) -> MaskZone {
+        let (angle_a, px_a) = layout.screen_to_polar(a);
+        let (angle_b, px_b) = layout.screen_to_polar(b);
+        let (cm_a, cm_b) = (px_a / pixels_per_cm, px_b / pixels_per_cm);
+        let (from, to) = if radar::angle_delta(angle_a, angle_b) >= 0.0 {
+            (angle_a, angle_b)
+        } else {
+            (angle_b, angle_a)
+        };
+        MaskZone {
+            min_angle: from.round().rem_euclid(360.0),
+            max_angle: to.round().rem_euclid(360.0),
+            min_cm: cm_a.min(cm_b).round(),
+            max_cm: cm_a.max(cm_b).round(),
+            ..MaskZone::default()
+        }
+    }
+
+    /// The mask the drag in progress would make.
+    pub fn draw_preview<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+        mouse: Vector2,
+        color: Color,
+    ) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let zone = self.span(layout, pixels_per_cm, start, mouse);
+        zone.draw(d, layout, pixels_per_cm, max_range_cm, color);
+    }
+}
+
+/// Replaces the `[[mask]]` entries of the config file at `path` with `masks`.
+pub fn save(path: &str, masks: &[MaskZone], profile: Option<&str>) -> Result<(), String> {
+    config::edit(path, |doc| {
+        let rounded = |x: f32| (f64::from(x) * 1000.0).round() / 1000.0;
+        let mut tables = ArrayOfTables::new();
+        for mask in masks {
+            let mut table = Table::new();
+            table["name"] = value(mask.name.as_str());
+            table["min_angle"] = value(rounded(mask.min_angle));
+            table["max_angle"] = value(rounded(mask.max_angle));
+            table["min_cm"] = value(rounded(mask.min_cm));
+            table["max_cm"] = value(rounded(mask.max_cm));
+            table["dim"] = value(mask.dim);
+            tables.push(table);
+        }
+        config::set_entries(doc, profile, "mask", tables);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(min_angle: f32, max_angle: f32) -> MaskZone {
+        MaskZone {
+            min_angle,
+            max_angle,
+            ..MaskZone::default()
+        }
+    }
+
+    #[test]
+    fn contains_inside_its_sector() {
+        let mask = mask(30.0, 60.0);
+        assert!(mask.contains(45.0, 10.0));
+        assert!(mask.contains(405.0, 10.0));
+        assert!(!mask.contains(90.0, 10.0));
+        assert!(!mask.contains(45.0, 40.0));
+    }
+
+    #[test]
+    fn wraps_through_zero() {
+        let mask = mask(350.0, 10.0);
+        for angle in [355.0, 0.0, 5.0, -5.0, 360.0] {
+            assert!(mask.contains(angle, 10.0), "{}", angle);
+        }
+        assert!(!mask.contains(180.0, 10.0));
+        assert_eq!(mask.sector(), (350.0, 370.0));
+    }
+
+    #[test]
+    fn a_full_circle_contains_everything() {
+        let mask = mask(0.0, 360.0);
+        assert!(mask.contains(0.0, 10.0));
+        assert!(mask.contains(359.0, 10.0));
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use tracing::warn;
+
+use crate::protocol::{Protocol, ProtocolStats, Raw};
+use crate::source::{LinkState, Sample, Source};
+
+const DEFAULT_MQTT_PORT: u16 = 1883;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    /// Broker address, e.g. `tcp://broker:1883`
+    pub url: String,
+    pub topic: String,
+    pub qos: u8,
+}
+
+/// Splits `tcp://host:port` (or `mqtt://`, or a bare `host[:port]`) into its parts.
+fn parse_broker_url(url: &str) -> Option<(String, u16)> {
+    let rest = url
+        .strip_prefix("tcp://")
+        .or_else(|| url.strip_prefix("mqtt://"))
+        .unwrap_or(url)
+        .trim_end_matches('/');
+
+    match rest.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None if !rest.is_empty() => Some((rest.to_string(), DEFAULT_MQTT_PORT)),
+        None => None,
+    }
+}
+
+/// Subscribes to a topic on an MQTT broker. The rumqttc connection is driven on
+/// its own thread and payloads are handed over through a channel, each payload
+/// holding one frame of the configured protocol.
+pub struct MqttSource {
+    describe: String,
+    rx: Receiver<Vec<u8>>,
+    state: Arc<Mutex<LinkState>>,
+    protocol: Box<dyn Protocol>,
+}
+
+impl MqttSource {
+    pub fn connect(config: MqttConfig, protocol: Box<dyn Protocol>) -> Result<Self, String> {
+        let (host, port) = parse_broker_url(&config.url)
+            .ok_or_else(|| format!("Invalid MQTT broker address: {}", config.url))?;
+        let qos = rumqttc::qos(config.qos).map_err(|_| format!("Invalid QoS: {}", config.qos))?;
+
+        let mut options = MqttOptions::new(format!("radar-{}", std::process::id()), &host, port);
+        options.set_keep_alive(Duration::from_secs(5));
+
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(LinkState::Connecting));
+        let thread_state = Arc::clone(&state);
+        let topic = config.topic.clone();
+
+        thread::spawn(move || run(options, topic, qos, tx, thread_state));
+
+        Ok(Self {
+            describe: format!("mqtt {}:{} {}", host, port, config.topic),
+            rx,
+            state,
+            protocol,
+        })
+    }
+}
+
+fn run(
+    options: MqttOptions,
+    topic: String,
+    qos: QoS,
+    tx: Sender<Vec<u8>>,
+    state: Arc<Mutex<LinkState>>,
+) {
+    let set_state = |s| *state.lock().unwrap() = s;
+    let (client, mut connection) = Client::new(options, 64);
+
+    // The iterator reconnects on its own, we only have to re-subscribe after
+    // every ConnAck since the session is clean.
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                set_state(LinkState::Connected);
+                if client.subscribe(topic.as_str(), qos).is_err() {
+                    return;
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                if tx.send(publish.payload.to_vec()).is_err() {
+                    // Receiver dropped, the window was closed
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("MQTT connection error: {}", e);
+                set_state(LinkState::Disconnected);
+                thread::sleep(RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+impl Source for MqttSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        for payload in self.rx.try_iter() {
+            self.protocol.decode(&payload, &mut samples);
+            // Messages usually come without a line ending
+            self.protocol.flush(&mut samples);
+        }
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        *self.state.lock().unwrap()
+    }
+
+    fn describe(&self) -> String {
+        self.describe.clone()
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        (self.protocol.name(), self.protocol.stats())
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        self.protocol.take_messages()
+    }
+
+    /// Text lines only, like stdin
+    fn tap(&mut self, on: bool) {
+        self.protocol.tap(on);
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        self.protocol.take_raw()
+    }
+}
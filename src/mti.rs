@@ -0,0 +1,245 @@
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::radar::Detection;
+use crate::source::Sample;
+use crate::view::{self, Layout};
+
+/// Learning stops after this long even without enough sweeps, so a sensor
+/// pointing one way learns too.
+const LEARN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Thickness of the background outline, pixels.
+const BACKGROUND_WIDTH: f32 = 2.0;
+
+/// `[mti]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MtiConfig {
+    /// Start in moving target mode, I toggles it
+    pub enabled: bool,
+    /// Sweeps the background is learned over
+    pub learn_sweeps: u64,
+    /// Returns closer to the background than this are part of it
+    pub tolerance_cm: f32,
+    /// Angular resolution of the background
+    pub bin_deg: f32,
+}
+
+impl Default for MtiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            learn_sweeps: 5,
+            tolerance_cm: 5.0,
+            bin_deg: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Bin {
+    sum: f32,
+    hits: u32,
+    readings: u32,
+}
+
+impl Bin {
+    /// Mean distance, if the bin echoed in at least half of its readings.
+    fn distance(&self) -> Option<f32> {
+        (self.hits > 0 && self.hits * 2 >= self.readings).then(|| self.sum / self.hits as f32)
+    }
+}
+
+/// A learned background, kept across restarts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Background {
+    pub bin_deg: f32,
+    /// Distance of every bin of every sensor, `None` where nothing echoed
+    pub layers: Vec<Vec<Option<f32>>>,
+}
+
+#[derive(Default)]
+struct Learning {
+    /// When and at which sweep the first samples came in
+    start: Option<(Instant, u64)>,
+}
+
+/// Moving target indication: learns what the static scene looks like, then
+/// shows only what differs from it and the learned background faintly.
+pub struct Mti {
+    pub enabled: bool,
+    learn_sweeps: u64,
+    tolerance_cm: f32,
+    bin_deg: f32,
+    /// One set of bins per sensor
+    bins: Vec<Vec<Bin>>,
+    learning: Option<Learning>,
+}
+
+impl Mti {
+    pub fn new(config: &MtiConfig) -> Self {
+        let mut mti = Self {
+            enabled: config.enabled,
+            learn_sweeps: config.learn_sweeps.max(1),
+            tolerance_cm: config.tolerance_cm.max(0.0),
+            bin_deg: config.bin_deg.clamp(0.1, 45.0),
+            bins: Vec::new(),
+            learning: None,
+        };
+        mti.relearn();
+        mti
+    }
+
+    /// Forgets the background and learns it again from the next sweeps.
+    pub fn relearn(&mut self) {
+        self.bins.clear();
+        self.learning = Some(Learning::default());
+    }
+
+    /// The background once it's learned.
+    pub fn learned(&self) -> Option<Background> {
+        if self.learning.is_some() || self.bins.is_empty() {
+            return None;
+        }
+        let layers = self
+            .bins
+            .iter()
+            .map(|bins| bins.iter().map(Bin::distance).collect())
+            .collect();
+        Some(Background {
+            bin_deg: self.bin_deg,
+            layers,
+        })
+    }
+
+    /// Takes a background learned before instead of learning it again. One
+    /// of another resolution is no use, the bins wouldn't line up.
+    pub fn restore(&mut self, background: Background) {
+        if (background.bin_deg - self.bin_deg).abs() > f32::EPSILON {
+            info!("Learning the background again, bin_deg changed");
+            return;
+        }
+        self.bins = background
+            .layers
+            .into_iter()
+            .map(|distances| {
+                distances
+                    .into_iter()
+                    .map(|distance| Bin {
+                        sum: distance.unwrap_or(0.0),
+                        hits: u32::from(distance.is_some()),
+                        readings: 1,
+                    })
+                    .collect()
+            })
+            .collect();
+        self.learning = None;
+    }
+
+    /// Swept so far and sweeps to go, while learning.
+    pub fn learning(&self, sweep: u64) -> Option<(u64, u64)> {
+        let learning = self.learning.as_ref()?;
+        let done = learning
+            .start
+            .map_or(0, |(_, first)| sweep.saturating_sub(first));
+        Some((done, self.learn_sweeps))
+    }
+
+    fn bin(&self, angle: f32) -> usize {
+        (angle.rem_euclid(360.0) / self.bin_deg) as usize
+    }
+
+    /// Learns from the first echo of every reading, if still learning.
+    /// `sweep` is the pipeline's sweep count.
+    pub fn record(&mut self, samples: &[Sample], sweep: u64, now: Instant) {
+        let Some(learning) = &mut self.learning else {
+            return;
+        };
+        if samples.is_empty() {
+            return;
+        }
+        let (started, first) = *learning.start.get_or_insert((now, sweep));
+        if sweep >= first + self.learn_sweeps
+            || now.saturating_duration_since(started) >= LEARN_TIMEOUT
+        {
+            self.learning = None;
+            return;
+        }
+        let count = (360.0 / self.bin_deg).ceil() as usize;
+        for sample in samples.iter().filter(|s| s.echo == 0) {
+            let i = self.bin(sample.angle);
+            let layer = usize::from(sample.layer);
+            if self.bins.len() <= layer {
+                self.bins.resize(layer + 1, Vec::new());
+            }
+            let bins = &mut self.bins[layer];
+            if bins.is_empty() {
+                bins.resize(count, Bin::default());
+            }
+            let Some(bin) = bins.get_mut(i) else {
+                continue;
+            };
+            bin.readings += 1;
+            if sample.distance > 0.0 {
+                bin.sum += sample.distance;
+                bin.hits += 1;
+            }
+        }
+    }
+
+    /// On and done learning, so detections are being sorted out.
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.learning.is_none()
+    }
+
+    fn background(&self, layer: u8, angle: f32) -> Option<f32> {
+        self.bins
+            .get(usize::from(layer))?
+            .get(self.bin(angle))?
+            .distance()
+    }
+
+    /// Whether the detection is something new: nothing was there while
+    /// learning, or it's nearer or further than the background.
+    pub fn is_moving(&self, det: &Detection) -> bool {
+        self.background(det.layer, det.angle)
+            .is_none_or(|d| (det.distance - d).abs() > self.tolerance_cm)
+    }
+
+    /// The learned background of every sensor as an outline.
+    pub fn draw_background<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        max_range_cm: f32,
+        color: Color,
+    ) {
+        if !self.is_active() {
+            return;
+        }
+        for bins in &self.bins {
+            for (i, bin) in bins.iter().enumerate() {
+                let Some(distance) = bin.distance() else {
+                    continue;
+                };
+                if distance >= max_range_cm {
+                    continue;
+                }
+                let start = i as f32 * self.bin_deg;
+                let r = distance * pixels_per_cm;
+                view::draw_sector(
+                    d,
+                    layout,
+                    (start, start + self.bin_deg),
+                    (r, r + BACKGROUND_WIDTH),
+                    color,
+                    true,
+                );
+            }
+        }
+    }
+}
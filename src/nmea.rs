@@ -0,0 +1,252 @@
+use crate::protocol::{MAX_MESSAGES, Protocol, ProtocolStats, Raw, Tap};
+use crate::source::Sample;
+
+/// Real NMEA sentences stop at 82 characters, this leaves room for a few
+/// more echoes.
+const MAX_SENTENCE_LEN: usize = 256;
+
+/// NMEA 0183 style sentences, one per line:
+///
+/// ```text
+/// $RDSWP,angle,distance[,distance...]*hh   sweep sample, one distance per echo
+/// $RDSTA,text*hh                           status text, shown in the console
+/// $RDBAT,volts[,percent]*hh                battery, shown in the console
+/// ```
+///
+/// `hh` is the XOR of every byte between `$` and `*` in hex. Sentences with
+/// a wrong checksum or none are dropped. Any talker ID works in place of
+/// `RD`, unknown sentence types are skipped without counting as errors. An
+/// empty distance is a reading with nothing heard.
+#[derive(Default)]
+pub struct NmeaProtocol {
+    buf: Vec<u8>,
+    /// Skipping the rest of an overlong line, up to its newline
+    discarding: bool,
+    stats: ProtocolStats,
+    messages: Vec<String>,
+    tap: Tap,
+}
+
+/// The part between `$` and `*` if the checksum matches. `Err` for a wrong
+/// or missing checksum, `Ok(None)` for a line that isn't a sentence.
+fn verify(line: &str) -> Result<Option<&str>, ()> {
+    let Some(body) = line.strip_prefix('$') else {
+        return Ok(None);
+    };
+    let (body, checksum) = body.rsplit_once('*').ok_or(())?;
+    let expected = u8::from_str_radix(checksum.trim(), 16).map_err(|_| ())?;
+    let actual = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    if actual == expected {
+        Ok(Some(body))
+    } else {
+        Err(())
+    }
+}
+
+fn parse_sweep(fields: &[&str]) -> Option<Vec<Sample>> {
+    let (angle, distances) = fields.split_first()?;
+    let angle: f32 = angle.trim().parse().ok()?;
+    if distances.is_empty() {
+        return None;
+    }
+    let heard: Vec<&&str> = distances.iter().filter(|d| !d.trim().is_empty()).collect();
+    if heard.is_empty() {
+        return Some(vec![Sample::new(angle, 0.0)]);
+    }
+    heard
+        .iter()
+        .enumerate()
+        .map(|(echo, d)| {
+            let distance = d.trim().parse().ok()?;
+            Some(Sample {
+                echo: echo.min(u8::MAX as usize) as u8,
+                ..Sample::new(angle, distance)
+            })
+        })
+        .collect()
+}
+
+fn battery(fields: &[&str]) -> Option<String> {
+    let volts: f32 = fields.first()?.trim().parse().ok()?;
+    match fields.get(1).and_then(|p| p.trim().parse::<f32>().ok()) {
+        Some(percent) => Some(format!("Battery: {:.2} V ({:.0}%)", volts, percent)),
+        None => Some(format!("Battery: {:.2} V", volts)),
+    }
+}
+
+impl NmeaProtocol {
+    fn message(&mut self, text: String) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(text);
+    }
+
+    fn decode_line(&mut self, out: &mut Vec<Sample>) {
+        let line = String::from_utf8_lossy(&self.buf).trim().to_string();
+        self.buf.clear();
+        if line.is_empty() {
+            return;
+        }
+        let errors = self.stats.malformed + self.stats.checksum_errors;
+        self.decode_sentence(&line, out);
+        let valid = self.stats.malformed + self.stats.checksum_errors == errors;
+        self.tap.record(&line, valid);
+    }
+
+    fn decode_sentence(&mut self, line: &str, out: &mut Vec<Sample>) {
+        let body = match verify(line) {
+            Ok(Some(body)) => body,
+            // Plain text from the firmware, like the line protocols keep it
+            Ok(None) => return self.message(line.to_string()),
+            Err(()) => {
+                self.stats.checksum_errors += 1;
+                return;
+            }
+        };
+        let fields: Vec<&str> = body.split(',').collect();
+        // Two letter talker, three letter sentence type
+        let kind = fields[0].get(2..).unwrap_or_default();
+        match kind {
+            "SWP" => match parse_sweep(&fields[1..]) {
+                Some(samples) => {
+                    self.stats.frames += 1;
+                    out.extend(samples);
+                }
+                None => self.stats.malformed += 1,
+            },
+            "STA" => {
+                self.stats.frames += 1;
+                let text = format!("Status: {}", fields[1..].join(","));
+                self.message(text);
+            }
+            "BAT" => match battery(&fields[1..]) {
+                Some(text) => {
+                    self.stats.frames += 1;
+                    self.message(text);
+                }
+                None => self.stats.malformed += 1,
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Protocol for NmeaProtocol {
+    fn name(&self) -> &'static str {
+        "nmea"
+    }
+
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<Sample>) {
+        for &b in bytes {
+            if b == b'\n' {
+                if !std::mem::take(&mut self.discarding) {
+                    self.decode_line(out);
+                }
+            } else if self.discarding {
+                continue;
+            } else if self.buf.len() >= MAX_SENTENCE_LEN {
+                // The tail of it is no sentence of its own either
+                self.stats.malformed += 1;
+                self.buf.clear();
+                self.discarding = true;
+            } else {
+                self.buf.push(b);
+            }
+        }
+    }
+
+    fn flush(&mut self, out: &mut Vec<Sample>) {
+        if !std::mem::take(&mut self.discarding) && !self.buf.is_empty() {
+            self.decode_line(out);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.discarding = false;
+    }
+
+    fn stats(&self) -> ProtocolStats {
+        self.stats
+    }
+
+    fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+
+    fn tap(&mut self, on: bool) {
+        self.tap.set(on);
+    }
+
+    fn take_raw(&mut self) -> Vec<Raw> {
+        self.tap.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `body` with its checksum, as a line.
+    fn sentence(body: &str) -> String {
+        let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        format!("${}*{:02X}\n", body, checksum)
+    }
+
+    fn decode(protocol: &mut NmeaProtocol, text: &str) -> Vec<Sample> {
+        let mut out = Vec::new();
+        protocol.decode(text.as_bytes(), &mut out);
+        out
+    }
+
+    #[test]
+    fn verifies_the_checksum() {
+        assert_eq!(verify("$RDSWP,90,25*0A"), Err(()));
+        let line = sentence("RDSWP,90,25");
+        assert_eq!(verify(line.trim()), Ok(Some("RDSWP,90,25")));
+        assert_eq!(verify("hello"), Ok(None));
+    }
+
+    #[test]
+    fn rejects_a_sentence_without_a_checksum() {
+        let mut protocol = NmeaProtocol::default();
+        assert!(decode(&mut protocol, "$RDSWP,90,25\n").is_empty());
+        assert_eq!(protocol.stats().checksum_errors, 1);
+    }
+
+    #[test]
+    fn decodes_every_echo() {
+        let mut protocol = NmeaProtocol::default();
+        let samples = decode(&mut protocol, &sentence("GPSWP,45.5,20,80"));
+        assert_eq!(samples.len(), 2);
+        assert_eq!((samples[0].angle, samples[0].distance), (45.5, 20.0));
+        assert_eq!((samples[1].echo, samples[1].distance), (1, 80.0));
+    }
+
+    #[test]
+    fn an_empty_distance_is_nothing_heard() {
+        let mut protocol = NmeaProtocol::default();
+        let samples = decode(&mut protocol, &sentence("RDSWP,90,"));
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].distance, 0.0);
+        assert_eq!(protocol.stats().malformed, 0);
+    }
+
+    #[test]
+    fn skips_an_overlong_line_to_its_end() {
+        let mut protocol = NmeaProtocol::default();
+        let long = format!(
+            "{}{}",
+            "x".repeat(MAX_SENTENCE_LEN + 10),
+            sentence("RDSWP,1,2")
+        );
+        assert!(decode(&mut protocol, &long).is_empty());
+        assert_eq!(decode(&mut protocol, &sentence("RDSWP,1,2")).len(), 1);
+        assert_eq!(protocol.stats().malformed, 1);
+    }
+}
@@ -0,0 +1,130 @@
+use std::io;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tracing::warn;
+
+/// `[notifications]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    /// Also while the window is showing, not just while it's minimized
+    pub always: bool,
+    /// At most one notification of a kind this often, seconds
+    pub min_interval_s: f32,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            always: false,
+            min_interval_s: 10.0,
+        }
+    }
+}
+
+/// What a notification is about, each kind is rate limited on its own.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Alarm,
+    Disconnect,
+}
+
+/// Native desktop notifications for alarms and a dropped link while the
+/// window is minimized, so the visualizer can sit in the background as a
+/// presence monitor. Goes through `notify-send` on Linux, `osascript` on
+/// macOS and a PowerShell balloon on Windows.
+pub struct Notifier {
+    config: NotifyConfig,
+    last: Vec<(Kind, Instant)>,
+    /// Still showing, reaped as they exit
+    children: Vec<Child>,
+}
+
+impl Notifier {
+    pub fn new(config: &NotifyConfig) -> Self {
+        Self {
+            config: config.clone(),
+            last: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// `minimized` is whether the window is, nothing is sent otherwise
+    /// unless `always` is set.
+    pub fn alarm(&mut self, text: &str, minimized: bool, now: Instant) {
+        self.send(Kind::Alarm, "Radar alarm", text, minimized, now);
+    }
+
+    pub fn disconnected(&mut self, source: &str, minimized: bool, now: Instant) {
+        let text = format!("{} disconnected", source);
+        self.send(Kind::Disconnect, "Radar link lost", &text, minimized, now);
+    }
+
+    fn send(&mut self, kind: Kind, title: &str, body: &str, minimized: bool, now: Instant) {
+        self.children
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+        if !self.config.enabled || !(minimized || self.config.always) {
+            return;
+        }
+        let interval = Duration::from_secs_f32(self.config.min_interval_s.max(0.0));
+        if let Some((_, last)) = self.last.iter_mut().find(|(k, _)| *k == kind) {
+            if now.saturating_duration_since(*last) < interval {
+                return;
+            }
+            *last = now;
+        } else {
+            self.last.push((kind, now));
+        }
+        match spawn(title, body) {
+            Ok(child) => self.children.push(child),
+            Err(e) => {
+                // Without a notification daemon there's no point trying again
+                warn!(
+                    "Failed to show a desktop notification, turning them off: {}",
+                    e
+                );
+                self.config.enabled = false;
+            }
+        }
+    }
+}
+
+/// Shows the notification with the platform's own tool. The scripts get
+/// the text from environment variables, so nothing in it needs quoting.
+fn spawn(title: &str, body: &str) -> io::Result<Child> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut c = Command::new("osascript");
+        c.args([
+            "-e",
+            "display notification (system attribute \"RADAR_BODY\") \
+             with title (system attribute \"RADAR_TITLE\")",
+        ]);
+        c
+    } else if cfg!(windows) {
+        let mut c = Command::new("powershell");
+        c.args([
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Warning; $n.Visible = $true; \
+             $n.ShowBalloonTip(5000, $env:RADAR_TITLE, $env:RADAR_BODY, 'Warning'); \
+             Start-Sleep -Seconds 6; $n.Dispose()",
+        ]);
+        c
+    } else {
+        let mut c = Command::new("notify-send");
+        c.args(["--app-name=radar", title, body]);
+        c
+    };
+    command
+        .env("RADAR_TITLE", title)
+        .env("RADAR_BODY", body)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+}
@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::UdpSocket;
+
+use raylib::prelude::*;
+use tracing::{info, warn};
+
+use crate::ui::UiScale;
+use crate::view::Layout;
+
+/// Poses kept for the trail, a few minutes at 10 Hz.
+const TRAIL_LEN: usize = 2000;
+/// The trail gets a new point once the platform moved this far.
+const TRAIL_STEP_CM: f32 = 2.0;
+
+/// Where the platform carrying the sensor is, in the world fixed frame the
+/// plot is drawn in: cm from where odometry started, +x towards 0° and +y
+/// towards 90°, the heading counter-clockwise from +x like data angles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub x_cm: f32,
+    pub y_cm: f32,
+    pub heading_deg: f32,
+}
+
+impl Pose {
+    /// `x,y,heading`.
+    fn parse(fields: &str) -> Option<Self> {
+        let mut fields = fields.split(',').map(|f| f.trim().parse::<f32>());
+        let (Some(Ok(x_cm)), Some(Ok(y_cm)), Some(Ok(heading_deg)), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return None;
+        };
+        Some(Self {
+            x_cm,
+            y_cm,
+            heading_deg,
+        })
+    }
+
+    /// Moves a reading from the sensor's frame into the world's, as angle
+    /// and distance from the world origin. The heading is added to the
+    /// angle, just like a sensor's `angle_offset_deg`.
+    pub fn place(&self, angle: f32, distance: f32) -> (f32, f32) {
+        let rad = (angle + self.heading_deg).to_radians();
+        let x = self.x_cm + distance * rad.cos();
+        let y = self.y_cm + distance * rad.sin();
+        (y.atan2(x).to_degrees().rem_euclid(360.0), x.hypot(y))
+    }
+
+    /// Screen position of the platform.
+    pub fn screen(&self, layout: &Layout, pixels_per_cm: f32) -> Vector2 {
+        layout.map_to_screen(self.x_cm, self.y_cm, pixels_per_cm)
+    }
+}
+
+/// Where poses come from.
+enum Input {
+    /// `POSE,x,y,heading` lines on the sensor's own link
+    Link,
+    /// `x,y,heading` datagrams
+    Udp(UdpSocket),
+}
+
+/// Pose stream of a sensor on a moving platform, e.g. a rover's wheel
+/// odometry, and the trail it left.
+pub struct Odometry {
+    input: Input,
+    pub pose: Option<Pose>,
+    /// World positions in cm, oldest first
+    trail: VecDeque<(f32, f32)>,
+}
+
+impl Odometry {
+    /// `serial` for poses on the sensor's link, or an address to receive
+    /// them on over UDP.
+    pub fn open(spec: &str) -> Result<Self, String> {
+        let input = if spec == "serial" {
+            info!("Reading POSE lines from the sensor");
+            Input::Link
+        } else {
+            let socket =
+                UdpSocket::bind(spec).map_err(|e| format!("Failed to bind {}: {}", spec, e))?;
+            socket
+                .set_nonblocking(true)
+                .map_err(|e| format!("Failed to set up {}: {}", spec, e))?;
+            info!("Receiving poses on {}", spec);
+            Input::Udp(socket)
+        };
+        Ok(Self {
+            input,
+            pose: None,
+            trail: VecDeque::new(),
+        })
+    }
+
+    /// Takes the poses out of the text lines from the source and reads any
+    /// that came in over the network. Returns the lines that were something
+    /// else, for the console.
+    pub fn update(&mut self, messages: Vec<String>) -> Vec<String> {
+        let mut rest = Vec::with_capacity(messages.len());
+        let mut poses = Vec::new();
+        match &self.input {
+            Input::Link => {
+                for line in messages {
+                    match line.strip_prefix("POSE,").and_then(Pose::parse) {
+                        Some(pose) => poses.push(pose),
+                        None => rest.push(line),
+                    }
+                }
+            }
+            Input::Udp(socket) => {
+                rest = messages;
+                let mut buf = [0u8; 512];
+                loop {
+                    match socket.recv(&mut buf) {
+                        Ok(n) => {
+                            let text = String::from_utf8_lossy(&buf[..n]);
+                            poses.extend(text.lines().filter_map(Pose::parse));
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => {
+                            warn!("Failed to receive a pose: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        for pose in poses {
+            self.moved(pose);
+        }
+        rest
+    }
+
+    fn moved(&mut self, pose: Pose) {
+        let far_enough = self
+            .trail
+            .back()
+            .is_none_or(|&(x, y)| (pose.x_cm - x).hypot(pose.y_cm - y) >= TRAIL_STEP_CM);
+        if far_enough {
+            if self.trail.len() == TRAIL_LEN {
+                self.trail.pop_front();
+            }
+            self.trail.push_back((pose.x_cm, pose.y_cm));
+        }
+        self.pose = Some(pose);
+    }
+
+    /// Draws the trail and the platform, a triangle pointing along its
+    /// heading.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        ui: UiScale,
+        color: Color,
+    ) {
+        let points: Vec<Vector2> = self
+            .trail
+            .iter()
+            .map(|&(x, y)| layout.map_to_screen(x, y, pixels_per_cm))
+            .collect();
+        for pair in points.windows(2) {
+            d.draw_line_ex(pair[0], pair[1], ui.px(2.0), color.alpha(0.6));
+        }
+        let Some(pose) = self.pose else {
+            return;
+        };
+        let at = pose.screen(layout, pixels_per_cm);
+        let size = ui.px(12.0);
+        // Offsets from the center are the same anywhere on the plot
+        let corner = |angle: f32| {
+            at + (layout.polar_to_screen(pose.heading_deg + angle, size) - layout.center)
+        };
+        d.draw_triangle_lines(corner(0.0), corner(140.0), corner(-140.0), color);
+    }
+}
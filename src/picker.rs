@@ -0,0 +1,154 @@
+use raylib::prelude::*;
+
+use crate::autobaud::Baud;
+use crate::ui::UiScale;
+
+/// Offered in the picker, 9600 is what the Arduino sketch uses. Auto comes
+/// last, after them.
+const BAUD_RATES: [u32; 8] = [9600, 19200, 38400, 57600, 115200, 230400, 256000, 460800];
+const WIDTH: f32 = 460.0;
+const HEIGHT: f32 = 340.0;
+const TEXT_SIZE: i32 = 16;
+
+/// Port and baud rate chooser drawn in the window, shown at startup when no
+/// port was given and again with its key to switch ports.
+#[derive(Default)]
+pub struct PortPicker {
+    pub open: bool,
+    ports: Vec<String>,
+    port: usize,
+    baud: usize,
+    port_scroll: i32,
+    baud_scroll: i32,
+    /// Window title, naming the key that opens it
+    title: String,
+}
+
+impl PortPicker {
+    /// Opens the picker, `key` is the label of the key that toggles it.
+    pub fn show(&mut self, key: &str) {
+        self.title = format!("Serial Port ({key})");
+        self.open = true;
+        self.rescan();
+    }
+
+    /// Lists the serial ports again, keeping the selection if it's still there.
+    pub fn rescan(&mut self) {
+        let selected = self.ports.get(self.port).cloned();
+        self.ports = serialport::available_ports()
+            .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
+            .unwrap_or_default();
+        self.port = selected
+            .and_then(|name| self.ports.iter().position(|p| *p == name))
+            .unwrap_or(0);
+    }
+
+    fn choice(&mut self) -> Option<(String, Baud)> {
+        let port = self.ports.get(self.port)?.clone();
+        self.open = false;
+        let baud = BAUD_RATES
+            .get(self.baud)
+            .map_or(Baud::Auto, |rate| Baud::Fixed(*rate));
+        Some((port, baud))
+    }
+
+    /// Up/Down pick the port, Left/Right the baud rate, F5 rescans and Enter
+    /// connects.
+    pub fn handle_keys(&mut self, rl: &RaylibHandle) -> Option<(String, Baud)> {
+        if !self.open {
+            return None;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.port = self.port.saturating_sub(1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.port = (self.port + 1).min(self.ports.len().saturating_sub(1));
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+            self.baud = self.baud.saturating_sub(1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+            self.baud = (self.baud + 1).min(BAUD_RATES.len());
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_F5) {
+            self.rescan();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return self.choice();
+        }
+        None
+    }
+
+    /// Draws the picker in the middle of the screen. Returns the port and
+    /// baud rate once Connect is clicked.
+    pub fn draw<D: RaylibDraw>(
+        &mut self,
+        d: &mut D,
+        screen_w: f32,
+        screen_h: f32,
+        ui: UiScale,
+    ) -> Option<(String, Baud)> {
+        if !self.open {
+            return None;
+        }
+        let x = (screen_w - ui.px(WIDTH)) / 2.0;
+        let y = (screen_h - ui.px(HEIGHT)) / 2.0;
+        // Laid out at the designed size, from the top left corner
+        let rect = |rx: f32, ry: f32, w: f32, h: f32| {
+            Rectangle::new(x + ui.px(rx), y + ui.px(ry), ui.px(w), ui.px(h))
+        };
+        d.gui_set_style(
+            GuiControl::DEFAULT,
+            GuiDefaultProperty::TEXT_SIZE,
+            ui.font(TEXT_SIZE),
+        );
+        if d.gui_window_box(rect(0.0, 0.0, WIDTH, HEIGHT), &self.title) {
+            self.open = false;
+            return None;
+        }
+
+        let list_h = HEIGHT - 120.0;
+        d.gui_label(rect(10.0, 30.0, 260.0, 24.0), "Port");
+        if self.ports.is_empty() {
+            d.gui_label(rect(10.0, 58.0, 260.0, 24.0), "No serial ports found");
+        } else {
+            let mut active = self.port as i32;
+            d.gui_list_view(
+                rect(10.0, 58.0, 260.0, list_h),
+                &self.ports.join(";"),
+                &mut self.port_scroll,
+                &mut active,
+            );
+            // Clicking the selected entry again deselects it in raygui
+            if active >= 0 {
+                self.port = active as usize;
+            }
+        }
+
+        d.gui_label(rect(290.0, 30.0, 160.0, 24.0), "Baud rate");
+        let mut bauds: Vec<String> = BAUD_RATES.iter().map(|b| b.to_string()).collect();
+        bauds.push(Baud::Auto.label());
+        let mut active = self.baud as i32;
+        d.gui_list_view(
+            rect(290.0, 58.0, 160.0, list_h),
+            &bauds.join(";"),
+            &mut self.baud_scroll,
+            &mut active,
+        );
+        if active >= 0 {
+            self.baud = active as usize;
+        }
+
+        let buttons_y = HEIGHT - 50.0;
+        if d.gui_button(rect(10.0, buttons_y, 140.0, 36.0), "Rescan (F5)") {
+            self.rescan();
+        }
+        if d.gui_button(
+            rect(WIDTH - 150.0, buttons_y, 140.0, 36.0),
+            "Connect (Enter)",
+        ) {
+            return self.choice();
+        }
+        None
+    }
+}
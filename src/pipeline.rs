@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use crate::alarm::{AlarmEvent, AlarmMonitor, AlarmZone};
+use crate::calibration::Calibration;
+use crate::filter::{Filter, FilterConfig};
+use crate::mask::{MaskZone, Masks};
+use crate::radar::{Detection, RadarState};
+use crate::source::Sample;
+use crate::tracker::Tracker;
+use crate::validate::{ValidationConfig, Validator};
+
+/// How long a detection is kept after it was received
+pub const DETECTION_LIFETIME: Duration = Duration::from_secs(3);
+
+/// What one batch of samples produced.
+#[derive(Default)]
+pub struct Update {
+    /// Samples that passed validation, after filtering. Always in the
+    /// sensor's frame, only the detections move into the world's with a pose
+    pub samples: Vec<Sample>,
+    pub detections: Vec<Detection>,
+    pub alarms: Vec<AlarmEvent>,
+}
+
+/// Everything between the source and the screen: validation, calibration,
+/// filtering, detection history, tracking, masks and alarm zones. Knows
+/// nothing about raylib, so it runs the same with or without a window.
+pub struct Pipeline {
+    pub validator: Validator,
+    pub calibration: Calibration,
+    pub filter: Filter,
+    pub radar: RadarState,
+    pub tracker: Tracker,
+    pub masks: Masks,
+    pub alarms: AlarmMonitor,
+}
+
+impl Pipeline {
+    pub fn new(
+        validation: ValidationConfig,
+        calibration: Calibration,
+        filter: FilterConfig,
+        masks: Vec<MaskZone>,
+        zones: Vec<AlarmZone>,
+    ) -> Self {
+        Self {
+            validator: Validator::new(validation),
+            calibration,
+            filter: Filter::new(filter),
+            radar: RadarState::default(),
+            tracker: Tracker::default(),
+            masks: Masks::new(masks),
+            alarms: AlarmMonitor::new(zones),
+        }
+    }
+
+    /// Runs a batch of samples from the source through every stage.
+    pub fn process(&mut self, samples: Vec<Sample>, now: Instant) -> Update {
+        let mut update = Update::default();
+        for sample in samples {
+            let Some(sample) = self.validator.check(sample) else {
+                continue;
+            };
+            // Validation sees what the sensor reported, its timeouts and
+            // ringing are in raw numbers
+            let sample = self.calibration.apply(sample);
+            let sample = self.filter.apply(sample);
+            update.samples.push(sample);
+            if let Some(det) = self.radar.push(sample, now) {
+                self.tracker.update(&det);
+                // Known clutter never sets anything off
+                if !self.masks.is_masked(&det) {
+                    update.alarms.extend(self.alarms.check(&det));
+                }
+                update.detections.push(det);
+            }
+        }
+        update
+    }
+
+    /// Forgets detections and tracks that are too old to matter.
+    pub fn prune(&mut self, now: Instant) {
+        self.radar.prune(now, DETECTION_LIFETIME);
+        self.tracker.prune(now);
+    }
+}
+
+/// What the display showed when it was frozen.
+struct Frozen {
+    since: Instant,
+    radar: RadarState,
+    tracker: Tracker,
+}
+
+/// Freezes what's drawn, so a blip can be looked at before it fades. The
+/// pipeline keeps running on live data meanwhile: alarms, logging, sinks
+/// and the API never pause, only the picture does.
+#[derive(Default)]
+pub struct Freeze {
+    frozen: Option<Frozen>,
+}
+
+impl Freeze {
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// Takes a snapshot of the detections and tracks of `pipeline`, or lets
+    /// go of it.
+    pub fn toggle(&mut self, pipeline: &Pipeline, now: Instant) {
+        self.frozen = match self.frozen {
+            Some(_) => None,
+            None => Some(Frozen {
+                since: now,
+                radar: pipeline.radar.clone(),
+                tracker: pipeline.tracker.clone(),
+            }),
+        };
+    }
+
+    /// Time to draw at: the moment it froze, so detections keep their age.
+    pub fn now(&self, live: Instant) -> Instant {
+        self.frozen.as_ref().map_or(live, |f| f.since)
+    }
+
+    /// Radar state to draw, the snapshot while frozen.
+    pub fn radar<'a>(&'a self, live: &'a RadarState) -> &'a RadarState {
+        self.frozen.as_ref().map_or(live, |f| &f.radar)
+    }
+
+    /// Tracks to draw, the snapshot while frozen.
+    pub fn tracker<'a>(&'a self, live: &'a Tracker) -> &'a Tracker {
+        self.frozen.as_ref().map_or(live, |f| &f.tracker)
+    }
+}
@@ -0,0 +1,235 @@
+use std::time::Instant;
+
+use tracing::info;
+
+use crate::autobaud::Baud;
+use crate::bench::BenchSource;
+use crate::config::Config;
+use crate::datalog::DataLog;
+use crate::discovery;
+use crate::grpc::{GrpcServer, GrpcSource};
+use crate::ipc::IpcSource;
+use crate::mqtt::{MqttConfig, MqttSource};
+use crate::pipeline::Update;
+use crate::protocol::ProtocolKind;
+use crate::radar::RadarState;
+use crate::rebroadcast::{TcpRebroadcast, UdpRebroadcast};
+use crate::replay::ReplaySource;
+use crate::rerun_sink::RerunSink;
+use crate::ros::{ScanPublisher, ScanSource};
+use crate::source::Source;
+use crate::stdin::StdinSource;
+
+/// Where the samples go after the pipeline, the other end of `Source`.
+/// `write` is called once per pass with that pass' update and must never
+/// block for long.
+pub trait Sink {
+    fn write(&mut self, update: &Update, radar: &RadarState, now: Instant);
+}
+
+/// What sources and sinks get to open themselves with besides their
+/// argument.
+pub struct Context<'a> {
+    pub config: &'a Config,
+    pub protocol: ProtocolKind,
+    /// `--topic` and `--qos`
+    pub topic: &'a str,
+    pub qos: u8,
+}
+
+/// Opens a source or sink from its argument.
+pub type Open<T> = fn(&str, &Context) -> Result<Box<T>, String>;
+
+/// A kind of source `--source NAME:ARG` can open.
+pub struct SourcePlugin {
+    pub name: &'static str,
+    /// What ARG is, for `--help`
+    pub arg: &'static str,
+    pub open: Open<dyn Source>,
+}
+
+/// A kind of sink `--sink NAME:ARG` can open.
+pub struct SinkPlugin {
+    pub name: &'static str,
+    pub arg: &'static str,
+    pub open: Open<dyn Sink>,
+}
+
+/// Every source that can be named on the command line. A new kind of sensor
+/// is added by implementing `Source` and listing it here.
+pub const SOURCES: &[SourcePlugin] = &[
+    SourcePlugin {
+        name: "serial",
+        arg: "PORT[@BAUD]",
+        open: |arg, ctx| {
+            let (port, baud) = match arg.rsplit_once('@') {
+                Some((port, baud)) => (
+                    port,
+                    Baud::parse(baud).ok_or_else(|| format!("Invalid baud rate: {}", baud))?,
+                ),
+                None => (arg, Baud::Fixed(9600)),
+            };
+            Ok(discovery::open(port, baud, ctx.protocol))
+        },
+    },
+    SourcePlugin {
+        name: "mqtt",
+        arg: "URL",
+        open: |arg, ctx| {
+            let config = MqttConfig {
+                url: arg.to_string(),
+                topic: ctx.topic.to_string(),
+                qos: ctx.qos,
+            };
+            Ok(Box::new(MqttSource::connect(
+                config,
+                ctx.protocol.create(),
+            )?))
+        },
+    },
+    SourcePlugin {
+        name: "laserscan",
+        arg: "ADDR",
+        open: |arg, _| Ok(Box::new(ScanSource::bind(arg)?)),
+    },
+    SourcePlugin {
+        name: "ipc",
+        arg: "PATH",
+        open: |arg, ctx| Ok(Box::new(IpcSource::open(arg, ctx.protocol.create())?)),
+    },
+    SourcePlugin {
+        name: "stdin",
+        arg: "",
+        open: |_, ctx| Ok(Box::new(StdinSource::open(ctx.protocol.create()))),
+    },
+    SourcePlugin {
+        name: "grpc",
+        arg: "URL",
+        open: |arg, _| Ok(Box::new(GrpcSource::connect(arg)?)),
+    },
+    SourcePlugin {
+        name: "replay",
+        arg: "CSV",
+        open: |arg, _| Ok(Box::new(ReplaySource::open(arg)?)),
+    },
+    SourcePlugin {
+        name: "bench",
+        arg: "POINTS_PER_S",
+        open: |arg, ctx| {
+            let rate = arg
+                .parse()
+                .map_err(|_| format!("Invalid point rate: {}", arg))?;
+            Ok(Box::new(BenchSource::new(rate, ctx.config.range.max_cm)))
+        },
+    },
+];
+
+/// Every sink that can be named on the command line, new exporters are
+/// listed here as well.
+pub const SINKS: &[SinkPlugin] = &[
+    SinkPlugin {
+        name: "csv",
+        arg: "PATH",
+        open: |arg, _| Ok(Box::new(DataLog::create(arg)?)),
+    },
+    SinkPlugin {
+        name: "rerun",
+        arg: "DEST",
+        open: |arg, _| Ok(Box::new(RerunSink::start(arg)?)),
+    },
+    SinkPlugin {
+        name: "laserscan",
+        arg: "ADDR",
+        open: |arg, ctx| {
+            Ok(Box::new(ScanPublisher::connect(
+                arg,
+                &ctx.config.coverage,
+                &ctx.config.validation,
+            )?))
+        },
+    },
+    SinkPlugin {
+        name: "grpc",
+        arg: "ADDR",
+        open: |arg, _| Ok(Box::new(GrpcServer::start(arg)?)),
+    },
+    SinkPlugin {
+        name: "udp",
+        arg: "ADDR",
+        open: |arg, _| Ok(Box::new(UdpRebroadcast::connect(arg)?)),
+    },
+    SinkPlugin {
+        name: "tcp",
+        arg: "ADDR",
+        open: |arg, _| Ok(Box::new(TcpRebroadcast::listen(arg)?)),
+    },
+];
+
+/// `NAME:ARG` or just `NAME`.
+fn split(spec: &str) -> (&str, &str) {
+    spec.split_once(':').unwrap_or((spec, ""))
+}
+
+fn names(names: impl Iterator<Item = &'static str>) -> String {
+    names.collect::<Vec<_>>().join(", ")
+}
+
+pub fn open_source(spec: &str, ctx: &Context) -> Result<Box<dyn Source>, String> {
+    let (name, arg) = split(spec);
+    let plugin = SOURCES.iter().find(|p| p.name == name).ok_or_else(|| {
+        format!(
+            "Unknown source {}, there are {}",
+            name,
+            names(SOURCES.iter().map(|p| p.name))
+        )
+    })?;
+    info!("Opening {} source {}", plugin.name, arg);
+    (plugin.open)(arg, ctx)
+}
+
+pub fn open_sink(spec: &str, ctx: &Context) -> Result<Box<dyn Sink>, String> {
+    let (name, arg) = split(spec);
+    let plugin = SINKS.iter().find(|p| p.name == name).ok_or_else(|| {
+        format!(
+            "Unknown sink {}, there are {}",
+            name,
+            names(SINKS.iter().map(|p| p.name))
+        )
+    })?;
+    info!("Opening {} sink {}", plugin.name, arg);
+    (plugin.open)(arg, ctx)
+}
+
+/// One line per source and sink with the argument it takes.
+pub fn usage() -> String {
+    let line = |kind: &str, name: &str, arg: &str| match arg {
+        "" => format!("  {} {}\n", kind, name),
+        arg => format!("  {} {}:{}\n", kind, name, arg),
+    };
+    let mut text = String::from("Sources and sinks:\n");
+    for p in SOURCES {
+        text.push_str(&line("--source", p.name, p.arg));
+    }
+    for p in SINKS {
+        text.push_str(&line("--sink", p.name, p.arg));
+    }
+    text
+}
+
+impl Sink for DataLog {
+    fn write(&mut self, update: &Update, _radar: &RadarState, _now: Instant) {
+        DataLog::write(self, &update.samples);
+    }
+}
+
+impl Sink for RerunSink {
+    fn write(&mut self, update: &Update, radar: &RadarState, _now: Instant) {
+        self.log(update, radar);
+    }
+}
+
+impl Sink for ScanPublisher {
+    fn write(&mut self, update: &Update, radar: &RadarState, now: Instant) {
+        self.record(update, radar, now);
+    }
+}
@@ -0,0 +1,112 @@
+use raylib::ffi;
+use raylib::prelude::*;
+
+/// Size of the dot texture, pixels. Points are drawn smaller, filtered down.
+const DOT_SIZE: i32 = 64;
+/// Where in the dot texture it's solid white, for quads without a dot.
+const SOLID_UV: f32 = 0.5;
+
+/// One quad of the batch, corners in the order raylib draws textures:
+/// top left, bottom left, bottom right, top right.
+struct Quad {
+    corners: [Vector2; 4],
+    /// Textured with the dot, or solid
+    dot: bool,
+    color: Color,
+}
+
+/// Batched renderer for the detections of dense sensors: every point and
+/// shadow line is a quad of one dot texture, all sent to the GPU in a
+/// single batch when flushed, instead of a 36 triangle circle and a draw
+/// call each. A 360° lidar scan of thousands of points stays at 60 FPS.
+/// The quads buffer is kept between frames, so a flush allocates nothing.
+pub struct PointBatch {
+    dot: Texture2D,
+    quads: Vec<Quad>,
+}
+
+impl PointBatch {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+    ) -> Result<Self, raylib::core::error::Error> {
+        let mut image = Image::gen_image_color(DOT_SIZE, DOT_SIZE, Color::BLANK);
+        let center = DOT_SIZE / 2;
+        image.draw_circle(center, center, center - 1, Color::WHITE);
+        let dot = rl.load_texture_from_image(thread, &image)?;
+        // Smooth edges once it's scaled down to a few pixels
+        dot.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+        Ok(Self {
+            dot,
+            quads: Vec::new(),
+        })
+    }
+
+    /// A round point of `radius` pixels.
+    pub fn dot(&mut self, pos: Vector2, radius: f32, color: Color) {
+        let (x0, y0, x1, y1) = (
+            pos.x - radius,
+            pos.y - radius,
+            pos.x + radius,
+            pos.y + radius,
+        );
+        self.quads.push(Quad {
+            corners: [
+                Vector2::new(x0, y0),
+                Vector2::new(x0, y1),
+                Vector2::new(x1, y1),
+                Vector2::new(x1, y0),
+            ],
+            dot: true,
+            color,
+        });
+    }
+
+    /// A line like `draw_line_ex`.
+    pub fn line(&mut self, from: Vector2, to: Vector2, thick: f32, color: Color) {
+        let along = to - from;
+        let length = along.length();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let side = Vector2::new(-along.y, along.x) * (thick / 2.0 / length);
+        self.quads.push(Quad {
+            corners: [from - side, from + side, to + side, to - side],
+            dot: false,
+            color,
+        });
+    }
+
+    /// Draws everything queued since the last flush. `_d` is the draw handle
+    /// of the target they go to, anything drawn with it afterwards goes on
+    /// top of them.
+    pub fn flush<D: RaylibDraw>(&mut self, _d: &mut D) {
+        if self.quads.is_empty() {
+            return;
+        }
+        let uv = [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)];
+        // SAFETY: only called while drawing, the draw handle proves it. rlgl
+        // starts a new batch by itself when this one fills up, at a quad
+        // boundary
+        unsafe {
+            ffi::rlSetTexture(self.dot.id);
+            ffi::rlBegin(ffi::RL_QUADS as i32);
+            for quad in &self.quads {
+                let c = quad.color;
+                ffi::rlColor4ub(c.r, c.g, c.b, c.a);
+                for (corner, (u, v)) in quad.corners.iter().zip(uv) {
+                    let (u, v) = if quad.dot {
+                        (u, v)
+                    } else {
+                        (SOLID_UV, SOLID_UV)
+                    };
+                    ffi::rlTexCoord2f(u, v);
+                    ffi::rlVertex2f(corner.x, corner.y);
+                }
+            }
+            ffi::rlEnd();
+            ffi::rlSetTexture(0);
+        }
+        self.quads.clear();
+    }
+}
@@ -0,0 +1,579 @@
+use serde::Deserialize;
+
+use crate::nmea::NmeaProtocol;
+use crate::rplidar::RplidarProtocol;
+use crate::source::Sample;
+use crate::tfmini::TfminiProtocol;
+
+/// Longest text line we buffer before giving up on finding its newline.
+const MAX_LINE_LEN: usize = 1024;
+/// Text lines kept for the console until someone asks for them.
+pub const MAX_MESSAGES: usize = 64;
+/// Lines kept for the raw inspector between two frames.
+const MAX_TAPPED: usize = 1024;
+
+/// Which wire format the sensor speaks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtocolKind {
+    /// `angle,distance\n`, `angle;d1,d2,d3\n` for several echoes or
+    /// `pan,tilt,distance\n` from a pan/tilt rig
+    #[default]
+    Csv,
+    /// `{"angle": 90, "distance": 12.5}\n` or `{"angle": 90, "distances": [12.5, 30]}\n`
+    Json,
+    /// `A5 5A | angle u16 LE (0.01 deg) | distance u16 LE (0.1 cm) | XOR checksum`
+    Binary,
+    /// Slamtec RPLidar A1/A2 scan packets, the sensor is started on connect
+    Rplidar,
+    /// Benewake TFmini / TF-Luna 9 byte frames, a single fixed beam
+    Tfmini,
+    /// `$RDSWP,angle,distance*hh` and the other NMEA style sentences
+    Nmea,
+}
+
+impl ProtocolKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Some(ProtocolKind::Csv),
+            "json" => Some(ProtocolKind::Json),
+            "binary" => Some(ProtocolKind::Binary),
+            "rplidar" => Some(ProtocolKind::Rplidar),
+            "tfmini" => Some(ProtocolKind::Tfmini),
+            "nmea" => Some(ProtocolKind::Nmea),
+            _ => None,
+        }
+    }
+
+    pub fn create(self) -> Box<dyn Protocol> {
+        match self {
+            ProtocolKind::Csv => Box::new(LineProtocol::new("csv", parse_csv)),
+            ProtocolKind::Json => Box::new(LineProtocol::new("json", parse_json)),
+            ProtocolKind::Binary => Box::new(BinaryProtocol::default()),
+            ProtocolKind::Rplidar => Box::new(RplidarProtocol::default()),
+            ProtocolKind::Tfmini => Box::new(TfminiProtocol::default()),
+            ProtocolKind::Nmea => Box::new(NmeaProtocol::default()),
+        }
+    }
+}
+
+/// Frame counters kept by every protocol, shown in the diagnostics overlay.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProtocolStats {
+    pub frames: u64,
+    pub malformed: u64,
+    pub checksum_errors: u64,
+}
+
+/// Traffic of a source as it came over the wire, for the raw inspector.
+pub enum Raw {
+    /// A text line and whether it decoded
+    Line { text: String, valid: bool },
+    /// Bytes as they were read, from a binary protocol
+    Bytes(Vec<u8>),
+}
+
+/// The lines a text protocol decoded, kept while the inspector is open.
+#[derive(Default)]
+pub struct Tap {
+    pub on: bool,
+    lines: Vec<Raw>,
+}
+
+impl Tap {
+    pub fn set(&mut self, on: bool) {
+        self.on = on;
+        if !on {
+            self.lines.clear();
+        }
+    }
+
+    pub fn record(&mut self, text: &str, valid: bool) {
+        if self.on && self.lines.len() < MAX_TAPPED {
+            self.lines.push(Raw::Line {
+                text: text.to_string(),
+                valid,
+            });
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<Raw> {
+        std::mem::take(&mut self.lines)
+    }
+}
+
+/// Turns the raw byte stream of a source into samples. Bytes may arrive in
+/// arbitrary chunks, so implementations buffer partial frames between calls.
+pub trait Protocol: Send {
+    fn name(&self) -> &'static str;
+    /// Bytes to send once the port is open, for sensors that have to be told
+    /// to start measuring.
+    fn start(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<Sample>);
+    /// Called at a message boundary (one MQTT publish), for transports that
+    /// frame messages themselves and don't send line endings.
+    fn flush(&mut self, _out: &mut Vec<Sample>) {}
+    /// Forgets a partial frame, when the stream starts over on a new
+    /// connection and the rest of it is never coming.
+    fn reset(&mut self) {}
+    fn stats(&self) -> ProtocolStats;
+    /// Text lines that weren't samples, e.g. responses to commands.
+    fn take_messages(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Whether the stream is text lines. Binary ones are shown as hex.
+    fn is_text(&self) -> bool {
+        false
+    }
+    /// Starts or stops keeping decoded lines for `take_raw`.
+    fn tap(&mut self, _on: bool) {}
+    fn take_raw(&mut self) -> Vec<Raw> {
+        Vec::new()
+    }
+}
+
+/// Strength or confidence on the wire, 0 to 255, as the 0 to 1 of a sample.
+fn wire_strength(value: f32) -> f32 {
+    (value / 255.0).clamp(0.0, 1.0)
+}
+
+/// `distance` or `distance:strength`.
+fn csv_echo(field: &str) -> Option<(f32, Option<f32>)> {
+    match field.split_once(':') {
+        Some((distance, strength)) => Some((
+            distance.trim().parse().ok()?,
+            Some(wire_strength(strength.trim().parse().ok()?)),
+        )),
+        None => Some((field.trim().parse().ok()?, None)),
+    }
+}
+
+/// Parses the classic `angle,distance` line format, `angle;d1,d2,d3` when
+/// the firmware reports every echo it heard at that angle, or
+/// `pan,tilt,distance` from a rig with two servos. Any distance can be
+/// followed by `:strength`, 0 to 255, and the line by `@millis`, the
+/// firmware's clock when it took the reading. A rebroadcast of several
+/// sensors ends its lines with `#layer`.
+pub fn parse_csv(line: &str) -> Option<Vec<Sample>> {
+    let Some((line, layer)) = line.rsplit_once('#') else {
+        return timed_csv(line);
+    };
+    let layer = layer.trim().parse().ok()?;
+    let mut samples = timed_csv(line)?;
+    for sample in &mut samples {
+        sample.layer = layer;
+    }
+    Some(samples)
+}
+
+fn timed_csv(line: &str) -> Option<Vec<Sample>> {
+    let Some((line, ms)) = line.rsplit_once('@') else {
+        return csv_samples(line);
+    };
+    let sensor_ms = ms.trim().parse().ok()?;
+    let mut samples = csv_samples(line)?;
+    for sample in &mut samples {
+        sample.sensor_ms = Some(sensor_ms);
+    }
+    Some(samples)
+}
+
+fn csv_samples(line: &str) -> Option<Vec<Sample>> {
+    let (a, distances) = match line.split_once(';') {
+        Some(multi) => multi,
+        None => {
+            let single = line.split_once(',')?;
+            if let Some((tilt, distance)) = single.1.split_once(',') {
+                let (distance, strength) = csv_echo(distance)?;
+                let sample = Sample {
+                    tilt: Some(tilt.trim().parse().ok()?),
+                    strength,
+                    ..Sample::new(single.0.trim().parse().ok()?, distance)
+                };
+                return Some(vec![sample]);
+            }
+            single
+        }
+    };
+    let angle: f32 = a.trim().parse().ok()?;
+    let (distances, strengths): (Vec<f32>, Vec<Option<f32>>) = distances
+        .split(',')
+        .map(csv_echo)
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let mut samples = echoes(angle, &distances);
+    for (sample, strength) in samples.iter_mut().zip(strengths) {
+        sample.strength = strength;
+    }
+    Some(samples)
+}
+
+/// The other way round: one csv line per reading, the echoes of a reading
+/// together as `angle;d1,d2`, so whatever `parse_csv` reads can be sent on.
+/// Pan/tilt readings get a line per echo, the format has no room for more.
+pub fn format_csv(samples: &[Sample]) -> String {
+    let echo = |s: &Sample| match s.strength {
+        Some(strength) => format!("{:.1}:{:.0}", s.distance, strength * 255.0),
+        None => format!("{:.1}", s.distance),
+    };
+    let mut text = String::new();
+    let mut rest = samples;
+    while let Some((first, _)) = rest.split_first() {
+        // Later echoes of the same reading follow the first one
+        let len = 1 + rest[1..]
+            .iter()
+            .take_while(|s| s.echo > 0 && s.layer == first.layer)
+            .count();
+        let (reading, next) = rest.split_at(len);
+        rest = next;
+        let lines = match first.tilt {
+            Some(tilt) => reading
+                .iter()
+                .map(|s| format!("{:.1},{:.1},{}", s.angle, tilt, echo(s)))
+                .collect(),
+            None if len == 1 => vec![format!("{:.1},{}", first.angle, echo(first))],
+            None => {
+                let echoes: Vec<String> = reading.iter().map(echo).collect();
+                vec![format!("{:.1};{}", first.angle, echoes.join(","))]
+            }
+        };
+        for line in lines {
+            text.push_str(&line);
+            if let Some(ms) = first.sensor_ms {
+                text.push_str(&format!("@{}", ms));
+            }
+            if first.layer > 0 {
+                text.push_str(&format!("#{}", first.layer));
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+#[derive(Deserialize)]
+struct JsonSample {
+    angle: f32,
+    distance: Option<f32>,
+    #[serde(default)]
+    distances: Vec<f32>,
+    tilt: Option<f32>,
+    /// 0 to 255, for every echo of the line
+    #[serde(alias = "confidence")]
+    strength: Option<f32>,
+    /// The firmware's `millis()`
+    #[serde(alias = "t")]
+    time_ms: Option<u32>,
+}
+
+pub fn parse_json(line: &str) -> Option<Vec<Sample>> {
+    let s: JsonSample = serde_json::from_str(line).ok()?;
+    let mut samples = match s.distance {
+        Some(d) => echoes(s.angle, &[d]),
+        None if !s.distances.is_empty() => echoes(s.angle, &s.distances),
+        None => return None,
+    };
+    for sample in &mut samples {
+        sample.tilt = s.tilt;
+        sample.strength = s.strength.map(wire_strength);
+        sample.sensor_ms = s.time_ms;
+    }
+    Some(samples)
+}
+
+/// One sample per distance, numbered in the order they were reported.
+fn echoes(angle: f32, distances: &[f32]) -> Vec<Sample> {
+    distances
+        .iter()
+        .enumerate()
+        .map(|(i, &distance)| Sample {
+            echo: i.min(u8::MAX as usize) as u8,
+            ..Sample::new(angle, distance)
+        })
+        .collect()
+}
+
+// ===================== TEXT LINES =====================
+/// Newline delimited text protocol, the parser decides what a line means.
+pub struct LineProtocol {
+    name: &'static str,
+    parse: fn(&str) -> Option<Vec<Sample>>,
+    buf: Vec<u8>,
+    stats: ProtocolStats,
+    messages: Vec<String>,
+    tap: Tap,
+    /// Skipping the rest of an overlong line, up to its newline
+    discarding: bool,
+}
+
+impl LineProtocol {
+    pub fn new(name: &'static str, parse: fn(&str) -> Option<Vec<Sample>>) -> Self {
+        Self {
+            name,
+            parse,
+            buf: Vec::new(),
+            stats: ProtocolStats::default(),
+            messages: Vec::new(),
+            tap: Tap::default(),
+            discarding: false,
+        }
+    }
+
+    fn decode_line(&mut self, out: &mut Vec<Sample>) {
+        let line = String::from_utf8_lossy(&self.buf);
+        let line = line.trim();
+        // Blank lines (e.g. from \r\n endings) are not errors
+        if !line.is_empty() {
+            let parsed = (self.parse)(line);
+            self.tap.record(line, parsed.is_some());
+            match parsed {
+                Some(samples) => {
+                    self.stats.frames += 1;
+                    out.extend(samples);
+                }
+                None => {
+                    self.stats.malformed += 1;
+                    if self.messages.len() == MAX_MESSAGES {
+                        self.messages.remove(0);
+                    }
+                    self.messages.push(line.to_string());
+                }
+            }
+        }
+        self.buf.clear();
+    }
+}
+
+impl Protocol for LineProtocol {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<Sample>) {
+        for &b in bytes {
+            if b == b'\n' {
+                if !std::mem::take(&mut self.discarding) {
+                    self.decode_line(out);
+                }
+            } else if self.discarding {
+                continue;
+            } else if self.buf.len() >= MAX_LINE_LEN {
+                // Runaway line, probably binary garbage or a wrong baud rate
+                self.stats.malformed += 1;
+                self.buf.clear();
+                self.discarding = true;
+            } else {
+                self.buf.push(b);
+            }
+        }
+    }
+
+    fn flush(&mut self, out: &mut Vec<Sample>) {
+        if !std::mem::take(&mut self.discarding) && !self.buf.is_empty() {
+            self.decode_line(out);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.discarding = false;
+    }
+
+    fn stats(&self) -> ProtocolStats {
+        self.stats
+    }
+
+    fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+
+    fn tap(&mut self, on: bool) {
+        self.tap.set(on);
+    }
+
+    fn take_raw(&mut self) -> Vec<Raw> {
+        self.tap.take()
+    }
+}
+
+// ===================== BINARY FRAMES =====================
+const FRAME_HEADER: [u8; 2] = [0xA5, 0x5A];
+const FRAME_LEN: usize = 7;
+
+/// Fixed size binary frames:
+///
+/// ```text
+/// A5 5A | angle: u16 LE, 0.01 deg | distance: u16 LE, 0.1 cm | checksum: XOR of the 4 payload bytes
+/// ```
+#[derive(Default)]
+pub struct BinaryProtocol {
+    buf: Vec<u8>,
+    stats: ProtocolStats,
+    /// Set while skipping bytes to find the next header, so a run of garbage
+    /// counts as one malformed frame instead of one per byte.
+    resyncing: bool,
+}
+
+impl Protocol for BinaryProtocol {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<Sample>) {
+        self.buf.extend_from_slice(bytes);
+
+        let mut start = 0;
+        while self.buf.len() - start >= FRAME_LEN {
+            let frame = &self.buf[start..start + FRAME_LEN];
+            if frame[..2] != FRAME_HEADER {
+                // Out of sync, slide forward one byte at a time until we find a header
+                if !self.resyncing {
+                    self.stats.malformed += 1;
+                    self.resyncing = true;
+                }
+                start += 1;
+                continue;
+            }
+
+            let payload = &frame[2..6];
+            let checksum = payload.iter().fold(0u8, |acc, b| acc ^ b);
+            if checksum != frame[6] {
+                self.stats.checksum_errors += 1;
+                // The header may have been a coincidence, resync from the next byte
+                self.resyncing = true;
+                start += 1;
+                continue;
+            }
+
+            let angle = u16::from_le_bytes([payload[0], payload[1]]);
+            let distance = u16::from_le_bytes([payload[2], payload[3]]);
+            out.push(Sample::new(angle as f32 / 100.0, distance as f32 / 10.0));
+            self.stats.frames += 1;
+            self.resyncing = false;
+            start += FRAME_LEN;
+        }
+        self.buf.drain(..start);
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.resyncing = false;
+    }
+
+    fn stats(&self) -> ProtocolStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_round_trips_echoes_and_layers() {
+        let mut samples = parse_csv("90;25.3:200,60@1234").unwrap();
+        for sample in &mut samples {
+            sample.layer = 2;
+        }
+        let text = format_csv(&samples);
+        assert_eq!(text, "90.0;25.3:200,60.0@1234#2\n");
+        let back = parse_csv(text.trim_end()).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[1].echo, 1);
+        assert_eq!(back[1].layer, 2);
+        assert_eq!(back[0].sensor_ms, Some(1234));
+    }
+
+    #[test]
+    fn csv_keeps_every_echo_of_a_tilt_reading() {
+        let mut samples = echoes(10.0, &[40.0, 80.0]);
+        for sample in &mut samples {
+            sample.tilt = Some(5.0);
+        }
+        assert_eq!(format_csv(&samples), "10.0,5.0,40.0\n10.0,5.0,80.0\n");
+    }
+
+    #[test]
+    fn reset_drops_a_partial_line() {
+        let mut protocol = ProtocolKind::Csv.create();
+        let mut out = Vec::new();
+        protocol.decode(b"90,2", &mut out);
+        protocol.reset();
+        protocol.decode(b"45,100\n", &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].angle, out[0].distance), (45.0, 100.0));
+        assert_eq!(protocol.stats().malformed, 0);
+    }
+
+    #[test]
+    fn overlong_line_is_dropped_up_to_its_newline() {
+        let mut protocol = ProtocolKind::Csv.create();
+        let mut out = Vec::new();
+        let mut line = vec![b'9'; MAX_LINE_LEN + 10];
+        line.extend_from_slice(b",45,100\n");
+        protocol.decode(&line, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(protocol.stats().malformed, 1);
+    }
+
+    #[test]
+    fn csv_leaves_a_single_sensor_without_a_layer() {
+        let samples = parse_csv("45,100").unwrap();
+        assert_eq!(format_csv(&samples), "45.0,100.0\n");
+        assert!(parse_csv("45,100#x").is_none());
+    }
+
+    /// A frame for `angle` in 0.01° and `distance` in 0.1 cm.
+    fn frame(angle: u16, distance: u16) -> Vec<u8> {
+        let mut frame = FRAME_HEADER.to_vec();
+        frame.extend(angle.to_le_bytes());
+        frame.extend(distance.to_le_bytes());
+        frame.push(frame[2..].iter().fold(0, |acc, b| acc ^ b));
+        frame
+    }
+
+    #[test]
+    fn binary_decodes_a_frame_split_over_reads() {
+        let mut protocol = BinaryProtocol::default();
+        let mut out = Vec::new();
+        let bytes = frame(9050, 1234);
+        protocol.decode(&bytes[..3], &mut out);
+        assert!(out.is_empty());
+        protocol.decode(&bytes[3..], &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].angle, out[0].distance), (90.5, 123.4));
+        assert_eq!(protocol.stats().frames, 1);
+    }
+
+    #[test]
+    fn binary_counts_a_run_of_garbage_once() {
+        let mut protocol = BinaryProtocol::default();
+        let mut out = Vec::new();
+        let mut bytes = vec![0x00, 0x11, 0x22, 0x33];
+        bytes.extend(frame(4500, 500));
+        protocol.decode(&bytes, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(protocol.stats().malformed, 1);
+    }
+
+    #[test]
+    fn binary_resyncs_after_a_bad_checksum() {
+        let mut protocol = BinaryProtocol::default();
+        let mut out = Vec::new();
+        let mut bytes = frame(1000, 100);
+        bytes[6] ^= 0xFF;
+        bytes.extend(frame(2000, 200));
+        protocol.decode(&bytes, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].angle, 20.0);
+        let stats = protocol.stats();
+        assert_eq!((stats.frames, stats.checksum_errors), (1, 1));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::odometry::Pose;
+use crate::source::Sample;
+
+/// How many detections are kept around, oldest are dropped first. Enough for
+/// a few seconds of a lidar at 4000 samples/s.
+const HISTORY_CAPACITY: usize = 16384;
+
+/// One echo that made it into the history buffer.
+#[derive(Clone, Copy, Debug)]
+pub struct Detection {
+    pub angle: f32,
+    pub distance: f32,
+    /// Angle the sensor reported it at. The same as `angle` unless a pose
+    /// moved it into the world's frame
+    pub bearing: f32,
+    pub time: Instant,
+    /// Part of a reading with several echoes, drawn as a blip instead of a
+    /// shadow line to the edge.
+    pub multi_echo: bool,
+    /// Signal strength from 0 to 1, when the sensor reports it
+    pub strength: Option<f32>,
+    /// Sensor it came from, see `Sample::layer`
+    pub layer: u8,
+}
+
+impl Detection {
+    /// How bright the blip is drawn, weak returns are dimmer. Never fully
+    /// off, a weak return is still a return.
+    pub fn intensity(&self) -> f32 {
+        self.strength.map_or(1.0, |s| 0.3 + 0.7 * s)
+    }
+
+    /// How big the blip is drawn relative to one without a strength, strong
+    /// returns stand out.
+    pub fn size(&self) -> f32 {
+        self.strength.map_or(1.0, |s| 0.6 + 0.8 * s)
+    }
+
+    /// 1.0 when fresh, linearly down to 0.0 once `lifetime` has passed.
+    pub fn alpha(&self, now: Instant, lifetime: Duration) -> f32 {
+        let age = now.saturating_duration_since(self.time).as_secs_f32();
+        (1.0 - age / lifetime.as_secs_f32()).clamp(0.0, 1.0)
+    }
+}
+
+/// Everything the radar knows about the world: the latest reading under the
+/// sweep line plus a ring buffer of recent detections.
+#[derive(Clone, Default)]
+pub struct RadarState {
+    /// Angle of the latest reading
+    pub angle: f32,
+    /// Every echo reported at `angle`, in the order the firmware sent them
+    pub echoes: Vec<f32>,
+    pub data_received: bool,
+    /// The sweep is moving towards smaller angles, as seen in the data
+    pub decreasing: bool,
+    /// How often the sweep changed direction, servo rigs do it at every end
+    pub reversals: u64,
+    /// Finished sweeps: every reversal plus every full turn past 0°
+    pub sweeps: u64,
+    /// Readings in the last finished sweep, hundreds for a lidar
+    pub sweep_samples: usize,
+    /// Readings in the sweep in progress
+    sweep_count: usize,
+    /// Where the platform is when the sensor moves, detections are kept in
+    /// the world's frame then while the sweep state stays the sensor's
+    pub pose: Option<Pose>,
+    history: VecDeque<Detection>,
+    /// Detections the latest reading added to `history`
+    reading_len: usize,
+}
+
+impl RadarState {
+    /// Records a sample and returns the detection it produced, if any.
+    pub fn push(&mut self, sample: Sample, now: Instant) -> Option<Detection> {
+        // Only the first sensor moves the sweep line and the readout, the
+        // others just add their detections
+        if sample.layer != 0 {
+            return (sample.distance > 0.0).then(|| self.remember(sample, now, sample.echo > 0));
+        }
+
+        // Echo 0 starts a new reading
+        if sample.echo == 0 {
+            self.echoes.clear();
+            self.reading_len = 0;
+
+            let sweeps_before = self.sweeps;
+            // Repeated readings at the same angle say nothing about direction
+            let delta = angle_delta(self.angle, sample.angle);
+            if self.data_received && delta != 0.0 {
+                let decreasing = delta < 0.0;
+                if decreasing != self.decreasing {
+                    self.reversals += 1;
+                    self.sweeps += 1;
+                } else if (delta > 0.0) == (sample.angle < self.angle) {
+                    // Moved forwards but the raw angle jumped back, or the
+                    // other way round: wrapped past 0°
+                    self.sweeps += 1;
+                }
+                self.decreasing = decreasing;
+            }
+            if self.sweeps != sweeps_before {
+                self.sweep_samples = self.sweep_count;
+                self.sweep_count = 0;
+            }
+            self.sweep_count += 1;
+        }
+        self.angle = sample.angle;
+        self.echoes.push(sample.distance);
+        self.data_received = true;
+
+        // Nothing heard at this angle
+        if sample.distance <= 0.0 {
+            return None;
+        }
+
+        let multi_echo = sample.echo > 0;
+        if multi_echo {
+            // Earlier echoes of this reading are the newest entries
+            for d in self.history.iter_mut().rev().take(self.reading_len) {
+                d.multi_echo = true;
+            }
+        }
+
+        let detection = self.remember(sample, now, multi_echo);
+        self.reading_len = (self.reading_len + 1).min(self.history.len());
+        Some(detection)
+    }
+
+    /// A sample moved into the world's frame if the platform moves.
+    fn place(&self, sample: Sample) -> Sample {
+        let Some(pose) = self.pose else {
+            return sample;
+        };
+        if sample.distance <= 0.0 {
+            return sample;
+        }
+        let (angle, distance) = pose.place(sample.angle, sample.distance);
+        Sample {
+            angle,
+            distance,
+            ..sample
+        }
+    }
+
+    fn remember(&mut self, sample: Sample, now: Instant, multi_echo: bool) -> Detection {
+        let bearing = sample.angle;
+        let sample = self.place(sample);
+        if self.history.len() == HISTORY_CAPACITY {
+            self.history.pop_front();
+        }
+        let detection = Detection {
+            angle: sample.angle,
+            distance: sample.distance,
+            bearing,
+            time: now,
+            multi_echo,
+            strength: sample.strength,
+            layer: sample.layer,
+        };
+        self.history.push_back(detection);
+        detection
+    }
+
+    /// First echo of the latest reading, 0 when nothing has arrived yet.
+    pub fn distance(&self) -> f32 {
+        self.echoes.first().copied().unwrap_or(0.0)
+    }
+
+    /// Detections younger than `max_age`, oldest first.
+    pub fn recent(&self, now: Instant, max_age: Duration) -> impl Iterator<Item = &Detection> {
+        self.history
+            .iter()
+            .filter(move |d| now.saturating_duration_since(d.time) < max_age)
+    }
+
+    /// Drops everything older than `max_age`.
+    pub fn prune(&mut self, now: Instant, max_age: Duration) {
+        while let Some(d) = self.history.front() {
+            if now.saturating_duration_since(d.time) < max_age {
+                break;
+            }
+            self.history.pop_front();
+        }
+    }
+}
+
+/// How many degrees the sector from `min` counter-clockwise to `max` spans.
+/// A `min` above `max` wraps through 0°, like 350 to 10.
+pub fn sector_width(min: f32, max: f32) -> f32 {
+    if max >= min {
+        max - min
+    } else {
+        (max - min).rem_euclid(360.0)
+    }
+}
+
+/// Whether `angle`, in any turn, is in the sector from `min` to `max`, see
+/// [`sector_width`].
+pub fn in_sector(angle: f32, min: f32, max: f32) -> bool {
+    let width = sector_width(min, max);
+    width >= 360.0 || (angle - min).rem_euclid(360.0) <= width
+}
+
+/// Shortest signed difference `to - from` in degrees, in (-180, 180], so a
+/// rotating sensor going 359 -> 0 still counts as moving forwards.
+pub fn angle_delta(from: f32, to: f32) -> f32 {
+    let d = (to - from).rem_euclid(360.0);
+    if d > 180.0 { d - 360.0 } else { d }
+}
@@ -0,0 +1,93 @@
+use serde::Deserialize;
+
+/// Limits for manual zooming, in cm.
+const MIN_RANGE_CM: f32 = 5.0;
+const MAX_RANGE_CM: f32 = 10_000.0;
+/// One +/- key press changes the range by this factor.
+const ZOOM_STEP: f32 = 1.25;
+/// Auto-scale leaves this much room beyond the furthest reading.
+const AUTO_HEADROOM: f32 = 1.1;
+
+/// `[range]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RangeConfig {
+    /// Distance at the outer edge of the plot, in cm
+    pub max_cm: f32,
+    /// Follow the furthest recent reading instead of a fixed range
+    pub auto: bool,
+}
+
+impl Default for RangeConfig {
+    fn default() -> Self {
+        Self {
+            max_cm: 40.0,
+            auto: false,
+        }
+    }
+}
+
+/// The distance shown at the edge of the plot, changed at runtime.
+pub struct RangeScale {
+    max_cm: f32,
+    pub auto: bool,
+}
+
+impl RangeScale {
+    pub fn new(config: &RangeConfig) -> Self {
+        Self {
+            max_cm: config.max_cm.clamp(MIN_RANGE_CM, MAX_RANGE_CM),
+            auto: config.auto,
+        }
+    }
+
+    pub fn max_cm(&self) -> f32 {
+        self.max_cm
+    }
+
+    /// Zooms by `steps` key presses, positive zooms in. Zooming by hand turns
+    /// auto-scale off, otherwise it would undo the change on the next frame.
+    pub fn zoom(&mut self, steps: f32) {
+        if steps == 0.0 {
+            return;
+        }
+        self.auto = false;
+        self.max_cm = (self.max_cm / ZOOM_STEP.powf(steps)).clamp(MIN_RANGE_CM, MAX_RANGE_CM);
+    }
+
+    /// Sets the range directly, which turns auto-scale off like zooming does.
+    pub fn set_max_cm(&mut self, max_cm: f32) {
+        self.auto = false;
+        self.max_cm = max_cm.clamp(MIN_RANGE_CM, MAX_RANGE_CM);
+    }
+
+    /// Fits the range to the furthest recent reading when auto-scale is on.
+    /// It grows right away but only shrinks once everything fits in half the
+    /// range, so the rings don't jump around with every sweep.
+    pub fn fit(&mut self, furthest_cm: Option<f32>) {
+        let Some(furthest) = furthest_cm else {
+            return;
+        };
+        if !self.auto {
+            return;
+        }
+        let wanted = nice_ceil(furthest * AUTO_HEADROOM).clamp(MIN_RANGE_CM, MAX_RANGE_CM);
+        if wanted > self.max_cm || wanted <= self.max_cm / 2.0 {
+            self.max_cm = wanted;
+        }
+    }
+}
+
+/// Smallest 1, 2, 5 x 10^n value that is at least `v`.
+fn nice_ceil(v: f32) -> f32 {
+    if v <= 0.0 {
+        return MIN_RANGE_CM;
+    }
+    let magnitude = 10f32.powf(v.log10().floor());
+    for step in [1.0, 2.0, 5.0, 10.0] {
+        if step * magnitude >= v {
+            return step * magnitude;
+        }
+    }
+    10.0 * magnitude
+}
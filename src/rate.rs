@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::radar::angle_delta;
+use crate::source::Sample;
+
+/// Degrees per second are counted over this long.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// Weight of the newest sweep in the smoothed period.
+const PERIOD_SMOOTHING: f32 = 0.3;
+
+/// `[sweep_rate]` section of the config file. With neither limit set the
+/// rate is only shown, never warned about.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SweepRateConfig {
+    /// Sweeps per minute below which the servo counts as stalling
+    pub min_per_minute: Option<f32>,
+    /// Sweeps per minute above which something is off as well
+    pub max_per_minute: Option<f32>,
+}
+
+/// Works out how fast the servo sweeps from the angles coming in, in sweeps
+/// per minute and degrees per second.
+pub struct SweepRate {
+    config: SweepRateConfig,
+    last_angle: Option<f32>,
+    window_start: Instant,
+    window_degrees: f32,
+    /// Seconds per sweep, smoothed
+    period_s: Option<f32>,
+    sweeps: u64,
+    sweep_started: Option<Instant>,
+    pub degrees_per_s: f32,
+    outside: bool,
+}
+
+impl SweepRate {
+    pub fn new(config: &SweepRateConfig, now: Instant) -> Self {
+        Self {
+            config: config.clone(),
+            last_angle: None,
+            window_start: now,
+            window_degrees: 0.0,
+            period_s: None,
+            sweeps: 0,
+            sweep_started: None,
+            degrees_per_s: 0.0,
+            outside: false,
+        }
+    }
+
+    /// Call after every pass with the samples that went through and the
+    /// sweep counter.
+    pub fn record(&mut self, samples: &[Sample], sweeps: u64, now: Instant) {
+        // Same readings that move the sweep line
+        for sample in samples.iter().filter(|s| s.layer == 0 && s.echo == 0) {
+            if let Some(last) = self.last_angle {
+                self.window_degrees += angle_delta(last, sample.angle).abs();
+            }
+            self.last_angle = Some(sample.angle);
+        }
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            self.degrees_per_s = self.window_degrees / elapsed.as_secs_f32();
+            self.window_start = now;
+            self.window_degrees = 0.0;
+        }
+
+        if sweeps != self.sweeps {
+            self.sweeps = sweeps;
+            if let Some(started) = self.sweep_started {
+                let period = now.saturating_duration_since(started).as_secs_f32();
+                self.period_s = Some(match self.period_s {
+                    Some(p) => p + (period - p) * PERIOD_SMOOTHING,
+                    None => period,
+                });
+            }
+            self.sweep_started = Some(now);
+        }
+        self.check(now);
+    }
+
+    /// Sweeps per minute, `None` until two sweeps went by. Drops while the
+    /// current sweep takes longer than the ones before, so a servo that
+    /// stopped shows up without waiting for a sweep that never ends.
+    pub fn per_minute(&self, now: Instant) -> Option<f32> {
+        let period = self.period_s?;
+        let current = self
+            .sweep_started
+            .map_or(0.0, |s| now.saturating_duration_since(s).as_secs_f32());
+        Some(60.0 / period.max(current).max(f32::EPSILON))
+    }
+
+    fn check(&mut self, now: Instant) {
+        let outside = self.warning(now).is_some();
+        if outside != self.outside {
+            match self.per_minute(now) {
+                Some(rate) if outside => warn!("Sweep rate {:.1}/min out of range", rate),
+                _ => info!("Sweep rate back in range"),
+            }
+            self.outside = outside;
+        }
+    }
+
+    /// What to show on screen while the rate is outside the configured
+    /// window.
+    pub fn warning(&self, now: Instant) -> Option<String> {
+        let rate = self.per_minute(now)?;
+        if self.config.min_per_minute.is_some_and(|min| rate < min) {
+            Some(format!("SERVO: {:.1} sweeps/min, stalling?", rate))
+        } else if self.config.max_per_minute.is_some_and(|max| rate > max) {
+            Some(format!("SERVO: {:.1} sweeps/min, too fast", rate))
+        } else {
+            None
+        }
+    }
+}
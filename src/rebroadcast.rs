@@ -0,0 +1,171 @@
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use tracing::{info, warn};
+
+use crate::pipeline::Update;
+use crate::plugin::Sink;
+use crate::protocol;
+use crate::radar::RadarState;
+
+/// Stays under the usual 1500 byte MTU.
+const MAX_DATAGRAM: usize = 1400;
+/// A TCP client further behind than this is dropped instead of buffered.
+const MAX_PENDING: usize = 1 << 20;
+
+fn resolve(addr: &str) -> Result<SocketAddr, String> {
+    addr.to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("Invalid address: {}", addr))
+}
+
+/// Sends every accepted sample on as csv lines in UDP datagrams, to a
+/// single host, the broadcast address or a multicast group any number of
+/// listeners can join.
+pub struct UdpRebroadcast {
+    socket: UdpSocket,
+    addr: SocketAddr,
+}
+
+impl UdpRebroadcast {
+    pub fn connect(addr: &str) -> Result<Self, String> {
+        let addr = resolve(addr)?;
+        let bind = if addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket =
+            UdpSocket::bind(bind).map_err(|e| format!("Failed to open a UDP socket: {}", e))?;
+        if addr.ip().is_multicast() {
+            info!("Sending samples to multicast group {}", addr);
+        } else {
+            socket
+                .set_broadcast(true)
+                .map_err(|e| format!("Failed to set up {}: {}", addr, e))?;
+            info!("Sending samples to {}", addr);
+        }
+        Ok(Self { socket, addr })
+    }
+
+    fn send(&self, datagram: &str) {
+        // Nobody listening is fine for UDP, only log what's unexpected
+        if let Err(e) = self.socket.send_to(datagram.as_bytes(), self.addr)
+            && e.kind() != io::ErrorKind::ConnectionRefused
+        {
+            warn!("Failed to send to {}: {}", self.addr, e);
+        }
+    }
+}
+
+impl Sink for UdpRebroadcast {
+    fn write(&mut self, update: &Update, _radar: &RadarState, _now: Instant) {
+        if update.samples.is_empty() {
+            return;
+        }
+        let text = protocol::format_csv(&update.samples);
+        // Whole lines per datagram, a lost one costs only its own readings
+        let mut datagram = String::new();
+        for line in text.split_inclusive('\n') {
+            if datagram.len() + line.len() > MAX_DATAGRAM && !datagram.is_empty() {
+                self.send(&datagram);
+                datagram.clear();
+            }
+            datagram.push_str(line);
+        }
+        self.send(&datagram);
+    }
+}
+
+struct Client {
+    stream: TcpStream,
+    peer: SocketAddr,
+    /// Written to the client as soon as it takes more
+    pending: Vec<u8>,
+}
+
+/// Listens for TCP clients and sends every accepted sample to all of them
+/// as csv lines, e.g. to a logger and a remote viewer reading
+/// `nc HOST PORT | radar --stdin`.
+pub struct TcpRebroadcast {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl TcpRebroadcast {
+    pub fn listen(addr: &str) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        info!("Rebroadcasting samples on {}", addr);
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        warn!("Failed to set up client {}: {}", peer, e);
+                        continue;
+                    }
+                    info!("Rebroadcast client {} connected", peer);
+                    self.clients.push(Client {
+                        stream,
+                        peer,
+                        pending: Vec::new(),
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    warn!("Failed to accept a rebroadcast client: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Writes what the client takes without blocking, `false` once it's
+    /// gone or too far behind.
+    fn flush(&mut self) -> bool {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    info!("Rebroadcast client {} went away: {}", self.peer, e);
+                    return false;
+                }
+            }
+        }
+        if self.pending.len() > MAX_PENDING {
+            warn!("Rebroadcast client {} fell behind, dropping it", self.peer);
+            return false;
+        }
+        true
+    }
+}
+
+impl Sink for TcpRebroadcast {
+    fn write(&mut self, update: &Update, _radar: &RadarState, _now: Instant) {
+        self.accept();
+        let text = protocol::format_csv(&update.samples);
+        self.clients.retain_mut(|client| {
+            client.pending.extend_from_slice(text.as_bytes());
+            client.flush()
+        });
+    }
+}
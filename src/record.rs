@@ -0,0 +1,267 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::capture::Timestamp;
+
+/// Frames waiting for the encoder. When it falls behind, frames are dropped
+/// instead of piling up in memory or stalling the renderer.
+const QUEUE_LEN: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    /// Animated GIF
+    #[default]
+    Gif,
+    /// One PNG per frame in a new directory
+    Png,
+    /// MP4 through an `ffmpeg` process, which has to be on the PATH
+    Mp4,
+}
+
+/// `[record]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RecordConfig {
+    pub format: RecordFormat,
+    /// Frames captured per second, independent of the render frame rate
+    pub fps: f32,
+    /// Frames are scaled down to at most this width
+    pub max_width: u32,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            format: RecordFormat::Gif,
+            fps: 10.0,
+            max_width: 960,
+        }
+    }
+}
+
+struct Frame {
+    rgba: Vec<u8>,
+}
+
+struct Worker {
+    tx: SyncSender<Frame>,
+    handle: JoinHandle<Result<(), String>>,
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    started: Instant,
+    last_frame: Option<Instant>,
+    dropped: u64,
+}
+
+/// Captures the composited frame at a fixed rate and hands it to an encoder
+/// thread.
+pub struct Recorder {
+    config: RecordConfig,
+    worker: Option<Worker>,
+}
+
+impl Recorder {
+    pub fn new(config: RecordConfig) -> Self {
+        Self {
+            config,
+            worker: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Time since the recording started.
+    pub fn elapsed(&self) -> Duration {
+        self.worker
+            .as_ref()
+            .map(|w| w.started.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Starts a recording in `dir` for a screen of the given size.
+    pub fn start(&mut self, dir: &Path, screen_w: i32, screen_h: i32) -> Result<PathBuf, String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        // Even sizes keep ffmpeg's yuv420p happy
+        let scale = (self.config.max_width as f32 / screen_w as f32).min(1.0);
+        let width = ((screen_w as f32 * scale) as u32 & !1).max(2);
+        let height = ((screen_h as f32 * scale) as u32 & !1).max(2);
+
+        let stem = format!("radar_{}", Timestamp::now().file_stem());
+        let fps = self.config.fps.clamp(1.0, 60.0);
+        let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+        let (path, handle) = match self.config.format {
+            RecordFormat::Gif => {
+                let path = dir.join(format!("{}.gif", stem));
+                let file = File::create(&path)
+                    .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+                let handle = thread::spawn(move || encode_gif(file, rx, width, height, fps));
+                (path, handle)
+            }
+            RecordFormat::Png => {
+                let path = dir.join(stem);
+                fs::create_dir_all(&path)
+                    .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+                let frames_dir = path.clone();
+                let handle = thread::spawn(move || encode_pngs(&frames_dir, rx, width, height));
+                (path, handle)
+            }
+            RecordFormat::Mp4 => {
+                let path = dir.join(format!("{}.mp4", stem));
+                let child = spawn_ffmpeg(&path, width, height, fps)?;
+                let handle = thread::spawn(move || encode_ffmpeg(child, rx));
+                (path, handle)
+            }
+        };
+
+        self.worker = Some(Worker {
+            tx,
+            handle,
+            path: path.clone(),
+            width,
+            height,
+            started: Instant::now(),
+            last_frame: None,
+            dropped: 0,
+        });
+        Ok(path)
+    }
+
+    /// Stops the recording and waits for the encoder to finish the file.
+    pub fn stop(&mut self) -> Result<PathBuf, String> {
+        let Some(worker) = self.worker.take() else {
+            return Err("Not recording".to_string());
+        };
+        drop(worker.tx);
+        worker
+            .handle
+            .join()
+            .map_err(|_| "Encoder thread panicked".to_string())??;
+        if worker.dropped > 0 {
+            warn!(
+                "{} frames were dropped, the encoder couldn't keep up.",
+                worker.dropped
+            );
+        }
+        Ok(worker.path)
+    }
+
+    /// Whether a frame is due, at most `fps` frames per second are captured.
+    pub fn wants_frame(&self, now: Instant) -> bool {
+        let interval = Duration::from_secs_f32(1.0 / self.config.fps.clamp(1.0, 60.0));
+        self.worker.as_ref().is_some_and(|w| {
+            w.last_frame
+                .is_none_or(|t| now.saturating_duration_since(t) >= interval)
+        })
+    }
+
+    /// Queues what is on screen right now. Has to be called while drawing.
+    pub fn capture(&mut self, rl: &RaylibHandle, thread: &RaylibThread, now: Instant) {
+        let Some(worker) = &mut self.worker else {
+            return;
+        };
+        worker.last_frame = Some(now);
+
+        let mut image = rl.load_image_from_screen(thread);
+        if image.width() as u32 != worker.width || image.height() as u32 != worker.height {
+            image.resize(worker.width as i32, worker.height as i32);
+        }
+        let rgba = image
+            .get_image_data()
+            .iter()
+            .flat_map(|c| [c.r, c.g, c.b, 255])
+            .collect();
+        if worker.tx.try_send(Frame { rgba }).is_err() {
+            worker.dropped += 1;
+        }
+    }
+}
+
+fn encode_gif(
+    file: File,
+    rx: Receiver<Frame>,
+    width: u32,
+    height: u32,
+    fps: f32,
+) -> Result<(), String> {
+    let err = |e: gif::EncodingError| format!("GIF encoding failed: {}", e);
+    let mut encoder =
+        gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[]).map_err(err)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(err)?;
+    let delay = (100.0 / fps).round() as u16;
+    for mut frame in rx {
+        // Speed 10 of 30 is a decent palette without taking ages per frame
+        let mut gif_frame =
+            gif::Frame::from_rgba_speed(width as u16, height as u16, &mut frame.rgba, 10);
+        gif_frame.delay = delay;
+        encoder.write_frame(&gif_frame).map_err(err)?;
+    }
+    Ok(())
+}
+
+fn encode_pngs(dir: &Path, rx: Receiver<Frame>, width: u32, height: u32) -> Result<(), String> {
+    for (i, frame) in rx.into_iter().enumerate() {
+        let path = dir.join(format!("frame_{:05}.png", i));
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut w| w.write_image_data(&frame.rgba))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn spawn_ffmpeg(path: &Path, width: u32, height: u32, fps: f32) -> Result<Child, String> {
+    Command::new("ffmpeg")
+        .args([
+            "-loglevel",
+            "error",
+            "-y",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-r", &format!("{}", fps), "-i", "-"])
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))
+}
+
+fn encode_ffmpeg(mut child: Child, rx: Receiver<Frame>) -> Result<(), String> {
+    let mut stdin = child.stdin.take().ok_or("ffmpeg has no stdin")?;
+    for frame in rx {
+        stdin
+            .write_all(&frame.rgba)
+            .map_err(|e| format!("Failed to write to ffmpeg: {}", e))?;
+    }
+    // Closing stdin tells ffmpeg the stream is over
+    drop(stdin);
+    let status = child.wait().map_err(|e| format!("ffmpeg failed: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("ffmpeg exited with {}", status))
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Instant;
+
+use tracing::info;
+
+use crate::datalog;
+use crate::protocol::ProtocolStats;
+use crate::source::{LinkState, Sample, Source};
+
+/// Plays a `--log` CSV back at the pace it was recorded, once.
+pub struct ReplaySource {
+    path: String,
+    /// Seconds after the first row and the sample logged then
+    rows: Vec<(f64, Sample)>,
+    next: usize,
+    started: Instant,
+}
+
+impl ReplaySource {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let mut rows = datalog::read(path)?;
+        let start = rows.first().map_or(0.0, |(time, _)| *time);
+        for (time, _) in &mut rows {
+            *time -= start;
+        }
+        info!("Replaying {} samples from {}", rows.len(), path);
+        Ok(Self {
+            path: path.to_string(),
+            rows,
+            next: 0,
+            started: Instant::now(),
+        })
+    }
+}
+
+impl Source for ReplaySource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let due = self.rows[self.next..]
+            .iter()
+            .take_while(|(time, _)| *time <= elapsed)
+            .count();
+        let samples = self.rows[self.next..self.next + due]
+            .iter()
+            .map(|(_, sample)| *sample)
+            .collect();
+        self.next += due;
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        if self.next < self.rows.len() {
+            LinkState::Connected
+        } else {
+            LinkState::Disconnected
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("replay {}", self.path)
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        (
+            "replay",
+            ProtocolStats {
+                frames: self.next as u64,
+                ..ProtocolStats::default()
+            },
+        )
+    }
+}
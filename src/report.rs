@@ -0,0 +1,615 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::datalog;
+use crate::radar::RadarState;
+use crate::source::Sample;
+use crate::store;
+
+/// Width of the sectors the ranges are averaged over, degrees.
+const SECTOR_DEG: f32 = 30.0;
+/// Closest approaches listed, each at least `APPROACH_GAP_S` apart so one
+/// object passing by isn't listed over and over.
+const APPROACHES: usize = 5;
+const APPROACH_GAP_S: f64 = 1.0;
+/// Alarm events listed one by one, the rest are only counted.
+const MAX_ALARM_ROWS: usize = 50;
+/// Sweeps drawn, spread evenly over the session.
+const SWEEP_IMAGES: usize = 4;
+const IMAGE_SIZE: f32 = 360.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "html" => Some(ReportFormat::Html),
+            "md" | "markdown" => Some(ReportFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// `radar report`: a stored session or a `--log` CSV to summarize.
+#[derive(Debug)]
+pub struct Request {
+    pub db: String,
+    pub session: Option<i64>,
+    pub log: Option<String>,
+    pub format: ReportFormat,
+    /// `report.html` or `report.md` when not given
+    pub out: Option<String>,
+    /// A session ID or CSV log to compare the sectors with
+    pub against: Option<String>,
+}
+
+/// One stored alarm event.
+pub struct AlarmRow {
+    pub time: f64,
+    pub zone: String,
+    pub severity: String,
+    pub angle: f32,
+    pub distance: f32,
+}
+
+/// What a recorded run left behind, samples with their Unix time.
+pub struct Recording {
+    pub title: String,
+    pub samples: Vec<(f64, Sample)>,
+    pub alarms: Vec<AlarmRow>,
+}
+
+struct Sector {
+    from: f32,
+    readings: usize,
+    returns: usize,
+    sum_cm: f64,
+    min_cm: f32,
+}
+
+/// Everything the report says, worked out once for either format.
+struct Summary {
+    start: f64,
+    duration_s: f64,
+    readings: usize,
+    returns: usize,
+    sweeps: u64,
+    /// Start and end of the arc the readings covered, going
+    /// counter-clockwise. Start is past end when it covers 0°.
+    angles: (f32, f32),
+    max_cm: f32,
+    sectors: Vec<Sector>,
+    /// Time, angle and distance
+    approaches: Vec<(f64, f32, f32)>,
+    /// Samples between sweep boundaries, for the images
+    sweep_ranges: Vec<(usize, usize)>,
+}
+
+fn summarize(recording: &Recording) -> Summary {
+    let samples = &recording.samples;
+    let start = samples.first().map_or(0.0, |(t, _)| *t);
+    let end = samples.last().map_or(start, |(t, _)| *t);
+    let mut sectors: Vec<Sector> = (0..(360.0 / SECTOR_DEG) as usize)
+        .map(|i| Sector {
+            from: i as f32 * SECTOR_DEG,
+            readings: 0,
+            returns: 0,
+            sum_cm: 0.0,
+            min_cm: f32::INFINITY,
+        })
+        .collect();
+
+    // The same sweep counting as the live display
+    let mut radar = RadarState::default();
+    let now = Instant::now();
+    let mut boundaries = vec![0];
+    let mut returns = 0;
+    let mut max_cm: f32 = 0.0;
+    for (i, (_, sample)) in samples.iter().enumerate() {
+        let sweeps = radar.sweeps;
+        radar.push(*sample, now);
+        if radar.sweeps != sweeps {
+            boundaries.push(i);
+        }
+        let angle = sample.angle.rem_euclid(360.0);
+        let last = sectors.len() - 1;
+        let sector = &mut sectors[((angle / SECTOR_DEG) as usize).min(last)];
+        if sample.echo == 0 {
+            sector.readings += 1;
+        }
+        if sample.distance > 0.0 {
+            returns += 1;
+            sector.returns += 1;
+            sector.sum_cm += f64::from(sample.distance);
+            sector.min_cm = sector.min_cm.min(sample.distance);
+            max_cm = max_cm.max(sample.distance);
+        }
+    }
+    boundaries.push(samples.len());
+    let sweep_ranges: Vec<(usize, usize)> = boundaries
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .filter(|(a, b)| b > a)
+        .collect();
+
+    let mut by_distance: Vec<&(f64, Sample)> =
+        samples.iter().filter(|(_, s)| s.distance > 0.0).collect();
+    by_distance.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+    let mut approaches: Vec<(f64, f32, f32)> = Vec::new();
+    for (time, sample) in by_distance {
+        if approaches.len() == APPROACHES {
+            break;
+        }
+        if approaches
+            .iter()
+            .all(|(t, _, _)| (t - time).abs() >= APPROACH_GAP_S)
+        {
+            approaches.push((*time, sample.angle, sample.distance));
+        }
+    }
+
+    Summary {
+        start,
+        duration_s: end - start,
+        readings: samples.iter().filter(|(_, s)| s.echo == 0).count(),
+        returns,
+        sweeps: radar.sweeps,
+        angles: covered_arc(samples.iter().map(|(_, s)| s.angle)),
+        max_cm,
+        sectors: sectors.into_iter().filter(|s| s.readings > 0).collect(),
+        approaches,
+        sweep_ranges,
+    }
+}
+
+/// The shortest arc all `angles` are on: what's left after the widest gap
+/// between neighbouring ones, the gap across 0° included, so a sensor
+/// looking from 300° to 60° covers 120° and not the whole turn.
+fn covered_arc(angles: impl Iterator<Item = f32>) -> (f32, f32) {
+    let mut angles: Vec<f32> = angles.map(|a| a.rem_euclid(360.0)).collect();
+    angles.sort_by(f32::total_cmp);
+    let (Some(&first), Some(&last)) = (angles.first(), angles.last()) else {
+        return (0.0, 0.0);
+    };
+    let mut arc = (first, last);
+    let mut widest = first + 360.0 - last;
+    for pair in angles.windows(2) {
+        if pair[1] - pair[0] > widest {
+            widest = pair[1] - pair[0];
+            arc = (pair[1], pair[0]);
+        }
+    }
+    arc
+}
+
+/// A PPI of one sweep as SVG: rings at quarters of the session's farthest
+/// return and a dot per return. A half circle unless the sensor looked
+/// behind itself.
+fn render_sweep(samples: &[(f64, Sample)], summary: &Summary) -> String {
+    let (from, to) = summary.angles;
+    let full = from > to || to > 180.0;
+    let r = IMAGE_SIZE / 2.0 - 10.0;
+    let (cx, cy, height) = if full {
+        (IMAGE_SIZE / 2.0, IMAGE_SIZE / 2.0, IMAGE_SIZE)
+    } else {
+        (
+            IMAGE_SIZE / 2.0,
+            IMAGE_SIZE / 2.0 + 5.0,
+            IMAGE_SIZE / 2.0 + 10.0,
+        )
+    };
+    let scale = r / summary.max_cm.max(1.0);
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><rect width="100%" height="100%" fill="#041004"/>"##,
+        w = IMAGE_SIZE,
+        h = height
+    );
+    for quarter in 1..=4 {
+        let _ = write!(
+            svg,
+            r##"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="none" stroke="#1f6f1f"/>"##,
+            cx,
+            cy,
+            r * quarter as f32 / 4.0
+        );
+    }
+    for (_, sample) in samples.iter().filter(|(_, s)| s.distance > 0.0) {
+        let rad = sample.angle.to_radians();
+        let d = sample.distance * scale;
+        let _ = write!(
+            svg,
+            r##"<circle cx="{:.1}" cy="{:.1}" r="2.5" fill="#5cff5c"/>"##,
+            cx + d * rad.cos(),
+            cy - d * rad.sin()
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Up to `SWEEP_IMAGES` sweeps, first and last included, as caption and SVG.
+fn sweep_images(recording: &Recording, summary: &Summary) -> Vec<(String, String)> {
+    let ranges = &summary.sweep_ranges;
+    if ranges.is_empty() {
+        return Vec::new();
+    }
+    let count = SWEEP_IMAGES.min(ranges.len());
+    let mut picked: Vec<usize> = (0..count)
+        .map(|i| match count {
+            1 => 0,
+            _ => i * (ranges.len() - 1) / (count - 1),
+        })
+        .collect();
+    picked.dedup();
+    picked
+        .into_iter()
+        .map(|i| {
+            let (a, b) = ranges[i];
+            let samples = &recording.samples[a..b];
+            let at = samples[0].0 - summary.start;
+            let caption = format!("Sweep {} at +{:.1} s", i + 1, at);
+            (caption, render_sweep(samples, summary))
+        })
+        .collect()
+}
+
+/// A second run to hold the sectors against, with its title.
+struct Against {
+    title: String,
+    summary: Summary,
+}
+
+/// `value` of this run minus `other`, `-` unless both have one.
+fn delta(value: Option<f64>, other: Option<f64>, unit: &str) -> String {
+    match (value, other) {
+        (Some(a), Some(b)) => format!("{:+.1}{}", a - b, unit),
+        _ => "-".to_string(),
+    }
+}
+
+/// Per sector differences to the other run, for the sectors either one read
+/// in.
+fn comparison_rows(summary: &Summary, against: &Summary) -> Vec<Vec<String>> {
+    let find = |sectors: &[Sector], from: f32| -> Option<(f64, Option<f64>, Option<f64>)> {
+        let s = sectors.iter().find(|s| s.from == from)?;
+        let hit_rate = 100.0 * s.returns as f64 / s.readings.max(1) as f64;
+        let ranges = (s.returns > 0).then(|| (s.sum_cm / s.returns as f64, f64::from(s.min_cm)));
+        Some((hit_rate, ranges.map(|r| r.0), ranges.map(|r| r.1)))
+    };
+    let mut froms: Vec<f32> = summary
+        .sectors
+        .iter()
+        .chain(&against.sectors)
+        .map(|s| s.from)
+        .collect();
+    froms.sort_by(f32::total_cmp);
+    froms.dedup();
+    froms
+        .into_iter()
+        .map(|from| {
+            let (a, b) = (find(&summary.sectors, from), find(&against.sectors, from));
+            vec![
+                format!("{:.0}°–{:.0}°", from, from + SECTOR_DEG),
+                delta(a.map(|a| a.0), b.map(|b| b.0), "%"),
+                delta(a.and_then(|a| a.1), b.and_then(|b| b.1), " cm"),
+                delta(a.and_then(|a| a.2), b.and_then(|b| b.2), " cm"),
+            ]
+        })
+        .collect()
+}
+
+/// Header and rows of every table, shared by both formats.
+fn tables(
+    recording: &Recording,
+    summary: &Summary,
+    against: Option<&Against>,
+) -> Vec<(String, Vec<String>, Vec<Vec<String>>)> {
+    let mut tables = Vec::new();
+    let rows = vec![
+        vec![
+            "Duration".to_string(),
+            format!("{:.1} s", summary.duration_s),
+        ],
+        vec!["Readings".to_string(), summary.readings.to_string()],
+        vec!["Returns".to_string(), summary.returns.to_string()],
+        vec!["Sweeps".to_string(), summary.sweeps.to_string()],
+        vec![
+            "Coverage".to_string(),
+            format!(
+                "{:.0}° to {:.0}°, {} of {} sectors with returns",
+                summary.angles.0,
+                summary.angles.1,
+                summary.sectors.iter().filter(|s| s.returns > 0).count(),
+                summary.sectors.len()
+            ),
+        ],
+        vec![
+            "Farthest return".to_string(),
+            format!("{:.1} cm", summary.max_cm),
+        ],
+        vec![
+            "Alarm events".to_string(),
+            recording.alarms.len().to_string(),
+        ],
+    ];
+    tables.push((
+        "Summary".to_string(),
+        vec![String::new(), String::new()],
+        rows,
+    ));
+
+    let rows = summary
+        .sectors
+        .iter()
+        .map(|s| {
+            let hit_rate = 100.0 * s.returns as f32 / s.readings.max(1) as f32;
+            let (average, closest) = match s.returns {
+                0 => ("-".to_string(), "-".to_string()),
+                n => (
+                    format!("{:.1} cm", s.sum_cm / n as f64),
+                    format!("{:.1} cm", s.min_cm),
+                ),
+            };
+            vec![
+                format!("{:.0}°–{:.0}°", s.from, s.from + SECTOR_DEG),
+                s.readings.to_string(),
+                format!("{:.0}%", hit_rate),
+                average,
+                closest,
+            ]
+        })
+        .collect();
+    tables.push((
+        "Range per sector".to_string(),
+        ["Sector", "Readings", "Returns", "Average range", "Closest"]
+            .map(String::from)
+            .to_vec(),
+        rows,
+    ));
+    if let Some(against) = against {
+        tables.push((
+            format!("Change from {}", against.title),
+            ["Sector", "Returns", "Average range", "Closest"]
+                .map(String::from)
+                .to_vec(),
+            comparison_rows(summary, &against.summary),
+        ));
+    }
+
+    let rows = summary
+        .approaches
+        .iter()
+        .map(|(time, angle, distance)| {
+            vec![
+                format!("+{:.1} s", time - summary.start),
+                format!("{:.0}°", angle),
+                format!("{:.1} cm", distance),
+            ]
+        })
+        .collect();
+    tables.push((
+        "Closest approaches".to_string(),
+        ["Time", "Angle", "Distance"].map(String::from).to_vec(),
+        rows,
+    ));
+
+    let rows = recording
+        .alarms
+        .iter()
+        .take(MAX_ALARM_ROWS)
+        .map(|a| {
+            vec![
+                format!("+{:.1} s", a.time - summary.start),
+                a.zone.clone(),
+                a.severity.clone(),
+                format!("{:.0}°", a.angle),
+                format!("{:.1} cm", a.distance),
+            ]
+        })
+        .collect();
+    tables.push((
+        "Alarm events".to_string(),
+        ["Time", "Zone", "Severity", "Angle", "Distance"]
+            .map(String::from)
+            .to_vec(),
+        rows,
+    ));
+    tables
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn html(recording: &Recording, summary: &Summary, against: Option<&Against>) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title>\
+         <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse;margin-bottom:1.5em}}\
+         td,th{{border:1px solid #ccc;padding:4px 10px;text-align:left}}figure{{display:inline-block;margin:0 1em 1em 0}}</style>\
+         </head><body>\n<h1>{0}</h1>\n<p>Started at Unix time {1:.0}</p>\n",
+        escape(&recording.title),
+        summary.start
+    );
+    for (title, header, rows) in tables(recording, summary, against) {
+        let _ = writeln!(out, "<h2>{}</h2>", escape(&title));
+        if rows.is_empty() {
+            out.push_str("<p>None</p>\n");
+            continue;
+        }
+        out.push_str("<table>");
+        if header.iter().any(|h| !h.is_empty()) {
+            out.push_str("<tr>");
+            for h in &header {
+                let _ = write!(out, "<th>{}</th>", h);
+            }
+            out.push_str("</tr>");
+        }
+        for row in rows {
+            out.push_str("<tr>");
+            for cell in row {
+                let _ = write!(out, "<td>{}</td>", escape(&cell));
+            }
+            out.push_str("</tr>");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("<h2>Sweeps</h2>\n");
+    for (caption, svg) in sweep_images(recording, summary) {
+        let _ = writeln!(
+            out,
+            "<figure>{}<figcaption>{}</figcaption></figure>",
+            svg, caption
+        );
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+/// Markdown can't hold the images, they're written next to the report as
+/// `<name>-sweep-N.svg` and linked.
+fn markdown(
+    recording: &Recording,
+    summary: &Summary,
+    against: Option<&Against>,
+    out_path: &Path,
+) -> Result<String, String> {
+    let mut out = format!(
+        "# {}\n\nStarted at Unix time {:.0}\n\n",
+        recording.title, summary.start
+    );
+    for (title, header, rows) in tables(recording, summary, against) {
+        let _ = writeln!(out, "## {}\n", title);
+        if rows.is_empty() {
+            out.push_str("None\n\n");
+            continue;
+        }
+        let _ = writeln!(out, "| {} |", header.join(" | "));
+        let _ = writeln!(out, "|{}", "---|".repeat(header.len()));
+        for row in rows {
+            let cells: Vec<String> = row.iter().map(|c| c.replace('|', "\\|")).collect();
+            let _ = writeln!(out, "| {} |", cells.join(" | "));
+        }
+        out.push('\n');
+    }
+    out.push_str("## Sweeps\n\n");
+    let stem = out_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("report");
+    for (i, (caption, svg)) in sweep_images(recording, summary).into_iter().enumerate() {
+        let name = format!("{}-sweep-{}.svg", stem, i + 1);
+        let path = out_path.with_file_name(&name);
+        fs::write(&path, svg).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        let _ = writeln!(out, "![{}]({})\n", caption, name);
+    }
+    Ok(out)
+}
+
+fn load_log(log: &str) -> Result<Recording, String> {
+    Ok(Recording {
+        title: format!("Radar report: {}", log),
+        samples: datalog::read(log)?,
+        alarms: Vec::new(),
+    })
+}
+
+/// Writes the report and says where.
+pub fn run(request: Request) -> Result<(), String> {
+    let recording = match (&request.log, request.session) {
+        (Some(log), _) => load_log(log)?,
+        (None, Some(session)) => store::load_session(&request.db, session)?,
+        (None, None) => return Err("report needs --session <ID> or --log <CSV>".to_string()),
+    };
+    if recording.samples.is_empty() {
+        return Err("Nothing recorded to report on".to_string());
+    }
+    // A number is a session in the same database, anything else a log
+    let against = match &request.against {
+        Some(other) => {
+            let recording = match other.parse() {
+                Ok(session) => store::load_session(&request.db, session)?,
+                Err(_) => load_log(other)?,
+            };
+            if recording.samples.is_empty() {
+                return Err(format!("Nothing recorded in {} to compare with", other));
+            }
+            Some(Against {
+                title: other.clone(),
+                summary: summarize(&recording),
+            })
+        }
+        None => None,
+    };
+    let out = request
+        .out
+        .unwrap_or_else(|| format!("report.{}", request.format.extension()));
+    let summary = summarize(&recording);
+    let text = match request.format {
+        ReportFormat::Html => html(&recording, &summary, against.as_ref()),
+        ReportFormat::Markdown => {
+            markdown(&recording, &summary, against.as_ref(), Path::new(&out))?
+        }
+    };
+    fs::write(&out, text).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+    println!("Wrote {}", out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(samples: &[(f32, f32)]) -> Recording {
+        Recording {
+            title: String::new(),
+            samples: samples
+                .iter()
+                .enumerate()
+                .map(|(i, (a, d))| (i as f64, Sample::new(*a, *d)))
+                .collect(),
+            alarms: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn coverage_without_wrap_is_the_span() {
+        assert_eq!(covered_arc([10.0, 170.0, 90.0].into_iter()), (10.0, 170.0));
+    }
+
+    #[test]
+    fn coverage_goes_across_zero() {
+        let arc = covered_arc([300.0, 330.0, 359.0, 0.0, 30.0, 60.0].into_iter());
+        assert_eq!(arc, (300.0, 60.0));
+    }
+
+    #[test]
+    fn coverage_of_nothing_is_empty() {
+        assert_eq!(covered_arc(std::iter::empty()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn comparison_is_this_run_minus_the_other() {
+        let now = summarize(&recording(&[(10.0, 50.0), (20.0, 70.0), (100.0, 0.0)]));
+        let then = summarize(&recording(&[(10.0, 80.0), (20.0, 0.0)]));
+        let rows = comparison_rows(&now, &then);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], ["0°–30°", "+50.0%", "-20.0 cm", "-30.0 cm"]);
+        // Only this run read the 90°–120° sector
+        assert_eq!(rows[1], ["90°–120°", "-", "-", "-"]);
+    }
+}
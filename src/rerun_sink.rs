@@ -0,0 +1,67 @@
+use crate::pipeline::Update;
+use crate::radar::RadarState;
+
+/// Logs detections as 2D points (x/y in cm, sensor at the origin) and the
+/// sweep angle as a scalar to rerun.io, on a wall clock timeline so sessions
+/// line up with other telemetry.
+pub struct RerunSink {
+    #[cfg(feature = "rerun")]
+    rec: rerun::RecordingStream,
+}
+
+impl RerunSink {
+    /// `dest` is an `.rrd` file to save to or the gRPC address of a running
+    /// viewer, e.g. `rerun+http://127.0.0.1:9876/proxy`.
+    #[cfg(feature = "rerun")]
+    pub fn start(dest: &str) -> Result<Self, String> {
+        let builder = rerun::RecordingStreamBuilder::new("radar");
+        let rec = if dest.ends_with(".rrd") {
+            builder.save(dest)
+        } else {
+            builder.connect_grpc_opts(dest)
+        }
+        .map_err(|e| format!("Failed to start rerun sink {}: {}", dest, e))?;
+        tracing::info!("Logging to rerun: {}", dest);
+        Ok(Self { rec })
+    }
+
+    #[cfg(not(feature = "rerun"))]
+    pub fn start(_dest: &str) -> Result<Self, String> {
+        Err("rerun support is not compiled in, rebuild with --features rerun".to_string())
+    }
+
+    #[cfg(feature = "rerun")]
+    pub fn log(&self, update: &Update, radar: &RadarState) {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        if update.samples.is_empty() {
+            return;
+        }
+        let unix_now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        self.rec.set_timestamp_secs_since_epoch("time", unix_now);
+
+        // Logging only fails once the sink is gone, which rerun reports itself
+        for det in &update.detections {
+            let rad = det.angle.to_radians();
+            let point = (det.distance * rad.cos(), det.distance * rad.sin());
+            let _ = self.rec.log(
+                "radar/detections",
+                &rerun::Points2D::new([point]).with_radii([1.0]),
+            );
+        }
+        let _ = self.rec.log(
+            "radar/sweep_angle",
+            &rerun::Scalars::single(f64::from(radar.angle)),
+        );
+        let _ = self.rec.log(
+            "radar/distance",
+            &rerun::Scalars::single(f64::from(radar.distance())),
+        );
+    }
+
+    #[cfg(not(feature = "rerun"))]
+    pub fn log(&self, _update: &Update, _radar: &RadarState) {}
+}
@@ -0,0 +1,254 @@
+use std::net::UdpSocket;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Update;
+use crate::protocol::ProtocolStats;
+use crate::radar::RadarState;
+use crate::source::{LinkState, Sample, Source};
+use crate::validate::ValidationConfig;
+use crate::view::CoverageConfig;
+
+/// Angle resolution of published scans.
+const SCAN_STEP_DEG: f32 = 1.0;
+/// Without a scan for this long the input counts as disconnected.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `sensor_msgs/LaserScan` as JSON, field for field, which is also what
+/// rosbridge sends and expects in the `msg` of a publish. Angles are in
+/// radians and ranges in meters, `null` is a beam that hit nothing.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LaserScan {
+    #[serde(default)]
+    pub header: Header,
+    pub angle_min: f32,
+    #[serde(default)]
+    pub angle_max: f32,
+    pub angle_increment: f32,
+    #[serde(default)]
+    pub time_increment: f32,
+    #[serde(default)]
+    pub scan_time: f32,
+    #[serde(default)]
+    pub range_min: f32,
+    #[serde(default = "infinite_range")]
+    pub range_max: f32,
+    pub ranges: Vec<Option<f32>>,
+    #[serde(default)]
+    pub intensities: Vec<f32>,
+}
+
+fn infinite_range() -> f32 {
+    f32::INFINITY
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Header {
+    #[serde(default)]
+    pub stamp: Stamp,
+    #[serde(default)]
+    pub frame_id: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Stamp {
+    pub sec: i64,
+    pub nanosec: u32,
+}
+
+impl Stamp {
+    fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            sec: since_epoch.as_secs() as i64,
+            nanosec: since_epoch.subsec_nanos(),
+        }
+    }
+}
+
+/// A datagram is either a bare scan or a rosbridge `{"op": "publish", "msg": ...}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Incoming {
+    Bridge { msg: LaserScan },
+    Scan(LaserScan),
+}
+
+/// Collects the samples of a sweep and sends it as one `LaserScan` datagram
+/// when the sweep ends. A small relay node on the ROS side (or rosbridge)
+/// republishes it, so nav stacks can use the rig like any other scanner.
+pub struct ScanPublisher {
+    socket: UdpSocket,
+    target: String,
+    frame_id: String,
+    min_angle: f32,
+    range_min_m: f32,
+    range_max_m: f32,
+    /// Latest first-echo distance per angle step of the sweep in progress, cm
+    bins: Vec<Option<f32>>,
+    sweep_start: Instant,
+    sweeps: u64,
+}
+
+impl ScanPublisher {
+    #[cfg(feature = "ros")]
+    pub fn connect(
+        target: &str,
+        coverage: &CoverageConfig,
+        validation: &ValidationConfig,
+    ) -> Result<Self, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .map_err(|e| format!("Failed to open UDP socket: {}", e))?;
+        let span = (coverage.max_angle - coverage.min_angle).max(SCAN_STEP_DEG);
+        tracing::info!("Publishing LaserScan to udp {}", target);
+        Ok(Self {
+            socket,
+            target: target.to_string(),
+            frame_id: String::from("radar"),
+            min_angle: coverage.min_angle,
+            range_min_m: validation.min_range_cm / 100.0,
+            range_max_m: validation.max_range_cm / 100.0,
+            bins: vec![None; (span / SCAN_STEP_DEG) as usize + 1],
+            sweep_start: Instant::now(),
+            sweeps: 0,
+        })
+    }
+
+    #[cfg(not(feature = "ros"))]
+    pub fn connect(
+        _target: &str,
+        _coverage: &CoverageConfig,
+        _validation: &ValidationConfig,
+    ) -> Result<Self, String> {
+        Err("ROS support is not compiled in, rebuild with --features ros".to_string())
+    }
+
+    /// Adds a batch of samples, sends the scan when the sweep is done.
+    pub fn record(&mut self, update: &Update, radar: &RadarState, now: Instant) {
+        if radar.sweeps != self.sweeps {
+            self.sweeps = radar.sweeps;
+            self.send(now);
+        }
+        for sample in update.samples.iter().filter(|s| s.echo == 0) {
+            let step = ((sample.angle - self.min_angle) / SCAN_STEP_DEG).round();
+            if step >= 0.0
+                && let Some(bin) = self.bins.get_mut(step as usize)
+            {
+                *bin = Some(sample.distance);
+            }
+        }
+    }
+
+    fn send(&mut self, now: Instant) {
+        let scan_time = now
+            .saturating_duration_since(self.sweep_start)
+            .as_secs_f32();
+        self.sweep_start = now;
+        let step = SCAN_STEP_DEG.to_radians();
+        let min = self.min_angle.to_radians();
+        let scan = LaserScan {
+            header: Header {
+                stamp: Stamp::now(),
+                frame_id: self.frame_id.clone(),
+            },
+            angle_min: min,
+            angle_max: min + step * (self.bins.len() - 1) as f32,
+            angle_increment: step,
+            time_increment: scan_time / self.bins.len() as f32,
+            scan_time,
+            range_min: self.range_min_m,
+            range_max: self.range_max_m,
+            ranges: self
+                .bins
+                .iter()
+                .map(|cm| cm.filter(|cm| *cm > 0.0).map(|cm| cm / 100.0))
+                .collect(),
+            intensities: Vec::new(),
+        };
+        self.bins.fill(None);
+        let Ok(text) = serde_json::to_vec(&scan) else {
+            return;
+        };
+        // UDP, a scan that doesn't arrive is replaced by the next one
+        let _ = self.socket.send_to(&text, &self.target);
+    }
+}
+
+/// Input for real lidars: `LaserScan` datagrams on a UDP port, every beam
+/// becomes a sample.
+pub struct ScanSource {
+    socket: UdpSocket,
+    addr: String,
+    last_scan: Option<Instant>,
+    stats: ProtocolStats,
+}
+
+impl ScanSource {
+    #[cfg(feature = "ros")]
+    pub fn bind(addr: &str) -> Result<Self, String> {
+        let socket =
+            UdpSocket::bind(addr).map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        Ok(Self {
+            socket,
+            addr: addr.to_string(),
+            last_scan: None,
+            stats: ProtocolStats::default(),
+        })
+    }
+
+    #[cfg(not(feature = "ros"))]
+    pub fn bind(_addr: &str) -> Result<Self, String> {
+        Err("ROS support is not compiled in, rebuild with --features ros".to_string())
+    }
+}
+
+/// Beams outside the scanner's own limits are "nothing there", like ROS does.
+fn scan_samples(scan: &LaserScan, out: &mut Vec<Sample>) {
+    for (i, range) in scan.ranges.iter().enumerate() {
+        let angle = (scan.angle_min + scan.angle_increment * i as f32).to_degrees();
+        let distance = range
+            .filter(|r| r.is_finite() && *r >= scan.range_min && *r <= scan.range_max)
+            .map_or(0.0, |r| r * 100.0);
+        out.push(Sample::new(angle.rem_euclid(360.0), distance));
+    }
+}
+
+impl Source for ScanSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        let mut buf = vec![0u8; 65_536];
+        while let Ok(n) = self.socket.recv(&mut buf) {
+            match serde_json::from_slice(&buf[..n]) {
+                Ok(Incoming::Bridge { msg: scan }) | Ok(Incoming::Scan(scan)) => {
+                    self.stats.frames += 1;
+                    self.last_scan = Some(Instant::now());
+                    scan_samples(&scan, &mut samples);
+                }
+                Err(_) => self.stats.malformed += 1,
+            }
+        }
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        match self.last_scan {
+            None => LinkState::Connecting,
+            Some(t) if t.elapsed() < SCAN_TIMEOUT => LinkState::Connected,
+            Some(_) => LinkState::Disconnected,
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("laserscan udp {}", self.addr)
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        ("laserscan", self.stats)
+    }
+}
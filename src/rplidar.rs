@@ -0,0 +1,352 @@
+use tracing::{error, warn};
+
+use crate::protocol::{Protocol, ProtocolStats};
+use crate::source::Sample;
+
+const SYNC: u8 = 0xA5;
+const DESCRIPTOR_HEADER: [u8; 2] = [0xA5, 0x5A];
+const DESCRIPTOR_LEN: usize = 7;
+
+const CMD_STOP: u8 = 0x25;
+const CMD_GET_HEALTH: u8 = 0x52;
+const CMD_EXPRESS_SCAN: u8 = 0x82;
+/// A2 and up spin their motor on command, the A1 has it on DTR instead.
+const CMD_SET_MOTOR_PWM: u8 = 0xF0;
+const MOTOR_PWM: u16 = 660;
+
+const TYPE_HEALTH: u8 = 0x06;
+const TYPE_SCAN: u8 = 0x81;
+const TYPE_EXPRESS: u8 = 0x82;
+
+const HEALTH_LEN: usize = 3;
+const NODE_LEN: usize = 5;
+const CAPSULE_LEN: usize = 84;
+const CABINS: usize = 16;
+
+/// What the bytes at the front of the buffer are expected to be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Expect {
+    Descriptor,
+    Health,
+    /// 5 byte nodes of a standard scan
+    Scan,
+    /// 84 byte capsules of an express scan
+    Express,
+    /// Payload of a response we don't care about
+    Skip(usize),
+}
+
+/// Express scan capsule: a start angle and 32 measurements whose angles are
+/// only known once the next capsule's start angle arrives.
+struct Capsule {
+    start_deg: f32,
+    /// (distance word, distance word, offset angle byte) per cabin
+    cabins: [(u16, u16, u8); CABINS],
+}
+
+/// Slamtec RPLidar A1/A2 binary protocol. The port is told to stop whatever
+/// it was doing, report its health and start an express scan, which gives
+/// 32 measurements per 84 byte capsule. Standard scan nodes are decoded as
+/// well, for firmware without express mode.
+pub struct RplidarProtocol {
+    buf: Vec<u8>,
+    stats: ProtocolStats,
+    expect: Expect,
+    previous: Option<Capsule>,
+    resyncing: bool,
+}
+
+impl Default for RplidarProtocol {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            stats: ProtocolStats::default(),
+            expect: Expect::Descriptor,
+            previous: None,
+            resyncing: false,
+        }
+    }
+}
+
+/// `A5 cmd` plus, with a payload, its size and the XOR of every byte.
+fn request(cmd: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![SYNC, cmd];
+    if !payload.is_empty() {
+        bytes.push(payload.len() as u8);
+        bytes.extend_from_slice(payload);
+        let checksum = bytes.iter().fold(0u8, |acc, b| acc ^ b);
+        bytes.push(checksum);
+    }
+    bytes
+}
+
+/// Sign extends the 6 bit q3 angle correction of an express measurement.
+fn offset_deg(raw: u8) -> f32 {
+    let raw = raw as i8;
+    let signed = if raw & 0x20 != 0 { raw - 64 } else { raw };
+    signed as f32 / 8.0
+}
+
+impl RplidarProtocol {
+    fn resync(&mut self) {
+        if !self.resyncing {
+            self.stats.malformed += 1;
+            self.resyncing = true;
+        }
+    }
+
+    /// Decodes the previous capsule now that `next` tells where it ends.
+    fn decode_capsule(&mut self, next: Capsule, out: &mut Vec<Sample>) {
+        if let Some(capsule) = self.previous.take() {
+            let mut span = next.start_deg - capsule.start_deg;
+            if span < 0.0 {
+                span += 360.0;
+            }
+            let step = span / (CABINS * 2) as f32;
+            for (k, &(d1, d2, offsets)) in capsule.cabins.iter().enumerate() {
+                let measurements = [
+                    (d1, ((d1 as u8 & 0x3) << 4) | (offsets & 0xF)),
+                    (d2, ((d2 as u8 & 0x3) << 4) | (offsets >> 4)),
+                ];
+                for (j, (word, offset)) in measurements.into_iter().enumerate() {
+                    let i = (k * 2 + j) as f32;
+                    let angle = capsule.start_deg + step * i - offset_deg(offset);
+                    // Distance in mm, 0 means nothing came back
+                    let distance = (word >> 2) as f32 / 10.0;
+                    out.push(Sample::new(angle.rem_euclid(360.0), distance));
+                }
+            }
+        }
+        self.previous = Some(next);
+    }
+}
+
+impl Protocol for RplidarProtocol {
+    fn name(&self) -> &'static str {
+        "rplidar"
+    }
+
+    fn start(&mut self) -> Vec<u8> {
+        let mut bytes = request(CMD_STOP, &[]);
+        bytes.extend(request(CMD_GET_HEALTH, &[]));
+        bytes.extend(request(CMD_SET_MOTOR_PWM, &MOTOR_PWM.to_le_bytes()));
+        // Working mode 0 is the legacy express scan every A1/A2 has
+        bytes.extend(request(CMD_EXPRESS_SCAN, &[0, 0, 0, 0, 0]));
+        bytes
+    }
+
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<Sample>) {
+        self.buf.extend_from_slice(bytes);
+
+        let mut start = 0;
+        loop {
+            let rest = &self.buf[start..];
+            match self.expect {
+                Expect::Descriptor => {
+                    if rest.len() < DESCRIPTOR_LEN {
+                        break;
+                    }
+                    if rest[..2] != DESCRIPTOR_HEADER {
+                        self.resync();
+                        start += 1;
+                        continue;
+                    }
+                    let len =
+                        u32::from_le_bytes([rest[2], rest[3], rest[4], rest[5]]) & 0x3FFF_FFFF;
+                    self.expect = match rest[6] {
+                        TYPE_HEALTH => Expect::Health,
+                        TYPE_SCAN => Expect::Scan,
+                        TYPE_EXPRESS => Expect::Express,
+                        _ => Expect::Skip(len as usize),
+                    };
+                    self.previous = None;
+                    self.resyncing = false;
+                    start += DESCRIPTOR_LEN;
+                }
+                Expect::Health => {
+                    if rest.len() < HEALTH_LEN {
+                        break;
+                    }
+                    let error_code = u16::from_le_bytes([rest[1], rest[2]]);
+                    match rest[0] {
+                        0 => {}
+                        1 => warn!("RPLidar health warning, code {:#06x}", error_code),
+                        _ => error!("RPLidar health error, code {:#06x}", error_code),
+                    }
+                    self.expect = Expect::Descriptor;
+                    start += HEALTH_LEN;
+                }
+                Expect::Skip(len) => {
+                    let n = len.min(rest.len());
+                    start += n;
+                    if n < len {
+                        self.expect = Expect::Skip(len - n);
+                        break;
+                    }
+                    self.expect = Expect::Descriptor;
+                }
+                Expect::Scan => {
+                    if rest.len() < NODE_LEN {
+                        break;
+                    }
+                    let node = &rest[..NODE_LEN];
+                    let start_flag = node[0] & 0x1;
+                    let inverse_flag = (node[0] >> 1) & 0x1;
+                    if start_flag == inverse_flag || node[1] & 0x1 != 1 {
+                        self.resync();
+                        start += 1;
+                        continue;
+                    }
+                    let angle_q6 = u16::from_le_bytes([node[1], node[2]]) >> 1;
+                    let distance_q2 = u16::from_le_bytes([node[3], node[4]]);
+                    out.push(Sample::new(
+                        angle_q6 as f32 / 64.0,
+                        distance_q2 as f32 / 40.0,
+                    ));
+                    self.stats.frames += 1;
+                    self.resyncing = false;
+                    start += NODE_LEN;
+                }
+                Expect::Express => {
+                    if rest.len() < CAPSULE_LEN {
+                        break;
+                    }
+                    let capsule = &rest[..CAPSULE_LEN];
+                    if capsule[0] >> 4 != 0xA || capsule[1] >> 4 != 0x5 {
+                        self.resync();
+                        self.previous = None;
+                        start += 1;
+                        continue;
+                    }
+                    let expected = (capsule[0] & 0xF) | ((capsule[1] & 0xF) << 4);
+                    let checksum = capsule[2..].iter().fold(0u8, |acc, b| acc ^ b);
+                    if checksum != expected {
+                        self.stats.checksum_errors += 1;
+                        self.resyncing = true;
+                        self.previous = None;
+                        start += 1;
+                        continue;
+                    }
+                    let start_q6 = u16::from_le_bytes([capsule[2], capsule[3]]) & 0x7FFF;
+                    let mut cabins = [(0, 0, 0); CABINS];
+                    for (k, cabin) in cabins.iter_mut().enumerate() {
+                        let c = &capsule[4 + k * 5..9 + k * 5];
+                        *cabin = (
+                            u16::from_le_bytes([c[0], c[1]]),
+                            u16::from_le_bytes([c[2], c[3]]),
+                            c[4],
+                        );
+                    }
+                    self.decode_capsule(
+                        Capsule {
+                            start_deg: start_q6 as f32 / 64.0,
+                            cabins,
+                        },
+                        out,
+                    );
+                    self.stats.frames += 1;
+                    self.resyncing = false;
+                    start += CAPSULE_LEN;
+                }
+            }
+        }
+        self.buf.drain(..start);
+    }
+
+    fn stats(&self) -> ProtocolStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor(kind: u8, len: u32) -> Vec<u8> {
+        let mut bytes = DESCRIPTOR_HEADER.to_vec();
+        bytes.extend(len.to_le_bytes());
+        bytes.push(kind);
+        bytes
+    }
+
+    /// An express capsule starting at `start_deg` with every distance at
+    /// `distance_mm` and no angle corrections.
+    fn capsule(start_deg: f32, distance_mm: u16) -> Vec<u8> {
+        let mut bytes = vec![0, 0];
+        bytes.extend(((start_deg * 64.0) as u16).to_le_bytes());
+        for _ in 0..CABINS {
+            bytes.extend((distance_mm << 2).to_le_bytes());
+            bytes.extend((distance_mm << 2).to_le_bytes());
+            bytes.push(0);
+        }
+        let checksum = bytes[2..].iter().fold(0u8, |acc, b| acc ^ b);
+        bytes[0] = 0xA0 | (checksum & 0xF);
+        bytes[1] = 0x50 | (checksum >> 4);
+        bytes
+    }
+
+    #[test]
+    fn requests_with_a_payload_carry_a_checksum() {
+        assert_eq!(request(CMD_STOP, &[]), [0xA5, 0x25]);
+        let pwm = request(CMD_SET_MOTOR_PWM, &MOTOR_PWM.to_le_bytes());
+        assert_eq!(
+            pwm,
+            [
+                0xA5,
+                0xF0,
+                0x02,
+                0x94,
+                0x02,
+                0xA5 ^ 0xF0 ^ 0x02 ^ 0x94 ^ 0x02
+            ]
+        );
+    }
+
+    #[test]
+    fn offsets_are_sign_extended() {
+        assert_eq!(offset_deg(0x08), 1.0);
+        assert_eq!(offset_deg(0x38), -1.0);
+    }
+
+    #[test]
+    fn decodes_standard_scan_nodes() {
+        let mut protocol = RplidarProtocol::default();
+        let mut out = Vec::new();
+        let mut bytes = descriptor(TYPE_SCAN, 0x4000_0005);
+        // Start flag set, 90° and 1 m
+        bytes.extend([0x01, 0x01, 0x2D, 0xA0, 0x0F]);
+        protocol.decode(&bytes, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].angle, out[0].distance), (90.0, 100.0));
+    }
+
+    #[test]
+    fn express_capsules_are_spread_up_to_the_next_one() {
+        let mut protocol = RplidarProtocol::default();
+        let mut out = Vec::new();
+        let mut bytes = descriptor(TYPE_EXPRESS, 0x4000_0054);
+        bytes.extend(capsule(0.0, 1000));
+        protocol.decode(&bytes, &mut out);
+        // Its angles are only known with the next capsule
+        assert!(out.is_empty());
+        protocol.decode(&capsule(32.0, 2000), &mut out);
+        assert_eq!(out.len(), CABINS * 2);
+        assert_eq!((out[0].angle, out[0].distance), (0.0, 100.0));
+        assert_eq!(out[31].angle, 31.0);
+        assert_eq!(protocol.stats().frames, 2);
+    }
+
+    #[test]
+    fn a_bad_capsule_checksum_drops_the_pending_one() {
+        let mut protocol = RplidarProtocol::default();
+        let mut out = Vec::new();
+        let mut bytes = descriptor(TYPE_EXPRESS, 0x4000_0054);
+        bytes.extend(capsule(0.0, 1000));
+        let mut bad = capsule(32.0, 1000);
+        bad[10] ^= 0xFF;
+        bytes.extend(bad);
+        protocol.decode(&bytes, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(protocol.stats().checksum_errors, 1);
+    }
+}
@@ -0,0 +1,109 @@
+use raylib::prelude::*;
+
+use crate::source::Sample;
+use crate::view::Panel;
+
+/// Angle resolution of the profile.
+const BINS_PER_DEG: f32 = 1.0;
+const BIN_COUNT: usize = (360.0 * BINS_PER_DEG) as usize;
+
+/// A-scope: the latest first-echo distance at every angle, drawn as a curve
+/// of distance over angle.
+pub struct AScope {
+    /// Distance per angle bin, `None` until something (or nothing) was heard there
+    profile: Vec<Option<f32>>,
+}
+
+impl Default for AScope {
+    fn default() -> Self {
+        Self {
+            profile: vec![None; BIN_COUNT],
+        }
+    }
+}
+
+impl AScope {
+    pub fn record(&mut self, sample: &Sample) {
+        if sample.echo != 0 {
+            return;
+        }
+        let bin = (sample.angle.rem_euclid(360.0) * BINS_PER_DEG) as usize % BIN_COUNT;
+        self.profile[bin] = Some(sample.distance);
+    }
+
+    /// Draws the chart into the panel, with its sector on the x axis.
+    pub fn draw<D: RaylibDraw>(&self, d: &mut D, panel: &Panel) {
+        let Panel {
+            rect,
+            angles,
+            current_angle,
+            max_range_cm,
+            unit,
+            ref theme,
+            ui,
+        } = *panel;
+        d.draw_rectangle_rec(rect, theme.background.alpha(0.85));
+        d.draw_rectangle_lines_ex(rect, 1.0, theme.outline);
+
+        let (min, max) = angles;
+        let span = (max - min).max(1.0);
+        let x_of = |angle: f32| rect.x + (angle - min) / span * rect.width;
+        let y_of =
+            |cm: f32| rect.y + rect.height - (cm / max_range_cm).clamp(0.0, 1.0) * rect.height;
+
+        // Half and full range gridlines
+        for fraction in [0.5, 1.0] {
+            let y = y_of(max_range_cm * fraction);
+            d.draw_line_v(
+                Vector2::new(rect.x, y),
+                Vector2::new(rect.x + rect.width, y),
+                theme.outline.alpha(0.4),
+            );
+        }
+
+        // Consecutive heard bins are joined, silence breaks the curve
+        let mut last: Option<Vector2> = None;
+        let first_bin = (min * BINS_PER_DEG) as usize;
+        let last_bin = ((max * BINS_PER_DEG) as usize).min(BIN_COUNT - 1);
+        for bin in first_bin..=last_bin {
+            let point = self.profile[bin]
+                .filter(|cm| *cm > 0.0)
+                .map(|cm| Vector2::new(x_of(bin as f32 / BINS_PER_DEG), y_of(cm)));
+            if let (Some(a), Some(b)) = (last, point) {
+                d.draw_line_ex(a, b, 2.0, theme.sweep);
+            }
+            last = point;
+        }
+
+        let x = x_of(current_angle.clamp(min, max));
+        d.draw_line_v(
+            Vector2::new(x, rect.y),
+            Vector2::new(x, rect.y + rect.height),
+            theme.detection.alpha(0.6),
+        );
+
+        let font_size = ui.font(14);
+        d.draw_text(
+            &unit.format(max_range_cm),
+            rect.x as i32 + 4,
+            rect.y as i32 + 4,
+            font_size,
+            theme.outline,
+        );
+        let bottom = (rect.y + rect.height) as i32 - font_size - 2;
+        d.draw_text(
+            &format!("{:.0}", min),
+            rect.x as i32 + 4,
+            bottom,
+            font_size,
+            theme.outline,
+        );
+        d.draw_text(
+            &format!("{:.0}", max),
+            (rect.x + rect.width) as i32 - ui.pxi(30),
+            bottom,
+            font_size,
+            theme.outline,
+        );
+    }
+}
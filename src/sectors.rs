@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::radar::Detection;
+use crate::view::Panel;
+
+/// Detections kept at most, so a lidar can't grow the window without bound.
+const MAX_DETECTIONS: usize = 50_000;
+
+/// `[sectors]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SectorConfig {
+    /// Start with the panel shown, / toggles it
+    pub enabled: bool,
+    /// Equal parts the covered arc is split into
+    pub count: usize,
+    /// Detections older than this no longer count, seconds
+    pub window_s: f32,
+}
+
+impl Default for SectorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            count: 6,
+            window_s: 10.0,
+        }
+    }
+}
+
+/// What one sector saw over the window.
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    count: usize,
+    min_cm: f32,
+    sum_cm: f32,
+}
+
+/// Detection count and nearest and mean distance per sector of the covered
+/// arc over the last few seconds, to tell which direction activity comes
+/// from.
+pub struct SectorStats {
+    pub enabled: bool,
+    count: usize,
+    window: Duration,
+    /// Time, angle and distance, oldest first
+    detections: VecDeque<(Instant, f32, f32)>,
+}
+
+impl SectorStats {
+    pub fn new(config: &SectorConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            count: config.count.clamp(1, 36),
+            window: Duration::from_secs_f32(config.window_s.max(0.1)),
+            detections: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, detections: &[Detection]) {
+        for det in detections {
+            if self.detections.len() == MAX_DETECTIONS {
+                self.detections.pop_front();
+            }
+            self.detections
+                .push_back((det.time, det.angle, det.distance));
+        }
+    }
+
+    fn totals(&mut self, sector: (f32, f32), now: Instant) -> Vec<Totals> {
+        while let Some(&(time, _, _)) = self.detections.front() {
+            if now.saturating_duration_since(time) <= self.window {
+                break;
+            }
+            self.detections.pop_front();
+        }
+        let (min, max) = sector;
+        let width = (max - min).max(1.0) / self.count as f32;
+        let mut totals = vec![Totals::default(); self.count];
+        for &(_, angle, distance) in &self.detections {
+            if angle < min || angle > max {
+                continue;
+            }
+            let i = (((angle - min) / width) as usize).min(self.count - 1);
+            let t = &mut totals[i];
+            t.min_cm = if t.count == 0 {
+                distance
+            } else {
+                t.min_cm.min(distance)
+            };
+            t.count += 1;
+            t.sum_cm += distance;
+        }
+        totals
+    }
+
+    /// Draws one row per sector into the panel: its angles, a bar for the
+    /// count, the count and the nearest and mean distance. The sector the
+    /// sweep is in is outlined.
+    pub fn draw<D: RaylibDraw>(&mut self, d: &mut D, panel: &Panel, now: Instant) {
+        let Panel {
+            rect,
+            angles,
+            current_angle,
+            unit,
+            ref theme,
+            ui,
+            ..
+        } = *panel;
+        let totals = self.totals(angles, now);
+        d.draw_rectangle_rec(rect, theme.background.alpha(0.85));
+        d.draw_rectangle_lines_ex(rect, 1.0, theme.outline);
+
+        let font_size = ui.font(14);
+        let pad = ui.px(4.0);
+        let header = font_size as f32 + 2.0 * pad;
+        let row_h = (rect.height - header) / self.count as f32;
+        let column = |fraction: f32| (rect.x + pad + fraction * (rect.width - 2.0 * pad)) as i32;
+        let text_y = |y: f32| (y + (row_h - font_size as f32) / 2.0) as i32;
+        for (label, fraction) in [
+            ("Sector", 0.0),
+            ("Count", 0.3),
+            ("Min", 0.55),
+            ("Mean", 0.78),
+        ] {
+            d.draw_text(
+                label,
+                column(fraction),
+                (rect.y + pad) as i32,
+                font_size,
+                theme.outline,
+            );
+        }
+
+        let (min, max) = angles;
+        let width = (max - min).max(1.0) / self.count as f32;
+        let busiest = totals.iter().map(|t| t.count).max().unwrap_or(0).max(1);
+        for (i, t) in totals.iter().enumerate() {
+            let y = rect.y + header + i as f32 * row_h;
+            let from = min + i as f32 * width;
+            if (from..from + width).contains(&current_angle) {
+                d.draw_rectangle_lines_ex(
+                    Rectangle::new(rect.x + 1.0, y, rect.width - 2.0, row_h),
+                    1.0,
+                    theme.detection.alpha(0.6),
+                );
+            }
+            let bar_x = column(0.3) as f32;
+            let bar_w = column(0.55) as f32 - bar_x - pad;
+            d.draw_rectangle_rec(
+                Rectangle::new(
+                    bar_x,
+                    y + row_h * 0.15,
+                    bar_w * t.count as f32 / busiest as f32,
+                    row_h * 0.7,
+                ),
+                theme.sweep.alpha(0.35),
+            );
+            let sector_label = format!("{:.0}-{:.0}", from, from + width);
+            d.draw_text(
+                &sector_label,
+                column(0.0),
+                text_y(y),
+                font_size,
+                theme.outline,
+            );
+            d.draw_text(
+                &t.count.to_string(),
+                column(0.3) + ui.pxi(2),
+                text_y(y),
+                font_size,
+                theme.outline,
+            );
+            let (nearest, mean) = if t.count == 0 {
+                (String::from("-"), String::from("-"))
+            } else {
+                (
+                    unit.format(t.min_cm),
+                    unit.format(t.sum_cm / t.count as f32),
+                )
+            };
+            d.draw_text(&nearest, column(0.55), text_y(y), font_size, theme.outline);
+            d.draw_text(&mean, column(0.78), text_y(y), font_size, theme.outline);
+        }
+    }
+}
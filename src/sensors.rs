@@ -0,0 +1,272 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::autobaud::Baud;
+use crate::discovery;
+use crate::protocol::{ProtocolKind, ProtocolStats, Raw};
+use crate::source::{LinkState, Sample, Source};
+use crate::theme::{HexColor, Theme};
+use crate::ui::UiScale;
+
+/// Keys that show and hide the layers, in `[[sensor]]` order.
+const TOGGLE_KEYS: [KeyboardKey; 9] = [
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+    KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN,
+    KeyboardKey::KEY_EIGHT,
+    KeyboardKey::KEY_NINE,
+];
+const LEGEND_FONT_SIZE: i32 = 18;
+const LEGEND_LINE_HEIGHT: i32 = 22;
+
+/// One `[[sensor]]` entry of the config file: a serial port and where the
+/// sensor on it is mounted.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SensorConfig {
+    pub name: String,
+    /// Path of the port, or `usb:VID:PID` / `arduino` to find it by its USB IDs
+    pub port: String,
+    pub baud: u32,
+    /// The top level `protocol` when not set
+    pub protocol: Option<ProtocolKind>,
+    /// Added to every angle it reports, 180 for a sensor facing backwards
+    pub angle_offset_deg: f32,
+    /// Position relative to the plot center in cm, +x towards 0° and +y
+    /// towards 90°
+    pub x_cm: f32,
+    pub y_cm: f32,
+    /// Readings beyond this count as nothing heard, 0 for no limit of its own
+    pub max_range_cm: f32,
+    /// Color of its detections, the distance gradient when not set
+    pub color: Option<HexColor>,
+    /// Weight of its readings in the fused view, `[fusion]` when not set
+    pub confidence: Option<f32>,
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            name: String::from("sensor"),
+            port: String::new(),
+            baud: 9600,
+            protocol: None,
+            angle_offset_deg: 0.0,
+            x_cm: 0.0,
+            y_cm: 0.0,
+            max_range_cm: 0.0,
+            color: None,
+            confidence: None,
+        }
+    }
+}
+
+impl SensorConfig {
+    /// Moves a reading from the sensor's own frame into the plot's. Samples
+    /// that hit nothing keep distance 0 and are only turned.
+    fn place(&self, sample: Sample, layer: u8) -> Sample {
+        let angle = sample.angle + self.angle_offset_deg;
+        let mut placed = Sample {
+            angle: angle.rem_euclid(360.0),
+            layer,
+            ..sample
+        };
+        if self.max_range_cm > 0.0 && sample.distance > self.max_range_cm {
+            placed.distance = 0.0;
+        }
+        if placed.distance <= 0.0 || (self.x_cm == 0.0 && self.y_cm == 0.0) {
+            return placed;
+        }
+        let rad = angle.to_radians();
+        let x = self.x_cm + sample.distance * rad.cos();
+        let y = self.y_cm + sample.distance * rad.sin();
+        placed.angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+        placed.distance = x.hypot(y);
+        placed
+    }
+}
+
+/// Several sensors read as one source. Every sample is moved to the plot's
+/// frame and tagged with the index of its sensor, the first one drives the
+/// sweep line and readouts.
+pub struct MultiSource {
+    sensors: Vec<(SensorConfig, Box<dyn Source>)>,
+}
+
+impl MultiSource {
+    pub fn open(sensors: &[SensorConfig], default_protocol: ProtocolKind) -> Self {
+        let sensors = sensors
+            .iter()
+            .map(|config| {
+                info!(
+                    "Sensor {}: Port: {}, Baud: {}",
+                    config.name, config.port, config.baud
+                );
+                let protocol = config.protocol.unwrap_or(default_protocol);
+                let source = discovery::open(&config.port, Baud::Fixed(config.baud), protocol);
+                (config.clone(), source)
+            })
+            .collect();
+        Self { sensors }
+    }
+}
+
+impl Source for MultiSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        for (layer, (config, source)) in self.sensors.iter_mut().enumerate() {
+            let layer = layer.min(u8::MAX as usize) as u8;
+            samples.extend(source.poll().into_iter().map(|s| config.place(s, layer)));
+        }
+        samples
+    }
+
+    /// The worst of every sensor's state.
+    fn state(&self) -> LinkState {
+        let states: Vec<LinkState> = self.sensors.iter().map(|(_, s)| s.state()).collect();
+        if states.contains(&LinkState::Disconnected) {
+            LinkState::Disconnected
+        } else if states.contains(&LinkState::Connecting) {
+            LinkState::Connecting
+        } else {
+            LinkState::Connected
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.sensors
+            .iter()
+            .map(|(config, source)| format!("{} {}", config.name, source.describe()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        let mut name = None;
+        let mut total = ProtocolStats::default();
+        for (_, source) in &self.sensors {
+            let (protocol, stats) = source.protocol_stats();
+            name = match name {
+                None => Some(protocol),
+                Some(n) if n == protocol => Some(n),
+                Some(_) => Some("mixed"),
+            };
+            total.frames += stats.frames;
+            total.malformed += stats.malformed;
+            total.checksum_errors += stats.checksum_errors;
+        }
+        (name.unwrap_or("none"), total)
+    }
+
+    /// Commands go to every sensor, it's an error only if none took it.
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        let mut result = Err(format!("{} is read-only", self.describe()));
+        for (_, source) in &mut self.sensors {
+            match source.send(line) {
+                Ok(()) => result = Ok(()),
+                Err(e) if result.is_err() => result = Err(e),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        self.sensors
+            .iter_mut()
+            .flat_map(|(_, source)| source.messages())
+            .collect()
+    }
+
+    fn tap(&mut self, on: bool) {
+        for (_, source) in &mut self.sensors {
+            source.tap(on);
+        }
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        self.sensors
+            .iter_mut()
+            .flat_map(|(_, source)| source.raw())
+            .collect()
+    }
+}
+
+struct Layer {
+    name: String,
+    color: Option<Color>,
+    visible: bool,
+}
+
+/// Display side of the `[[sensor]]` list: a color, a legend entry and a
+/// show/hide toggle per sensor. Empty with a single source.
+pub struct Layers {
+    layers: Vec<Layer>,
+}
+
+impl Layers {
+    pub fn new(sensors: &[SensorConfig]) -> Self {
+        Self {
+            layers: sensors
+                .iter()
+                .map(|s| Layer {
+                    name: s.name.clone(),
+                    color: s.color.map(|c| c.0),
+                    visible: true,
+                })
+                .collect(),
+        }
+    }
+
+    /// `1`-`9` show and hide the matching layer.
+    pub fn toggle_keys(&mut self, rl: &RaylibHandle) {
+        for (layer, key) in self.layers.iter_mut().zip(TOGGLE_KEYS) {
+            if rl.is_key_pressed(key) {
+                layer.visible = !layer.visible;
+            }
+        }
+    }
+
+    /// Samples from unknown layers (a single source) are always shown.
+    pub fn is_visible(&self, layer: u8) -> bool {
+        self.layers
+            .get(layer as usize)
+            .is_none_or(|layer| layer.visible)
+    }
+
+    pub fn color(&self, layer: u8) -> Option<Color> {
+        self.layers
+            .get(layer as usize)
+            .and_then(|layer| layer.color)
+    }
+
+    /// One line per layer with its key, color and name, ending at `bottom`.
+    pub fn draw_legend<D: RaylibDraw>(&self, d: &mut D, bottom: f32, ui: UiScale, theme: &Theme) {
+        let line_height = ui.pxi(LEGEND_LINE_HEIGHT);
+        let top = bottom as i32 - self.layers.len() as i32 * line_height;
+        for (i, layer) in self.layers.iter().enumerate() {
+            let y = top + i as i32 * line_height;
+            let alpha = if layer.visible { 1.0 } else { 0.4 };
+            let swatch = layer.color.unwrap_or(theme.detection).alpha(alpha);
+            let swatch_size = ui.pxi(12);
+            d.draw_rectangle(10, y + ui.pxi(3), swatch_size, swatch_size, swatch);
+            let text = if layer.visible {
+                format!("{} {}", i + 1, layer.name)
+            } else {
+                format!("{} {} (hidden)", i + 1, layer.name)
+            };
+            d.draw_text(
+                &text,
+                18 + swatch_size,
+                y,
+                ui.font(LEGEND_FONT_SIZE),
+                theme.outline.alpha(alpha),
+            );
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use toml_edit::value;
+
+use crate::config::{self, Config};
+use crate::hold::Persistence;
+use crate::shader::{Params, Preset};
+use crate::theme::ThemeKind;
+use crate::ui::UiScale;
+use crate::units::Unit;
+
+const PANEL_WIDTH: f32 = 340.0;
+const ROW_HEIGHT: f32 = 30.0;
+/// Room for the text left of a slider.
+const LABEL_WIDTH: f32 = 130.0;
+const TEXT_SIZE: i32 = 16;
+
+/// `[visuals]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct VisualsConfig {
+    /// Post-processing look, F7 cycles through them
+    pub preset: Preset,
+    /// Uniforms of the shader, each preset uses the ones that apply to it
+    pub shader_intensity: f32,
+    pub scanlines: f32,
+    pub bloom: f32,
+    pub curvature: f32,
+    pub noise: f32,
+    /// Where the preset shaders are read from, and read again whenever they
+    /// change. The built-in copies are used for missing files
+    pub shader_dir: String,
+    /// Whether detections fade with time or wait for the next sweep, K
+    /// switches
+    pub persistence: Persistence,
+    /// Move the sweep line smoothly between sparse readings
+    pub interpolate_sweep: bool,
+    /// Seconds for the sweep trail to fade by half, the theme's when not set
+    pub fade_half_life_s: Option<f32>,
+}
+
+impl Default for VisualsConfig {
+    fn default() -> Self {
+        Self {
+            preset: Preset::Phosphor,
+            shader_intensity: 1.5,
+            scanlines: 0.12,
+            bloom: 0.6,
+            curvature: 0.04,
+            noise: 0.03,
+            shader_dir: String::from("shaders"),
+            persistence: Persistence::Fade,
+            interpolate_sweep: true,
+            fade_half_life_s: None,
+        }
+    }
+}
+
+/// Everything the panel can change, copied out of the running state before
+/// drawing it and back in when it changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Values {
+    pub preset: Preset,
+    pub shader: Params,
+    pub fade_half_life_s: f32,
+    pub max_range_cm: f32,
+    pub theme: ThemeKind,
+    pub unit: Unit,
+    /// `max_cm` of every alarm zone, in config order
+    pub alarm_max_cm: Vec<f32>,
+}
+
+impl Values {
+    /// What a config file sets, the fade of its theme when it has none.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            preset: config.visuals.preset,
+            shader: Params::new(&config.visuals),
+            fade_half_life_s: config
+                .visuals
+                .fade_half_life_s
+                .unwrap_or_else(|| config.theme.theme().fade_half_life()),
+            max_range_cm: config.range.max_cm,
+            theme: config.theme,
+            unit: config.units,
+            alarm_max_cm: config.alarms.iter().map(|z| z.max_cm).collect(),
+        }
+    }
+
+    /// The running values with whatever was edited between the `old` and
+    /// `new` file, so changes made in the window and not saved yet stay.
+    pub fn reloaded(&self, old: &Values, new: &Values) -> Values {
+        fn pick<T: PartialEq + Clone>(current: &T, old: &T, new: &T) -> T {
+            if old == new { current } else { new }.clone()
+        }
+        let alarm_max_cm = self
+            .alarm_max_cm
+            .iter()
+            .enumerate()
+            .map(
+                |(i, current)| match (old.alarm_max_cm.get(i), new.alarm_max_cm.get(i)) {
+                    (Some(old), Some(new)) => pick(current, old, new),
+                    _ => *current,
+                },
+            )
+            .collect();
+        Values {
+            preset: pick(&self.preset, &old.preset, &new.preset),
+            shader: pick(&self.shader, &old.shader, &new.shader),
+            fade_half_life_s: pick(
+                &self.fade_half_life_s,
+                &old.fade_half_life_s,
+                &new.fade_half_life_s,
+            ),
+            max_range_cm: pick(&self.max_range_cm, &old.max_range_cm, &new.max_range_cm),
+            theme: pick(&self.theme, &old.theme, &new.theme),
+            unit: pick(&self.unit, &old.unit, &new.unit),
+            alarm_max_cm,
+        }
+    }
+}
+
+/// Settings panel on the right side of the window, toggled with Tab.
+/// Changes apply right away and are written to the config file when the
+/// panel is closed.
+#[derive(Default)]
+pub struct SettingsPanel {
+    pub open: bool,
+    /// Something changed since the panel was opened
+    pub dirty: bool,
+    rect: Rectangle,
+}
+
+impl SettingsPanel {
+    /// Whether `pos` is on the panel, the plot ignores the mouse there.
+    pub fn covers(&self, pos: Vector2) -> bool {
+        self.open && self.rect.check_collision_point_rec(pos)
+    }
+
+    /// Draws the controls. Returns the new values when one was changed.
+    /// `zone_names` labels the alarm thresholds, `key` is the label of the
+    /// key that toggles the panel.
+    pub fn draw<D: RaylibDraw>(
+        &mut self,
+        d: &mut D,
+        values: &Values,
+        zone_names: &[&str],
+        key: &str,
+        screen_w: f32,
+        ui: UiScale,
+    ) -> Option<Values> {
+        if !self.open {
+            return None;
+        }
+        let (panel_w, row_h, label_w) = (ui.px(PANEL_WIDTH), ui.px(ROW_HEIGHT), ui.px(LABEL_WIDTH));
+        let rows = 10 + values.alarm_max_cm.len();
+        self.rect = Rectangle::new(
+            screen_w - panel_w - ui.px(10.0),
+            ui.px(60.0),
+            panel_w,
+            row_h * (rows as f32 + 1.0) + ui.px(10.0),
+        );
+        d.gui_set_style(
+            GuiControl::DEFAULT,
+            GuiDefaultProperty::TEXT_SIZE,
+            ui.font(TEXT_SIZE),
+        );
+        if d.gui_window_box(self.rect, &format!("Settings ({key})")) {
+            self.open = false;
+        }
+
+        let mut new = values.clone();
+        let x = self.rect.x + ui.px(10.0);
+        let mut y = self.rect.y + row_h + ui.px(8.0);
+        let slider = |y: f32| {
+            Rectangle::new(
+                x + label_w,
+                y,
+                panel_w - label_w - ui.px(70.0),
+                row_h - ui.px(8.0),
+            )
+        };
+        let combo = |y: f32| {
+            Rectangle::new(
+                x + label_w,
+                y,
+                panel_w - label_w - ui.px(20.0),
+                row_h - ui.px(8.0),
+            )
+        };
+
+        d.gui_label(Rectangle::new(x, y, label_w, row_h - ui.px(8.0)), "Shader");
+        let names: Vec<&str> = Preset::ALL.iter().map(|p| p.label()).collect();
+        let mut active = Preset::ALL
+            .iter()
+            .position(|p| *p == new.preset)
+            .unwrap_or(0) as i32;
+        d.gui_combo_box(combo(y), &names.join(";"), &mut active);
+        new.preset = Preset::ALL[active as usize % Preset::ALL.len()];
+        y += row_h;
+
+        let shader_sliders = [
+            ("Shader intensity", &mut new.shader.intensity, 3.0),
+            ("Scanlines", &mut new.shader.scanlines, 0.5),
+            ("Bloom", &mut new.shader.bloom, 2.0),
+            ("Curvature", &mut new.shader.curvature, 0.2),
+            ("Noise", &mut new.shader.noise, 0.3),
+        ];
+        for (label, value, max) in shader_sliders {
+            let text = format!("{:.2}", value);
+            d.gui_slider_bar(slider(y), label, &text, value, 0.0, max);
+            y += row_h;
+        }
+        d.gui_slider_bar(
+            slider(y),
+            "Trail half-life",
+            &format!("{:.2} s", new.fade_half_life_s),
+            &mut new.fade_half_life_s,
+            0.02,
+            2.0,
+        );
+        y += row_h;
+        d.gui_slider_bar(
+            slider(y),
+            "Max range",
+            &new.unit.format(new.max_range_cm),
+            &mut new.max_range_cm,
+            5.0,
+            values.max_range_cm.max(1000.0),
+        );
+        y += row_h;
+
+        d.gui_label(Rectangle::new(x, y, label_w, row_h - ui.px(8.0)), "Theme");
+        let names: Vec<&str> = ThemeKind::ALL.iter().map(|t| t.label()).collect();
+        let mut active = ThemeKind::ALL
+            .iter()
+            .position(|t| *t == new.theme)
+            .unwrap_or(0) as i32;
+        d.gui_combo_box(combo(y), &names.join(";"), &mut active);
+        new.theme = ThemeKind::ALL[active as usize % ThemeKind::ALL.len()];
+        y += row_h;
+
+        d.gui_label(Rectangle::new(x, y, label_w, row_h - ui.px(8.0)), "Units");
+        let names: Vec<&str> = Unit::ALL.iter().map(|u| u.label()).collect();
+        let mut active = Unit::ALL.iter().position(|u| *u == new.unit).unwrap_or(0) as i32;
+        d.gui_combo_box(combo(y), &names.join(";"), &mut active);
+        new.unit = Unit::ALL[active as usize % Unit::ALL.len()];
+        y += row_h;
+
+        for (max_cm, name) in new.alarm_max_cm.iter_mut().zip(zone_names) {
+            let upper = max_cm.max(values.max_range_cm);
+            let text = new.unit.format(*max_cm);
+            d.gui_slider_bar(
+                slider(y),
+                &format!("Alarm {}", name),
+                &text,
+                max_cm,
+                0.0,
+                upper,
+            );
+            y += row_h;
+        }
+
+        if new != *values {
+            self.dirty = true;
+            Some(new)
+        } else {
+            None
+        }
+    }
+}
+
+/// Rounds for the config file, a slider gives values like 1.4999999.
+fn rounded(x: f32) -> f64 {
+    (f64::from(x) * 100.0).round() / 100.0
+}
+
+/// Writes `values` into the config file at `path`. With a `profile` in use
+/// they go into its table instead.
+pub fn save(path: &str, values: &Values, profile: Option<&str>) -> Result<(), String> {
+    config::edit(path, |doc| {
+        let root = config::section(doc, profile);
+        root["theme"] = value(values.theme.label());
+        root["units"] = value(values.unit.label());
+        root["range"]["max_cm"] = value(rounded(values.max_range_cm));
+        root["visuals"]["preset"] = value(values.preset.label());
+        let shader = &values.shader;
+        root["visuals"]["shader_intensity"] = value(rounded(shader.intensity));
+        root["visuals"]["scanlines"] = value(rounded(shader.scanlines));
+        root["visuals"]["bloom"] = value(rounded(shader.bloom));
+        root["visuals"]["curvature"] = value(rounded(shader.curvature));
+        root["visuals"]["noise"] = value(rounded(shader.noise));
+        root["visuals"]["fade_half_life_s"] = value(rounded(values.fade_half_life_s));
+        // The profile's own zones if it has them, the shared ones otherwise
+        let zones = match root.get("alarm") {
+            Some(_) => root.get_mut("alarm"),
+            None => doc.get_mut("alarm"),
+        };
+        if let Some(zones) = zones.and_then(|a| a.as_array_of_tables_mut()) {
+            for (zone, max_cm) in zones.iter_mut().zip(&values.alarm_max_cm) {
+                zone["max_cm"] = value(rounded(*max_cm));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Values {
+        Values::from_config(&Config::default())
+    }
+
+    #[test]
+    fn reload_keeps_unsaved_edits_where_the_file_didnt_change() {
+        let old = values();
+        let mut current = old.clone();
+        current.max_range_cm = 250.0;
+        let mut new = old.clone();
+        new.unit = Unit::M;
+        let reloaded = current.reloaded(&old, &new);
+        assert_eq!(reloaded.max_range_cm, 250.0);
+        assert_eq!(reloaded.unit, Unit::M);
+    }
+
+    #[test]
+    fn reload_takes_the_file_where_both_changed() {
+        let old = values();
+        let mut current = old.clone();
+        current.theme = ThemeKind::Amber;
+        let mut new = old.clone();
+        new.theme = ThemeKind::Mono;
+        assert_eq!(current.reloaded(&old, &new).theme, ThemeKind::Mono);
+    }
+
+    #[test]
+    fn reload_only_changes_the_running_zones() {
+        // By position, a zone added to the file isn't running yet
+        let old = Values {
+            alarm_max_cm: vec![10.0, 20.0],
+            ..values()
+        };
+        let current = Values {
+            alarm_max_cm: vec![15.0, 20.0],
+            ..old.clone()
+        };
+        let new = Values {
+            alarm_max_cm: vec![10.0, 30.0, 40.0],
+            ..old.clone()
+        };
+        assert_eq!(current.reloaded(&old, &new).alarm_max_cm, [15.0, 30.0]);
+    }
+}
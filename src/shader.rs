@@ -0,0 +1,328 @@
+use std::fs;
+use std::time::SystemTime;
+
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::settings::VisualsConfig;
+
+/// Seconds between checks of the shader file for changes.
+const RELOAD_INTERVAL: f64 = 0.5;
+
+/// Uniforms of the post-processing shader the config and keyboard can change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    pub intensity: f32,
+    pub scanlines: f32,
+    pub bloom: f32,
+    pub curvature: f32,
+    pub noise: f32,
+}
+
+impl Params {
+    pub fn new(config: &VisualsConfig) -> Self {
+        Self {
+            intensity: config.shader_intensity,
+            scanlines: config.scanlines,
+            bloom: config.bloom,
+            curvature: config.curvature,
+            noise: config.noise,
+        }
+    }
+}
+
+/// Which of the [`Params`] `[` and `]` change, F6 picks the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Param {
+    #[default]
+    Intensity,
+    Scanlines,
+    Bloom,
+    Curvature,
+    Noise,
+}
+
+impl Param {
+    pub fn cycled(self) -> Self {
+        match self {
+            Param::Intensity => Param::Scanlines,
+            Param::Scanlines => Param::Bloom,
+            Param::Bloom => Param::Curvature,
+            Param::Curvature => Param::Noise,
+            Param::Noise => Param::Intensity,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Param::Intensity => "intensity",
+            Param::Scanlines => "scanlines",
+            Param::Bloom => "bloom",
+            Param::Curvature => "curvature",
+            Param::Noise => "noise",
+        }
+    }
+
+    /// One key press worth, and the range the value is kept in.
+    fn step(self) -> (f32, f32, f32) {
+        match self {
+            Param::Intensity => (0.1, 0.0, 3.0),
+            Param::Scanlines => (0.02, 0.0, 0.5),
+            Param::Bloom => (0.1, 0.0, 2.0),
+            Param::Curvature => (0.01, 0.0, 0.2),
+            Param::Noise => (0.01, 0.0, 0.3),
+        }
+    }
+
+    pub fn value(self, params: &mut Params) -> &mut f32 {
+        match self {
+            Param::Intensity => &mut params.intensity,
+            Param::Scanlines => &mut params.scanlines,
+            Param::Bloom => &mut params.bloom,
+            Param::Curvature => &mut params.curvature,
+            Param::Noise => &mut params.noise,
+        }
+    }
+
+    /// Moves this parameter of `params` `steps` key presses up or down.
+    pub fn adjust(self, params: &mut Params, steps: f32) {
+        let (step, min, max) = self.step();
+        let value = self.value(params);
+        *value = (*value + step * steps).clamp(min, max);
+    }
+}
+
+#[derive(Default)]
+struct Locations {
+    intensity: i32,
+    scanlines: i32,
+    bloom: i32,
+    curvature: i32,
+    noise: i32,
+    time: i32,
+    resolution: i32,
+}
+
+impl Locations {
+    fn new(shader: &Shader) -> Self {
+        Self {
+            intensity: shader.get_shader_location("intensity"),
+            scanlines: shader.get_shader_location("scanlines"),
+            bloom: shader.get_shader_location("bloom"),
+            curvature: shader.get_shader_location("curvature"),
+            noise: shader.get_shader_location("noise"),
+            time: shader.get_shader_location("time"),
+            resolution: shader.get_shader_location("resolution"),
+        }
+    }
+}
+
+/// The post-processing looks to pick from, each its own fragment shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Green tinted CRT with glow, the original look
+    #[default]
+    Phosphor,
+    /// Curved tube and scanlines in the theme's own colors
+    Crt,
+    /// Glow only, flat and sharp
+    Bloom,
+    /// Straight to the screen
+    None,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Phosphor, Preset::Crt, Preset::Bloom, Preset::None];
+
+    /// Next preset for the hotkey.
+    pub fn cycled(self) -> Self {
+        match self {
+            Preset::Phosphor => Preset::Crt,
+            Preset::Crt => Preset::Bloom,
+            Preset::Bloom => Preset::None,
+            Preset::None => Preset::Phosphor,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Preset::Phosphor => "phosphor",
+            Preset::Crt => "crt",
+            Preset::Bloom => "bloom",
+            Preset::None => "none",
+        }
+    }
+
+    /// File name in the shader directory and the copy built into the binary.
+    fn source(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Preset::Phosphor => Some((
+                "radar_phosphor.fs",
+                include_str!("../shaders/radar_phosphor.fs"),
+            )),
+            Preset::Crt => Some(("crt.fs", include_str!("../shaders/crt.fs"))),
+            Preset::Bloom => Some(("bloom.fs", include_str!("../shaders/bloom.fs"))),
+            Preset::None => None,
+        }
+    }
+}
+
+/// The post-processing shader of the current preset, read from the shader
+/// directory and loaded again whenever its file changes so it can be
+/// tweaked while the radar runs.
+pub struct PostShader {
+    preset: Preset,
+    /// `None` for [`Preset::None`]
+    shader: Option<Shader>,
+    locations: Locations,
+    params: Params,
+    dir: String,
+    /// Of the file when it was last read, `None` if it wasn't there
+    modified: Option<SystemTime>,
+    last_check: f64,
+}
+
+impl PostShader {
+    /// Loads `preset` from `dir`, or the embedded copy if `dir` is empty or
+    /// the file is missing or doesn't compile.
+    pub fn load(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        dir: &str,
+        preset: Preset,
+        params: Params,
+    ) -> Self {
+        let mut post = Self {
+            preset,
+            shader: None,
+            locations: Locations::default(),
+            params,
+            dir: dir.to_string(),
+            modified: None,
+            last_check: 0.0,
+        };
+        post.set_preset(rl, thread, preset);
+        post
+    }
+
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
+
+    /// Switches to another preset, reading its file fresh.
+    pub fn set_preset(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, preset: Preset) {
+        self.preset = preset;
+        self.modified = None;
+        self.shader = None;
+        let Some((_, embedded)) = preset.source() else {
+            return;
+        };
+        let path = self.path();
+        let file = path.as_deref().and_then(Self::read);
+        self.modified = file.as_ref().map(|(_, modified)| *modified);
+        let shader = file
+            .and_then(|(source, _)| Self::compile(rl, thread, path.as_deref()?, &source))
+            .unwrap_or_else(|| rl.load_shader_from_memory(thread, None, Some(embedded)));
+        self.locations = Locations::new(&shader);
+        self.shader = Some(shader);
+        self.set_params(self.params);
+    }
+
+    /// Shader to draw the scene through, `None` with [`Preset::None`].
+    pub fn shader_mut(&mut self) -> Option<&mut Shader> {
+        self.shader.as_mut()
+    }
+
+    fn path(&self) -> Option<String> {
+        let (file, _) = self.preset.source()?;
+        (!self.dir.is_empty()).then(|| format!("{}/{}", self.dir, file))
+    }
+
+    fn read(path: &str) -> Option<(String, SystemTime)> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        let source = fs::read_to_string(path).ok()?;
+        Some((source, modified))
+    }
+
+    /// raylib falls back to its default shader when compiling fails, which
+    /// has none of our uniforms.
+    fn compile(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        path: &str,
+        source: &str,
+    ) -> Option<Shader> {
+        let shader = rl.load_shader_from_memory(thread, None, Some(source));
+        if shader.get_shader_location("intensity") < 0 {
+            warn!(
+                "Shader {} doesn't compile or has no intensity uniform, not using it.",
+                path
+            );
+            return None;
+        }
+        Some(shader)
+    }
+
+    pub fn params(&self) -> Params {
+        self.params
+    }
+
+    pub fn set_params(&mut self, params: Params) {
+        self.params = params;
+        let Some(shader) = &mut self.shader else {
+            return;
+        };
+        let l = &self.locations;
+        let values = [
+            (l.intensity, params.intensity),
+            (l.scanlines, params.scanlines),
+            (l.bloom, params.bloom),
+            (l.curvature, params.curvature),
+            (l.noise, params.noise),
+        ];
+        for (loc, value) in values {
+            shader.set_shader_value(loc, value);
+        }
+    }
+
+    /// Per-frame uniforms for the animated noise and resolution dependent
+    /// scanlines. `time` is seconds since start.
+    pub fn set_frame(&mut self, time: f64, screen_w: f32, screen_h: f32) {
+        let Some(shader) = &mut self.shader else {
+            return;
+        };
+        shader.set_shader_value(self.locations.time, time as f32);
+        shader.set_shader_value(self.locations.resolution, Vector2::new(screen_w, screen_h));
+    }
+
+    /// Loads the file again if it changed since the last check. A broken
+    /// edit keeps the shader that was running.
+    pub fn reload_if_changed(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, time: f64) {
+        if time - self.last_check < RELOAD_INTERVAL {
+            return;
+        }
+        self.last_check = time;
+        let Some(path) = self.path() else {
+            return;
+        };
+        let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) else {
+            return;
+        };
+        if self.modified == Some(modified) {
+            return;
+        }
+        // Remembered even if it fails, so a broken file isn't retried every check
+        self.modified = Some(modified);
+        let Some((source, _)) = Self::read(&path) else {
+            return;
+        };
+        if let Some(shader) = Self::compile(rl, thread, &path, &source) {
+            info!("Reloaded shader {}", path);
+            self.locations = Locations::new(&shader);
+            self.shader = Some(shader);
+            self.set_params(self.params);
+        }
+    }
+}
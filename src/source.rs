@@ -0,0 +1,226 @@
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+use serialport::SerialPort;
+use tracing::warn;
+
+use crate::protocol::{Protocol, ProtocolStats, Raw};
+
+/// Bytes kept for the raw inspector between two frames.
+const MAX_RAW_BYTES: usize = 16 * 1024;
+
+/// One echo from the sensor: servo angle in degrees and echo distance in cm.
+/// Firmware that reports several echoes per angle produces one sample per echo,
+/// numbered from 0 in the order they were listed.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub angle: f32,
+    pub distance: f32,
+    pub echo: u8,
+    /// Return signal strength from 0 to 1, for sensors that report one
+    pub strength: Option<f32>,
+    /// Index of the `[[sensor]]` it came from, 0 with a single source
+    pub layer: u8,
+    /// Elevation in degrees on pan/tilt rigs, `angle` is the pan then
+    pub tilt: Option<f32>,
+    /// The firmware's `millis()` when it took the reading, if it sends one
+    pub sensor_ms: Option<u32>,
+}
+
+impl Sample {
+    pub fn new(angle: f32, distance: f32) -> Self {
+        Self {
+            angle,
+            distance,
+            echo: 0,
+            strength: None,
+            layer: 0,
+            tilt: None,
+            sensor_ms: None,
+        }
+    }
+}
+
+/// Connection state of a data source, shown in the corner of the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+impl LinkState {
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkState::Connecting => "CONNECTING",
+            LinkState::Connected => "CONNECTED",
+            LinkState::Disconnected => "DISCONNECTED",
+        }
+    }
+}
+
+/// Anything that can feed samples to the radar. `poll` is called once per frame
+/// and must never block for long.
+pub trait Source {
+    fn poll(&mut self) -> Vec<Sample>;
+    fn state(&self) -> LinkState;
+    /// Short human readable description, e.g. the port name or broker address.
+    fn describe(&self) -> String;
+    /// Decoder counters of the protocol this source is using.
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats);
+    /// Writes a command line to the device.
+    fn send(&mut self, _line: &str) -> Result<(), String> {
+        Err(format!("{} is read-only", self.describe()))
+    }
+    /// Text lines from the device that weren't samples.
+    fn messages(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Starts or stops keeping the raw traffic for `raw`, while the
+    /// inspector is open.
+    fn tap(&mut self, _on: bool) {}
+    /// What came over the wire since the last call.
+    fn raw(&mut self) -> Vec<Raw> {
+        Vec::new()
+    }
+}
+
+/// Stand-in until a port is picked in the window.
+pub struct NoSource;
+
+impl Source for NoSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        Vec::new()
+    }
+
+    fn state(&self) -> LinkState {
+        LinkState::Disconnected
+    }
+
+    fn describe(&self) -> String {
+        String::from("No port")
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        ("none", ProtocolStats::default())
+    }
+}
+
+// ===================== SERIAL =====================
+pub struct SerialSource {
+    port_name: String,
+    port: Option<Box<dyn SerialPort>>,
+    protocol: Box<dyn Protocol>,
+    tap: bool,
+    /// Bytes read while tapped, for binary protocols
+    raw: Vec<u8>,
+}
+
+impl SerialSource {
+    pub fn open(port_name: &str, baud_rate: u32, mut protocol: Box<dyn Protocol>) -> Self {
+        let mut port = serialport::new(port_name, baud_rate)
+            .timeout(Duration::from_millis(10))
+            .open()
+            .ok();
+
+        match &mut port {
+            None => warn!("Failed to open serial port {}.", port_name),
+            Some(port) => {
+                let start = protocol.start();
+                if !start.is_empty() {
+                    // DTR low spins the motor on RPLidar A1 adapters, sensors
+                    // that need a start command don't reset on it like an Arduino
+                    let _ = port.write_data_terminal_ready(false);
+                    if port.write_all(&start).is_err() {
+                        warn!("Failed to send the start command.");
+                    }
+                }
+            }
+        }
+
+        Self {
+            port_name: port_name.to_string(),
+            port,
+            protocol,
+            tap: false,
+            raw: Vec::new(),
+        }
+    }
+
+    /// Lets go of the port, e.g. to open it again with other settings.
+    pub fn close(&mut self) {
+        self.port = None;
+    }
+}
+
+impl Source for SerialSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        let Some(ref mut port) = self.port else {
+            return samples;
+        };
+
+        // Times out after 10 ms when nothing is waiting. Big enough for a
+        // lidar at 256000 baud between two frames.
+        let mut buf = [0u8; 4096];
+        match port.read(&mut buf) {
+            Ok(n) => {
+                if self.tap && !self.protocol.is_text() && self.raw.len() < MAX_RAW_BYTES {
+                    self.raw.extend_from_slice(&buf[..n]);
+                }
+                self.protocol.decode(&buf[..n], &mut samples);
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+            Err(e) => {
+                // Unplugged, the handle is useless from here on
+                warn!("Lost serial port {}: {}", self.port_name, e);
+                self.port = None;
+            }
+        }
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        if self.port.is_some() {
+            LinkState::Connected
+        } else {
+            LinkState::Disconnected
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.port_name.clone()
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        (self.protocol.name(), self.protocol.stats())
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        let Some(port) = &mut self.port else {
+            return Err("Serial port is not open".to_string());
+        };
+        port.write_all(format!("{}\n", line).as_bytes())
+            .map_err(|e| format!("Failed to send: {}", e))
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        self.protocol.take_messages()
+    }
+
+    fn tap(&mut self, on: bool) {
+        if self.tap != on {
+            self.tap = on;
+            self.raw.clear();
+            self.protocol.tap(on);
+        }
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        let mut raw = self.protocol.take_raw();
+        if !self.raw.is_empty() {
+            raw.push(Raw::Bytes(std::mem::take(&mut self.raw)));
+        }
+        raw
+    }
+}
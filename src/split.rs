@@ -0,0 +1,283 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::shader::{Params, PostShader, Preset};
+use crate::theme::Theme;
+use crate::ui::UiScale;
+use crate::view::{CoverageConfig, DisplayMode, Layout, Viewport};
+
+/// What a `[[view]]` shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewKind {
+    /// The main plot with everything on it, the whole window without one
+    #[default]
+    Radar,
+    /// Another plot of the live detections, e.g. of one sensor only
+    Ppi,
+    Scope,
+    Waterfall,
+    Sectors,
+}
+
+/// One `[[view]]` entry of the config file: a part of the window and what
+/// it shows, for a split screen like the radar next to the A-scope or two
+/// sensors side by side.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ViewConfig {
+    pub kind: ViewKind,
+    /// Left and top edge as fractions of the window
+    pub x: f32,
+    pub y: f32,
+    /// Size as fractions of the window
+    pub width: f32,
+    pub height: f32,
+    /// Only the detections of this `[[sensor]]`, counting from 1 (ppi)
+    pub layer: Option<u8>,
+    /// Post-processing look of this view, the radar keeps the one in
+    /// `[visuals]`
+    pub preset: Preset,
+}
+
+impl Default for ViewConfig {
+    fn default() -> Self {
+        Self {
+            kind: ViewKind::Radar,
+            x: 0.0,
+            y: 0.0,
+            width: 0.5,
+            height: 1.0,
+            layer: None,
+            preset: Preset::None,
+        }
+    }
+}
+
+impl ViewConfig {
+    /// Screen area in a window of `screen_w` by `screen_h`.
+    pub fn rect(&self, screen_w: f32, screen_h: f32) -> Rectangle {
+        // Leave room for the smallest view so the size clamps stay ordered
+        let x = self.x.clamp(0.0, 0.95);
+        let y = self.y.clamp(0.0, 0.95);
+        Rectangle::new(
+            x * screen_w,
+            y * screen_h,
+            self.width.clamp(0.05, 1.0 - x) * screen_w,
+            self.height.clamp(0.05, 1.0 - y) * screen_h,
+        )
+    }
+}
+
+/// A view besides the radar: drawn into a texture of its own and shown
+/// through its own shader, zoomed and panned on its own.
+pub struct SplitView {
+    pub config: ViewConfig,
+    pub texture: Option<RenderTexture2D>,
+    post: PostShader,
+    pub viewport: Viewport,
+}
+
+impl SplitView {
+    /// Its plot in texture coordinates, for a ppi view.
+    pub fn layout(&self, mode: DisplayMode, coverage: &CoverageConfig, rect: Rectangle) -> Layout {
+        let local = Rectangle::new(0.0, 0.0, rect.width, rect.height);
+        Layout::within(mode, coverage, local).viewed(&self.viewport)
+    }
+}
+
+/// The window split into the views of `[[view]]`, or just the radar.
+pub struct SplitLayout {
+    /// Where the radar goes, the whole window when `None`
+    radar: Option<ViewConfig>,
+    pub views: Vec<SplitView>,
+    /// View the reset view key resets, picked by left click, the radar
+    /// when `None`
+    pub focus: Option<usize>,
+}
+
+impl SplitLayout {
+    pub fn load(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        configs: &[ViewConfig],
+        shader_dir: &str,
+        params: Params,
+    ) -> Self {
+        let mut radar = None;
+        let mut views = Vec::new();
+        for config in configs {
+            match config.kind {
+                ViewKind::Radar if radar.is_some() => {
+                    warn!("Only one radar view, ignoring the others")
+                }
+                ViewKind::Radar => radar = Some(config.clone()),
+                _ => views.push(SplitView {
+                    config: config.clone(),
+                    texture: None,
+                    post: PostShader::load(rl, thread, shader_dir, config.preset, params),
+                    viewport: Viewport::default(),
+                }),
+            }
+        }
+        if !configs.is_empty() {
+            info!("Split screen with {} views", configs.len());
+        }
+        Self {
+            radar,
+            views,
+            focus: None,
+        }
+    }
+
+    /// Layout of the radar in a window of `screen_w` by `screen_h`.
+    pub fn radar_layout(
+        &self,
+        mode: DisplayMode,
+        coverage: &CoverageConfig,
+        screen_w: f32,
+        screen_h: f32,
+    ) -> Layout {
+        match &self.radar {
+            Some(view) => Layout::within(mode, coverage, view.rect(screen_w, screen_h)),
+            None => Layout::new(mode, coverage, screen_w, screen_h),
+        }
+    }
+
+    /// The view under the mouse, if any.
+    pub fn view_at(&self, mouse: Vector2, screen_w: f32, screen_h: f32) -> Option<usize> {
+        self.views.iter().rposition(|v| {
+            v.config
+                .rect(screen_w, screen_h)
+                .check_collision_point_rec(mouse)
+        })
+    }
+
+    /// Makes sure every view has a texture of its current size and its
+    /// shader is up to date. Call once per frame before drawing into them.
+    pub fn prepare(
+        &mut self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        time: f64,
+        screen_w: f32,
+        screen_h: f32,
+    ) {
+        for view in &mut self.views {
+            let rect = view.config.rect(screen_w, screen_h);
+            let (w, h) = (rect.width.max(1.0) as i32, rect.height.max(1.0) as i32);
+            let stale = view
+                .texture
+                .as_ref()
+                .is_none_or(|t| t.texture().width != w || t.texture().height != h);
+            if stale {
+                view.texture = rl.load_render_texture(thread, w as u32, h as u32).ok();
+                if view.texture.is_none() {
+                    warn!(
+                        "Failed to create a texture for the {:?} view",
+                        view.config.kind
+                    );
+                }
+            }
+            view.post.reload_if_changed(rl, thread, time);
+            view.post.set_frame(time, rect.width, rect.height);
+        }
+    }
+
+    /// Wheel zoom and middle button pan for the ppi view under the mouse,
+    /// a left click puts the focus on the view under the mouse or, with
+    /// none, back on the radar.
+    pub fn handle_mouse(
+        &mut self,
+        rl: &RaylibHandle,
+        hovered: Option<usize>,
+        mode: DisplayMode,
+        coverage: &CoverageConfig,
+    ) {
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.focus = hovered;
+        }
+        let Some(i) = hovered else {
+            return;
+        };
+        let (screen_w, screen_h) = (rl.get_screen_width() as f32, rl.get_screen_height() as f32);
+        let view = &mut self.views[i];
+        if view.config.kind != ViewKind::Ppi {
+            return;
+        }
+        let rect = view.config.rect(screen_w, screen_h);
+        let local = Rectangle::new(0.0, 0.0, rect.width, rect.height);
+        let base = Layout::within(mode, coverage, local);
+        let mouse = rl.get_mouse_position() - Vector2::new(rect.x, rect.y);
+        let wheel = rl.get_mouse_wheel_move();
+        if wheel != 0.0 {
+            view.viewport.zoom_at(&base, mouse, wheel);
+        }
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_MIDDLE) {
+            view.viewport.pan += rl.get_mouse_delta();
+        }
+    }
+
+    /// Resets the zoom of the focused view. Returns `false` when the radar
+    /// has the focus, its own view is reset then.
+    pub fn reset_focused(&mut self) -> bool {
+        match self.focus.and_then(|i| self.views.get_mut(i)) {
+            Some(view) => {
+                view.viewport = Viewport::default();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Puts every view on screen through its shader, framed, the focused
+    /// one highlighted.
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, ui: UiScale, theme: &Theme) {
+        let (screen_w, screen_h) = (d.get_screen_width() as f32, d.get_screen_height() as f32);
+        for (i, view) in self.views.iter_mut().enumerate() {
+            let Some(texture) = &view.texture else {
+                continue;
+            };
+            let rect = view.config.rect(screen_w, screen_h);
+            let source = Rectangle::new(
+                0.0,
+                0.0,
+                texture.texture().width as f32,
+                -texture.texture().height as f32,
+            );
+            match view.post.shader_mut() {
+                Some(shader) => {
+                    let mut s = d.begin_shader_mode(shader);
+                    s.draw_texture_pro(texture, source, rect, Vector2::zero(), 0.0, Color::WHITE);
+                }
+                None => {
+                    d.draw_texture_pro(texture, source, rect, Vector2::zero(), 0.0, Color::WHITE)
+                }
+            }
+            let (thick, color) = if self.focus == Some(i) {
+                (ui.px(2.0), theme.sweep)
+            } else {
+                (1.0, theme.outline)
+            };
+            d.draw_rectangle_lines_ex(rect, thick, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_past_the_right_edge_keeps_a_minimum_size() {
+        let config = ViewConfig {
+            x: 1.0,
+            ..ViewConfig::default()
+        };
+        let rect = config.rect(1000.0, 500.0);
+        assert_eq!(rect.x, 950.0);
+        assert!((rect.width - 50.0).abs() < 1e-3);
+        assert_eq!(rect.height, 500.0);
+    }
+}
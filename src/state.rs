@@ -0,0 +1,230 @@
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::mti::Background;
+use crate::theme::ThemeKind;
+use crate::units::Unit;
+use crate::view::DisplayMode;
+
+/// `[state]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StateConfig {
+    /// Pick up where the last run left off
+    pub enabled: bool,
+    pub path: String,
+    /// Also saved this often while running, so a power cut loses at most
+    /// this many seconds of changes
+    pub save_every_s: f32,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: String::from("radar_state.json"),
+            save_every_s: 30.0,
+        }
+    }
+}
+
+/// Window position and size, in screen pixels.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// What the config file said about the settings in [`SavedState`] when they
+/// were saved, to tell whether it was edited since.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileSettings {
+    pub theme: ThemeKind,
+    pub unit: Unit,
+    pub display: DisplayMode,
+    pub max_range_cm: f32,
+    pub auto_range: bool,
+}
+
+impl FileSettings {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            theme: config.theme,
+            unit: config.units,
+            display: config.display,
+            max_range_cm: config.range.max_cm,
+            auto_range: config.range.auto,
+        }
+    }
+}
+
+/// What the radar was set to when it last ran. Markers, masks and zones
+/// aren't in here, their editors write them to the config file right away.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedState {
+    /// Not set while fullscreen
+    pub window: Option<WindowState>,
+    /// Serial port picked in the window or given on the command line
+    pub port: Option<String>,
+    /// Its baud rate, "auto" for autobaud
+    pub baud: Option<String>,
+    pub theme: Option<ThemeKind>,
+    pub unit: Option<Unit>,
+    pub display: Option<DisplayMode>,
+    pub max_range_cm: Option<f32>,
+    pub auto_range: bool,
+    pub zoom: Option<f32>,
+    pub pan: [f32; 2],
+    /// Moving target background, so it isn't learned again
+    pub background: Option<Background>,
+    /// The config file's settings at the time
+    pub file: Option<FileSettings>,
+}
+
+impl SavedState {
+    /// What the last run left, nothing when it's turned off, the first run
+    /// or the file is unreadable.
+    pub fn load(config: &StateConfig) -> Self {
+        if !config.enabled {
+            return Self::default();
+        }
+        let text = match fs::read_to_string(&config.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Failed to read {}: {}", config.path, e);
+                return Self::default();
+            }
+        };
+        match serde_json::from_str(&text) {
+            Ok(state) => {
+                info!("Resuming from {}", config.path);
+                state
+            }
+            Err(e) => {
+                warn!("Ignoring the broken state file {}: {}", config.path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Leaves out the settings the config file changed since they were saved,
+    /// and all of them with a `--profile`: an edit to the file or a profile
+    /// asked for wins over what the last run was left at.
+    pub fn settled(mut self, config: &Config, profile: bool) -> Self {
+        let now = FileSettings::of(config);
+        let was = self.file.filter(|_| !profile);
+        if was.is_none_or(|was| was.theme != now.theme) {
+            self.theme = None;
+        }
+        if was.is_none_or(|was| was.unit != now.unit) {
+            self.unit = None;
+        }
+        if was.is_none_or(|was| was.display != now.display) {
+            self.display = None;
+        }
+        if was.is_none_or(|was| {
+            (was.max_range_cm, was.auto_range) != (now.max_range_cm, now.auto_range)
+        }) {
+            self.max_range_cm = None;
+        }
+        self
+    }
+
+    /// Written to a temporary file, flushed to disk and then renamed over
+    /// the old one, so pulling the plug mid-write leaves the last state.
+    fn save(&self, path: &str) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to encode the state: {}", e))?;
+        let tmp = format!("{}.tmp", path);
+        let write = |text: &str| -> std::io::Result<()> {
+            let mut file = File::create(&tmp)?;
+            file.write_all(text.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)
+        };
+        write(&text).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+/// Saves the state on a timer and on exit.
+pub struct StateSaver {
+    config: StateConfig,
+    last: Instant,
+}
+
+impl StateSaver {
+    pub fn new(config: &StateConfig, now: Instant) -> Self {
+        Self {
+            config: config.clone(),
+            last: now,
+        }
+    }
+
+    /// Whether the periodic save is due.
+    pub fn due(&self, now: Instant) -> bool {
+        let every = Duration::from_secs_f32(self.config.save_every_s.max(1.0));
+        self.config.enabled && now.saturating_duration_since(self.last) >= every
+    }
+
+    pub fn save(&mut self, state: &SavedState, now: Instant) {
+        if !self.config.enabled {
+            return;
+        }
+        self.last = now;
+        if let Err(e) = state.save(&self.config.path) {
+            warn!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(config: &Config) -> SavedState {
+        SavedState {
+            theme: Some(ThemeKind::Amber),
+            unit: Some(Unit::M),
+            max_range_cm: Some(250.0),
+            file: Some(FileSettings::of(config)),
+            ..SavedState::default()
+        }
+    }
+
+    #[test]
+    fn keeps_what_the_file_still_says() {
+        let config = Config::default();
+        let state = saved(&config).settled(&config, false);
+        assert_eq!(state.theme, Some(ThemeKind::Amber));
+        assert_eq!(state.max_range_cm, Some(250.0));
+    }
+
+    #[test]
+    fn an_edited_file_wins() {
+        let mut config = Config::default();
+        let state = saved(&config);
+        config.units = Unit::Ft;
+        let state = state.settled(&config, false);
+        assert_eq!(state.unit, None);
+        assert_eq!(state.theme, Some(ThemeKind::Amber));
+    }
+
+    #[test]
+    fn a_profile_wins() {
+        let config = Config::default();
+        let state = saved(&config).settled(&config, true);
+        assert_eq!(
+            (state.theme, state.unit, state.max_range_cm),
+            (None, None, None)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use crate::source::LinkState;
+
+/// Samples are counted over this long before the rate is updated.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// Weight of the newest frame in the smoothed latency.
+const LATENCY_SMOOTHING: f32 = 0.1;
+
+/// Numbers for the diagnostics overlay that tell a glitchy sensor from a
+/// slow renderer: how much data arrives, how often the link dropped and how
+/// long a reading takes to reach the screen.
+pub struct LinkStats {
+    /// Samples per second over the last full window
+    pub sample_rate: f32,
+    /// Times the source came back after being disconnected
+    pub reconnects: u32,
+    /// Smoothed estimate from the byte arriving to the frame showing it, ms
+    pub latency_ms: f32,
+    window_start: Instant,
+    window_samples: usize,
+    /// The link was up at some point
+    connected_before: bool,
+    /// ...and went down since
+    dropped: bool,
+    /// When this frame's samples were read, if there were any
+    polled_at: Option<Instant>,
+}
+
+impl LinkStats {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            sample_rate: 0.0,
+            reconnects: 0,
+            latency_ms: 0.0,
+            window_start: now,
+            window_samples: 0,
+            connected_before: false,
+            dropped: false,
+            polled_at: None,
+        }
+    }
+
+    /// Call right after polling the source.
+    pub fn polled(&mut self, samples: usize, state: LinkState, now: Instant) {
+        self.window_samples += samples;
+        let elapsed = now - self.window_start;
+        if elapsed >= RATE_WINDOW {
+            self.sample_rate = self.window_samples as f32 / elapsed.as_secs_f32();
+            self.window_start = now;
+            self.window_samples = 0;
+        }
+
+        if state == LinkState::Connected {
+            if self.dropped {
+                self.reconnects += 1;
+                self.dropped = false;
+            }
+            self.connected_before = true;
+        } else if self.connected_before {
+            self.dropped = true;
+        }
+        self.polled_at = (samples > 0).then_some(now);
+    }
+
+    /// Call once the frame is on screen. A reading waits for the next poll
+    /// for half a frame on average before the time measured here starts.
+    pub fn presented(&mut self, frame_time: f32, now: Instant) {
+        let Some(polled_at) = self.polled_at.take() else {
+            return;
+        };
+        let latency = (now - polled_at).as_secs_f32() * 1000.0 + frame_time * 500.0;
+        self.latency_ms = if self.latency_ms == 0.0 {
+            latency
+        } else {
+            self.latency_ms + (latency - self.latency_ms) * LATENCY_SMOOTHING
+        };
+    }
+
+    /// A different source is being read, its link starts over.
+    pub fn switched_source(&mut self) {
+        self.connected_before = false;
+        self.dropped = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_a_reconnect_once_per_drop() {
+        let now = Instant::now();
+        let mut stats = LinkStats::new(now);
+        for state in [
+            LinkState::Connecting,
+            LinkState::Connected,
+            LinkState::Disconnected,
+            LinkState::Connecting,
+            LinkState::Connected,
+            LinkState::Connected,
+        ] {
+            stats.polled(0, state, now);
+        }
+        assert_eq!(stats.reconnects, 1);
+    }
+
+    #[test]
+    fn a_new_source_isnt_a_reconnect() {
+        let now = Instant::now();
+        let mut stats = LinkStats::new(now);
+        stats.polled(0, LinkState::Connected, now);
+        stats.polled(0, LinkState::Disconnected, now);
+        stats.switched_source();
+        stats.polled(0, LinkState::Connected, now);
+        assert_eq!(stats.reconnects, 0);
+    }
+}
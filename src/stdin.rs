@@ -0,0 +1,99 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use tracing::{info, warn};
+
+use crate::protocol::{Protocol, ProtocolStats, Raw};
+use crate::source::{LinkState, Sample, Source};
+
+/// Reads the wire format from standard input, for data piped in from
+/// `socat`, `mosquitto_sub` or a script. Reading blocks, so it happens on its
+/// own thread and the chunks are handed over through a channel.
+pub struct StdinSource {
+    rx: Receiver<Vec<u8>>,
+    protocol: Box<dyn Protocol>,
+    /// The writing end was closed
+    ended: bool,
+}
+
+impl StdinSource {
+    pub fn open(protocol: Box<dyn Protocol>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdin = io::stdin().lock();
+            let mut buf = [0u8; 4096];
+            loop {
+                match stdin.read(&mut buf) {
+                    Ok(0) => return,
+                    Ok(n) => {
+                        if tx.send(buf[..n].to_vec()).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        warn!("Failed to read stdin: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+        Self {
+            rx,
+            protocol,
+            ended: false,
+        }
+    }
+}
+
+impl Source for StdinSource {
+    fn poll(&mut self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        loop {
+            match self.rx.try_recv() {
+                Ok(chunk) => self.protocol.decode(&chunk, &mut samples),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.ended {
+                        info!("End of stdin");
+                        // The last line may come without a line ending
+                        self.protocol.flush(&mut samples);
+                        self.ended = true;
+                    }
+                    break;
+                }
+            }
+        }
+        samples
+    }
+
+    fn state(&self) -> LinkState {
+        if self.ended {
+            LinkState::Disconnected
+        } else {
+            LinkState::Connected
+        }
+    }
+
+    fn describe(&self) -> String {
+        String::from("stdin")
+    }
+
+    fn protocol_stats(&self) -> (&'static str, ProtocolStats) {
+        (self.protocol.name(), self.protocol.stats())
+    }
+
+    fn messages(&mut self) -> Vec<String> {
+        self.protocol.take_messages()
+    }
+
+    /// Text lines only, binary input is best looked at with `xxd`
+    fn tap(&mut self, on: bool) {
+        self.protocol.tap(on);
+    }
+
+    fn raw(&mut self) -> Vec<Raw> {
+        self.protocol.take_raw()
+    }
+}
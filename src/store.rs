@@ -0,0 +1,420 @@
+use crate::alarm::AlarmZone;
+use crate::pipeline::Update;
+use crate::report::Recording;
+
+/// Used by `--db` and the subcommands when no path is given.
+pub const DEFAULT_DB_PATH: &str = "radar.db";
+
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    started REAL NOT NULL,
+    ended REAL NOT NULL,
+    source TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS samples (
+    session INTEGER NOT NULL REFERENCES sessions(id),
+    time REAL NOT NULL,
+    angle REAL NOT NULL,
+    distance REAL NOT NULL,
+    echo INTEGER NOT NULL,
+    layer INTEGER NOT NULL,
+    strength REAL
+);
+CREATE INDEX IF NOT EXISTS samples_session ON samples(session, time);
+CREATE TABLE IF NOT EXISTS alarms (
+    session INTEGER NOT NULL REFERENCES sessions(id),
+    time REAL NOT NULL,
+    zone TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    angle REAL NOT NULL,
+    distance REAL NOT NULL
+);
+";
+
+#[cfg(feature = "sqlite")]
+fn unix_now() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// Every accepted sample and alarm of a run in an SQLite database, under a
+/// session of its own. Queried and trimmed with the `sessions`, `export` and
+/// `trim` subcommands.
+pub struct SessionStore {
+    #[cfg(feature = "sqlite")]
+    conn: rusqlite::Connection,
+    #[cfg(feature = "sqlite")]
+    session: i64,
+}
+
+impl SessionStore {
+    /// Opens or creates the database at `path` and starts a new session for
+    /// `source`.
+    #[cfg(feature = "sqlite")]
+    pub fn open(path: &str, source: &str) -> Result<Self, String> {
+        let conn = rusqlite::Connection::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to set up {}: {}", path, e))?;
+        let now = unix_now();
+        conn.execute(
+            "INSERT INTO sessions (started, ended, source) VALUES (?1, ?1, ?2)",
+            rusqlite::params![now, source],
+        )
+        .map_err(|e| format!("Failed to start a session in {}: {}", path, e))?;
+        let session = conn.last_insert_rowid();
+        tracing::info!("Storing session {} in {}", session, path);
+        Ok(Self { conn, session })
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn open(_path: &str, _source: &str) -> Result<Self, String> {
+        Err("SQLite support is not compiled in, rebuild with --features sqlite".to_string())
+    }
+
+    /// Stores a batch in one transaction. `zones` names the alarms.
+    #[cfg(feature = "sqlite")]
+    pub fn write(&mut self, update: &Update, zones: &[AlarmZone]) {
+        if update.samples.is_empty() && update.alarms.is_empty() {
+            return;
+        }
+        let time = unix_now();
+        let session = self.session;
+        let result = (|| -> rusqlite::Result<()> {
+            let tx = self.conn.transaction()?;
+            {
+                let mut insert = tx.prepare_cached(
+                    "INSERT INTO samples (session, time, angle, distance, echo, layer, strength)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )?;
+                for s in &update.samples {
+                    insert.execute(rusqlite::params![
+                        session, time, s.angle, s.distance, s.echo, s.layer, s.strength
+                    ])?;
+                }
+                let mut insert = tx.prepare_cached(
+                    "INSERT INTO alarms (session, time, zone, severity, angle, distance)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for event in &update.alarms {
+                    let zone = &zones[event.zone];
+                    insert.execute(rusqlite::params![
+                        session,
+                        time,
+                        zone.name,
+                        zone.severity.label(),
+                        event.angle,
+                        event.distance
+                    ])?;
+                }
+            }
+            // Kept current, so a killed process still leaves an end time
+            tx.execute(
+                "UPDATE sessions SET ended = ?1 WHERE id = ?2",
+                rusqlite::params![time, session],
+            )?;
+            tx.commit()
+        })();
+        if let Err(e) = result {
+            tracing::warn!("Failed to store session {}: {}", session, e);
+        }
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn write(&mut self, _update: &Update, _zones: &[AlarmZone]) {}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "json" => Some(ExportFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// What the database subcommands were asked to do.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub enum Command {
+    /// `radar sessions`: one line per stored session
+    Sessions { db: String },
+    /// `radar export --session <ID>`: the samples, or alarms, of a session to stdout
+    Export {
+        db: String,
+        session: i64,
+        format: ExportFormat,
+        alarms: bool,
+    },
+    /// `radar trim --keep <N>`: drops all but the newest sessions
+    Trim { db: String, keep: usize },
+}
+
+/// Reads a stored session back for `radar report`, which runs without
+/// SQLite on a `--log` CSV as well.
+#[cfg(feature = "sqlite")]
+pub fn load_session(db: &str, session: i64) -> Result<Recording, String> {
+    use rusqlite::{Connection, OpenFlags};
+
+    use crate::report::AlarmRow;
+    use crate::source::Sample;
+
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", db, e))?;
+    let failed = |e: rusqlite::Error| format!("Failed to query {}: {}", db, e);
+    let source: String = conn
+        .query_row(
+            "SELECT source FROM sessions WHERE id = ?1",
+            [session],
+            |row| row.get(0),
+        )
+        .map_err(|_| format!("No session {} in {}, see radar sessions", session, db))?;
+    let mut query = conn
+        .prepare(
+            "SELECT time, angle, distance, echo, layer, strength FROM samples
+             WHERE session = ?1 ORDER BY rowid",
+        )
+        .map_err(failed)?;
+    let samples = query
+        .query_map([session], |row| {
+            Ok((
+                row.get::<_, f64>(0)?,
+                Sample {
+                    echo: row.get(3)?,
+                    layer: row.get(4)?,
+                    strength: row.get(5)?,
+                    ..Sample::new(row.get(1)?, row.get(2)?)
+                },
+            ))
+        })
+        .map_err(failed)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(failed)?;
+    let mut query = conn
+        .prepare(
+            "SELECT time, zone, severity, angle, distance FROM alarms
+             WHERE session = ?1 ORDER BY rowid",
+        )
+        .map_err(failed)?;
+    let alarms = query
+        .query_map([session], |row| {
+            Ok(AlarmRow {
+                time: row.get(0)?,
+                zone: row.get(1)?,
+                severity: row.get(2)?,
+                angle: row.get(3)?,
+                distance: row.get(4)?,
+            })
+        })
+        .map_err(failed)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(failed)?;
+    Ok(Recording {
+        title: format!("Radar report: session {} ({})", session, source),
+        samples,
+        alarms,
+    })
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_session(_db: &str, _session: i64) -> Result<Recording, String> {
+    Err("SQLite support is not compiled in, rebuild with --features sqlite or report on a --log CSV".to_string())
+}
+
+/// Runs a subcommand, its output goes to stdout.
+#[cfg(feature = "sqlite")]
+pub fn run(command: Command) -> Result<(), String> {
+    use rusqlite::{Connection, OpenFlags};
+    use std::io::{self, BufWriter, Write};
+
+    let db = match &command {
+        Command::Sessions { db } | Command::Export { db, .. } | Command::Trim { db, .. } => db,
+    };
+    // Only ever reads what a run stored, a typo shouldn't create a new file
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .map_err(|e| format!("Failed to open {}: {}", db, e))?;
+    let failed = |e: rusqlite::Error| format!("Failed to query {}: {}", db, e);
+    let mut out = BufWriter::new(io::stdout().lock());
+    let write_failed = |e: io::Error| format!("Failed to write: {}", e);
+
+    match command {
+        Command::Sessions { .. } => {
+            let mut query = conn
+                .prepare(
+                    "SELECT id, started, ended, source,
+                        (SELECT COUNT(*) FROM samples WHERE samples.session = sessions.id),
+                        (SELECT COUNT(*) FROM alarms WHERE alarms.session = sessions.id)
+                     FROM sessions ORDER BY id",
+                )
+                .map_err(failed)?;
+            let rows = query
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, f64>(1)?,
+                        row.get::<_, f64>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, i64>(4)?,
+                        row.get::<_, i64>(5)?,
+                    ))
+                })
+                .map_err(failed)?;
+            writeln!(out, "id,started,seconds,samples,alarms,source").map_err(write_failed)?;
+            for row in rows {
+                let (id, started, ended, source, samples, alarms) = row.map_err(failed)?;
+                writeln!(
+                    out,
+                    "{},{:.0},{:.0},{},{},{}",
+                    id,
+                    started,
+                    ended - started,
+                    samples,
+                    alarms,
+                    source
+                )
+                .map_err(write_failed)?;
+            }
+        }
+        Command::Export {
+            session,
+            format,
+            alarms: false,
+            ..
+        } => {
+            let mut query = conn
+                .prepare(
+                    "SELECT time, angle, distance, echo, layer, strength FROM samples
+                     WHERE session = ?1 ORDER BY rowid",
+                )
+                .map_err(failed)?;
+            let rows = query
+                .query_map([session], |row| {
+                    Ok((
+                        row.get::<_, f64>(0)?,
+                        row.get::<_, f32>(1)?,
+                        row.get::<_, f32>(2)?,
+                        row.get::<_, u8>(3)?,
+                        row.get::<_, u8>(4)?,
+                        row.get::<_, Option<f32>>(5)?,
+                    ))
+                })
+                .map_err(failed)?;
+            if format == ExportFormat::Csv {
+                writeln!(out, "time,angle,distance,echo,layer,strength").map_err(write_failed)?;
+            }
+            for row in rows {
+                let (time, angle, distance, echo, layer, strength) = row.map_err(failed)?;
+                match format {
+                    ExportFormat::Csv => writeln!(
+                        out,
+                        "{:.3},{:.1},{:.1},{},{},{}",
+                        time,
+                        angle,
+                        distance,
+                        echo,
+                        layer,
+                        strength.map(|s| format!("{:.3}", s)).unwrap_or_default()
+                    ),
+                    ExportFormat::Jsonl => writeln!(
+                        out,
+                        "{}",
+                        serde_json::json!({
+                            "time": time,
+                            "angle": angle,
+                            "distance": distance,
+                            "echo": echo,
+                            "layer": layer,
+                            "strength": strength,
+                        })
+                    ),
+                }
+                .map_err(write_failed)?;
+            }
+        }
+        Command::Export {
+            session, format, ..
+        } => {
+            let mut query = conn
+                .prepare(
+                    "SELECT time, zone, severity, angle, distance FROM alarms
+                     WHERE session = ?1 ORDER BY rowid",
+                )
+                .map_err(failed)?;
+            let rows = query
+                .query_map([session], |row| {
+                    Ok((
+                        row.get::<_, f64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, f32>(3)?,
+                        row.get::<_, f32>(4)?,
+                    ))
+                })
+                .map_err(failed)?;
+            if format == ExportFormat::Csv {
+                writeln!(out, "time,zone,severity,angle,distance").map_err(write_failed)?;
+            }
+            for row in rows {
+                let (time, zone, severity, angle, distance) = row.map_err(failed)?;
+                match format {
+                    ExportFormat::Csv => writeln!(
+                        out,
+                        "{:.3},{},{},{:.1},{:.1}",
+                        time, zone, severity, angle, distance
+                    ),
+                    ExportFormat::Jsonl => writeln!(
+                        out,
+                        "{}",
+                        serde_json::json!({
+                            "time": time,
+                            "zone": zone,
+                            "severity": severity,
+                            "angle": angle,
+                            "distance": distance,
+                        })
+                    ),
+                }
+                .map_err(write_failed)?;
+            }
+        }
+        Command::Trim { keep, .. } => {
+            let keep = i64::try_from(keep).unwrap_or(i64::MAX);
+            let cutoff = "(SELECT id FROM sessions ORDER BY id DESC LIMIT -1 OFFSET ?1)";
+            for table in ["samples", "alarms"] {
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE session IN {}", table, cutoff),
+                    [keep],
+                )
+                .map_err(failed)?;
+            }
+            let removed = conn
+                .execute(
+                    &format!("DELETE FROM sessions WHERE id IN {}", cutoff),
+                    [keep],
+                )
+                .map_err(failed)?;
+            // Hands the freed pages back to the file system
+            conn.execute_batch("VACUUM").map_err(failed)?;
+            writeln!(out, "Removed {} sessions", removed).map_err(write_failed)?;
+        }
+    }
+    out.flush().map_err(write_failed)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn run(_command: Command) -> Result<(), String> {
+    Err("SQLite support is not compiled in, rebuild with --features sqlite".to_string())
+}
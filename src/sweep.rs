@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+use crate::radar::angle_delta;
+
+/// Weight of the newest step in the smoothed rate.
+const RATE_SMOOTHING: f32 = 0.3;
+/// Longer than this without a new angle and the sweep counts as stopped.
+const STALL: Duration = Duration::from_secs(1);
+
+/// Moves the sweep line smoothly between the angles the servo reports, at
+/// the rate it has been turning. It never runs more than one of the usual
+/// steps ahead of the last reported angle, so a stopped servo only shows a
+/// small overshoot. Detections keep their reported angles.
+#[derive(Default)]
+pub struct SweepAnimator {
+    angle: f32,
+    since: Option<Instant>,
+    /// Degrees per second, always positive
+    rate: f32,
+    /// Typical angle between two readings
+    step: f32,
+}
+
+impl SweepAnimator {
+    /// Call once a frame with the latest reported angle.
+    pub fn update(&mut self, angle: f32, now: Instant) {
+        let Some(since) = self.since else {
+            self.angle = angle;
+            self.since = Some(now);
+            return;
+        };
+        let delta = angle_delta(self.angle, angle).abs();
+        if delta == 0.0 {
+            return;
+        }
+        let dt = now.saturating_duration_since(since);
+        if dt >= STALL {
+            self.rate = 0.0;
+            self.step = 0.0;
+        } else if !dt.is_zero() {
+            let rate = delta / dt.as_secs_f32();
+            if self.rate == 0.0 {
+                self.rate = rate;
+                self.step = delta;
+            } else {
+                self.rate += (rate - self.rate) * RATE_SMOOTHING;
+                self.step += (delta - self.step) * RATE_SMOOTHING;
+            }
+        }
+        self.angle = angle;
+        self.since = Some(now);
+    }
+
+    /// Where to draw the sweep line at `now`, towards smaller angles if
+    /// `decreasing`.
+    pub fn angle(&self, decreasing: bool, now: Instant) -> f32 {
+        let Some(since) = self.since else {
+            return self.angle;
+        };
+        let elapsed = now.saturating_duration_since(since).as_secs_f32();
+        let ahead = (self.rate * elapsed).min(self.step);
+        if decreasing {
+            self.angle - ahead
+        } else {
+            self.angle + ahead
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::protocol::{Protocol, ProtocolStats};
+use crate::source::Sample;
+
+const FRAME_HEADER: [u8; 2] = [0x59, 0x59];
+const FRAME_LEN: usize = 9;
+/// The sensor has no angle of its own, it looks straight ahead.
+const ANGLE_DEG: f32 = 90.0;
+/// Below this the distance is unreliable, per the datasheet.
+const MIN_STRENGTH: u16 = 100;
+/// Reported when the receiver is saturated, the distance is garbage then too.
+const SATURATED_STRENGTH: u16 = 65535;
+/// Strength the blip is drawn at full intensity from.
+const FULL_STRENGTH: f32 = 10_000.0;
+
+/// Benewake TFmini / TFmini Plus / TF-Luna standard 9 byte UART frames:
+///
+/// ```text
+/// 59 59 | distance: u16 LE, cm | strength: u16 LE | temperature or reserved: u16 | checksum: low byte of the sum of the first 8
+/// ```
+#[derive(Default)]
+pub struct TfminiProtocol {
+    buf: Vec<u8>,
+    stats: ProtocolStats,
+    resyncing: bool,
+}
+
+/// Strength on a log scale from the reliability threshold to `FULL_STRENGTH`.
+fn strength_fraction(strength: u16) -> f32 {
+    let min = f32::from(MIN_STRENGTH).ln();
+    ((f32::from(strength).ln() - min) / (FULL_STRENGTH.ln() - min)).clamp(0.0, 1.0)
+}
+
+impl Protocol for TfminiProtocol {
+    fn name(&self) -> &'static str {
+        "tfmini"
+    }
+
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<Sample>) {
+        self.buf.extend_from_slice(bytes);
+
+        let mut start = 0;
+        while self.buf.len() - start >= FRAME_LEN {
+            let frame = &self.buf[start..start + FRAME_LEN];
+            if frame[..2] != FRAME_HEADER {
+                if !self.resyncing {
+                    self.stats.malformed += 1;
+                    self.resyncing = true;
+                }
+                start += 1;
+                continue;
+            }
+
+            let checksum = frame[..8].iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+            if checksum != frame[8] {
+                self.stats.checksum_errors += 1;
+                self.resyncing = true;
+                start += 1;
+                continue;
+            }
+
+            let distance = u16::from_le_bytes([frame[2], frame[3]]);
+            let strength = u16::from_le_bytes([frame[4], frame[5]]);
+            let reliable = (MIN_STRENGTH..SATURATED_STRENGTH).contains(&strength);
+            out.push(Sample {
+                // Unreliable readings are "nothing heard", not a close object
+                distance: if reliable { f32::from(distance) } else { 0.0 },
+                strength: reliable.then(|| strength_fraction(strength)),
+                ..Sample::new(ANGLE_DEG, 0.0)
+            });
+            self.stats.frames += 1;
+            self.resyncing = false;
+            start += FRAME_LEN;
+        }
+        self.buf.drain(..start);
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.resyncing = false;
+    }
+
+    fn stats(&self) -> ProtocolStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(distance: u16, strength: u16) -> Vec<u8> {
+        let mut frame = FRAME_HEADER.to_vec();
+        frame.extend(distance.to_le_bytes());
+        frame.extend(strength.to_le_bytes());
+        frame.extend([0x00, 0x09]);
+        frame.push(frame.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)));
+        frame
+    }
+
+    fn decode(bytes: &[u8]) -> (Vec<Sample>, ProtocolStats) {
+        let mut protocol = TfminiProtocol::default();
+        let mut out = Vec::new();
+        protocol.decode(bytes, &mut out);
+        (out, protocol.stats())
+    }
+
+    #[test]
+    fn decodes_a_reading_straight_ahead() {
+        let (out, stats) = decode(&frame(250, 1000));
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].angle, out[0].distance), (ANGLE_DEG, 250.0));
+        assert_eq!(out[0].strength, Some(0.5));
+        assert_eq!(stats.frames, 1);
+    }
+
+    #[test]
+    fn weak_and_saturated_returns_are_nothing_heard() {
+        let mut bytes = frame(30, MIN_STRENGTH - 1);
+        bytes.extend(frame(30, SATURATED_STRENGTH));
+        let (out, _) = decode(&bytes);
+        assert_eq!(out.len(), 2);
+        assert!(
+            out.iter()
+                .all(|s| s.distance == 0.0 && s.strength.is_none())
+        );
+    }
+
+    #[test]
+    fn strength_is_clamped_to_the_scale() {
+        assert_eq!(strength_fraction(MIN_STRENGTH), 0.0);
+        assert_eq!(strength_fraction(60_000), 1.0);
+    }
+
+    #[test]
+    fn a_bad_checksum_is_counted_and_skipped() {
+        let mut bytes = frame(100, 500);
+        bytes[8] ^= 0x01;
+        bytes.extend(frame(120, 500));
+        let (out, stats) = decode(&bytes);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].distance, 120.0);
+        assert_eq!(stats.checksum_errors, 1);
+    }
+}
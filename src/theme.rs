@@ -0,0 +1,219 @@
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Built-in color presets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeKind {
+    /// Green phosphor, the original look
+    #[default]
+    Classic,
+    Amber,
+    /// White on black
+    Mono,
+    HighContrast,
+    /// Blue / orange, tells detections apart from the grid without red-green
+    Colorblind,
+}
+
+impl ThemeKind {
+    pub const ALL: [ThemeKind; 5] = [
+        ThemeKind::Classic,
+        ThemeKind::Amber,
+        ThemeKind::Mono,
+        ThemeKind::HighContrast,
+        ThemeKind::Colorblind,
+    ];
+
+    /// Next preset for the hotkey.
+    pub fn cycled(self) -> Self {
+        match self {
+            ThemeKind::Classic => ThemeKind::Amber,
+            ThemeKind::Amber => ThemeKind::Mono,
+            ThemeKind::Mono => ThemeKind::HighContrast,
+            ThemeKind::HighContrast => ThemeKind::Colorblind,
+            ThemeKind::Colorblind => ThemeKind::Classic,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeKind::Classic => "classic",
+            ThemeKind::Amber => "amber",
+            ThemeKind::Mono => "mono",
+            ThemeKind::HighContrast => "high-contrast",
+            ThemeKind::Colorblind => "colorblind",
+        }
+    }
+
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeKind::Classic => Theme {
+                background: Color::new(10, 15, 10, 255),
+                fade: Color::new(0, 10, 0, 18),
+                outline: Color::new(30, 120, 50, 255),
+                detection: Color::new(255, 60, 60, 255),
+                sweep: Color::new(150, 255, 170, 255),
+                warning: Color::new(230, 180, 40, 255),
+                track: Color::new(220, 255, 120, 255),
+                gradient: [
+                    Color::new(255, 60, 60, 255),
+                    Color::new(255, 220, 60, 255),
+                    Color::new(60, 255, 120, 255),
+                ],
+            },
+            ThemeKind::Amber => Theme {
+                background: Color::new(15, 10, 5, 255),
+                fade: Color::new(10, 5, 0, 18),
+                outline: Color::new(140, 90, 20, 255),
+                detection: Color::new(255, 80, 40, 255),
+                sweep: Color::new(255, 200, 90, 255),
+                warning: Color::new(255, 240, 150, 255),
+                track: Color::new(255, 230, 180, 255),
+                gradient: [
+                    Color::new(255, 60, 30, 255),
+                    Color::new(255, 140, 40, 255),
+                    Color::new(255, 210, 120, 255),
+                ],
+            },
+            ThemeKind::Mono => Theme {
+                background: Color::new(0, 0, 0, 255),
+                fade: Color::new(0, 0, 0, 18),
+                outline: Color::new(110, 110, 110, 255),
+                detection: Color::new(255, 255, 255, 255),
+                sweep: Color::new(200, 200, 200, 255),
+                warning: Color::new(255, 255, 255, 255),
+                track: Color::new(180, 180, 180, 255),
+                gradient: [
+                    Color::new(255, 255, 255, 255),
+                    Color::new(190, 190, 190, 255),
+                    Color::new(120, 120, 120, 255),
+                ],
+            },
+            ThemeKind::HighContrast => Theme {
+                background: Color::new(0, 0, 0, 255),
+                fade: Color::new(0, 0, 0, 30),
+                outline: Color::new(255, 255, 255, 255),
+                detection: Color::new(255, 0, 255, 255),
+                sweep: Color::new(0, 255, 255, 255),
+                warning: Color::new(255, 255, 0, 255),
+                track: Color::new(255, 255, 0, 255),
+                gradient: [
+                    Color::new(255, 0, 255, 255),
+                    Color::new(255, 255, 0, 255),
+                    Color::new(0, 255, 255, 255),
+                ],
+            },
+            ThemeKind::Colorblind => Theme {
+                background: Color::new(5, 10, 20, 255),
+                fade: Color::new(0, 5, 15, 18),
+                outline: Color::new(86, 180, 233, 255),
+                detection: Color::new(230, 159, 0, 255),
+                sweep: Color::new(180, 220, 255, 255),
+                warning: Color::new(240, 228, 66, 255),
+                track: Color::new(204, 121, 167, 255),
+                gradient: [
+                    Color::new(213, 94, 0, 255),
+                    Color::new(230, 159, 0, 255),
+                    Color::new(0, 114, 178, 255),
+                ],
+            },
+        }
+    }
+}
+
+/// Colors used by the renderer.
+#[derive(Clone, Copy, Debug)]
+pub struct Theme {
+    pub background: Color,
+    /// Drawn over the persistence layer every frame, the alpha is how much
+    /// of the sweep trail fades per frame at 60 FPS
+    pub fade: Color,
+    /// Rings, markers and regular UI text
+    pub outline: Color,
+    pub detection: Color,
+    pub sweep: Color,
+    /// UI text that needs attention
+    pub warning: Color,
+    pub track: Color,
+    /// Detection colors from near to far
+    pub gradient: [Color; 3],
+}
+
+/// A color written as `"#rrggbb"` in the config file.
+#[derive(Clone, Copy, Debug)]
+pub struct HexColor(pub Color);
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let hex = text.trim_start_matches('#');
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid color: {}", text)))?;
+        Ok(HexColor(Color::new(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+            255,
+        )))
+    }
+}
+
+/// `[gradient]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GradientConfig {
+    /// Color detections by distance, otherwise they all use the theme's
+    /// detection color
+    pub enabled: bool,
+    /// Evenly spaced stops from near to far, empty uses the theme's gradient
+    pub stops: Vec<HexColor>,
+}
+
+impl Default for GradientConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stops: Vec::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// Color of a detection at `fraction` of the max range, 0 is right at the sensor.
+    pub fn detection_color(&self, gradient: &GradientConfig, fraction: f32) -> Color {
+        if !gradient.enabled {
+            self.detection
+        } else if gradient.stops.is_empty() {
+            sample(self.gradient.len(), |i| self.gradient[i], fraction)
+        } else {
+            sample(gradient.stops.len(), |i| gradient.stops[i].0, fraction)
+        }
+    }
+
+    /// Seconds for the sweep trail to fade by half with this theme's `fade`.
+    pub fn fade_half_life(&self) -> f32 {
+        let per_frame = 1.0 - f32::from(self.fade.a) / 255.0;
+        0.5f32.ln() / per_frame.ln() / 60.0
+    }
+
+    /// Fade for a frame that took `dt` seconds, so the trail halves every
+    /// `half_life` seconds whatever the frame rate. Never below one step of
+    /// alpha, which would leave the trail on screen forever.
+    pub fn fade_color(&self, dt: f32, half_life: f32) -> Color {
+        let alpha = 1.0 - 0.5f32.powf(dt / half_life.max(0.01));
+        self.fade.alpha(alpha.clamp(1.0 / 255.0, 1.0))
+    }
+}
+
+/// Linear interpolation between `len` evenly spaced stops.
+fn sample(len: usize, stop: impl Fn(usize) -> Color, fraction: f32) -> Color {
+    if len < 2 {
+        return stop(0);
+    }
+    let pos = fraction.clamp(0.0, 1.0) * (len - 1) as f32;
+    let i = (pos.floor() as usize).min(len - 2);
+    stop(i).lerp(stop(i + 1), pos - i as f32)
+}
@@ -0,0 +1,294 @@
+use std::time::Instant;
+
+use crate::source::Sample;
+
+/// A gap this many times the usual spacing between samples means frames
+/// went missing.
+const GAP_FACTOR: f32 = 2.5;
+/// Weight of the newest interval in the usual spacing.
+const INTERVAL_SMOOTHING: f32 = 0.05;
+/// How fast the best transport delay seen is allowed to grow again, ms per
+/// second. Covers the drift between the sensor's crystal and ours (100 ppm
+/// is 0.1 ms/s) without losing track of the floor.
+const OFFSET_CREEP_MS_PER_S: f64 = 0.2;
+/// Weight of the newest frame in the smoothed latency.
+const LATENCY_SMOOTHING: f32 = 0.1;
+/// How long timestamped samples are held back, so one that arrives after a
+/// newer one still goes out in order.
+const JITTER_MS: f64 = 75.0;
+/// A sample this much older than the newest one is from a clock that
+/// started over, like an Arduino resetting when its port is opened.
+const RESYNC_MS: i32 = 1000;
+
+/// Works with the `millis()` timestamps firmware can send along with its
+/// samples: puts them back in order in a short jitter buffer, counts the
+/// ones that went missing or came too late, follows the firmware through a
+/// reset, measures the sweep rate on the sensor's own clock and how long a
+/// reading takes from the sensor to the screen.
+///
+/// The two clocks are never synchronized, so the latency is measured against
+/// the fastest a sample ever got here: it's how much later than that the
+/// reading shows up, which is what changes when a link or the renderer gets
+/// slow.
+pub struct SensorClock {
+    start: Instant,
+    /// Sensor time of the newest sample passed on
+    last_ms: Option<u32>,
+    /// Usual spacing between samples, ms
+    interval_ms: f32,
+    /// Smallest host minus sensor time seen, ms
+    offset_ms: Option<f64>,
+    offset_at: f64,
+    /// Sensor time of the newest sample this frame, for `presented`
+    newest: Option<u32>,
+    /// Held back samples and when they arrived, host ms
+    pending: Vec<(Sample, f64)>,
+    sweep_started_ms: Option<u32>,
+    /// Frames missing from the sequence
+    pub dropped: u64,
+    /// Samples that arrived after newer ones had already been passed on
+    pub late: u64,
+    /// Times the sensor's clock started over
+    pub resyncs: u64,
+    /// Sweeps per second on the sensor's clock
+    pub sweep_rate_hz: f32,
+    /// Smoothed sensor to screen delay beyond the fastest one seen, ms
+    pub latency_ms: f32,
+}
+
+/// Milliseconds from `from` to `ms`. Differences wrap with millis(), which
+/// overflows after 49 days.
+fn since(from: u32, ms: u32) -> i32 {
+    ms.wrapping_sub(from) as i32
+}
+
+impl SensorClock {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            start: now,
+            last_ms: None,
+            interval_ms: 0.0,
+            offset_ms: None,
+            offset_at: 0.0,
+            newest: None,
+            pending: Vec::new(),
+            sweep_started_ms: None,
+            dropped: 0,
+            late: 0,
+            resyncs: 0,
+            sweep_rate_hz: 0.0,
+            latency_ms: 0.0,
+        }
+    }
+
+    /// The firmware sends timestamps.
+    pub fn is_active(&self) -> bool {
+        self.last_ms.is_some()
+    }
+
+    fn host_ms(&self, now: Instant) -> f64 {
+        now.saturating_duration_since(self.start).as_secs_f64() * 1000.0
+    }
+
+    /// Passes samples on in sensor time order, once they've waited out the
+    /// jitter buffer, and drops what's older than samples already passed on.
+    /// Samples without timestamps go straight through. Call right after
+    /// polling, every frame, even when nothing came in.
+    pub fn arrange(&mut self, samples: Vec<Sample>, now: Instant) -> Vec<Sample> {
+        let host = self.host_ms(now);
+        let mut kept = Vec::with_capacity(samples.len());
+        for sample in samples {
+            let Some(ms) = sample.sensor_ms else {
+                kept.push(sample);
+                continue;
+            };
+            let newest = self
+                .pending
+                .iter()
+                .filter_map(|(s, _)| s.sensor_ms)
+                .max_by_key(|&p| since(ms, p))
+                .or(self.last_ms);
+            if let Some(newest) = newest
+                && since(newest, ms) < -RESYNC_MS
+            {
+                // The firmware restarted: what's held goes out on the old
+                // clock, then everything starts over on the new one
+                self.release(f64::INFINITY, &mut kept);
+                self.resync();
+            } else if let Some(last) = self.last_ms
+                && since(last, ms) < 0
+            {
+                self.late += 1;
+                continue;
+            }
+            self.track_offset(host - f64::from(ms), host);
+            self.pending.push((sample, host));
+        }
+        self.release(host - JITTER_MS, &mut kept);
+        kept
+    }
+
+    /// Moves the held samples up to the newest one that arrived before
+    /// `due` over to `out`, oldest first.
+    fn release(&mut self, due: f64, out: &mut Vec<Sample>) {
+        let base = self
+            .last_ms
+            .or_else(|| self.pending.first().and_then(|(s, _)| s.sensor_ms))
+            .unwrap_or_default();
+        let key = |s: &Sample| s.sensor_ms.map_or(0, |ms| since(base, ms));
+        // Everything older than a sample that's due goes with it
+        let Some(cutoff) = self
+            .pending
+            .iter()
+            .filter(|(_, arrived)| *arrived <= due)
+            .map(|(s, _)| key(s))
+            .max()
+        else {
+            return;
+        };
+        self.pending.sort_by_key(|(s, _)| key(s));
+        let split = self.pending.partition_point(|(s, _)| key(s) <= cutoff);
+        let released: Vec<_> = self.pending.drain(..split).collect();
+        for (sample, _) in released {
+            let ms = sample.sensor_ms.unwrap_or_default();
+            if let Some(last) = self.last_ms {
+                self.track_interval(since(last, ms) as f32);
+            }
+            self.last_ms = Some(ms);
+            self.newest = Some(ms);
+            out.push(sample);
+        }
+    }
+
+    /// Forgets everything measured on the sensor's old clock.
+    fn resync(&mut self) {
+        self.last_ms = None;
+        self.interval_ms = 0.0;
+        self.offset_ms = None;
+        self.sweep_started_ms = None;
+        self.resyncs += 1;
+    }
+
+    fn track_interval(&mut self, dt: f32) {
+        // Several echoes of one reading share a timestamp
+        if dt <= 0.0 {
+            return;
+        }
+        if self.interval_ms > 0.0 && dt > self.interval_ms * GAP_FACTOR {
+            self.dropped += (dt / self.interval_ms).round() as u64 - 1;
+            return;
+        }
+        self.interval_ms = if self.interval_ms == 0.0 {
+            dt
+        } else {
+            self.interval_ms + (dt - self.interval_ms) * INTERVAL_SMOOTHING
+        };
+    }
+
+    fn track_offset(&mut self, offset: f64, host: f64) {
+        let crept = self
+            .offset_ms
+            .map(|o| o + (host - self.offset_at) / 1000.0 * OFFSET_CREEP_MS_PER_S);
+        self.offset_ms = Some(crept.map_or(offset, |o| o.min(offset)));
+        self.offset_at = host;
+    }
+
+    /// Call when a sweep is done.
+    pub fn sweep_done(&mut self) {
+        let Some(now_ms) = self.last_ms else {
+            return;
+        };
+        if let Some(started) = self.sweep_started_ms {
+            let period = now_ms.wrapping_sub(started);
+            if period > 0 {
+                self.sweep_rate_hz = 1000.0 / period as f32;
+            }
+        }
+        self.sweep_started_ms = Some(now_ms);
+    }
+
+    /// Call once the frame is on screen.
+    pub fn presented(&mut self, now: Instant) {
+        let (Some(ms), Some(offset)) = (self.newest.take(), self.offset_ms) else {
+            return;
+        };
+        let latency = (self.host_ms(now) - (f64::from(ms) + offset)) as f32;
+        self.latency_ms = if self.latency_ms == 0.0 {
+            latency
+        } else {
+            self.latency_ms + (latency - self.latency_ms) * LATENCY_SMOOTHING
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(ms: u32) -> Sample {
+        Sample {
+            sensor_ms: Some(ms),
+            ..Sample::new(90.0, 100.0)
+        }
+    }
+
+    fn times(samples: &[Sample]) -> Vec<u32> {
+        samples.iter().filter_map(|s| s.sensor_ms).collect()
+    }
+
+    #[test]
+    fn reorders_across_batches() {
+        let start = Instant::now();
+        let mut clock = SensorClock::new(start);
+        assert!(clock.arrange(vec![at(10), at(30)], start).is_empty());
+        let later = start + Duration::from_millis(20);
+        assert!(clock.arrange(vec![at(20)], later).is_empty());
+        let out = clock.arrange(Vec::new(), start + Duration::from_millis(200));
+        assert_eq!(times(&out), [10, 20, 30]);
+        assert_eq!(clock.late, 0);
+    }
+
+    #[test]
+    fn drops_what_comes_after_newer_ones_went_out() {
+        let start = Instant::now();
+        let mut clock = SensorClock::new(start);
+        clock.arrange(vec![at(100)], start);
+        let out = clock.arrange(Vec::new(), start + Duration::from_millis(100));
+        assert_eq!(times(&out), [100]);
+        let out = clock.arrange(vec![at(50)], start + Duration::from_millis(200));
+        assert!(out.is_empty());
+        assert_eq!(clock.late, 1);
+    }
+
+    #[test]
+    fn follows_a_firmware_reset() {
+        let start = Instant::now();
+        let mut clock = SensorClock::new(start);
+        clock.arrange(vec![at(500_000)], start);
+        clock.arrange(Vec::new(), start + Duration::from_millis(100));
+        let reset = start + Duration::from_millis(200);
+        clock.arrange(vec![at(5), at(15)], reset);
+        let out = clock.arrange(Vec::new(), reset + Duration::from_millis(100));
+        assert_eq!(times(&out), [5, 15]);
+        assert_eq!((clock.late, clock.resyncs), (0, 1));
+    }
+
+    #[test]
+    fn passes_untimed_samples_straight_through() {
+        let start = Instant::now();
+        let mut clock = SensorClock::new(start);
+        let out = clock.arrange(vec![Sample::new(1.0, 2.0)], start);
+        assert_eq!(out.len(), 1);
+        assert!(!clock.is_active());
+    }
+
+    #[test]
+    fn counts_missing_frames() {
+        let start = Instant::now();
+        let mut clock = SensorClock::new(start);
+        clock.arrange(vec![at(0), at(10), at(20), at(30), at(70)], start);
+        clock.arrange(Vec::new(), start + Duration::from_millis(100));
+        assert_eq!(clock.dropped, 3);
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::radar::Detection;
+
+/// Detections further than this from a track's position start a new track.
+const GATE_CM: f32 = 6.0;
+/// A track is only shown once it has been seen this many times.
+const CONFIRM_HITS: u32 = 3;
+/// Tracks not updated for this long are dropped.
+const TRACK_TIMEOUT: Duration = Duration::from_secs(3);
+/// Velocity is measured over at least this much time, consecutive hits
+/// within one pass of the sweep are far too close together to be useful.
+const MIN_VELOCITY_DT: f32 = 0.25;
+/// Weight of new measurements in the position / velocity averages.
+const POSITION_SMOOTHING: f32 = 0.5;
+const VELOCITY_SMOOTHING: f32 = 0.3;
+/// The trail only reaches back this far.
+const TRAIL_AGE: Duration = Duration::from_secs(5);
+/// A new trail point once the track moved this far, cm.
+const TRAIL_STEP_CM: f32 = 1.0;
+
+/// An object followed across sweeps.
+#[derive(Clone, Debug)]
+pub struct Track {
+    pub id: u32,
+    pub angle: f32,
+    pub distance: f32,
+    /// Change of distance in cm/s, negative when approaching
+    pub radial_velocity: f32,
+    /// Velocity along x and y in cm/s, see `position`
+    pub velocity: (f32, f32),
+    pub hits: u32,
+    pub last_seen: Instant,
+    /// Distance, position and time the velocity was last measured against
+    velocity_ref: (f32, (f32, f32), Instant),
+    /// Recent positions and when the track was there, oldest first
+    trail: VecDeque<((f32, f32), Instant)>,
+}
+
+impl Track {
+    pub fn confirmed(&self) -> bool {
+        self.hits >= CONFIRM_HITS
+    }
+
+    /// Position in cm with the sensor at the origin, x to the right, y forward.
+    pub fn position(&self) -> (f32, f32) {
+        to_cartesian(self.angle, self.distance)
+    }
+
+    /// Speed over the ground in cm/s, whichever way it goes.
+    pub fn speed(&self) -> f32 {
+        self.velocity.0.hypot(self.velocity.1)
+    }
+
+    /// Angle and distance of where the track has been lately, oldest first.
+    pub fn trail(&self) -> impl Iterator<Item = (f32, f32, Instant)> + '_ {
+        self.trail.iter().map(|&((x, y), time)| {
+            let (angle, distance) = to_polar(x, y);
+            (angle, distance, time)
+        })
+    }
+
+    /// Angle and distance the track gets to in `seconds` if it keeps going
+    /// the way it does.
+    pub fn predicted(&self, seconds: f32) -> (f32, f32) {
+        let (x, y) = self.position();
+        to_polar(x + self.velocity.0 * seconds, y + self.velocity.1 * seconds)
+    }
+
+    /// A confirmed track at `position` going `velocity`, cm and cm/s.
+    #[cfg(test)]
+    pub(crate) fn moving(
+        id: u32,
+        position: (f32, f32),
+        velocity: (f32, f32),
+        now: Instant,
+    ) -> Self {
+        let (angle, distance) = to_polar(position.0, position.1);
+        Self {
+            id,
+            angle,
+            distance,
+            radial_velocity: 0.0,
+            velocity,
+            hits: CONFIRM_HITS,
+            last_seen: now,
+            velocity_ref: (distance, position, now),
+            trail: VecDeque::new(),
+        }
+    }
+
+    fn extend_trail(&mut self, now: Instant) {
+        let position = self.position();
+        let moved = self
+            .trail
+            .back()
+            .is_none_or(|&((x, y), _)| (position.0 - x).hypot(position.1 - y) >= TRAIL_STEP_CM);
+        if moved {
+            self.trail.push_back((position, now));
+        }
+        while let Some(&(_, time)) = self.trail.front()
+            && now.saturating_duration_since(time) > TRAIL_AGE
+        {
+            self.trail.pop_front();
+        }
+    }
+}
+
+fn to_cartesian(angle: f32, distance: f32) -> (f32, f32) {
+    let rad = angle.to_radians();
+    (distance * rad.cos(), distance * rad.sin())
+}
+
+fn to_polar(x: f32, y: f32) -> (f32, f32) {
+    (y.atan2(x).to_degrees(), x.hypot(y))
+}
+
+/// Nearest-neighbour tracker: every detection is assigned to the closest track
+/// inside the gate, or opens a new one.
+#[derive(Clone, Default)]
+pub struct Tracker {
+    tracks: Vec<Track>,
+    next_id: u32,
+}
+
+impl Tracker {
+    pub fn update(&mut self, det: &Detection) {
+        let (x, y) = to_cartesian(det.angle, det.distance);
+
+        let nearest = self
+            .tracks
+            .iter_mut()
+            .map(|t| {
+                let (tx, ty) = t.position();
+                ((tx - x).hypot(ty - y), t)
+            })
+            .filter(|(dist, _)| *dist <= GATE_CM)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        match nearest {
+            Some((_, track)) => {
+                let (tx, ty) = track.position();
+                let (angle, distance) = to_polar(
+                    tx + (x - tx) * POSITION_SMOOTHING,
+                    ty + (y - ty) * POSITION_SMOOTHING,
+                );
+                track.angle = angle;
+                track.distance = distance;
+                track.hits += 1;
+                track.last_seen = det.time;
+                track.extend_trail(det.time);
+
+                let (ref_distance, (ref_x, ref_y), ref_time) = track.velocity_ref;
+                let dt = det.time.saturating_duration_since(ref_time).as_secs_f32();
+                if dt >= MIN_VELOCITY_DT {
+                    let v = (det.distance - ref_distance) / dt;
+                    track.radial_velocity += (v - track.radial_velocity) * VELOCITY_SMOOTHING;
+                    let (vx, vy) = ((x - ref_x) / dt, (y - ref_y) / dt);
+                    track.velocity.0 += (vx - track.velocity.0) * VELOCITY_SMOOTHING;
+                    track.velocity.1 += (vy - track.velocity.1) * VELOCITY_SMOOTHING;
+                    track.velocity_ref = (det.distance, (x, y), det.time);
+                }
+            }
+            None => {
+                self.next_id += 1;
+                let mut track = Track {
+                    id: self.next_id,
+                    angle: det.angle,
+                    distance: det.distance,
+                    radial_velocity: 0.0,
+                    velocity: (0.0, 0.0),
+                    hits: 1,
+                    last_seen: det.time,
+                    velocity_ref: (det.distance, (x, y), det.time),
+                    trail: VecDeque::new(),
+                };
+                track.extend_trail(det.time);
+                self.tracks.push(track);
+            }
+        }
+    }
+
+    /// Drops tracks that haven't been seen for a while.
+    pub fn prune(&mut self, now: Instant) {
+        self.tracks
+            .retain(|t| now.saturating_duration_since(t.last_seen) < TRACK_TIMEOUT);
+    }
+
+    /// Tracks seen often enough to be trusted.
+    pub fn confirmed(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|t| t.confirmed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn det(angle: f32, distance: f32, time: Instant) -> Detection {
+        Detection {
+            angle,
+            distance,
+            bearing: angle,
+            time,
+            multi_echo: false,
+            strength: None,
+            layer: 0,
+        }
+    }
+
+    #[test]
+    fn approaching_target_keeps_its_id() {
+        let mut tracker = Tracker::default();
+        let start = Instant::now();
+        for step in 0..4 {
+            let time = start + Duration::from_millis(500 * step);
+            tracker.update(&det(90.0, 100.0 - 2.0 * step as f32, time));
+        }
+        assert_eq!(tracker.tracks.len(), 1);
+        let track = tracker.confirmed().next().unwrap();
+        assert_eq!(track.id, 1);
+        assert_eq!(track.hits, 4);
+        assert!(track.radial_velocity < 0.0);
+    }
+
+    #[test]
+    fn detection_outside_the_gate_starts_a_new_track() {
+        let mut tracker = Tracker::default();
+        let now = Instant::now();
+        tracker.update(&det(90.0, 100.0, now));
+        tracker.update(&det(90.0, 100.0 + GATE_CM * 2.0, now));
+        let ids: Vec<u32> = tracker.tracks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [1, 2]);
+    }
+}
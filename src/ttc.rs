@@ -0,0 +1,195 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::tracker::Track;
+use crate::ui::UiScale;
+use crate::view::Layout;
+
+/// `[ttc]` section of the config file. No threshold, no alerts.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TtcConfig {
+    /// Alert once a track would reach the sensor within this many seconds
+    pub threshold_s: Option<f32>,
+    /// How close to the sensor counts as a collision, so something passing
+    /// by further out doesn't set it off
+    pub radius_cm: f32,
+    /// Tracks slower than this are left out, their velocity is mostly noise
+    pub min_speed_cm_s: f32,
+}
+
+impl Default for TtcConfig {
+    fn default() -> Self {
+        Self {
+            threshold_s: None,
+            radius_cm: 20.0,
+            min_speed_cm_s: 2.0,
+        }
+    }
+}
+
+/// The track that gets to the sensor first.
+#[derive(Clone, Copy, Debug)]
+pub struct Collision {
+    pub track: u32,
+    pub seconds: f32,
+    pub angle: f32,
+    pub distance: f32,
+    /// 0 right at the threshold, 1 when it's about to hit
+    pub urgency: f32,
+}
+
+/// Time to collision of the tracks heading for the sensor, alerting when
+/// the soonest one drops below the threshold.
+pub struct TtcMonitor {
+    config: TtcConfig,
+    pub collision: Option<Collision>,
+}
+
+impl TtcMonitor {
+    pub fn new(config: &TtcConfig) -> Self {
+        Self {
+            config: config.clone(),
+            collision: None,
+        }
+    }
+
+    /// Call every frame with the confirmed tracks and where the sensor is,
+    /// in the same frame as the tracks. Returns the collision when an alert
+    /// starts, or moves on to another track.
+    pub fn update<'a>(
+        &mut self,
+        tracks: impl Iterator<Item = &'a Track>,
+        sensor: (f32, f32),
+    ) -> Option<Collision> {
+        let threshold = self.config.threshold_s.filter(|t| *t > 0.0)?;
+        let soonest = tracks
+            .filter_map(|track| {
+                let seconds = self.time_to_collision(track, sensor)?;
+                Some(Collision {
+                    track: track.id,
+                    seconds,
+                    angle: track.angle,
+                    distance: track.distance,
+                    urgency: (1.0 - seconds / threshold).clamp(0.0, 1.0),
+                })
+            })
+            .filter(|c| c.seconds < threshold)
+            .min_by(|a, b| a.seconds.total_cmp(&b.seconds));
+
+        let started = match (self.collision, soonest) {
+            (Some(old), Some(c)) if old.track == c.track => None,
+            (Some(_), None) => {
+                info!("Collision alert over");
+                None
+            }
+            (_, c) => c,
+        };
+        if let Some(c) = started {
+            warn!("Track #{} collides in {:.1} s", c.track, c.seconds);
+        }
+        self.collision = soonest;
+        started
+    }
+
+    /// Seconds until the track, moving on in a straight line, comes within
+    /// `radius_cm` of the sensor. `None` when it's not heading there.
+    fn time_to_collision(&self, track: &Track, sensor: (f32, f32)) -> Option<f32> {
+        let (x, y) = track.position();
+        let (rx, ry) = (x - sensor.0, y - sensor.1);
+        let (vx, vy) = track.velocity;
+        let r = self.config.radius_cm.max(0.0);
+        let speed_sq = vx * vx + vy * vy;
+        let closing = rx * vx + ry * vy;
+        if speed_sq.sqrt() < self.config.min_speed_cm_s || closing >= 0.0 {
+            return None;
+        }
+        let dist_sq = rx * rx + ry * ry;
+        if dist_sq <= r * r {
+            return Some(0.0);
+        }
+        // |rel + v t| = r, the earlier root is where it enters the circle
+        let discriminant = closing * closing - speed_sq * (dist_sq - r * r);
+        if discriminant < 0.0 {
+            return None;
+        }
+        Some((-closing - discriminant.sqrt()) / speed_sq)
+    }
+
+    /// Pulses a ring around the track on the way in, faster the sooner it
+    /// hits.
+    pub fn draw<D: RaylibDraw>(
+        &self,
+        d: &mut D,
+        layout: &Layout,
+        pixels_per_cm: f32,
+        time: f64,
+        ui: UiScale,
+        color: Color,
+    ) {
+        let Some(c) = self.collision else {
+            return;
+        };
+        let pulse = self.pulse(time);
+        let pos = layout.polar_to_screen(c.angle, c.distance * pixels_per_cm);
+        let r = ui.px(14.0 + 10.0 * pulse);
+        d.draw_circle_v(pos, r, color.alpha(0.15 + 0.25 * pulse));
+        d.draw_ring(pos, r, r + ui.px(3.0), 0.0, 360.0, 0, color);
+    }
+
+    /// 0 to 1 and back, one to five times a second as the urgency rises.
+    pub fn pulse(&self, time: f64) -> f32 {
+        let rate = 1.0 + 4.0 * self.collision.map_or(0.0, |c| c.urgency) as f64;
+        ((time * rate * std::f64::consts::TAU).sin() * 0.5 + 0.5) as f32
+    }
+
+    /// "COLLISION #3 in 1.4 s", while an alert is on.
+    pub fn readout(&self) -> Option<String> {
+        self.collision
+            .map(|c| format!("COLLISION #{} in {:.1} s", c.track, c.seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn monitor() -> TtcMonitor {
+        TtcMonitor::new(&TtcConfig {
+            threshold_s: Some(5.0),
+            ..TtcConfig::default()
+        })
+    }
+
+    #[test]
+    fn head_on_approach_reaches_the_radius() {
+        let mut ttc = monitor();
+        let track = Track::moving(1, (0.0, 100.0), (0.0, -20.0), Instant::now());
+        let collision = ttc.update([&track].into_iter(), (0.0, 0.0)).unwrap();
+        assert_eq!(collision.track, 1);
+        // 80 cm to go to the 20 cm radius at 20 cm/s
+        assert!((collision.seconds - 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn receding_and_passing_tracks_never_collide() {
+        let ttc = monitor();
+        let now = Instant::now();
+        let receding = Track::moving(1, (0.0, 100.0), (0.0, 20.0), now);
+        assert!(ttc.time_to_collision(&receding, (0.0, 0.0)).is_none());
+        let passing = Track::moving(2, (50.0, 100.0), (0.0, -20.0), now);
+        assert!(ttc.time_to_collision(&passing, (0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn same_track_starts_the_alert_once() {
+        let mut ttc = monitor();
+        let track = Track::moving(1, (0.0, 100.0), (0.0, -20.0), Instant::now());
+        assert!(ttc.update([&track].into_iter(), (0.0, 0.0)).is_some());
+        assert!(ttc.update([&track].into_iter(), (0.0, 0.0)).is_none());
+        assert_eq!(ttc.collision.map(|c| c.track), Some(1));
+    }
+}
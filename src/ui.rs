@@ -0,0 +1,91 @@
+/// Screen size the fonts, offsets and line widths in the code are picked
+/// for, the size the window opens at.
+pub const BASE_WIDTH: f32 = 1200.0;
+pub const BASE_HEIGHT: f32 = 700.0;
+
+/// How much bigger than designed the text and lines are drawn, so the
+/// overlay keeps its proportions on a 4K screen and stays readable on a
+/// HiDPI laptop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiScale(f32);
+
+impl Default for UiScale {
+    fn default() -> Self {
+        UiScale(1.0)
+    }
+}
+
+impl UiScale {
+    /// From the current screen size, the monitor's DPI scale (1 on a
+    /// regular monitor, 2 on a Retina one) and `[window] ui_scale` on top.
+    /// A window smaller than designed still gets at least the DPI size, text
+    /// below that is unreadable.
+    pub fn new(screen_w: f32, screen_h: f32, dpi: f32, user: f32) -> Self {
+        let fit = (screen_w / BASE_WIDTH).min(screen_h / BASE_HEIGHT);
+        let dpi = if dpi.is_finite() && dpi > 0.0 {
+            dpi
+        } else {
+            1.0
+        };
+        UiScale((fit.max(dpi) * user).clamp(0.5, 6.0))
+    }
+
+    pub fn factor(self) -> f32 {
+        self.0
+    }
+
+    /// Font size for one designed at `size`.
+    pub fn font(self, size: i32) -> i32 {
+        ((size as f32 * self.0).round() as i32).max(1)
+    }
+
+    /// Length in pixels for one designed at `px`, for offsets and line widths.
+    pub fn px(self, px: f32) -> f32 {
+        px * self.0
+    }
+
+    /// Whole pixels, for the integer text positions.
+    pub fn pxi(self, px: i32) -> i32 {
+        (px as f32 * self.0).round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_designed_size_is_one() {
+        assert_eq!(
+            UiScale::new(BASE_WIDTH, BASE_HEIGHT, 1.0, 1.0).factor(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn grows_with_the_smaller_side() {
+        let ui = UiScale::new(BASE_WIDTH * 3.0, BASE_HEIGHT * 2.0, 1.0, 1.0);
+        assert_eq!(ui.factor(), 2.0);
+        assert_eq!(ui.font(16), 32);
+        assert_eq!(ui.pxi(10), 20);
+    }
+
+    #[test]
+    fn keeps_the_dpi_size_in_a_small_window() {
+        assert_eq!(UiScale::new(600.0, 350.0, 2.0, 1.0).factor(), 2.0);
+        assert_eq!(UiScale::new(600.0, 350.0, f32::NAN, 1.0).factor(), 1.0);
+    }
+
+    #[test]
+    fn applies_and_clamps_the_users_scale() {
+        assert_eq!(
+            UiScale::new(BASE_WIDTH, BASE_HEIGHT, 1.0, 1.5).factor(),
+            1.5
+        );
+        assert_eq!(
+            UiScale::new(BASE_WIDTH, BASE_HEIGHT, 1.0, 100.0).factor(),
+            6.0
+        );
+        assert_eq!(UiScale::new(BASE_WIDTH, BASE_HEIGHT, 1.0, 0.0).font(16), 8);
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Unit distances are shown in. Everything internal stays in cm, conversion
+/// only happens when a number is put on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    #[default]
+    Cm,
+    M,
+    In,
+    Ft,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 4] = [Unit::Cm, Unit::M, Unit::In, Unit::Ft];
+
+    /// Next unit for the hotkey.
+    pub fn cycled(self) -> Self {
+        match self {
+            Unit::Cm => Unit::M,
+            Unit::M => Unit::In,
+            Unit::In => Unit::Ft,
+            Unit::Ft => Unit::Cm,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Unit::Cm => "cm",
+            Unit::M => "m",
+            Unit::In => "in",
+            Unit::Ft => "ft",
+        }
+    }
+
+    pub fn convert(self, cm: f32) -> f32 {
+        match self {
+            Unit::Cm => cm,
+            Unit::M => cm / 100.0,
+            Unit::In => cm / 2.54,
+            Unit::Ft => cm / 30.48,
+        }
+    }
+
+    /// Digits after the decimal point that are still meaningful for an
+    /// HC-SR04-class sensor, plus one for small values.
+    fn decimals(self, value: f32) -> usize {
+        let base = match self {
+            Unit::Cm | Unit::In => 0,
+            Unit::Ft => 1,
+            Unit::M => 2,
+        };
+        if base == 0 && value.abs() < 10.0 {
+            1
+        } else {
+            base
+        }
+    }
+
+    /// `cm` converted and formatted with its unit, e.g. "1.25 m".
+    pub fn format(self, cm: f32) -> String {
+        let value = self.convert(cm);
+        format!("{:.*} {}", self.decimals(value), value, self.label())
+    }
+
+    /// A speed given in cm/s, e.g. "0.12 m/s".
+    pub fn format_speed(self, cm_per_s: f32) -> String {
+        let value = self.convert(cm_per_s);
+        format!(
+            "{:.*} {}/s",
+            self.decimals(value).max(1),
+            value,
+            self.label()
+        )
+    }
+}
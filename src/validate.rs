@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::radar;
+use crate::source::Sample;
+
+/// `[validation]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Echoes closer than this are sensor ringing, not objects
+    pub min_range_cm: f32,
+    /// Echoes at or beyond this are timeouts (the HC-SR04 reports 400)
+    pub max_range_cm: f32,
+    /// Largest believable change between consecutive samples at similar
+    /// angles, 0 turns the spike check off
+    pub max_jump_cm: f32,
+    /// Samples this close in angle are expected to see the same thing
+    pub similar_angle_deg: f32,
+    /// Samples reporting a strength or confidence below this, 0 to 255, are
+    /// dropped. Ones without a strength always pass
+    pub min_strength: f32,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            min_range_cm: 2.0,
+            max_range_cm: 400.0,
+            max_jump_cm: 0.0,
+            similar_angle_deg: 3.0,
+            min_strength: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RejectStats {
+    pub out_of_range: u64,
+    pub spikes: u64,
+    pub weak: u64,
+}
+
+/// Drops readings that can't be real before they reach the filter and renderer.
+pub struct Validator {
+    config: ValidationConfig,
+    /// Last accepted sample per layer and echo index
+    last: HashMap<(u8, u8), Sample>,
+    /// Last rejected spike per echo index. A second sample agreeing with it
+    /// means the scene really changed, so the jump is accepted after all.
+    pending: HashMap<(u8, u8), Sample>,
+    stats: RejectStats,
+}
+
+impl Validator {
+    pub fn new(config: ValidationConfig) -> Self {
+        Self {
+            config,
+            last: HashMap::new(),
+            pending: HashMap::new(),
+            stats: RejectStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> RejectStats {
+        self.stats
+    }
+
+    pub fn check(&mut self, sample: Sample) -> Option<Sample> {
+        // Zero means no echo, let it through so the readout stays honest
+        if sample.distance <= 0.0 {
+            return Some(sample);
+        }
+
+        if sample.distance < self.config.min_range_cm || sample.distance >= self.config.max_range_cm
+        {
+            self.stats.out_of_range += 1;
+            return None;
+        }
+
+        if sample
+            .strength
+            .is_some_and(|s| s * 255.0 < self.config.min_strength)
+        {
+            self.stats.weak += 1;
+            return None;
+        }
+
+        if self.config.max_jump_cm > 0.0 && self.is_spike(&sample) {
+            self.stats.spikes += 1;
+            self.pending.insert((sample.layer, sample.echo), sample);
+            return None;
+        }
+
+        let key = (sample.layer, sample.echo);
+        self.pending.remove(&key);
+        self.last.insert(key, sample);
+        Some(sample)
+    }
+
+    fn is_spike(&self, sample: &Sample) -> bool {
+        let near = |other: &Sample| {
+            radar::angle_delta(other.angle, sample.angle).abs() <= self.config.similar_angle_deg
+                && (other.distance - sample.distance).abs() <= self.config.max_jump_cm
+        };
+
+        let Some(last) = self.last.get(&(sample.layer, sample.echo)) else {
+            return false;
+        };
+        // Too far apart in angle to compare, anything goes
+        if radar::angle_delta(last.angle, sample.angle).abs() > self.config.similar_angle_deg {
+            return false;
+        }
+        if near(last) {
+            return false;
+        }
+        // Confirmed by the previous (rejected) sample
+        !self
+            .pending
+            .get(&(sample.layer, sample.echo))
+            .is_some_and(near)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spikes_are_caught_across_zero() {
+        let mut validator = Validator::new(ValidationConfig {
+            max_jump_cm: 20.0,
+            ..ValidationConfig::default()
+        });
+        assert!(validator.check(Sample::new(359.5, 100.0)).is_some());
+        assert!(validator.check(Sample::new(0.5, 300.0)).is_none());
+        assert_eq!(validator.stats().spikes, 1);
+        // A second sample agreeing with the spike, back on the other side
+        assert!(validator.check(Sample::new(359.8, 300.0)).is_some());
+    }
+}
@@ -0,0 +1,521 @@
+use std::ops::Deref;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::font::Fonts;
+use crate::theme::Theme;
+use crate::ui::UiScale;
+use crate::units::Unit;
+
+/// How the plot is laid out on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// Classic half circle at the bottom of the screen, 0°–180°
+    #[default]
+    Half,
+    /// Full 360° plan position indicator centered on screen
+    Full,
+}
+
+impl DisplayMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            DisplayMode::Half => DisplayMode::Full,
+            DisplayMode::Full => DisplayMode::Half,
+        }
+    }
+}
+
+/// What the grid under the detections looks like. The detections are at the
+/// same place either way, seen from above.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    /// Range rings and angle markers around the sensor
+    #[default]
+    Polar,
+    /// A square x/y grid, x along 0° and y along 90°, for a sensor mounted
+    /// on a wall scanning a room
+    Cartesian,
+}
+
+impl Projection {
+    pub fn toggled(self) -> Self {
+        match self {
+            Projection::Polar => Projection::Cartesian,
+            Projection::Cartesian => Projection::Polar,
+        }
+    }
+}
+
+/// Which way data angles grow on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AngleDirection {
+    /// 0° on the right, counter-clockwise
+    #[default]
+    Ccw,
+    /// 0° on the left, clockwise, for rigs mounted the other way round
+    Cw,
+}
+
+/// `[coverage]` section of the config file: the sector the sensor actually sweeps.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CoverageConfig {
+    pub min_angle: f32,
+    pub max_angle: f32,
+    pub direction: AngleDirection,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            min_angle: 0.0,
+            max_angle: 360.0,
+            direction: AngleDirection::Ccw,
+        }
+    }
+}
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 20.0;
+/// One mouse wheel notch zooms by this factor.
+const ZOOM_STEP: f32 = 1.15;
+
+/// Zoom and pan of the plot on top of the window layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub zoom: f32,
+    /// Offset of the plot center from where the layout puts it, in pixels
+    pub pan: Vector2,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: Vector2::zero(),
+        }
+    }
+}
+
+impl Viewport {
+    /// Zooms by `steps` wheel notches, keeping the point under `mouse` in place.
+    /// `base` is the layout without this viewport applied.
+    pub fn zoom_at(&mut self, base: &Layout, mouse: Vector2, steps: f32) {
+        let zoom = (self.zoom * ZOOM_STEP.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+        let k = zoom / self.zoom;
+        let center = base.center + self.pan;
+        self.pan = mouse + (center - mouse) * k - base.center;
+        self.zoom = zoom;
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.zoom == 1.0 && self.pan == Vector2::zero()
+    }
+}
+
+// Ring radii as a fraction of the max range
+const ARC_SCALES: [f32; 4] = [1.0, 0.75, 0.5, 0.25];
+
+/// Screen geometry of the radar plot, recomputed every frame from the window size.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub mode: DisplayMode,
+    pub direction: AngleDirection,
+    /// Active sector in data angles, `min < max`
+    pub sector: (f32, f32),
+    pub center: Vector2,
+    /// Radius of the outer edge in pixels, i.e. max range
+    pub radius: f32,
+    /// Bottom of the plot area, the readouts live below it
+    pub plot_bottom: f32,
+}
+
+impl Layout {
+    pub fn new(mode: DisplayMode, coverage: &CoverageConfig, screen_w: f32, screen_h: f32) -> Self {
+        let plot_bottom = screen_h * 0.926;
+        let direction = coverage.direction;
+        let span = match mode {
+            DisplayMode::Half => 180.0,
+            DisplayMode::Full => 360.0,
+        };
+        let min = coverage.min_angle.clamp(0.0, span);
+        let max = coverage.max_angle.clamp(0.0, span);
+        // A backwards or empty sector is a config mistake, show everything
+        let sector = if min < max { (min, max) } else { (0.0, span) };
+
+        match mode {
+            DisplayMode::Half => Self {
+                mode,
+                direction,
+                sector,
+                center: Vector2::new(screen_w / 2.0, plot_bottom),
+                radius: screen_w * 0.5,
+                plot_bottom,
+            },
+            DisplayMode::Full => {
+                // Leave a margin for the angle labels around the circle
+                let radius = (screen_w / 2.0).min(plot_bottom / 2.0) * 0.9;
+                Self {
+                    mode,
+                    direction,
+                    sector,
+                    center: Vector2::new(screen_w / 2.0, plot_bottom / 2.0),
+                    radius,
+                    plot_bottom,
+                }
+            }
+        }
+    }
+
+    /// The layout for a part of the window, like one view of a split screen.
+    pub fn within(mode: DisplayMode, coverage: &CoverageConfig, rect: Rectangle) -> Self {
+        let mut layout = Self::new(mode, coverage, rect.width, rect.height);
+        // The half circle is as wide as the window, a short view cuts it down
+        layout.radius = layout.radius.min(layout.plot_bottom * 0.95);
+        layout.center += Vector2::new(rect.x, rect.y);
+        layout.plot_bottom += rect.y;
+        layout
+    }
+
+    /// The same layout seen through `viewport`. Everything that is drawn goes
+    /// through the layout, so this zooms and pans the whole plot.
+    pub fn viewed(self, viewport: &Viewport) -> Self {
+        Self {
+            center: self.center + viewport.pan,
+            radius: self.radius * viewport.zoom,
+            ..self
+        }
+    }
+
+    /// Screen position of a point `dist_px` pixels from the center along the
+    /// data angle `angle_deg`.
+    pub fn polar_to_screen(&self, angle_deg: f32, dist_px: f32) -> Vector2 {
+        let rad = self.screen_angle(angle_deg).to_radians();
+        Vector2::new(
+            self.center.x + dist_px * rad.cos(),
+            self.center.y - dist_px * rad.sin(),
+        )
+    }
+
+    /// Inverse of `polar_to_screen`: data angle in 0..360 and distance from
+    /// the center in pixels of a screen position.
+    pub fn screen_to_polar(&self, pos: Vector2) -> (f32, f32) {
+        let dx = pos.x - self.center.x;
+        let dy = self.center.y - pos.y;
+        let screen_angle = dy.atan2(dx).to_degrees();
+        let angle = match self.direction {
+            AngleDirection::Ccw => screen_angle,
+            AngleDirection::Cw => 180.0 - screen_angle,
+        };
+        (angle.rem_euclid(360.0), dx.hypot(dy))
+    }
+
+    /// Screen position of a point on the map, `x_cm` towards 0° and `y_cm`
+    /// towards 90°.
+    pub fn map_to_screen(&self, x_cm: f32, y_cm: f32, pixels_per_cm: f32) -> Vector2 {
+        let angle = y_cm.atan2(x_cm).to_degrees();
+        self.polar_to_screen(angle, x_cm.hypot(y_cm) * pixels_per_cm)
+    }
+
+    /// Inverse of `map_to_screen`.
+    pub fn screen_to_map(&self, pos: Vector2, pixels_per_cm: f32) -> (f32, f32) {
+        let (angle, dist_px) = self.screen_to_polar(pos);
+        let (rad, cm) = (angle.to_radians(), dist_px / pixels_per_cm);
+        (cm * rad.cos(), cm * rad.sin())
+    }
+
+    /// Screen angle of a data angle, 0° pointing right and counter-clockwise.
+    fn screen_angle(&self, angle_deg: f32) -> f32 {
+        match self.direction {
+            AngleDirection::Ccw => angle_deg,
+            AngleDirection::Cw => 180.0 - angle_deg,
+        }
+    }
+
+    /// Start and end of a sector of data angles in raylib's convention,
+    /// which measures angles clockwise on screen.
+    fn raylib_angles(&self, (a, b): (f32, f32)) -> (f32, f32) {
+        let (a, b) = (-self.screen_angle(a), -self.screen_angle(b));
+        let (start, end) = if a < b { (a, b) } else { (b, a) };
+        (start + 360.0, end + 360.0)
+    }
+
+    fn covers_everything(&self) -> bool {
+        let (min, max) = self.sector;
+        match self.mode {
+            DisplayMode::Half => min <= 0.0 && max >= 180.0,
+            DisplayMode::Full => min <= 0.0 && max >= 360.0,
+        }
+    }
+
+    /// Data angles that get a marker line and label: every 30° inside the
+    /// sector, plus its edges when it doesn't span the whole plot.
+    fn marker_angles(&self) -> Vec<f32> {
+        let (min, max) = self.sector;
+        let mut angles: Vec<f32> = (0..360)
+            .step_by(30)
+            .map(|a| a as f32)
+            .filter(|a| {
+                (min < *a && *a < max)
+                    || (*a == 0.0 && self.mode == DisplayMode::Full && self.covers_everything())
+            })
+            .collect();
+        if !self.covers_everything() {
+            angles.push(min);
+            angles.push(max);
+        }
+        angles
+    }
+}
+
+/// Draws the range rings, angle markers and their labels.
+pub fn draw_grid<D>(d: &mut D, layout: &Layout, ui: UiScale, fonts: &mut Fonts, color: Color)
+where
+    D: RaylibDraw + Deref<Target = RaylibHandle>,
+{
+    // Radar Arcs
+    for scale in ARC_SCALES {
+        let r = layout.radius * scale;
+        if layout.mode == DisplayMode::Full && layout.covers_everything() {
+            d.draw_circle_lines_v(layout.center, r, color);
+        } else {
+            let (start, end) = layout.raylib_angles(layout.sector);
+            d.draw_circle_sector_lines(layout.center, r, start, end, 128, color);
+        }
+    }
+
+    // Angle Markers
+    for angle in layout.marker_angles() {
+        let line_end = layout.polar_to_screen(angle, layout.radius);
+        d.draw_line_ex(layout.center, line_end, ui.px(2.0), color);
+
+        let text_pos = layout.polar_to_screen(angle, layout.radius * 1.05);
+        let label = format!("{:.0}", angle);
+        let font_size = ui.font(20);
+        let text_size = fonts.measure(d, &label, font_size);
+        fonts.draw(
+            d,
+            &label,
+            (text_pos.x - text_size as f32 / 2.0) as i32,
+            (text_pos.y - font_size as f32 / 2.0) as i32,
+            font_size,
+            color,
+        );
+    }
+}
+
+/// Spacing of the map grid lines: 1, 2 or 5 times a power of ten, four to
+/// ten lines across the max range.
+fn map_step_cm(max_range_cm: f32) -> f32 {
+    let rough = (max_range_cm / 4.0).max(f32::EPSILON);
+    let power = 10f32.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * power)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * power)
+}
+
+impl Layout {
+    /// Screen area the map grid covers: the square around the full circle,
+    /// or the rectangle above the center in the half circle layout.
+    fn map_rect(&self) -> Rectangle {
+        let r = self.radius;
+        let height = match self.mode {
+            DisplayMode::Half => r,
+            DisplayMode::Full => 2.0 * r,
+        };
+        Rectangle::new(self.center.x - r, self.center.y - r, 2.0 * r, height)
+    }
+
+    /// Grid line offsets from the center in cm, both ways up to `extent_cm`.
+    fn map_lines(extent_cm: f32, step_cm: f32) -> impl Iterator<Item = f32> {
+        let n = (extent_cm / step_cm).floor() as i32;
+        (-n..=n).map(move |k| k as f32 * step_cm)
+    }
+}
+
+/// Draws the x/y grid of the Cartesian projection, the axes through the
+/// sensor a bit thicker than the rest.
+pub fn draw_map_grid<D: RaylibDraw>(
+    d: &mut D,
+    layout: &Layout,
+    max_range_cm: f32,
+    ui: UiScale,
+    color: Color,
+) {
+    let rect = layout.map_rect();
+    let step = map_step_cm(max_range_cm);
+    let px_per_cm = layout.radius / max_range_cm.max(f32::EPSILON);
+    let faint = color.alpha(0.5);
+    for offset in Layout::map_lines(max_range_cm, step) {
+        let x = layout.center.x + offset * px_per_cm;
+        let (thickness, c) = if offset == 0.0 {
+            (2.0, color)
+        } else {
+            (1.0, faint)
+        };
+        d.draw_line_ex(
+            Vector2::new(x, rect.y),
+            Vector2::new(x, rect.y + rect.height),
+            ui.px(thickness),
+            c,
+        );
+        let y = layout.center.y - offset * px_per_cm;
+        if y >= rect.y && y <= rect.y + rect.height {
+            d.draw_line_ex(
+                Vector2::new(rect.x, y),
+                Vector2::new(rect.x + rect.width, y),
+                ui.px(thickness),
+                c,
+            );
+        }
+    }
+    d.draw_rectangle_lines_ex(rect, ui.px(2.0), color);
+}
+
+/// Labels the map grid lines with x along the bottom and y along the left
+/// edge, 0 at the sensor.
+pub fn draw_map_labels<D>(
+    d: &mut D,
+    layout: &Layout,
+    max_range_cm: f32,
+    unit: Unit,
+    ui: UiScale,
+    fonts: &mut Fonts,
+    color: Color,
+) where
+    D: RaylibDraw + Deref<Target = RaylibHandle>,
+{
+    let rect = layout.map_rect();
+    let step = map_step_cm(max_range_cm);
+    let px_per_cm = layout.radius / max_range_cm.max(f32::EPSILON);
+    let font_size = ui.font(16);
+    // With clockwise angles 0° is on the left, so is positive x
+    let x_sign = match layout.direction {
+        AngleDirection::Ccw => 1.0,
+        AngleDirection::Cw => -1.0,
+    };
+    for offset in Layout::map_lines(max_range_cm, step) {
+        let label = unit.format(offset * x_sign);
+        let x = layout.center.x + offset * px_per_cm;
+        fonts.draw(
+            d,
+            &label,
+            (x + ui.px(3.0)) as i32,
+            (rect.y + rect.height - font_size as f32 - ui.px(3.0)) as i32,
+            font_size,
+            color,
+        );
+        let y = layout.center.y - offset * px_per_cm;
+        // The bottom line already has the x labels
+        if y >= rect.y && y < rect.y + rect.height {
+            fonts.draw(
+                d,
+                &unit.format(offset),
+                (rect.x + ui.px(3.0)) as i32,
+                (y - font_size as f32 - ui.px(2.0)) as i32,
+                font_size,
+                color,
+            );
+        }
+    }
+}
+
+/// Labels every range ring with its distance. Drawn on the scene rather than
+/// the persistence layer, so the old labels don't linger after zooming.
+pub fn draw_ring_labels<D>(
+    d: &mut D,
+    layout: &Layout,
+    max_range_cm: f32,
+    unit: Unit,
+    ui: UiScale,
+    fonts: &mut Fonts,
+    color: Color,
+) where
+    D: RaylibDraw + Deref<Target = RaylibHandle>,
+{
+    let font_size = ui.font(16);
+    // Just inside the start of the sector, clear of the edge marker line
+    let angle = layout.sector.0 + 2.0;
+    for scale in ARC_SCALES {
+        let label = unit.format(max_range_cm * scale);
+        let pos = layout.polar_to_screen(angle, layout.radius * scale);
+        let text_size = fonts.measure(d, &label, font_size);
+        // Keep the label inside the ring on either side of the center
+        let x = if pos.x >= layout.center.x {
+            pos.x - text_size as f32 - ui.px(4.0)
+        } else {
+            pos.x + ui.px(4.0)
+        };
+        fonts.draw(
+            d,
+            &label,
+            x as i32,
+            (pos.y - font_size as f32 - ui.px(2.0)) as i32,
+            font_size,
+            color,
+        );
+    }
+}
+
+/// Draws the part of the ring between `radii` (in pixels) that lies within
+/// the `angles` sector, outlined or filled.
+pub fn draw_sector<D: RaylibDraw>(
+    d: &mut D,
+    layout: &Layout,
+    angles: (f32, f32),
+    radii: (f32, f32),
+    color: Color,
+    filled: bool,
+) {
+    let (start, end) = layout.raylib_angles(angles);
+    let (inner, outer) = radii;
+    // 0 segments lets raylib pick enough for a smooth arc at this size
+    if filled {
+        d.draw_ring(layout.center, inner, outer, start, end, 0, color);
+    } else {
+        d.draw_ring_lines(layout.center, inner, outer, start, end, 0, color);
+    }
+}
+
+/// Draws a line from `from` to `to` as dashes `dash` pixels long with gaps
+/// as long between them.
+pub fn draw_dashed_line<D: RaylibDraw>(
+    d: &mut D,
+    from: Vector2,
+    to: Vector2,
+    dash: f32,
+    thick: f32,
+    color: Color,
+) {
+    let length = from.distance_to(to);
+    if length <= 0.0 || dash <= 0.0 {
+        return;
+    }
+    let step = (to - from) / length;
+    let mut at = 0.0;
+    while at < length {
+        let end = (at + dash).min(length);
+        d.draw_line_ex(from + step * at, from + step * end, thick, color);
+        at += 2.0 * dash;
+    }
+}
+
+/// What a side panel needs to know to draw itself.
+pub struct Panel {
+    /// Screen area of the panel
+    pub rect: Rectangle,
+    /// Sector shown on the angle axis
+    pub angles: (f32, f32),
+    pub current_angle: f32,
+    pub max_range_cm: f32,
+    pub unit: Unit,
+    pub theme: Theme,
+    pub ui: UiScale,
+}
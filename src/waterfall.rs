@@ -0,0 +1,138 @@
+use raylib::prelude::*;
+
+use crate::source::Sample;
+use crate::view::Panel;
+
+/// One column per degree, one row per sweep.
+const COLUMNS: usize = 360;
+const ROWS: usize = 120;
+/// Bytes per pixel of the RGBA texture.
+const BPP: usize = 4;
+
+/// Near to far, bright to dark, independent of the theme so the panel reads
+/// the same in every color scheme.
+const COLORMAP: [Color; 5] = [
+    Color::new(252, 255, 164, 255),
+    Color::new(250, 160, 30, 255),
+    Color::new(200, 60, 80, 255),
+    Color::new(100, 20, 110, 255),
+    Color::new(20, 10, 50, 255),
+];
+
+/// B-scan: the last `ROWS` sweeps stacked on top of each other, newest at
+/// the top, with angle across and distance as color.
+pub struct Waterfall {
+    /// First-echo distance per row and column, row 0 is the sweep in progress.
+    /// Negative means nothing was recorded there.
+    cells: Vec<f32>,
+    pixels: Vec<u8>,
+    texture: Texture2D,
+    /// Range and theme the pixels were last colored for
+    colored_for: Option<(f32, Color)>,
+    dirty: bool,
+    last_angle: Option<f32>,
+    increasing: bool,
+}
+
+impl Waterfall {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+    ) -> Result<Self, raylib::core::error::Error> {
+        let image = Image::gen_image_color(COLUMNS as i32, ROWS as i32, Color::BLANK);
+        let texture = rl.load_texture_from_image(thread, &image)?;
+        Ok(Self {
+            cells: vec![-1.0; COLUMNS * ROWS],
+            pixels: vec![0; COLUMNS * ROWS * BPP],
+            texture,
+            colored_for: None,
+            dirty: true,
+            last_angle: None,
+            increasing: true,
+        })
+    }
+
+    pub fn record(&mut self, sample: &Sample) {
+        if sample.echo != 0 {
+            return;
+        }
+        let angle = sample.angle.rem_euclid(360.0);
+
+        // A sweep ends where the servo turns around or a rotating sensor wraps
+        if let Some(last) = self.last_angle {
+            let delta = angle - last;
+            let wrapped = delta.abs() > 180.0;
+            let increasing = delta > 0.0;
+            if wrapped || (delta != 0.0 && increasing != self.increasing) {
+                self.scroll();
+            }
+            if !wrapped && delta != 0.0 {
+                self.increasing = increasing;
+            }
+        }
+        self.last_angle = Some(angle);
+
+        let column = (angle as usize).min(COLUMNS - 1);
+        self.cells[column] = sample.distance.max(0.0);
+        self.dirty = true;
+    }
+
+    /// Moves every sweep one row down and starts an empty one at the top.
+    fn scroll(&mut self) {
+        self.cells.copy_within(0..COLUMNS * (ROWS - 1), COLUMNS);
+        self.cells[..COLUMNS].fill(-1.0);
+        self.dirty = true;
+    }
+
+    fn color_of(&self, cm: f32, max_range_cm: f32, background: Color) -> Color {
+        if cm <= 0.0 || cm >= max_range_cm {
+            return background;
+        }
+        let pos = cm / max_range_cm * (COLORMAP.len() - 1) as f32;
+        let i = (pos.floor() as usize).min(COLORMAP.len() - 2);
+        COLORMAP[i].lerp(COLORMAP[i + 1], pos - i as f32)
+    }
+
+    pub fn draw<D: RaylibDraw>(&mut self, d: &mut D, panel: &Panel) {
+        let theme = &panel.theme;
+        let colored_for = Some((panel.max_range_cm, theme.background));
+        if self.dirty || self.colored_for != colored_for {
+            for (i, cm) in self.cells.iter().enumerate() {
+                let c = self.color_of(*cm, panel.max_range_cm, theme.background);
+                self.pixels[i * BPP..(i + 1) * BPP].copy_from_slice(&[c.r, c.g, c.b, c.a]);
+            }
+            // Sizes always match, the texture was made from these dimensions
+            let _ = self.texture.update_texture(&self.pixels);
+            self.colored_for = colored_for;
+            self.dirty = false;
+        }
+
+        let rect = panel.rect;
+        let (min, max) = panel.angles;
+        let source = Rectangle::new(min, 0.0, (max - min).max(1.0), ROWS as f32);
+        d.draw_texture_pro(
+            &self.texture,
+            source,
+            rect,
+            Vector2::zero(),
+            0.0,
+            Color::WHITE,
+        );
+        d.draw_rectangle_lines_ex(rect, 1.0, theme.outline);
+
+        let x = rect.x
+            + (panel.current_angle.clamp(min, max) - min) / (max - min).max(1.0) * rect.width;
+        d.draw_line_v(
+            Vector2::new(x, rect.y),
+            Vector2::new(x, rect.y + 6.0),
+            theme.sweep,
+        );
+        d.draw_text(
+            &format!("{} sweeps", ROWS),
+            rect.x as i32 + 4,
+            (rect.y + rect.height) as i32 - panel.ui.pxi(16),
+            panel.ui.font(14),
+            theme.outline,
+        );
+    }
+}
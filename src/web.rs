@@ -0,0 +1,44 @@
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+use tiny_http::{Header, Request, Response};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// The built-in viewer, served at `/`.
+pub const PAGE: &str = include_str!("../web/index.html");
+
+/// Frames waiting for a slow browser before new ones are dropped.
+const CLIENT_QUEUE_LEN: usize = 64;
+
+/// Upgrades a request to `/ws` and returns the sender that feeds it. Every
+/// client gets its own writer thread, it stops when the browser goes away.
+pub fn accept(request: Request) -> Option<SyncSender<String>> {
+    let key = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Sec-WebSocket-Key"))
+        .map(|h| h.value.to_string());
+    let Some(key) = key else {
+        let _ =
+            request.respond(Response::from_string("Expected a WebSocket").with_status_code(400));
+        return None;
+    };
+
+    let accept = Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes()))
+        .expect("accept key is valid in a header");
+    let stream = request.upgrade("websocket", Response::empty(101).with_header(accept));
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    let (tx, rx) = mpsc::sync_channel::<String>(CLIENT_QUEUE_LEN);
+    thread::spawn(move || {
+        for text in rx {
+            if socket.send(Message::text(text)).is_err() {
+                return;
+            }
+        }
+        let _ = socket.close(None);
+    });
+    Some(tx)
+}
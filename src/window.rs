@@ -0,0 +1,73 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// What the fullscreen key switches to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FullscreenKind {
+    /// Changes the monitor's video mode to the window size
+    #[default]
+    Exclusive,
+    /// A window without decorations covering the whole monitor, which leaves
+    /// the other monitors alone and switches back instantly
+    Borderless,
+}
+
+/// `[window]` section of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Index of the monitor the window opens on, e.g. a dedicated radar
+    /// screen. The primary one when not set
+    pub monitor: Option<i32>,
+    pub fullscreen: FullscreenKind,
+    /// Go fullscreen right away, for a display that shows nothing else
+    pub start_fullscreen: bool,
+    /// Text and lines bigger or smaller than the screen size and DPI call
+    /// for, 1.5 draws them half again as big
+    pub ui_scale: f32,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            monitor: None,
+            fullscreen: FullscreenKind::Exclusive,
+            start_fullscreen: false,
+            ui_scale: 1.0,
+        }
+    }
+}
+
+/// Moves the window to the configured monitor and makes it fullscreen if
+/// asked to. Called once after the window opens.
+pub fn setup(rl: &mut RaylibHandle, config: &WindowConfig) {
+    let count = get_monitor_count();
+    if let Some(monitor) = config.monitor {
+        if (0..count).contains(&monitor) {
+            rl.set_window_monitor(monitor);
+            info!(
+                "Opened on monitor {} ({})",
+                monitor,
+                get_monitor_name(monitor).unwrap_or_default()
+            );
+        } else {
+            warn!(
+                "There is no monitor {}, only {}. Staying on the primary one.",
+                monitor, count
+            );
+        }
+    }
+    if config.start_fullscreen {
+        toggle_fullscreen(rl, config.fullscreen);
+    }
+}
+
+/// Switches between a window and the configured kind of fullscreen.
+pub fn toggle_fullscreen(rl: &mut RaylibHandle, kind: FullscreenKind) {
+    match kind {
+        FullscreenKind::Exclusive => rl.toggle_fullscreen(),
+        FullscreenKind::Borderless => rl.toggle_borderless_windowed(),
+    }
+}